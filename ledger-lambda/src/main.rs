@@ -4,11 +4,18 @@ use actix_web::App;
 use base64::Engine;
 use lambda_web::{run_actix_on_lambda, LambdaError};
 use ledger_lib::auth::jwt::JWTAuth;
+use ledger_lib::auth::oidc::OidcProvider;
+use ledger_lib::auth::password::Passwords;
+use ledger_lib::client_ip::TrustedProxies;
 use ledger_lib::config::Config;
+use ledger_lib::events::EventBus;
+use ledger_lib::idempotency::IdempotencyWindow;
+use ledger_lib::rate_limit::CounterStorage;
+use ledger_lib::webhook::{WebhookDispatcher, WebhookTargets};
 use ledger_repo::sqlx_repo::create_repos;
 use std::env;
-use tracing::info;
 use tracing::level_filters::LevelFilter;
+use tracing::{error, info};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::registry;
 
@@ -34,23 +41,64 @@ async fn main() -> Result<(), LambdaError> {
     tracing::subscriber::set_global_default(subscriber).expect("set up subscriber");
     drop(tracing_guard);
 
-    let base64_engine = base64::engine::general_purpose::STANDARD;
-    let secret = base64_engine.decode(env::var("SECRET").expect("SECRET not set"))?;
+    let signup_mode = config.signup_mode();
+    let mut repos = create_repos(config.database_url, 1).await;
+    if config.rate_limits.storage == CounterStorage::Memory {
+        repos.rate_limit_repo = ledger_repo::mem_repo::create_rate_limit_repo();
+    }
 
-    let (user_repo, transaction_repo, template_repo) =
-        create_repos(config.database_url, 1).await;
+    if let Err(e) = ledger_lib::admin::grant_admins(&*repos.user_repo, &config.admins).await {
+        error!(%e, "Unable to grant admins");
+    }
 
-    let jwt_auth = JWTAuth::from_secret(secret);
+    // Lambda instances don't live long enough for a periodic task, so purge whenever one starts
+    if let Err(e) = ledger_lib::trash::purge_trash(&repos, config.trash_retention_days).await {
+        error!(%e, "Unable to purge trash");
+    }
+    let idempotency_window = IdempotencyWindow::hours(config.idempotency_window_hours);
+    if let Err(e) = ledger_lib::idempotency::purge_expired_keys(&repos, idempotency_window).await {
+        error!(%e, "Unable to purge idempotency keys");
+    }
+    if let Err(e) = ledger_lib::auth::refresh_token::purge_expired_tokens(&repos).await {
+        error!(%e, "Unable to purge refresh tokens");
+    }
+    if let Err(e) = ledger_lib::rate_limit::purge_counters(&repos).await {
+        error!(%e, "Unable to purge rate limit counters");
+    }
+    let webhook_targets = WebhookTargets {
+        allow_private: config.allow_private_webhook_targets,
+    };
+    let dispatcher = WebhookDispatcher::with_targets(webhook_targets);
+    if let Err(e) = dispatcher.deliver_due(&*repos.webhook_repo).await {
+        error!(%e, "Unable to deliver webhooks");
+    }
+
+    let jwt_auth = match &config.jwt {
+        Some(jwt_config) => JWTAuth::from_config(jwt_config)?,
+        None => {
+            let base64_engine = base64::engine::general_purpose::STANDARD;
+            JWTAuth::from_secret(base64_engine.decode(env::var("SECRET").expect("SECRET not set"))?)
+        }
+    };
+    let event_bus = EventBus::new();
+    let oidc_provider = config.oidc.clone().map(OidcProvider::new);
+    let passwords = Passwords::from_config(&config.password)?;
+    let trusted_proxies = TrustedProxies(config.trusted_proxies.clone());
 
     let factory = move || {
         App::new()
             .wrap(ledger_lib::tracing::create_middleware())
             .configure(ledger_lib::app_config_func(
                 jwt_auth.clone(),
-                user_repo.clone(),
-                transaction_repo.clone(),
-                template_repo.clone(),
-                config.signups_enabled,
+                repos.clone(),
+                signup_mode,
+                idempotency_window,
+                event_bus.clone(),
+                config.rate_limits.clone(),
+                oidc_provider.clone(),
+                passwords.clone(),
+                trusted_proxies.clone(),
+                webhook_targets,
             ))
     };
     run_actix_on_lambda(factory).await?;
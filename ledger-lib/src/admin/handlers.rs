@@ -0,0 +1,87 @@
+use actix_web::{web, HttpResponse, Responder};
+use std::sync::Arc;
+
+use super::Admin;
+use crate::error::HandlerError;
+use crate::user::UserId;
+use ledger_repo::refresh_token_repo::RefreshTokenRepo;
+use ledger_repo::user_repo::UserRepo;
+
+/// Admins can't lock themselves out, which could leave nobody to manage users
+fn ensure_other_user(admin: &Admin, user_id: &str) -> Result<(), HandlerError> {
+    if admin.user_id == user_id {
+        return Err(HandlerError::Conflict(
+            "Admins can't disable or delete their own account".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Every user with their transaction count and when they last logged in
+#[get("/users")]
+pub async fn get_users(
+    _admin: Admin,
+    user_repo: web::Data<Arc<dyn UserRepo>>,
+) -> Result<impl Responder, HandlerError> {
+    let users = user_repo.get_users().await?;
+    Ok(HttpResponse::Ok().json(users))
+}
+
+/// Stops the user from logging in, and logs out their sessions. Their access tokens and API keys
+/// stop working too.
+#[post("/users/{user_id}/disable")]
+pub async fn disable_user(
+    admin: Admin,
+    user_repo: web::Data<Arc<dyn UserRepo>>,
+    refresh_token_repo: web::Data<Arc<dyn RefreshTokenRepo>>,
+    user_id: web::Path<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    ensure_other_user(&admin, &user_id)?;
+    user_repo.set_disabled(&user_id, true).await?;
+    refresh_token_repo.revoke_all_families(&user_id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[post("/users/{user_id}/enable")]
+pub async fn enable_user(
+    _admin: Admin,
+    user_repo: web::Data<Arc<dyn UserRepo>>,
+    user_id: web::Path<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    user_repo.set_disabled(&user_id, false).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Logs out the user's sessions, and makes them choose a new password the next time they log in
+#[post("/users/{user_id}/reset_password")]
+pub async fn reset_password(
+    _admin: Admin,
+    user_repo: web::Data<Arc<dyn UserRepo>>,
+    refresh_token_repo: web::Data<Arc<dyn RefreshTokenRepo>>,
+    user_id: web::Path<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    user_repo.require_password_reset(&user_id).await?;
+    refresh_token_repo.revoke_all_families(&user_id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[delete("/users/{user_id}")]
+pub async fn delete_user(
+    admin: Admin,
+    user_repo: web::Data<Arc<dyn UserRepo>>,
+    user_id: web::Path<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    ensure_other_user(&admin, &user_id)?;
+    user_repo.delete_user(&user_id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Every user's invite codes, to see who has been inviting people
+#[get("/invite_codes")]
+pub async fn get_invite_codes(
+    _admin: Admin,
+    user_repo: web::Data<Arc<dyn UserRepo>>,
+) -> Result<impl Responder, HandlerError> {
+    let invite_codes = user_repo.get_all_invite_codes().await?;
+    Ok(HttpResponse::Ok().json(invite_codes))
+}
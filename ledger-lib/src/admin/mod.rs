@@ -0,0 +1,69 @@
+mod handlers;
+
+use crate::error::HandlerError;
+use crate::user::UserId;
+use actix_web::dev::Payload;
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError};
+use actix_web::web::Data;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, Scope};
+use futures_util::future::LocalBoxFuture;
+use ledger_repo::user_repo::{UserRepo, UserRepoError};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Managing other users' accounts, which only admins can do
+pub fn admin_service() -> Scope {
+    web::scope("/admin")
+        .service(handlers::get_users)
+        .service(handlers::disable_user)
+        .service(handlers::enable_user)
+        .service(handlers::reset_password)
+        .service(handlers::delete_user)
+        .service(handlers::get_invite_codes)
+}
+
+/// The admin making a request. Extracting it fails with 403 for users that aren't admins.
+pub(crate) struct Admin {
+    pub user_id: UserId,
+}
+
+impl FromRequest for Admin {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let user_id = req.extensions().get::<UserId>().cloned();
+        let user_repo = req.app_data::<Data<Arc<dyn UserRepo>>>().cloned();
+        Box::pin(async move {
+            let (Some(user_id), Some(user_repo)) = (user_id, user_repo) else {
+                return Err(ErrorInternalServerError(
+                    "Missing user id or user repo for admin request",
+                ));
+            };
+            let user = user_repo
+                .get_user(&user_id)
+                .await
+                .map_err(HandlerError::from)?;
+            if !user.admin {
+                return Err(ErrorForbidden("Only admins can manage users"));
+            }
+            Ok(Admin { user_id })
+        })
+    }
+}
+
+/// Makes the users admins, so there is someone to manage users without editing the database.
+/// Users that don't exist yet are skipped.
+pub async fn grant_admins(
+    user_repo: &dyn UserRepo,
+    admins: &[String],
+) -> Result<(), UserRepoError> {
+    for user_id in admins {
+        match user_repo.set_admin(user_id, true).await {
+            Ok(()) => info!(user_id, "Granted admin"),
+            Err(UserRepoError::UserNotFound(_)) => warn!(user_id, "Admin user doesn't exist"),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
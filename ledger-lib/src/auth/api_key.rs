@@ -0,0 +1,164 @@
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::web::Data;
+use actix_web::{HttpMessage, HttpResponse};
+use chrono::Utc;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use ledger_repo::api_key_repo::{ApiKeyOwner, ApiKeyRepo, ApiKeyScope};
+use rand::Rng;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::error;
+
+/// API keys start with this, which tells them apart from JWTs
+pub const API_KEY_PREFIX: &str = "ledger_";
+
+pub(crate) fn generate_key() -> String {
+    let key: [u8; 32] = rand::thread_rng().gen();
+    format!("{}{}", API_KEY_PREFIX, hex::encode(key))
+}
+
+/// Looks up an API key, returning nothing if it doesn't exist or has expired
+pub(crate) async fn authenticate(req: &ServiceRequest, key: &str) -> Option<ApiKeyOwner> {
+    let api_key_repo = req.app_data::<Data<Arc<dyn ApiKeyRepo>>>()?;
+    let owner = match api_key_repo.find_api_key(&super::hash_token(key)).await {
+        Ok(owner) => owner?,
+        Err(e) => {
+            error!(%e, "Unable to look up API key");
+            return None;
+        }
+    };
+
+    owner
+        .api_key
+        .expires_at
+        .is_none_or(|expires_at| expires_at > Utc::now())
+        .then_some(owner)
+}
+
+/// The scopes of the API key a request was authenticated with. Requests authenticated any other
+/// way don't have these, and can do everything.
+#[derive(Clone, Debug)]
+pub struct ApiKeyScopes(pub HashSet<ApiKeyScope>);
+
+/// Limits what requests authenticated with an API key can do in a service. It has to be wrapped
+/// inside the authentication middleware, which adds the [ApiKeyScopes].
+#[derive(Clone, Copy)]
+pub struct RequireScope {
+    read: Option<ApiKeyScope>,
+    write: &'static [ApiKeyScope],
+}
+
+impl RequireScope {
+    /// API keys need the `read` scope for `GET` requests, and every `write` scope for other
+    /// requests. With no `write` scopes, API keys can only make `GET` requests.
+    pub const fn new(read: ApiKeyScope, write: &'static [ApiKeyScope]) -> RequireScope {
+        RequireScope {
+            read: Some(read),
+            write,
+        }
+    }
+
+    /// API keys can't be used at all
+    pub const fn no_api_keys() -> RequireScope {
+        RequireScope {
+            read: None,
+            write: &[],
+        }
+    }
+
+    fn allows(&self, method: &Method, scopes: &HashSet<ApiKeyScope>) -> bool {
+        if method == Method::GET || method == Method::HEAD {
+            self.read.is_some_and(|read| scopes.contains(&read))
+        } else {
+            self.read.is_some()
+                && !self.write.is_empty()
+                && self.write.iter().all(|write| scopes.contains(write))
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireScope
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RequireScopeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireScopeMiddleware {
+            service,
+            required: *self,
+        }))
+    }
+}
+
+pub struct RequireScopeMiddleware<S> {
+    service: S,
+    required: RequireScope,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireScopeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let allowed = req
+            .extensions()
+            .get::<ApiKeyScopes>()
+            .is_none_or(|scopes| self.required.allows(req.method(), &scopes.0));
+        if !allowed {
+            let response = HttpResponse::Forbidden()
+                .body("API key does not have the scope needed for this request");
+            return Box::pin(ready(Ok(req.into_response(response).map_into_right_body())));
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { Ok(fut.await?.map_into_left_body()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RequireScope;
+    use actix_web::http::Method;
+    use ledger_repo::api_key_repo::ApiKeyScope;
+    use std::collections::HashSet;
+
+    #[test]
+    async fn scopes() {
+        let required = RequireScope::new(ApiKeyScope::ReadOnly, &[ApiKeyScope::TransactionsWrite]);
+        let read_only = HashSet::from([ApiKeyScope::ReadOnly]);
+        let write_only = HashSet::from([ApiKeyScope::TransactionsWrite]);
+
+        assert!(required.allows(&Method::GET, &read_only));
+        assert!(!required.allows(&Method::POST, &read_only));
+        assert!(!required.allows(&Method::GET, &write_only));
+        assert!(required.allows(&Method::DELETE, &write_only));
+
+        let read_only_service = RequireScope::new(ApiKeyScope::ReadOnly, &[]);
+        assert!(!read_only_service.allows(&Method::POST, &read_only));
+
+        let all_scopes = HashSet::from([
+            ApiKeyScope::ReadOnly,
+            ApiKeyScope::TransactionsWrite,
+            ApiKeyScope::TemplatesWrite,
+            ApiKeyScope::Export,
+        ]);
+        assert!(!RequireScope::no_api_keys().allows(&Method::GET, &all_scopes));
+    }
+}
@@ -1,50 +1,398 @@
+use crate::auth;
+use crate::auth::invite_code;
 use crate::auth::jwt::JWTAuth;
+use crate::auth::lockout;
+use crate::auth::oidc::{self, IdTokenClaims, OidcError, OidcProvider};
 use crate::auth::password;
+use crate::auth::password::Passwords;
+use crate::auth::refresh_token;
+use crate::auth::totp;
+use crate::auth::totp::SecondFactor;
+use crate::auth::SignupMode;
+use crate::client_ip::client_ip;
 use crate::error::HandlerError;
+use crate::rate_limit;
 use crate::user::UserId;
+use actix_web::http::header;
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use ledger_repo::oidc_repo::{OidcRepo, OidcRepoError};
+use ledger_repo::rate_limit_repo::RateLimitRepo;
+use ledger_repo::refresh_token_repo::{RefreshTokenRepo, RefreshTokenRepoError};
+use ledger_repo::session_repo::SessionRepo;
+use ledger_repo::totp_repo::TotpRepo;
 use ledger_repo::user_repo::User;
-use ledger_repo::user_repo::UserRepo;
+use ledger_repo::user_repo::{UserRepo, UserRepoError};
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::json;
 use std::sync::Arc;
+use tracing::{error, warn};
 
 #[derive(Serialize, Deserialize)]
 pub struct UserCredentials {
     pub id: UserId,
     pub password: String,
+    /// Names the session started by `get_token`. The `User-Agent` is used if it isn't given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_name: Option<String>,
+    /// Required by `get_token` for users with two-factor authentication enabled
+    #[serde(flatten)]
+    pub second_factor: SecondFactor,
+    /// Required by `get_token` for users who have been made to reset their password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_password: Option<String>,
+    /// Required by `signup` when signups need an invite
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invite_code: Option<String>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    /// Seconds until the access token expires
+    pub expires_in: u64,
+    pub refresh_token: String,
+}
+
+impl TokenResponse {
+    fn new(jwt_auth: &JWTAuth, user_id: UserId, refresh_token: String) -> TokenResponse {
+        TokenResponse {
+            access_token: jwt_auth.create_token(user_id),
+            token_type: "Bearer".to_string(),
+            expires_in: JWTAuth::ACCESS_TOKEN_LIFETIME,
+            refresh_token,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RefreshTokenRequest {
+    refresh_token: String,
+}
+
+/// Creates an account. When signups need an invite, this counts as one of the invite code's uses.
 #[post("/signup")]
 pub async fn signup(
     user_repo: web::Data<Arc<dyn UserRepo>>,
+    signup_mode: web::Data<SignupMode>,
+    passwords: web::Data<Passwords>,
     credentials: web::Json<UserCredentials>,
 ) -> Result<impl Responder, HandlerError> {
     let credentials = credentials.into_inner();
-    let password_hash = password::encode_password(credentials.password)?;
+    passwords.check_policy(&credentials.password)?;
+    let invite_code_hash = match (**signup_mode, credentials.invite_code) {
+        (SignupMode::InviteOnly, None) => {
+            return Err(HandlerError::Forbidden(
+                "An invite code is required to sign up".to_string(),
+            ))
+        }
+        (SignupMode::InviteOnly, Some(code)) => Some(invite_code::hash_code(&code)),
+        _ => None,
+    };
+    let password_hash = passwords.encode_password(&credentials.password)?;
 
-    user_repo
-        .create_user(User::new(credentials.id, password_hash))
-        .await?;
+    let user = User::new(credentials.id, password_hash);
+    match invite_code_hash {
+        Some(code_hash) => user_repo.create_user_with_invite(user, &code_hash).await?,
+        None => user_repo.create_user(user).await?,
+    }
 
     Ok(HttpResponse::Ok())
 }
 
+/// Logs in, starting a new refresh token family. Users with two-factor authentication enabled also
+/// need to give a TOTP code or a recovery code. Without one, the response says that one is needed.
+/// Users who have been made to reset their password are told so until they give a new password.
+///
+/// Repeated failures lock the user, and the client address, out for a while. Passwords hashed with
+/// other parameters than the configured ones are hashed again once the login succeeds.
+#[allow(clippy::too_many_arguments)]
 #[post("/get_token")]
 pub async fn get_token(
     user_repo: web::Data<Arc<dyn UserRepo>>,
+    refresh_token_repo: web::Data<Arc<dyn RefreshTokenRepo>>,
+    session_repo: web::Data<Arc<dyn SessionRepo>>,
+    totp_repo: web::Data<Arc<dyn TotpRepo>>,
+    rate_limit_repo: web::Data<Arc<dyn RateLimitRepo>>,
+    passwords: web::Data<Passwords>,
     credentials: web::Json<UserCredentials>,
     req: HttpRequest,
 ) -> Result<impl Responder, HandlerError> {
     let credentials = credentials.into_inner();
+    let rate_limit_repo = rate_limit_repo.as_ref().as_ref();
+
+    let login_keys = lockout::login_keys(&credentials.id, client_ip(&req).as_deref());
+    if let Some(retry_after) = lockout::retry_after(rate_limit_repo, &login_keys).await? {
+        return Ok(rate_limit::too_many_requests(retry_after));
+    }
+
+    let user = match user_repo.get_user(&credentials.id).await {
+        Ok(user) => user,
+        Err(e) => {
+            if let UserRepoError::UserNotFound(_) = e {
+                lockout::record_failure(rate_limit_repo, &login_keys).await?;
+            }
+            return Err(e.into());
+        }
+    };
+
+    let matched =
+        password::verify_password(credentials.password.clone(), user.password_hash.clone())?;
+    if !matched {
+        lockout::record_failure(rate_limit_repo, &login_keys).await?;
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    if user.disabled {
+        return Ok(HttpResponse::Forbidden().body("Account is disabled"));
+    }
 
-    let user = user_repo.get_user(&credentials.id).await?;
+    if let Some(totp) = totp_repo
+        .get_totp(&user.id)
+        .await?
+        .filter(|totp| totp.enabled)
+    {
+        if credentials.second_factor.is_empty() {
+            return Ok(HttpResponse::Unauthorized().json(json!({ "two_factor_required": true })));
+        }
+        let verified = totp::verify_second_factor(
+            totp_repo.as_ref().as_ref(),
+            &user.id,
+            &totp.secret,
+            &credentials.second_factor,
+        )
+        .await?;
+        if !verified {
+            lockout::record_failure(rate_limit_repo, &login_keys).await?;
+            return Ok(HttpResponse::Unauthorized().finish());
+        }
+    }
+    lockout::record_success(rate_limit_repo, &user.id).await?;
 
-    let matched = password::verify_password(credentials.password, user.password_hash)?;
-    if matched {
-        let jwt_auth = req.app_data::<JWTAuth>().unwrap();
-        Ok(HttpResponse::Ok().body(jwt_auth.create_token(user.id)))
-    } else {
-        Ok(HttpResponse::Unauthorized().finish())
+    if user.password_reset_required {
+        let Some(new_password) = credentials.new_password else {
+            return Ok(
+                HttpResponse::Unauthorized().json(json!({ "password_reset_required": true }))
+            );
+        };
+        passwords.check_policy(&new_password)?;
+        let password_hash = passwords.encode_password(&new_password)?;
+        user_repo
+            .update_password_hash(&user.id, &password_hash)
+            .await?;
+    } else if passwords.needs_rehash(&user.password_hash) {
+        let password_hash = passwords.encode_password(&credentials.password)?;
+        user_repo
+            .update_password_hash(&user.id, &password_hash)
+            .await?;
     }
+
+    let tokens = start_session(
+        user_repo.as_ref().as_ref(),
+        refresh_token_repo.as_ref().as_ref(),
+        session_repo.as_ref().as_ref(),
+        user.id,
+        credentials.device_name,
+        &req,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+/// Starts a new refresh token family for a user that has logged in, and issues its first tokens.
+/// The session is named after the `User-Agent` if `device_name` isn't given.
+async fn start_session(
+    user_repo: &dyn UserRepo,
+    refresh_token_repo: &dyn RefreshTokenRepo,
+    session_repo: &dyn SessionRepo,
+    user_id: UserId,
+    device_name: Option<String>,
+    req: &HttpRequest,
+) -> Result<TokenResponse, HandlerError> {
+    let token = refresh_token::generate_token();
+    let family_id = refresh_token::generate_family_id();
+    refresh_token_repo
+        .create_family(&user_id, &family_id, &auth::hash_token(&token))
+        .await?;
+
+    let device_name = device_name.or_else(|| {
+        req.headers()
+            .get(header::USER_AGENT)
+            .and_then(|ua| ua.to_str().ok())
+            .map(|ua| ua.to_owned())
+    });
+    session_repo
+        .start_session(&family_id, device_name, client_ip(req))
+        .await?;
+    user_repo.record_login(&user_id).await?;
+
+    let jwt_auth = req.app_data::<JWTAuth>().unwrap();
+    Ok(TokenResponse::new(jwt_auth, user_id, token))
+}
+
+/// Exchanges a refresh token for a new access token and a new refresh token. The old refresh token
+/// can't be used again.
+#[post("/refresh")]
+pub async fn refresh(
+    refresh_token_repo: web::Data<Arc<dyn RefreshTokenRepo>>,
+    session_repo: web::Data<Arc<dyn SessionRepo>>,
+    request: web::Json<RefreshTokenRequest>,
+    req: HttpRequest,
+) -> Result<impl Responder, HandlerError> {
+    let new_token = refresh_token::generate_token();
+    let owner = refresh_token_repo
+        .rotate_token(
+            &auth::hash_token(&request.refresh_token),
+            &auth::hash_token(&new_token),
+            refresh_token::issued_after(),
+        )
+        .await
+        .inspect_err(|e| {
+            if let RefreshTokenRepoError::TokenReused(family_id) = e {
+                warn!(family_id, "Refresh token reused, revoked its family");
+            }
+        })?;
+    session_repo
+        .touch_session(&owner.family_id, client_ip(&req))
+        .await?;
+
+    let jwt_auth = req.app_data::<JWTAuth>().unwrap();
+    Ok(HttpResponse::Ok().json(TokenResponse::new(jwt_auth, owner.user_id, new_token)))
+}
+
+/// Revokes the refresh token's family. Access tokens already issued stay valid until they expire.
+#[post("/logout")]
+pub async fn logout(
+    refresh_token_repo: web::Data<Arc<dyn RefreshTokenRepo>>,
+    request: web::Json<RefreshTokenRequest>,
+) -> Result<impl Responder, HandlerError> {
+    refresh_token_repo
+        .revoke_family(&auth::hash_token(&request.refresh_token))
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// The public keys tokens are signed with, for services that verify them without calling us
+#[get("/jwks.json")]
+pub async fn jwks(req: HttpRequest) -> impl Responder {
+    let jwt_auth = req.app_data::<JWTAuth>().unwrap();
+    HttpResponse::Ok().json(jwt_auth.jwks())
+}
+
+/// Starts logging in with the OpenID Connect provider, by redirecting to it
+#[get("/oidc/authorize")]
+pub async fn oidc_authorize(
+    provider: web::Data<OidcProvider>,
+    oidc_repo: web::Data<Arc<dyn OidcRepo>>,
+) -> Result<impl Responder, HandlerError> {
+    let login = OidcProvider::new_login();
+    let url = provider.authorization_url(&login).await?;
+    oidc_repo.create_login(login, oidc::started_after()).await?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, url.as_str()))
+        .finish())
+}
+
+#[derive(Deserialize)]
+pub struct OidcCallback {
+    state: String,
+    code: Option<String>,
+    error: Option<String>,
+}
+
+/// Creates a user linked to the subject, returning their id. Provisioned users have no password,
+/// so they can only log in through the provider.
+async fn provision_user(
+    oidc_repo: &dyn OidcRepo,
+    claims: &IdTokenClaims,
+) -> Result<String, HandlerError> {
+    for user_id in claims.user_ids() {
+        let user = User::new(user_id.clone(), String::new());
+        match oidc_repo
+            .create_linked_user(&claims.iss, &claims.sub, user)
+            .await
+        {
+            Ok(()) => return Ok(user_id),
+            // a local user can have the same name, and doesn't get taken over
+            Err(OidcRepoError::UserAlreadyExists(_)) => continue,
+            // another login for the subject got there first
+            Err(OidcRepoError::IdentityAlreadyLinked(_, _)) => {
+                if let Some(user_id) = oidc_repo.get_linked_user(&claims.iss, &claims.sub).await? {
+                    return Ok(user_id);
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(HandlerError::Conflict(format!(
+        "No free user id for {}",
+        claims.user_id()
+    )))
+}
+
+/// Finishes logging in with the OpenID Connect provider, which redirects here. The user linked to
+/// the provider's subject gets a new session, the same as from `get_token`. Subjects that aren't
+/// linked get a new user when signups are enabled.
+///
+/// The provider is trusted to have checked any second factor, so TOTP isn't required.
+#[allow(clippy::too_many_arguments)]
+#[get("/oidc/callback")]
+pub async fn oidc_callback(
+    provider: web::Data<OidcProvider>,
+    oidc_repo: web::Data<Arc<dyn OidcRepo>>,
+    user_repo: web::Data<Arc<dyn UserRepo>>,
+    refresh_token_repo: web::Data<Arc<dyn RefreshTokenRepo>>,
+    session_repo: web::Data<Arc<dyn SessionRepo>>,
+    callback: web::Query<OidcCallback>,
+    req: HttpRequest,
+) -> Result<impl Responder, HandlerError> {
+    let callback = callback.into_inner();
+    let Some(login) = oidc_repo
+        .take_login(&callback.state, oidc::started_after())
+        .await?
+    else {
+        return Err(HandlerError::BadRequest(
+            "Login is unknown or has expired".into(),
+        ));
+    };
+    let Some(code) = callback.code else {
+        let error = callback.error.unwrap_or_else(|| "no code".into());
+        warn!(error, "Provider didn't authorize login");
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+
+    let claims = match provider.exchange_code(&code, &login).await {
+        Ok(claims) => claims,
+        Err(OidcError::Rejected(reason)) => {
+            warn!(reason, "OIDC login rejected");
+            return Ok(HttpResponse::Unauthorized().finish());
+        }
+        Err(OidcError::Provider(e)) => {
+            error!(%e, "Unable to finish OIDC login");
+            return Ok(HttpResponse::BadGateway().finish());
+        }
+    };
+
+    let user_id = match oidc_repo.get_linked_user(&claims.iss, &claims.sub).await? {
+        Some(user_id) => user_id,
+        None if provider.provision_users => {
+            provision_user(oidc_repo.as_ref().as_ref(), &claims).await?
+        }
+        None => return Ok(HttpResponse::Forbidden().body("No user is linked to this account")),
+    };
+    if user_repo.get_user(&user_id).await?.disabled {
+        return Ok(HttpResponse::Forbidden().body("Account is disabled"));
+    }
+
+    let tokens = start_session(
+        user_repo.as_ref().as_ref(),
+        refresh_token_repo.as_ref().as_ref(),
+        session_repo.as_ref().as_ref(),
+        user_id,
+        None,
+        &req,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(tokens))
 }
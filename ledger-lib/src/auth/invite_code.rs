@@ -0,0 +1,36 @@
+use rand::Rng;
+
+/// Invite codes are passed on by hand, so they are grouped like recovery codes
+pub(crate) fn generate_code() -> String {
+    let code: [u8; 8] = rand::thread_rng().gen();
+    hex::encode(code)
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Invite codes are hashed like refresh tokens, ignoring case and dashes
+pub(crate) fn hash_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    super::hash_token(&normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_code, hash_code};
+
+    #[test]
+    async fn hash_ignores_formatting() {
+        let code = generate_code();
+        assert_eq!(19, code.len());
+        assert_eq!(hash_code(&code), hash_code(&code.to_uppercase()));
+        assert_eq!(hash_code(&code), hash_code(&code.replace('-', "")));
+        assert_ne!(hash_code(&code), hash_code(&generate_code()));
+    }
+}
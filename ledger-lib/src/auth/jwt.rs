@@ -1,13 +1,159 @@
+use crate::config::JwtConfig;
 use crate::user::UserId;
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use anyhow::{anyhow, bail, Context};
+use base64::Engine;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, OctetKeyPairParameters,
+    OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::Deserialize;
 use serde::Serialize;
+use simple_asn1::{oid, ASN1Block};
+use std::collections::HashMap;
+use std::fs;
+use std::str::FromStr;
 use std::time::UNIX_EPOCH;
 
+/// The algorithms signing keys can use
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyAlgorithm {
+    EdDSA,
+    RS256,
+}
+
+impl FromStr for KeyAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "EdDSA" => Ok(KeyAlgorithm::EdDSA),
+            "RS256" => Ok(KeyAlgorithm::RS256),
+            _ => Err(anyhow!("Invalid key algorithm {}", s)),
+        }
+    }
+}
+
+impl From<KeyAlgorithm> for Algorithm {
+    fn from(value: KeyAlgorithm) -> Self {
+        match value {
+            KeyAlgorithm::EdDSA => Algorithm::EdDSA,
+            KeyAlgorithm::RS256 => Algorithm::RS256,
+        }
+    }
+}
+
+/// A key that tokens are verified with, and that is published in the JWKS
+pub struct PublicKey {
+    kid: String,
+    algorithm: KeyAlgorithm,
+    decoding_key: DecodingKey,
+    jwk: Jwk,
+}
+
+impl PublicKey {
+    /// Reads a `PUBLIC KEY` PEM, like the ones written by `openssl pkey -pubout`
+    pub fn from_pem(
+        kid: &str,
+        algorithm: KeyAlgorithm,
+        pem: &[u8],
+    ) -> Result<PublicKey, anyhow::Error> {
+        let pem = pem::parse(pem).context("Unable to parse PEM")?;
+        if pem.tag != "PUBLIC KEY" {
+            bail!("Expected a PUBLIC KEY PEM, found {}", pem.tag);
+        }
+        let key = subject_public_key(&pem.contents, algorithm)?;
+
+        let base64_engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let (decoding_key, parameters) = match algorithm {
+            KeyAlgorithm::EdDSA => (
+                DecodingKey::from_ed_der(&key),
+                AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: base64_engine.encode(&key),
+                }),
+            ),
+            KeyAlgorithm::RS256 => {
+                let (modulus, exponent) = rsa_components(&key)?;
+                (
+                    DecodingKey::from_rsa_der(&key),
+                    AlgorithmParameters::RSA(RSAKeyParameters {
+                        key_type: RSAKeyType::RSA,
+                        n: base64_engine.encode(modulus),
+                        e: base64_engine.encode(exponent),
+                    }),
+                )
+            }
+        };
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                algorithm: Some(algorithm.into()),
+                key_id: Some(kid.to_owned()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+
+        Ok(PublicKey {
+            kid: kid.to_owned(),
+            algorithm,
+            decoding_key,
+            jwk,
+        })
+    }
+}
+
+/// Gets the key out of a DER encoded SubjectPublicKeyInfo, checking that it is for `algorithm`.
+/// For EdDSA this is the key itself, and for RS256 it is a DER encoded RSAPublicKey.
+fn subject_public_key(der: &[u8], algorithm: KeyAlgorithm) -> Result<Vec<u8>, anyhow::Error> {
+    let expected_oid = match algorithm {
+        KeyAlgorithm::EdDSA => oid!(1, 3, 101, 112),
+        KeyAlgorithm::RS256 => oid!(1, 2, 840, 113_549, 1, 1, 1),
+    };
+    let blocks = simple_asn1::from_der(der).context("Unable to parse public key")?;
+    let [ASN1Block::Sequence(_, info)] = blocks.as_slice() else {
+        bail!("Public key is not a SubjectPublicKeyInfo");
+    };
+    let [ASN1Block::Sequence(_, key_algorithm), ASN1Block::BitString(_, _, key)] = info.as_slice()
+    else {
+        bail!("Public key is not a SubjectPublicKeyInfo");
+    };
+    match key_algorithm.first() {
+        Some(ASN1Block::ObjectIdentifier(_, oid)) if *oid == expected_oid => Ok(key.clone()),
+        _ => Err(anyhow!("Public key is not for {:?}", algorithm)),
+    }
+}
+
+/// The big-endian modulus and exponent of a DER encoded RSAPublicKey
+fn rsa_components(der: &[u8]) -> Result<(Vec<u8>, Vec<u8>), anyhow::Error> {
+    let blocks = simple_asn1::from_der(der).context("Unable to parse RSA public key")?;
+    let [ASN1Block::Sequence(_, components)] = blocks.as_slice() else {
+        bail!("Public key is not an RSAPublicKey");
+    };
+    let [ASN1Block::Integer(_, modulus), ASN1Block::Integer(_, exponent)] = components.as_slice()
+    else {
+        bail!("Public key is not an RSAPublicKey");
+    };
+    Ok((modulus.to_bytes_be().1, exponent.to_bytes_be().1))
+}
+
+#[derive(Clone)]
+struct VerificationKey {
+    algorithm: Algorithm,
+    decoding_key: DecodingKey,
+}
+
 #[derive(Clone)]
 pub struct JWTAuth {
+    kid: Option<String>,
+    algorithm: Algorithm,
     encoding_key: EncodingKey,
-    decoding_key: DecodingKey,
+    /// Keys by their id. The secret from [JWTAuth::from_secret] doesn't have one.
+    verification_keys: HashMap<Option<String>, VerificationKey>,
+    jwks: JwkSet,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -17,27 +163,131 @@ struct Claims {
 }
 
 impl JWTAuth {
-    const EXPIRE_TIME: u64 = 30 * 24 * 60 * 60;
+    /// Access tokens can't be revoked, so they are short-lived and renewed with a refresh token
+    pub const ACCESS_TOKEN_LIFETIME: u64 = 15 * 60;
 
+    /// Signs tokens with a shared secret, which anything verifying them needs as well. There are
+    /// no public keys to publish.
     pub fn from_secret(secret: Vec<u8>) -> JWTAuth {
+        let verification_key = VerificationKey {
+            algorithm: Algorithm::HS256,
+            decoding_key: DecodingKey::from_secret(&secret),
+        };
         JWTAuth {
+            kid: None,
+            algorithm: Algorithm::HS256,
             encoding_key: EncodingKey::from_secret(&secret),
-            decoding_key: DecodingKey::from_secret(&secret),
+            verification_keys: HashMap::from([(None, verification_key)]),
+            jwks: JwkSet { keys: vec![] },
         }
     }
 
+    /// Signs tokens with the private key of `signing_kid`, which is a `PRIVATE KEY` PEM. Tokens
+    /// signed by any of the public keys are accepted, so keys can be rotated by signing with a new
+    /// key while keeping the old public key until the tokens it signed have expired.
+    pub fn from_keys(
+        signing_kid: &str,
+        private_key_pem: &[u8],
+        public_keys: Vec<PublicKey>,
+    ) -> Result<JWTAuth, anyhow::Error> {
+        let algorithm = public_keys
+            .iter()
+            .find(|key| key.kid == signing_kid)
+            .ok_or_else(|| anyhow!("No public key for signing key {}", signing_kid))?
+            .algorithm;
+        let encoding_key = match algorithm {
+            KeyAlgorithm::EdDSA => EncodingKey::from_ed_pem(private_key_pem),
+            KeyAlgorithm::RS256 => EncodingKey::from_rsa_pem(private_key_pem),
+        }
+        .context("Unable to read private key")?;
+
+        let jwt_auth = JWTAuth {
+            kid: Some(signing_kid.to_owned()),
+            algorithm: algorithm.into(),
+            encoding_key,
+            jwks: JwkSet {
+                keys: public_keys.iter().map(|key| key.jwk.clone()).collect(),
+            },
+            verification_keys: public_keys
+                .into_iter()
+                .map(|key| {
+                    let verification_key = VerificationKey {
+                        algorithm: key.algorithm.into(),
+                        decoding_key: key.decoding_key,
+                    };
+                    (Some(key.kid), verification_key)
+                })
+                .collect(),
+        };
+
+        // catches private keys that were paired with the wrong public key
+        let token = jwt_auth.create_token(UserId::new());
+        jwt_auth
+            .validate_token(&token)
+            .with_context(|| format!("Private key doesn't match public key {}", signing_kid))?;
+        Ok(jwt_auth)
+    }
+
+    /// Reads the keys from the files named in the config
+    pub fn from_config(config: &JwtConfig) -> Result<JWTAuth, anyhow::Error> {
+        let signing_key = config
+            .keys
+            .iter()
+            .find(|key| key.kid == config.signing_key)
+            .ok_or_else(|| anyhow!("Signing key {} is not configured", config.signing_key))?;
+        let private_key_file = signing_key
+            .private_key_file
+            .as_ref()
+            .ok_or_else(|| anyhow!("Signing key {} has no private key file", signing_key.kid))?;
+        let private_key_pem =
+            fs::read(private_key_file).context("Unable to read private key file")?;
+
+        let public_keys = config
+            .keys
+            .iter()
+            .map(|key| {
+                let pem = fs::read(&key.public_key_file)
+                    .with_context(|| format!("Unable to read public key file for {}", key.kid))?;
+                PublicKey::from_pem(&key.kid, key.algorithm, &pem)
+                    .with_context(|| format!("Unable to read public key {}", key.kid))
+            })
+            .collect::<Result<_, _>>()?;
+
+        JWTAuth::from_keys(&config.signing_key, &private_key_pem, public_keys)
+    }
+
+    /// The public keys tokens can be verified with
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+
     pub fn create_token(&self, user_id: UserId) -> String {
         let claims = Claims {
             exp: Self::generate_exp(),
             sub: user_id,
         };
+        let header = Header {
+            kid: self.kid.clone(),
+            ..Header::new(self.algorithm)
+        };
 
-        jsonwebtoken::encode(&Header::default(), &claims, &self.encoding_key).unwrap()
+        jsonwebtoken::encode(&header, &claims, &self.encoding_key).unwrap()
     }
 
     pub fn validate_token(&self, token: &str) -> Result<UserId, jsonwebtoken::errors::Error> {
-        let claim =
-            jsonwebtoken::decode::<Claims>(token, &self.decoding_key, &Validation::default())?;
+        let header = jsonwebtoken::decode_header(token)?;
+        let key = self
+            .verification_keys
+            .get(&header.kid)
+            .ok_or(ErrorKind::InvalidToken)?;
+
+        // only the key's own algorithm is accepted, so a public key can't be used as an HMAC
+        // secret
+        let claim = jsonwebtoken::decode::<Claims>(
+            token,
+            &key.decoding_key,
+            &Validation::new(key.algorithm),
+        )?;
         Ok(claim.claims.sub)
     }
 
@@ -46,14 +296,27 @@ impl JWTAuth {
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs()
-            + Self::EXPIRE_TIME) as usize
+            + Self::ACCESS_TOKEN_LIFETIME) as usize
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::auth::jwt::JWTAuth;
+    use crate::auth::jwt::{JWTAuth, KeyAlgorithm, PublicKey};
     use base64::Engine;
+    use jsonwebtoken::jwk::AlgorithmParameters;
+    use jsonwebtoken::{decode, DecodingKey, Validation};
+
+    const ED25519_KEY: &[u8] = include_bytes!("../../tests/keys/ed25519.pem");
+    const ED25519_PUBLIC_KEY: &[u8] = include_bytes!("../../tests/keys/ed25519.pub.pem");
+    const OLD_ED25519_KEY: &[u8] = include_bytes!("../../tests/keys/ed25519-old.pem");
+    const OLD_ED25519_PUBLIC_KEY: &[u8] = include_bytes!("../../tests/keys/ed25519-old.pub.pem");
+    const RSA_KEY: &[u8] = include_bytes!("../../tests/keys/rsa.pem");
+    const RSA_PUBLIC_KEY: &[u8] = include_bytes!("../../tests/keys/rsa.pub.pem");
+
+    fn public_key(kid: &str, algorithm: KeyAlgorithm, pem: &[u8]) -> PublicKey {
+        PublicKey::from_pem(kid, algorithm, pem).unwrap()
+    }
 
     #[test]
     async fn valid_token() {
@@ -74,4 +337,130 @@ mod tests {
         let token = base64_engine.encode(token_bytes);
         assert!(jwt_auth.validate_token(&token).is_err())
     }
+
+    #[test]
+    async fn asymmetric_keys() {
+        for (algorithm, private_key, public_key_pem) in [
+            (KeyAlgorithm::EdDSA, ED25519_KEY, ED25519_PUBLIC_KEY),
+            (KeyAlgorithm::RS256, RSA_KEY, RSA_PUBLIC_KEY),
+        ] {
+            let jwt_auth = JWTAuth::from_keys(
+                "key",
+                private_key,
+                vec![public_key("key", algorithm, public_key_pem)],
+            )
+            .unwrap();
+
+            let token = jwt_auth.create_token("alice".into());
+            let header = jsonwebtoken::decode_header(&token).unwrap();
+            assert_eq!(Some("key".to_string()), header.kid);
+            assert_eq!(jwt_auth.validate_token(&token), Ok("alice".into()));
+
+            // the published key verifies the token as well
+            let jwk = jwt_auth.jwks().find("key").unwrap();
+            let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+            let validation = Validation::new(algorithm.into());
+            assert!(decode::<serde_json::Value>(&token, &decoding_key, &validation).is_ok());
+        }
+    }
+
+    #[test]
+    async fn key_rotation() {
+        let old_jwt_auth = JWTAuth::from_keys(
+            "old",
+            OLD_ED25519_KEY,
+            vec![public_key(
+                "old",
+                KeyAlgorithm::EdDSA,
+                OLD_ED25519_PUBLIC_KEY,
+            )],
+        )
+        .unwrap();
+        let old_token = old_jwt_auth.create_token("alice".into());
+
+        let jwt_auth = JWTAuth::from_keys(
+            "new",
+            ED25519_KEY,
+            vec![
+                public_key("new", KeyAlgorithm::EdDSA, ED25519_PUBLIC_KEY),
+                public_key("old", KeyAlgorithm::EdDSA, OLD_ED25519_PUBLIC_KEY),
+            ],
+        )
+        .unwrap();
+        assert_eq!(jwt_auth.validate_token(&old_token), Ok("alice".into()));
+        let token = jwt_auth.create_token("bob".into());
+        assert_eq!(
+            Some("new".to_string()),
+            jsonwebtoken::decode_header(&token).unwrap().kid
+        );
+
+        // once the old key is dropped its tokens are rejected
+        let jwt_auth = JWTAuth::from_keys(
+            "new",
+            ED25519_KEY,
+            vec![public_key("new", KeyAlgorithm::EdDSA, ED25519_PUBLIC_KEY)],
+        )
+        .unwrap();
+        assert!(jwt_auth.validate_token(&old_token).is_err());
+        assert_eq!(jwt_auth.validate_token(&token), Ok("bob".into()));
+    }
+
+    #[test]
+    async fn mismatched_keys() {
+        let result = JWTAuth::from_keys(
+            "key",
+            OLD_ED25519_KEY,
+            vec![public_key("key", KeyAlgorithm::EdDSA, ED25519_PUBLIC_KEY)],
+        );
+        assert!(result.is_err());
+        let result = JWTAuth::from_keys(
+            "other",
+            ED25519_KEY,
+            vec![public_key("key", KeyAlgorithm::EdDSA, ED25519_PUBLIC_KEY)],
+        );
+        assert!(result.is_err());
+        assert!(PublicKey::from_pem("key", KeyAlgorithm::RS256, ED25519_PUBLIC_KEY).is_err());
+        assert!(PublicKey::from_pem("key", KeyAlgorithm::EdDSA, ED25519_KEY).is_err());
+    }
+
+    #[test]
+    async fn hmac_tokens_rejected_by_public_keys() {
+        // an HS256 token signed with the public key as the secret, without a kid
+        let public_key_jwt_auth = JWTAuth::from_secret(ED25519_PUBLIC_KEY.to_vec());
+        let token = public_key_jwt_auth.create_token("mallory".into());
+
+        let jwt_auth = JWTAuth::from_keys(
+            "key",
+            ED25519_KEY,
+            vec![public_key("key", KeyAlgorithm::EdDSA, ED25519_PUBLIC_KEY)],
+        )
+        .unwrap();
+        assert!(jwt_auth.validate_token(&token).is_err());
+    }
+
+    #[test]
+    async fn jwks() {
+        let jwt_auth = JWTAuth::from_keys(
+            "ed",
+            ED25519_KEY,
+            vec![
+                public_key("ed", KeyAlgorithm::EdDSA, ED25519_PUBLIC_KEY),
+                public_key("rsa", KeyAlgorithm::RS256, RSA_PUBLIC_KEY),
+            ],
+        )
+        .unwrap();
+        let kids: Vec<_> = jwt_auth
+            .jwks()
+            .keys
+            .iter()
+            .map(|key| key.common.key_id.clone().unwrap())
+            .collect();
+        assert_eq!(vec!["ed", "rsa"], kids);
+
+        let AlgorithmParameters::RSA(rsa) = &jwt_auth.jwks().find("rsa").unwrap().algorithm else {
+            panic!("Expected an RSA key");
+        };
+        assert_eq!("AQAB", rsa.e);
+        assert!(JWTAuth::from_secret(vec![1, 2, 3]).jwks().keys.is_empty());
+    }
 }
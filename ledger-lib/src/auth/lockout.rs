@@ -0,0 +1,126 @@
+//! Locks out password guessing. Failed logins are counted per user and per client address, and
+//! once there have been [ALLOWED_FAILURES] in a row, each further failure doubles how long logins
+//! are refused for.
+
+use crate::error::HandlerError;
+use chrono::{DateTime, Duration, Utc};
+use ledger_repo::rate_limit_repo::{FailedLogins, RateLimitRepo};
+use std::cmp::min;
+use tracing::warn;
+
+const ALLOWED_FAILURES: i32 = 5;
+const BASE_LOCKOUT_SECONDS: i64 = 30;
+const MAX_LOCKOUT_SECONDS: i64 = 60 * 60;
+/// Failures are forgotten after going this long without another
+const FAILURE_MEMORY_HOURS: i64 = 24;
+
+fn lockout(failures: i32) -> Option<Duration> {
+    if failures < ALLOWED_FAILURES {
+        return None;
+    }
+    let doublings = min(failures - ALLOWED_FAILURES, 16) as u32;
+    Some(Duration::seconds(min(
+        BASE_LOCKOUT_SECONDS << doublings,
+        MAX_LOCKOUT_SECONDS,
+    )))
+}
+
+fn locked_until(failed_logins: &FailedLogins) -> Option<DateTime<Utc>> {
+    lockout(failed_logins.failures).map(|lockout| failed_logins.last_failure_at + lockout)
+}
+
+/// The keys failed logins are counted under, which are the user and the client address
+pub(crate) fn login_keys(user_id: &str, client_ip: Option<&str>) -> Vec<String> {
+    vec![
+        format!("user:{}", user_id),
+        format!("ip:{}", client_ip.unwrap_or("unknown")),
+    ]
+}
+
+/// How long until logins are allowed again, if any of the keys are locked out
+pub(crate) async fn retry_after(
+    rate_limit_repo: &dyn RateLimitRepo,
+    keys: &[String],
+) -> Result<Option<Duration>, HandlerError> {
+    let now = Utc::now();
+    let mut retry_after = None;
+    for key in keys {
+        let Some(failed_logins) = rate_limit_repo.get_failed_logins(key).await? else {
+            continue;
+        };
+        if let Some(locked_until) = locked_until(&failed_logins).filter(|until| *until > now) {
+            retry_after = retry_after.max(Some(locked_until - now));
+        }
+    }
+    Ok(retry_after)
+}
+
+pub(crate) async fn record_failure(
+    rate_limit_repo: &dyn RateLimitRepo,
+    keys: &[String],
+) -> Result<(), HandlerError> {
+    let reset_before = Utc::now() - Duration::hours(FAILURE_MEMORY_HOURS);
+    for key in keys {
+        let failed_logins = rate_limit_repo
+            .record_failed_login(key, reset_before)
+            .await?;
+        if let Some(lockout) = lockout(failed_logins.failures) {
+            warn!(
+                key,
+                failures = failed_logins.failures,
+                lockout_seconds = lockout.num_seconds(),
+                "Locked out logins"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Logging in clears the user's failures, but not the client address's, so that an attacker with
+/// one account can't use it to keep guessing the passwords of others
+pub(crate) async fn record_success(
+    rate_limit_repo: &dyn RateLimitRepo,
+    user_id: &str,
+) -> Result<(), HandlerError> {
+    rate_limit_repo
+        .clear_failed_logins(&format!("user:{}", user_id))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{locked_until, lockout};
+    use chrono::{Duration, TimeZone, Utc};
+    use ledger_repo::rate_limit_repo::FailedLogins;
+
+    #[test]
+    async fn lockout_doubles() {
+        assert_eq!(None, lockout(1));
+        assert_eq!(None, lockout(4));
+        assert_eq!(Some(Duration::seconds(30)), lockout(5));
+        assert_eq!(Some(Duration::seconds(60)), lockout(6));
+        assert_eq!(Some(Duration::seconds(120)), lockout(7));
+        assert_eq!(Some(Duration::hours(1)), lockout(12));
+        assert_eq!(Some(Duration::hours(1)), lockout(i32::MAX));
+    }
+
+    #[test]
+    async fn locked_until_last_failure() {
+        let last_failure_at = Utc.timestamp_opt(1000, 0).unwrap();
+        assert_eq!(
+            Some(last_failure_at + Duration::seconds(60)),
+            locked_until(&FailedLogins {
+                failures: 6,
+                last_failure_at
+            })
+        );
+        assert_eq!(
+            None,
+            locked_until(&FailedLogins {
+                failures: 2,
+                last_failure_at
+            })
+        );
+    }
+}
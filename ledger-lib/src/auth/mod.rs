@@ -1,28 +1,79 @@
+use crate::error::HandlerError;
 use crate::user::UserId;
 use actix_web::dev::ServiceRequest;
+use actix_web::web::Data;
 use actix_web::{web, Error, HttpMessage, Scope};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use actix_web_httpauth::extractors::{bearer, AuthenticationError};
 use actix_web_httpauth::headers::www_authenticate::bearer::Bearer;
+use api_key::ApiKeyScopes;
 use jwt::JWTAuth;
+use ledger_repo::user_repo::{UserRepo, UserRepoError};
+use oidc::OidcProvider;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
 use tracing_actix_web::RootSpan;
 
+pub mod api_key;
 mod handlers;
+pub mod invite_code;
 pub mod jwt;
+mod lockout;
+pub mod oidc;
 pub mod password;
+pub mod refresh_token;
+pub mod totp;
 
-/// Validates credentials using [JWTAuth]. If valid, injects the user id into request and into the
-/// [RootSpan]
+/// Refresh tokens and API keys are random, so unlike passwords a fast unsalted hash is enough to
+/// keep them from being usable if the database leaks
+pub(crate) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Whether credentials issued to the user can still be used, which they can't once the user is
+/// deleted or disabled
+async fn is_active(req: &ServiceRequest, user_id: &str) -> Result<bool, Error> {
+    let user_repo = req.app_data::<Data<Arc<dyn UserRepo>>>().unwrap();
+    match user_repo.get_user(user_id).await {
+        Ok(user) => Ok(!user.disabled),
+        Err(UserRepoError::UserNotFound(_)) => Ok(false),
+        Err(e) => Err(HandlerError::from(e).into()),
+    }
+}
+
+/// Validates credentials, which are either a JWT from [JWTAuth] or an API key, and checks that
+/// their user is active. If valid, injects the user id into request and into the [RootSpan], along
+/// with the [ApiKeyScopes] for API keys.
 pub async fn credentials_validator(
     req: ServiceRequest,
     credentials: BearerAuth,
 ) -> Result<ServiceRequest, (Error, ServiceRequest)> {
-    let jwt_auth = req.app_data::<JWTAuth>().unwrap();
-    if let Ok(user) = jwt_auth.validate_token(credentials.token()) {
+    let token = credentials.token();
+    let authenticated = if token.starts_with(api_key::API_KEY_PREFIX) {
+        api_key::authenticate(&req, token)
+            .await
+            .map(|owner| (owner.user_id, Some(ApiKeyScopes(owner.api_key.scopes))))
+    } else {
+        let jwt_auth = req.app_data::<JWTAuth>().unwrap();
+        jwt_auth.validate_token(token).ok().map(|user| (user, None))
+    };
+    let authenticated = match authenticated {
+        Some((user, scopes)) => match is_active(&req, &user).await {
+            Ok(true) => Some((user, scopes)),
+            Ok(false) => None,
+            Err(e) => return Err((e, req)),
+        },
+        None => None,
+    };
+
+    if let Some((user, scopes)) = authenticated {
         if let Some(root_span) = req.extensions().get::<RootSpan>() {
             root_span.record("user_id", user.as_str());
         }
         req.extensions_mut().insert::<UserId>(user);
+        if let Some(scopes) = scopes {
+            req.extensions_mut().insert(scopes);
+        }
         Ok(req)
     } else {
         let challenge = Bearer::build().error(bearer::Error::InvalidToken).finish();
@@ -30,14 +81,39 @@ pub async fn credentials_validator(
     }
 }
 
-pub fn auth_service(signups_enabled: bool) -> Scope {
-    let mut auth_scope = web::scope("/auth").service(handlers::get_token);
-    if signups_enabled {
+/// Who can create an account
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignupMode {
+    Closed,
+    Open,
+    /// Signing up needs an invite code from an existing user. Logging in with OIDC doesn't create
+    /// accounts, as there's no code to check.
+    InviteOnly,
+}
+
+pub fn auth_service(signup_mode: SignupMode, oidc_provider: Option<OidcProvider>) -> Scope {
+    let mut auth_scope = web::scope("/auth")
+        .app_data(web::Data::new(signup_mode))
+        .service(handlers::get_token)
+        .service(handlers::refresh)
+        .service(handlers::logout);
+    if signup_mode != SignupMode::Closed {
         auth_scope = auth_scope.service(handlers::signup);
     }
+    if let Some(mut oidc_provider) = oidc_provider {
+        oidc_provider.provision_users = signup_mode == SignupMode::Open;
+        auth_scope = auth_scope
+            .app_data(web::Data::new(oidc_provider))
+            .service(handlers::oidc_authorize)
+            .service(handlers::oidc_callback);
+    }
     auth_scope
 }
 
+pub fn well_known_service() -> Scope {
+    web::scope("/.well-known").service(handlers::jwks)
+}
+
 #[cfg(test)]
 mod tests {
     use super::credentials_validator;
@@ -47,14 +123,20 @@ mod tests {
     use actix_web::test::TestRequest;
     use actix_web::{http, test, web, App, Responder};
     use actix_web_httpauth::middleware::HttpAuthentication;
+    use ledger_repo::user_repo::{User, UserRepo};
     use rstest::fixture;
     use rstest::rstest;
+    use std::sync::Arc;
 
     macro_rules! build_service {
         ($jwt_auth:ident) => {{
+            build_service!($jwt_auth, ledger_repo::mem_repo::create_repos().user_repo)
+        }};
+        ($jwt_auth:ident, $user_repo:expr) => {{
             let bearer_auth_middleware = HttpAuthentication::bearer(credentials_validator);
             let app = App::new()
                 .app_data($jwt_auth)
+                .app_data(web::Data::new($user_repo))
                 .route("/", web::get().to(return_user))
                 .wrap(bearer_auth_middleware);
             test::init_service(app).await
@@ -72,8 +154,13 @@ mod tests {
     async fn valid_user(jwt_auth: JWTAuth) {
         let user_id: UserId = "test".into();
         let token = jwt_auth.create_token(user_id.clone());
+        let user_repo = ledger_repo::mem_repo::create_repos().user_repo;
+        user_repo
+            .create_user(User::new(user_id.clone(), String::new()))
+            .await
+            .unwrap();
 
-        let service = build_service!(jwt_auth);
+        let service = build_service!(jwt_auth, user_repo);
 
         let request = TestRequest::get()
             .uri("/")
@@ -113,6 +200,31 @@ mod tests {
         assert_ne!(user_id.as_bytes(), &body)
     }
 
+    #[rstest]
+    #[test]
+    async fn disabled_user(jwt_auth: JWTAuth) {
+        let user_id: UserId = "test".into();
+        let token = jwt_auth.create_token(user_id.clone());
+        let user_repo: Arc<dyn UserRepo> = ledger_repo::mem_repo::create_repos().user_repo;
+        user_repo
+            .create_user(User::new(user_id.clone(), String::new()))
+            .await
+            .unwrap();
+        user_repo.set_disabled(&user_id, true).await.unwrap();
+
+        let service = build_service!(jwt_auth, user_repo);
+
+        let request = TestRequest::get()
+            .uri("/")
+            .insert_header((
+                http::header::AUTHORIZATION,
+                (String::from("Bearer ") + &token),
+            ))
+            .to_request();
+        let response = test::call_service(&service, request).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED)
+    }
+
     #[rstest]
     #[test]
     async fn no_token(jwt_auth: JWTAuth) {
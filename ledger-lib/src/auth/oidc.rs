@@ -0,0 +1,296 @@
+//! Logging in with an external OpenID Connect provider, using the authorization code flow with
+//! PKCE. A login starts at `/auth/oidc/authorize`, which redirects to the provider. The provider
+//! redirects back to `/auth/oidc/callback` with a code, which is exchanged for an ID token naming
+//! the provider's subject. Subjects are linked to ledger users the first time they log in.
+
+use crate::config::OidcConfig;
+use anyhow::{anyhow, Context};
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use ledger_repo::oidc_repo::OidcLogin;
+use rand::Rng;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::iter;
+use thiserror::Error;
+
+/// Logins have to be finished within this many minutes of being started
+pub const LOGIN_LIFETIME_MINUTES: i64 = 10;
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// The highest number a provisioned user's id is suffixed with when it's taken
+const MAX_USER_ID_SUFFIX: u32 = 20;
+
+#[derive(Error, Debug)]
+pub enum OidcError {
+    /// The provider refused the code, or sent an ID token that isn't valid for this login
+    #[error("{0}")]
+    Rejected(String),
+    #[error(transparent)]
+    Provider(#[from] anyhow::Error),
+}
+
+/// The parts of the provider's discovery document that are needed
+#[derive(Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+}
+
+/// The claims of an ID token that are used, after it has been validated
+#[derive(Deserialize, Debug)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    #[serde(default)]
+    nonce: Option<String>,
+    #[serde(default)]
+    pub preferred_username: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+impl IdTokenClaims {
+    /// The id given to a user provisioned for this subject
+    pub fn user_id(&self) -> &str {
+        self.preferred_username
+            .as_deref()
+            .or(self.email.as_deref())
+            .unwrap_or(&self.sub)
+    }
+
+    /// The ids to try for a user provisioned for this subject, in order, as [Self::user_id] can
+    /// already be taken by another user. The others are it suffixed with a number.
+    pub fn user_ids(&self) -> impl Iterator<Item = String> + '_ {
+        let user_id = self.user_id();
+        iter::once(user_id.to_owned())
+            .chain((2..=MAX_USER_ID_SUFFIX).map(move |n| format!("{}-{}", user_id, n)))
+    }
+}
+
+#[derive(Clone)]
+pub struct OidcProvider {
+    config: OidcConfig,
+    client: reqwest::Client,
+    /// Whether subjects that aren't linked yet get a new user
+    pub(crate) provision_users: bool,
+}
+
+fn random_string() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The S256 code challenge for a PKCE code verifier
+pub fn code_challenge(code_verifier: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier))
+}
+
+/// Logins started before this have expired
+pub(crate) fn started_after() -> DateTime<Utc> {
+    Utc::now() - Duration::minutes(LOGIN_LIFETIME_MINUTES)
+}
+
+impl OidcProvider {
+    pub fn new(config: OidcConfig) -> OidcProvider {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("HTTP client should be built");
+        OidcProvider {
+            config,
+            client,
+            provision_users: false,
+        }
+    }
+
+    /// A new login, with a random state, nonce and PKCE code verifier
+    pub fn new_login() -> OidcLogin {
+        OidcLogin {
+            state: random_string(),
+            code_verifier: random_string(),
+            nonce: random_string(),
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, anyhow::Error> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("Unable to get {}", url))?
+            .error_for_status()?;
+        let body = response.bytes().await.context("Unable to read response")?;
+        serde_json::from_slice(&body).with_context(|| format!("Unable to parse {}", url))
+    }
+
+    async fn metadata(&self) -> Result<ProviderMetadata, anyhow::Error> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.config.issuer.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = self.get_json(&url).await?;
+        if metadata.issuer != self.config.issuer {
+            return Err(anyhow!(
+                "Provider issuer {} doesn't match {}",
+                metadata.issuer,
+                self.config.issuer
+            ));
+        }
+        Ok(metadata)
+    }
+
+    /// Where to send the user to log in with the provider
+    pub async fn authorization_url(&self, login: &OidcLogin) -> Result<Url, anyhow::Error> {
+        let metadata = self.metadata().await?;
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            [
+                ("response_type", "code"),
+                ("client_id", &self.config.client_id),
+                ("redirect_uri", &self.config.redirect_uri),
+                ("scope", "openid profile email"),
+                ("state", &login.state),
+                ("nonce", &login.nonce),
+                ("code_challenge", &code_challenge(&login.code_verifier)),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .context("Invalid authorization endpoint")?;
+        Ok(url)
+    }
+
+    /// Exchanges the code the provider redirected back with for an ID token, and validates it
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        login: &OidcLogin,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.config.redirect_uri),
+            ("client_id", &self.config.client_id),
+            ("code_verifier", &login.code_verifier),
+        ];
+        if let Some(client_secret) = &self.config.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+        let response = self
+            .client
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .context("Unable to exchange code")?;
+        let status = response.status();
+        let body = response.bytes().await.context("Unable to read response")?;
+        if status.is_client_error() {
+            let error = serde_json::from_slice::<TokenErrorResponse>(&body)
+                .map(|e| e.error)
+                .unwrap_or_else(|_| status.to_string());
+            return Err(OidcError::Rejected(format!("Code was rejected: {}", error)));
+        }
+        if !status.is_success() {
+            return Err(anyhow!("Unexpected response status {}", status).into());
+        }
+        let token_response: TokenResponse =
+            serde_json::from_slice(&body).context("Unable to parse token response")?;
+
+        let jwks: JwkSet = self.get_json(&metadata.jwks_uri).await?;
+        self.validate_id_token(&token_response.id_token, &jwks, login)
+    }
+
+    fn validate_id_token(
+        &self,
+        id_token: &str,
+        jwks: &JwkSet,
+        login: &OidcLogin,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let rejected = |e: jsonwebtoken::errors::Error| OidcError::Rejected(e.to_string());
+
+        let header = jsonwebtoken::decode_header(id_token).map_err(rejected)?;
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(OidcError::Rejected(
+                "ID tokens have to be signed with a public key".into(),
+            ));
+        }
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        }
+        .ok_or_else(|| OidcError::Rejected("ID token was signed with an unknown key".into()))?;
+        let decoding_key = DecodingKey::from_jwk(jwk).map_err(rejected)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, &decoding_key, &validation)
+            .map_err(rejected)?
+            .claims;
+
+        // the nonce ties the token to this login, so a token from another login can't be replayed
+        if claims.nonce.as_deref() != Some(&login.nonce) {
+            return Err(OidcError::Rejected("ID token nonce doesn't match".into()));
+        }
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{code_challenge, IdTokenClaims};
+
+    #[test]
+    async fn pkce_challenge() {
+        // from RFC 7636 appendix B
+        assert_eq!(
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM",
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk")
+        );
+    }
+
+    #[test]
+    async fn user_id() {
+        let mut claims = IdTokenClaims {
+            iss: "https://idp.example.com".into(),
+            sub: "248289761001".into(),
+            nonce: None,
+            preferred_username: Some("jane".into()),
+            email: Some("jane@example.com".into()),
+        };
+        assert_eq!("jane", claims.user_id());
+        claims.preferred_username = None;
+        assert_eq!("jane@example.com", claims.user_id());
+        claims.email = None;
+        assert_eq!("248289761001", claims.user_id());
+
+        let user_ids: Vec<String> = claims.user_ids().collect();
+        assert_eq!(20, user_ids.len());
+        assert_eq!("248289761001", user_ids[0]);
+        assert_eq!("248289761001-2", user_ids[1]);
+        assert_eq!("248289761001-20", user_ids[19]);
+    }
+}
@@ -1,12 +1,145 @@
+use crate::config::PasswordConfig;
+use crate::error::HandlerError;
+use anyhow::Context;
 use argon2::Config;
+use std::collections::HashSet;
+use std::fs;
+use std::sync::Arc;
 
-pub fn encode_password(password: String) -> Result<String, argon2::Error> {
-    let config = Config::default();
-    let salt: [u8; 32] = rand::random();
-    let password_hash = argon2::hash_encoded(password.as_bytes(), &salt, &config)?;
-    Ok(password_hash)
+/// How passwords are hashed, and which passwords users can choose
+#[derive(Clone)]
+pub struct Passwords {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    min_length: usize,
+    breached_passwords: Arc<HashSet<String>>,
 }
 
+impl Passwords {
+    /// Reads the breached passwords, if there are any, which are listed one per line
+    pub fn from_config(config: &PasswordConfig) -> Result<Passwords, anyhow::Error> {
+        let breached_passwords = match &config.breached_passwords_file {
+            Some(path) => fs::read_to_string(path)
+                .with_context(|| format!("Unable to read breached passwords from {:?}", path))?
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_owned())
+                .collect(),
+            None => HashSet::new(),
+        };
+        Ok(Passwords {
+            memory_kib: config.memory_kib,
+            iterations: config.iterations,
+            parallelism: config.parallelism,
+            min_length: config.min_length,
+            breached_passwords: Arc::new(breached_passwords),
+        })
+    }
+
+    fn argon2_config(&self) -> Config<'static> {
+        Config {
+            mem_cost: self.memory_kib,
+            time_cost: self.iterations,
+            lanes: self.parallelism,
+            ..Config::default()
+        }
+    }
+
+    pub fn encode_password(&self, password: &str) -> Result<String, argon2::Error> {
+        let salt: [u8; 32] = rand::random();
+        let password_hash =
+            argon2::hash_encoded(password.as_bytes(), &salt, &self.argon2_config())?;
+        Ok(password_hash)
+    }
+
+    /// Whether the hash was made with other parameters than the current ones, so the password
+    /// should be hashed again the next time it is known
+    pub fn needs_rehash(&self, password_hash: &str) -> bool {
+        let config = self.argon2_config();
+        let params = format!(
+            "${}$v={}$m={},t={},p={}$",
+            config.variant, config.version, config.mem_cost, config.time_cost, config.lanes
+        );
+        !password_hash.is_empty() && !password_hash.starts_with(&params)
+    }
+
+    /// Checks a password a user has chosen against the policy
+    pub fn check_policy(&self, password: &str) -> Result<(), HandlerError> {
+        if password.chars().count() < self.min_length {
+            return Err(HandlerError::UnprocessableEntity(format!(
+                "Password must be at least {} characters long",
+                self.min_length
+            )));
+        }
+        if self.breached_passwords.contains(password) {
+            return Err(HandlerError::UnprocessableEntity(
+                "Password has appeared in a data breach".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Passwords {
+    fn default() -> Self {
+        Passwords::from_config(&PasswordConfig::default()).unwrap()
+    }
+}
+
+/// Users without a password hash, like those provisioned by single sign-on, never match
 pub fn verify_password(password: String, password_hash: String) -> Result<bool, argon2::Error> {
+    if password_hash.is_empty() {
+        return Ok(false);
+    }
     argon2::verify_encoded(&password_hash, password.as_bytes())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_password, Passwords};
+    use crate::config::PasswordConfig;
+    use std::{env, fs};
+    use uuid::Uuid;
+
+    fn passwords(memory_kib: u32) -> Passwords {
+        Passwords::from_config(&PasswordConfig {
+            memory_kib,
+            ..PasswordConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    async fn rehash_when_params_change() {
+        let old = passwords(4096);
+        let new = passwords(8192);
+        let password_hash = old.encode_password("correct horse").unwrap();
+
+        assert!(!old.needs_rehash(&password_hash));
+        assert!(new.needs_rehash(&password_hash));
+        assert!(verify_password("correct horse".to_string(), password_hash).unwrap());
+        assert!(!new.needs_rehash(""));
+    }
+
+    #[test]
+    async fn policy() {
+        let path = env::temp_dir().join(format!("breached-{}.txt", Uuid::new_v4()));
+        fs::write(&path, "password1\n\n  letmein123  \n").unwrap();
+        let passwords = Passwords::from_config(&PasswordConfig {
+            breached_passwords_file: Some(path.clone()),
+            ..PasswordConfig::default()
+        })
+        .unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert!(passwords.check_policy("").is_err());
+        assert!(passwords.check_policy("short").is_err());
+        assert!(passwords.check_policy("password1").is_err());
+        assert!(passwords.check_policy("letmein123").is_err());
+        assert!(passwords.check_policy("correct horse").is_ok());
+        // length is counted in characters rather than bytes
+        assert!(passwords.check_policy("pässwörd").is_ok());
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Duration, Utc};
+use ledger_repo::Repos;
+use rand::Rng;
+use std::time::Duration as StdDuration;
+use tracing::{error, info};
+
+/// Refresh tokens expire this many days after they are issued. As each use issues a new token,
+/// a session only ends after going unused for this long.
+pub const REFRESH_TOKEN_LIFETIME_DAYS: i64 = 30;
+const PURGE_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+pub(crate) fn generate_token() -> String {
+    let token: [u8; 32] = rand::thread_rng().gen();
+    hex::encode(token)
+}
+
+pub(crate) fn generate_family_id() -> String {
+    let id: [u8; 16] = rand::thread_rng().gen();
+    hex::encode(id)
+}
+
+/// Tokens issued before this have expired
+pub(crate) fn issued_after() -> DateTime<Utc> {
+    Utc::now() - Duration::days(REFRESH_TOKEN_LIFETIME_DAYS)
+}
+
+/// Deletes expired refresh tokens, and the sessions they belonged to
+pub async fn purge_expired_tokens(repos: &Repos) -> Result<(), anyhow::Error> {
+    let tokens = repos
+        .refresh_token_repo
+        .purge_tokens(issued_after())
+        .await?;
+    info!(tokens, "Purged expired refresh tokens");
+
+    Ok(())
+}
+
+/// Purges expired refresh tokens every [PURGE_INTERVAL]. Errors are logged, so this never
+/// returns.
+pub async fn purge_expired_tokens_periodically(repos: Repos) {
+    let mut interval = actix_web::rt::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = purge_expired_tokens(&repos).await {
+            error!(%e, "Unable to purge refresh tokens");
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Time-based one-time passwords ([RFC 6238](https://www.rfc-editor.org/rfc/rfc6238)) for two-factor
+//! authentication, with the parameters authenticator apps default to: HMAC-SHA1, six digits and
+//! 30 second steps.
+
+use crate::error::HandlerError;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use ledger_repo::totp_repo::TotpRepo;
+use rand::Rng;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+
+const DIGITS: u32 = 6;
+const STEP_SECONDS: i64 = 30;
+/// Codes from this many steps either side of the current one are accepted, to allow for clock
+/// drift and slow typing
+const ALLOWED_SKEW_STEPS: i64 = 1;
+const ISSUER: &str = "Ledger";
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// The second factor given when logging in, or when turning two-factor authentication off
+#[derive(Serialize, Deserialize, Default)]
+pub struct SecondFactor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_code: Option<String>,
+    /// Each recovery code can be used once, instead of a TOTP code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_code: Option<String>,
+}
+
+impl SecondFactor {
+    pub fn is_empty(&self) -> bool {
+        self.totp_code.is_none() && self.recovery_code.is_none()
+    }
+}
+
+pub(crate) fn generate_secret() -> Vec<u8> {
+    let secret: [u8; 20] = rand::thread_rng().gen();
+    secret.to_vec()
+}
+
+/// The secret in the base32 form authenticator apps expect
+pub fn encode_secret(secret: &[u8]) -> String {
+    base32::encode(base32::Alphabet::RFC4648 { padding: false }, secret)
+}
+
+pub fn decode_secret(secret: &str) -> Option<Vec<u8>> {
+    base32::decode(base32::Alphabet::RFC4648 { padding: false }, secret)
+}
+
+/// The `otpauth://` URI that authenticator apps read from a QR code
+pub(crate) fn otpauth_uri(secret: &[u8], user_id: &str) -> String {
+    let mut uri = Url::parse("otpauth://totp").unwrap();
+    uri.path_segments_mut()
+        .unwrap()
+        .push(&format!("{ISSUER}:{user_id}"));
+    uri.query_pairs_mut()
+        .append_pair("secret", &encode_secret(secret))
+        .append_pair("issuer", ISSUER)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &STEP_SECONDS.to_string());
+    uri.into()
+}
+
+fn step_at(time: DateTime<Utc>) -> i64 {
+    time.timestamp().div_euclid(STEP_SECONDS)
+}
+
+fn code_for_step(secret: &[u8], step: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // dynamic truncation
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes(hash[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+/// The code an authenticator app shows at `time`
+pub fn code_at(secret: &[u8], time: DateTime<Utc>) -> String {
+    code_for_step(secret, step_at(time))
+}
+
+/// Returns the step the code is from if it is valid at `now`. Callers need to record the step so
+/// that the code can't be used again.
+pub(crate) fn verify_code(secret: &[u8], code: &str, now: DateTime<Utc>) -> Option<i64> {
+    let code = code.trim();
+    let current_step = step_at(now);
+    (current_step - ALLOWED_SKEW_STEPS..=current_step + ALLOWED_SKEW_STEPS)
+        .find(|&step| code_for_step(secret, step) == code)
+}
+
+/// Recovery codes are formatted like `1a2b-3c4d-5e6f-7a8b`
+pub(crate) fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code: [u8; 8] = rand::thread_rng().gen();
+            hex::encode(code)
+                .as_bytes()
+                .chunks(4)
+                .map(|chunk| std::str::from_utf8(chunk).unwrap())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect()
+}
+
+/// Recovery codes are hashed like refresh tokens, ignoring case and dashes
+pub(crate) fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    super::hash_token(&normalized)
+}
+
+/// Checks the second factor of a user with two-factor authentication enabled. Successful checks
+/// use the code up.
+pub(crate) async fn verify_second_factor(
+    totp_repo: &dyn TotpRepo,
+    user_id: &str,
+    secret: &[u8],
+    second_factor: &SecondFactor,
+) -> Result<bool, HandlerError> {
+    if let Some(code) = &second_factor.totp_code {
+        return match verify_code(secret, code, Utc::now()) {
+            Some(step) => Ok(totp_repo.use_step(user_id, step).await?),
+            None => Ok(false),
+        };
+    }
+    if let Some(code) = &second_factor.recovery_code {
+        return Ok(totp_repo
+            .use_recovery_code(user_id, &hash_recovery_code(code))
+            .await?);
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{code_at, generate_recovery_codes, hash_recovery_code, otpauth_uri, verify_code};
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    /// The SHA1 secret from the RFC's test vectors
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(timestamp, 0).unwrap()
+    }
+
+    #[test]
+    async fn rfc_test_vectors() {
+        // the RFC's codes have eight digits, of which these are the last six
+        assert_eq!("287082", code_at(SECRET, at(59)));
+        assert_eq!("081804", code_at(SECRET, at(1111111109)));
+        assert_eq!("050471", code_at(SECRET, at(1111111111)));
+        assert_eq!("005924", code_at(SECRET, at(1234567890)));
+        assert_eq!("279037", code_at(SECRET, at(2000000000)));
+    }
+
+    #[test]
+    async fn verify_allows_skew() {
+        let now = at(1234567890);
+        let step = 1234567890 / 30;
+        let code = code_at(SECRET, now);
+
+        assert_eq!(Some(step), verify_code(SECRET, &code, now));
+        assert_eq!(
+            Some(step),
+            verify_code(SECRET, &code, now + Duration::seconds(30))
+        );
+        assert_eq!(
+            Some(step),
+            verify_code(SECRET, &code, now - Duration::seconds(30))
+        );
+        assert_eq!(
+            None,
+            verify_code(SECRET, &code, now + Duration::seconds(90))
+        );
+        assert_eq!(None, verify_code(SECRET, "000000", now));
+        assert_eq!(None, verify_code(b"another secret", &code, now));
+    }
+
+    #[test]
+    async fn uri() {
+        assert_eq!(
+            "otpauth://totp/Ledger:alice%20smith?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+            &issuer=Ledger&algorithm=SHA1&digits=6&period=30",
+            otpauth_uri(SECRET, "alice smith")
+        );
+    }
+
+    #[test]
+    async fn recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(10, codes.len());
+        assert!(codes.iter().all(|code| code.len() == 19));
+        assert_eq!(
+            hash_recovery_code(&codes[0]),
+            hash_recovery_code(&codes[0].replace('-', "").to_uppercase())
+        );
+        assert_ne!(hash_recovery_code(&codes[0]), hash_recovery_code(&codes[1]));
+    }
+}
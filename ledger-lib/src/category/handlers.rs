@@ -0,0 +1,104 @@
+use crate::error::HandlerError;
+use crate::user::UserId;
+use actix_web::{web, HttpResponse, Responder};
+use ledger_repo::category_repo::{Category, CategoryKind, CategoryRepo};
+use ledger_repo::transaction_repo::TransactionRepo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct CategoryMetadata {
+    kind: CategoryKind,
+    color: Option<String>,
+    icon: Option<String>,
+    #[serde(default)]
+    archived: bool,
+}
+
+/// A category along with its metadata. Categories that are only used by transactions and have no
+/// metadata stored will not have a `kind`.
+#[derive(Serialize)]
+pub struct CategoryResponse {
+    name: String,
+    kind: Option<CategoryKind>,
+    color: Option<String>,
+    icon: Option<String>,
+    archived: bool,
+}
+
+impl From<Category> for CategoryResponse {
+    fn from(value: Category) -> Self {
+        CategoryResponse {
+            name: value.name,
+            kind: Some(value.kind),
+            color: value.color,
+            icon: value.icon,
+            archived: value.archived,
+        }
+    }
+}
+
+#[get("")]
+pub async fn get_all_categories(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    category_repo: web::Data<Arc<dyn CategoryRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+
+    let mut categories: HashMap<String, CategoryResponse> = category_repo
+        .get_categories(&user_id)
+        .await?
+        .into_iter()
+        .map(|c| (c.name.clone(), c.into()))
+        .collect();
+    for name in transaction_repo.get_all_categories(&user_id).await? {
+        categories
+            .entry(name.clone())
+            .or_insert_with(|| CategoryResponse {
+                name,
+                kind: None,
+                color: None,
+                icon: None,
+                archived: false,
+            });
+    }
+
+    let mut categories: Vec<CategoryResponse> = categories.into_values().collect();
+    categories.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+#[put("/{name}")]
+pub async fn set_category(
+    category_repo: web::Data<Arc<dyn CategoryRepo>>,
+    user_id: web::ReqData<UserId>,
+    name: web::Path<String>,
+    metadata: web::Json<CategoryMetadata>,
+) -> Result<impl Responder, HandlerError> {
+    let metadata = metadata.into_inner();
+    let category = Category::new(
+        name.into_inner(),
+        metadata.kind,
+        metadata.color,
+        metadata.icon,
+        metadata.archived,
+    );
+    let category = category_repo
+        .set_category(&user_id.into_inner(), category)
+        .await?;
+    Ok(HttpResponse::Ok().json(category))
+}
+
+#[delete("/{name}")]
+pub async fn delete_category(
+    category_repo: web::Data<Arc<dyn CategoryRepo>>,
+    user_id: web::ReqData<UserId>,
+    name: web::Path<String>,
+) -> Result<impl Responder, HandlerError> {
+    let category = category_repo
+        .delete_category(&user_id.into_inner(), &name.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(category))
+}
@@ -0,0 +1,12 @@
+mod handlers;
+
+use actix_web::{web, Scope};
+
+/// Category endpoints. These are nested under the transaction service since categories are used by
+/// transactions
+pub fn category_service() -> Scope {
+    web::scope("/categories")
+        .service(handlers::get_all_categories)
+        .service(handlers::set_category)
+        .service(handlers::delete_category)
+}
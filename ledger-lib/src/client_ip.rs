@@ -0,0 +1,108 @@
+//! Works out the address of the client making a request. Anyone can set forwarding headers, so
+//! they are only believed on requests from proxies that are trusted to set them. Otherwise client
+//! addresses would be spoofable, and so would the rate limits and lockouts counted by them.
+
+use actix_web::web::Data;
+use actix_web::HttpRequest;
+use std::net::IpAddr;
+
+/// Proxies in front of the server, whose `X-Forwarded-For` headers give the client address
+#[derive(Clone, Default, Debug)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+impl TrustedProxies {
+    /// The address the request came from, unless it came through trusted proxies. Then the
+    /// `X-Forwarded-For` header is followed back from the right, as each proxy appends the address
+    /// it got the request from, to the first address that isn't a trusted proxy.
+    pub fn client_ip(&self, req: &HttpRequest) -> Option<IpAddr> {
+        let mut client_ip = req.peer_addr()?.ip();
+        let forwarded_for: Vec<&str> = req
+            .headers()
+            .get_all("x-forwarded-for")
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|ip| ip.trim())
+            .collect();
+        for ip in forwarded_for.into_iter().rev() {
+            if !self.0.contains(&client_ip) {
+                break;
+            }
+            match ip.parse() {
+                Ok(ip) => client_ip = ip,
+                Err(_) => break,
+            }
+        }
+        Some(client_ip)
+    }
+}
+
+/// The client address of a request, as far as the configured [TrustedProxies] can tell
+pub(crate) fn client_ip(req: &HttpRequest) -> Option<String> {
+    let client_ip = match req.app_data::<Data<TrustedProxies>>() {
+        Some(trusted_proxies) => trusted_proxies.client_ip(req),
+        None => req.peer_addr().map(|addr| addr.ip()),
+    };
+    client_ip.map(|ip| ip.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrustedProxies;
+    use actix_web::test::TestRequest;
+
+    fn client_ip(trusted_proxies: &[&str], peer: &str, forwarded_for: Option<&str>) -> String {
+        let trusted_proxies = TrustedProxies(
+            trusted_proxies
+                .iter()
+                .map(|ip| ip.parse().unwrap())
+                .collect(),
+        );
+        let mut request =
+            TestRequest::default().peer_addr(format!("{}:443", peer).parse().unwrap());
+        if let Some(forwarded_for) = forwarded_for {
+            request = request.insert_header(("X-Forwarded-For", forwarded_for));
+        }
+        trusted_proxies
+            .client_ip(&request.to_http_request())
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    async fn untrusted_peers_are_the_client() {
+        assert_eq!("192.0.2.1", client_ip(&[], "192.0.2.1", None));
+        assert_eq!(
+            "192.0.2.1",
+            client_ip(&[], "192.0.2.1", Some("198.51.100.7"))
+        );
+        assert_eq!(
+            "192.0.2.1",
+            client_ip(&["10.0.0.1"], "192.0.2.1", Some("198.51.100.7"))
+        );
+    }
+
+    #[test]
+    async fn trusted_proxies_are_followed() {
+        assert_eq!(
+            "198.51.100.7",
+            client_ip(&["10.0.0.1"], "10.0.0.1", Some("198.51.100.7"))
+        );
+        // whatever the client claims comes before the address the proxy appended
+        assert_eq!(
+            "198.51.100.7",
+            client_ip(&["10.0.0.1"], "10.0.0.1", Some("203.0.113.9, 198.51.100.7"))
+        );
+        assert_eq!(
+            "198.51.100.7",
+            client_ip(
+                &["10.0.0.1", "10.0.0.2"],
+                "10.0.0.1",
+                Some("203.0.113.9, 198.51.100.7, 10.0.0.2")
+            )
+        );
+        assert_eq!(
+            "10.0.0.1",
+            client_ip(&["10.0.0.1"], "10.0.0.1", Some("unknown"))
+        );
+    }
+}
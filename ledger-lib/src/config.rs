@@ -1,6 +1,11 @@
-use anyhow::Context;
+use crate::auth::jwt::KeyAlgorithm;
+use crate::auth::SignupMode;
+use crate::rate_limit::RateLimits;
+use anyhow::{bail, Context};
 use serde::Deserialize;
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::{env, fs};
 
 #[derive(Deserialize)]
@@ -9,15 +14,165 @@ pub struct SSLConfig {
     pub certificate_chain_file: PathBuf,
 }
 
+#[derive(Deserialize)]
+pub struct JwtKeyConfig {
+    pub kid: String,
+    pub algorithm: KeyAlgorithm,
+    pub public_key_file: PathBuf,
+    /// Only needed for the signing key
+    pub private_key_file: Option<PathBuf>,
+}
+
+/// Parses `kid:algorithm:public_key_file[:private_key_file]`, which is how keys are given in the
+/// `JWT_KEYS` env var
+impl FromStr for JwtKeyConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.splitn(4, ':').collect();
+        let [kid, algorithm, public_key_file, rest @ ..] = parts.as_slice() else {
+            bail!(
+                "Invalid key {}, expected kid:algorithm:public_key_file[:private_key_file]",
+                s
+            );
+        };
+        Ok(JwtKeyConfig {
+            kid: kid.to_string(),
+            algorithm: algorithm.parse()?,
+            public_key_file: PathBuf::from(public_key_file),
+            private_key_file: rest.first().map(PathBuf::from),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct JwtConfig {
+    /// The `kid` of the key that signs tokens
+    pub signing_key: String,
+    /// Every key that tokens are accepted from, including the signing key. After switching to a
+    /// new signing key, the old one should stay here until the tokens it signed have expired.
+    pub keys: Vec<JwtKeyConfig>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct OidcConfig {
+    /// The provider's issuer URL, which its discovery document is found under
+    pub issuer: String,
+    pub client_id: String,
+    /// Not needed by public clients, as PKCE protects the code
+    pub client_secret: Option<String>,
+    /// Where the provider sends users back to, which is `/auth/oidc/callback` on this server
+    pub redirect_uri: String,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PasswordConfig {
+    /// Argon2 memory cost in KiB
+    #[serde(default = "default_memory_kib")]
+    pub memory_kib: u32,
+    /// Argon2 time cost
+    #[serde(default = "default_iterations")]
+    pub iterations: u32,
+    #[serde(default = "default_parallelism")]
+    pub parallelism: u32,
+    /// Minimum number of characters in passwords users choose
+    #[serde(default = "default_min_length")]
+    pub min_length: usize,
+    /// A file listing passwords known from data breaches, one per line, which users can't choose
+    pub breached_passwords_file: Option<PathBuf>,
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        PasswordConfig {
+            memory_kib: default_memory_kib(),
+            iterations: default_iterations(),
+            parallelism: default_parallelism(),
+            min_length: default_min_length(),
+            breached_passwords_file: None,
+        }
+    }
+}
+
+/// The parameters passwords have always been hashed with, so changing them is a choice
+fn default_memory_kib() -> u32 {
+    4096
+}
+
+fn default_iterations() -> u32 {
+    3
+}
+
+fn default_parallelism() -> u32 {
+    1
+}
+
+fn default_min_length() -> usize {
+    8
+}
+
 #[derive(Deserialize)]
 pub struct Config {
     pub database_url: String,
     pub signups_enabled: bool,
+    /// Whether signing up needs an invite code from an existing user, when signups are enabled
+    #[serde(default)]
+    pub signups_require_invite: bool,
     pub honeycomb_api_key: String,
     pub ssl: Option<SSLConfig>,
+    /// Number of days deleted transactions and templates are kept in the trash before they are
+    /// purged
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: i64,
+    /// Number of hours the response to a request with an `Idempotency-Key` is kept for retries
+    #[serde(default = "default_idempotency_window_hours")]
+    pub idempotency_window_hours: i64,
+    /// Whether to listen for changes made through other servers sharing the database, so they are
+    /// pushed to this server's event streams as well
+    #[serde(default)]
+    pub listen_for_changes: bool,
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    /// Proxies in front of the server, whose `X-Forwarded-For` headers are believed. Without any,
+    /// client addresses are the addresses requests come from.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Whether webhooks can be sent to loopback, private and link-local addresses, which are
+    /// refused by default so webhooks can't reach services on the server's network
+    #[serde(default)]
+    pub allow_private_webhook_targets: bool,
+    /// Keys to sign tokens with. Without them, tokens are signed with a generated secret, or the
+    /// `SECRET` env var on Lambda. In the env, `JWT_SIGNING_KEY` is the signing key's `kid` and
+    /// `JWT_KEYS` is a comma separated list of keys.
+    pub jwt: Option<JwtConfig>,
+    /// A provider users can log in with instead of a password
+    pub oidc: Option<OidcConfig>,
+    /// Users that are made admins when the server starts
+    #[serde(default)]
+    pub admins: Vec<String>,
+    /// Password hashing parameters and policy. Changing the hashing parameters rehashes passwords
+    /// as users log in.
+    #[serde(default)]
+    pub password: PasswordConfig,
+}
+
+fn default_trash_retention_days() -> i64 {
+    30
+}
+
+fn default_idempotency_window_hours() -> i64 {
+    24
 }
 
 impl Config {
+    pub fn signup_mode(&self) -> SignupMode {
+        match (self.signups_enabled, self.signups_require_invite) {
+            (false, _) => SignupMode::Closed,
+            (true, false) => SignupMode::Open,
+            (true, true) => SignupMode::InviteOnly,
+        }
+    }
+
     pub fn from_file(path: PathBuf) -> Result<Config, anyhow::Error> {
         let config = fs::read_to_string(path).context("Unable to read config file")?;
         let config: Config =
@@ -30,14 +185,126 @@ impl Config {
         let signups_enabled = signups_enabled
             .parse()
             .context("Unable to parse SIGNUPS_ENABLED value")?;
+        let signups_require_invite = match env::var("SIGNUPS_REQUIRE_INVITE") {
+            Ok(require_invite) => require_invite
+                .parse()
+                .context("Unable to parse SIGNUPS_REQUIRE_INVITE value")?,
+            Err(_) => false,
+        };
         let database_url = read_env("DATABASE_URL")?;
         let honeycomb_api_key = read_env("HONEYCOMB_API_KEY")?;
+        let trash_retention_days = match env::var("TRASH_RETENTION_DAYS") {
+            Ok(days) => days
+                .parse()
+                .context("Unable to parse TRASH_RETENTION_DAYS value")?,
+            Err(_) => default_trash_retention_days(),
+        };
+        let idempotency_window_hours = match env::var("IDEMPOTENCY_WINDOW_HOURS") {
+            Ok(hours) => hours
+                .parse()
+                .context("Unable to parse IDEMPOTENCY_WINDOW_HOURS value")?,
+            Err(_) => default_idempotency_window_hours(),
+        };
+
+        let listen_for_changes = match env::var("LISTEN_FOR_CHANGES") {
+            Ok(listen) => listen
+                .parse()
+                .context("Unable to parse LISTEN_FOR_CHANGES value")?,
+            Err(_) => false,
+        };
+
+        let mut rate_limits = RateLimits::default();
+        if let Ok(storage) = env::var("RATE_LIMIT_STORAGE") {
+            rate_limits.storage = storage
+                .parse()
+                .context("Unable to parse RATE_LIMIT_STORAGE value")?;
+        }
+        if let Ok(limits) = env::var("RATE_LIMITS") {
+            rate_limits.limits =
+                RateLimits::parse_limits(&limits).context("Unable to parse RATE_LIMITS value")?;
+        }
+
+        let trusted_proxies = match env::var("TRUSTED_PROXIES") {
+            Ok(proxies) => proxies
+                .split(',')
+                .map(|proxy| proxy.trim())
+                .filter(|proxy| !proxy.is_empty())
+                .map(|proxy| proxy.parse())
+                .collect::<Result<_, _>>()
+                .context("Unable to parse TRUSTED_PROXIES value")?,
+            Err(_) => Vec::new(),
+        };
+
+        let allow_private_webhook_targets = match env::var("ALLOW_PRIVATE_WEBHOOK_TARGETS") {
+            Ok(allow) => allow
+                .parse()
+                .context("Unable to parse ALLOW_PRIVATE_WEBHOOK_TARGETS value")?,
+            Err(_) => false,
+        };
+
+        let admins = match env::var("ADMINS") {
+            Ok(admins) => admins
+                .split(',')
+                .map(|admin| admin.trim().to_owned())
+                .filter(|admin| !admin.is_empty())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        let jwt = match env::var("JWT_SIGNING_KEY") {
+            Ok(signing_key) => Some(JwtConfig {
+                signing_key,
+                keys: read_env("JWT_KEYS")?
+                    .split(',')
+                    .map(|key| key.trim())
+                    .filter(|key| !key.is_empty())
+                    .map(|key| key.parse())
+                    .collect::<Result<_, _>>()
+                    .context("Unable to parse JWT_KEYS value")?,
+            }),
+            Err(_) => None,
+        };
+
+        let mut password = PasswordConfig::default();
+        if let Ok(memory_kib) = env::var("PASSWORD_MEMORY_KIB") {
+            password.memory_kib = memory_kib
+                .parse()
+                .context("Unable to parse PASSWORD_MEMORY_KIB value")?;
+        }
+        if let Ok(iterations) = env::var("PASSWORD_ITERATIONS") {
+            password.iterations = iterations
+                .parse()
+                .context("Unable to parse PASSWORD_ITERATIONS value")?;
+        }
+        if let Ok(parallelism) = env::var("PASSWORD_PARALLELISM") {
+            password.parallelism = parallelism
+                .parse()
+                .context("Unable to parse PASSWORD_PARALLELISM value")?;
+        }
+        if let Ok(min_length) = env::var("PASSWORD_MIN_LENGTH") {
+            password.min_length = min_length
+                .parse()
+                .context("Unable to parse PASSWORD_MIN_LENGTH value")?;
+        }
+        password.breached_passwords_file =
+            env::var("BREACHED_PASSWORDS_FILE").ok().map(PathBuf::from);
 
         let config = Config {
             database_url,
             signups_enabled,
+            signups_require_invite,
             honeycomb_api_key,
             ssl: None,
+            trash_retention_days,
+            idempotency_window_hours,
+            listen_for_changes,
+            rate_limits,
+            trusted_proxies,
+            allow_private_webhook_targets,
+            jwt,
+            oidc: None,
+            admins,
+            password,
         };
         Ok(config)
     }
@@ -46,3 +313,34 @@ impl Config {
 fn read_env(key: &str) -> Result<String, anyhow::Error> {
     env::var(key).with_context(|| format!("Unable to read env var: {}", key))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::JwtKeyConfig;
+    use crate::auth::jwt::KeyAlgorithm;
+    use std::path::PathBuf;
+
+    #[test]
+    async fn parse_jwt_key() {
+        let key: JwtKeyConfig = "2024-01:EdDSA:/keys/public.pem:/keys/private.pem"
+            .parse()
+            .unwrap();
+        assert_eq!("2024-01", key.kid);
+        assert_eq!(KeyAlgorithm::EdDSA, key.algorithm);
+        assert_eq!(PathBuf::from("/keys/public.pem"), key.public_key_file);
+        assert_eq!(
+            Some(PathBuf::from("/keys/private.pem")),
+            key.private_key_file
+        );
+
+        // only the signing key needs a private key
+        let key: JwtKeyConfig = "2023-01:RS256:/keys/old.pem".parse().unwrap();
+        assert_eq!(KeyAlgorithm::RS256, key.algorithm);
+        assert_eq!(None, key.private_key_file);
+
+        assert!("2023-01:HS256:/keys/old.pem"
+            .parse::<JwtKeyConfig>()
+            .is_err());
+        assert!("2023-01:RS256".parse::<JwtKeyConfig>().is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use crate::duplicate::{find_duplicates, DEFAULT_DAYS};
+use crate::error::HandlerError;
+use crate::transaction::Filter;
+use crate::user::UserId;
+use actix_web::{web, HttpResponse, Responder};
+use ledger_repo::duplicate_repo::DuplicateRepo;
+use ledger_repo::transaction_repo::TransactionRepo;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct DuplicateOptions {
+    days: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct DismissDuplicate {
+    transactions: (i32, i32),
+}
+
+#[get("")]
+pub async fn get_duplicates(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    duplicate_repo: web::Data<Arc<dyn DuplicateRepo>>,
+    user_id: web::ReqData<UserId>,
+    filter: web::Query<Filter>,
+    options: web::Query<DuplicateOptions>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+
+    let transactions = transaction_repo
+        .get_all_transactions(&user_id, filter.into_inner().into(), None)
+        .await?;
+    let dismissed: HashSet<(i32, i32)> = duplicate_repo
+        .get_dismissed_pairs(&user_id)
+        .await?
+        .into_iter()
+        .collect();
+
+    let groups = find_duplicates(
+        transactions,
+        options.days.unwrap_or(DEFAULT_DAYS),
+        &dismissed,
+    );
+    Ok(HttpResponse::Ok().json(groups))
+}
+
+/// Marks a pair of transactions as not being duplicates so they are no longer reported
+#[post("/dismiss")]
+pub async fn dismiss_duplicate(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    duplicate_repo: web::Data<Arc<dyn DuplicateRepo>>,
+    user_id: web::ReqData<UserId>,
+    dismiss: web::Json<DismissDuplicate>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    let (transaction_id, other_transaction_id) = dismiss.into_inner().transactions;
+    if transaction_id == other_transaction_id {
+        return Err(HandlerError::BadRequest(
+            "A transaction cannot be a duplicate of itself".to_string(),
+        ));
+    }
+
+    // Make sure both transactions belong to the user
+    transaction_repo
+        .get_transaction(&user_id, transaction_id)
+        .await?;
+    transaction_repo
+        .get_transaction(&user_id, other_transaction_id)
+        .await?;
+
+    duplicate_repo
+        .dismiss_pair(&user_id, transaction_id, other_transaction_id)
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
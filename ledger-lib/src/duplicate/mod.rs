@@ -0,0 +1,176 @@
+mod handlers;
+
+use crate::error::HandlerError;
+use actix_web::{web, Scope};
+use chrono::{Duration, NaiveDate};
+use ledger_repo::transaction_repo::{Filter, NewTransaction, Transaction, TransactionRepo};
+use rust_decimal::Decimal;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// How many days apart two transactions can be and still be considered duplicates, unless the
+/// request says otherwise
+pub(crate) const DEFAULT_DAYS: i64 = 3;
+
+/// Duplicate detection endpoints, nested under the transaction service
+pub fn duplicate_service() -> Scope {
+    web::scope("/duplicates")
+        .service(handlers::get_duplicates)
+        .service(handlers::dismiss_duplicate)
+}
+
+fn words(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Texts are similar if one's words are all in the other, or they share at least half their
+/// words. A missing text is similar to anything since imports and manual entry often fill in
+/// different fields.
+fn similar_text(text: Option<&str>, other: Option<&str>) -> bool {
+    let (Some(text), Some(other)) = (text, other) else {
+        return true;
+    };
+    let (words, other_words) = (words(text), words(other));
+    if words.is_subset(&other_words) || other_words.is_subset(&words) {
+        return true;
+    }
+    let common = words.intersection(&other_words).count();
+    let all = words.union(&other_words).count();
+    common * 2 >= all
+}
+
+/// The fields used to decide whether two transactions are duplicates
+pub(crate) struct Candidate<'a> {
+    pub date: NaiveDate,
+    pub amount: Decimal,
+    pub transactee: Option<&'a str>,
+    pub note: Option<&'a str>,
+}
+
+impl<'a> From<&'a Transaction> for Candidate<'a> {
+    fn from(value: &'a Transaction) -> Self {
+        Candidate {
+            date: value.date,
+            amount: value.amount,
+            transactee: value.transactee.as_deref(),
+            note: value.note.as_deref(),
+        }
+    }
+}
+
+impl Candidate<'_> {
+    /// Same amount, dates at most `days` apart and similar transactee, or similar note when
+    /// there is no transactee
+    pub fn is_duplicate(&self, other: &Candidate, days: i64) -> bool {
+        if self.amount != other.amount || (self.date - other.date).num_days().abs() > days {
+            return false;
+        }
+        match (self.transactee, other.transactee) {
+            (Some(_), Some(_)) => similar_text(self.transactee, other.transactee),
+            _ => similar_text(self.note, other.note),
+        }
+    }
+}
+
+/// Finds groups of likely duplicate transactions, ignoring pairs that were dismissed. Each group
+/// is sorted by date, as are the groups by their first transaction.
+pub(crate) fn find_duplicates(
+    transactions: Vec<Transaction>,
+    days: i64,
+    dismissed: &HashSet<(i32, i32)>,
+) -> Vec<Vec<Transaction>> {
+    let mut transactions = transactions;
+    transactions.sort_by(|a, b| a.amount.cmp(&b.amount).then_with(|| a.cmp(b)));
+
+    // Union-find over indexes into `transactions`
+    let mut parents: Vec<usize> = (0..transactions.len()).collect();
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+
+    for i in 0..transactions.len() {
+        let candidate = Candidate::from(&transactions[i]);
+        for j in (i + 1)..transactions.len() {
+            let other = &transactions[j];
+            // Sorted by amount then date, so nothing further along can match
+            if other.amount != candidate.amount || (other.date - candidate.date).num_days() > days {
+                break;
+            }
+            let pair = (
+                transactions[i].id.min(other.id),
+                transactions[i].id.max(other.id),
+            );
+            if !dismissed.contains(&pair) && candidate.is_duplicate(&other.into(), days) {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[a] = b;
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<Transaction>> = HashMap::new();
+    for (i, transaction) in transactions.into_iter().enumerate() {
+        let r = root(&mut parents, i);
+        groups.entry(r).or_default().push(transaction);
+    }
+
+    let mut groups: Vec<Vec<Transaction>> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort();
+            group
+        })
+        .collect();
+    groups.sort_by(|a, b| a[0].cmp(&b[0]));
+    groups
+}
+
+/// Finds existing transactions that the new transaction would likely be a duplicate of
+pub(crate) async fn find_possible_duplicates(
+    transaction_repo: &dyn TransactionRepo,
+    user_id: &str,
+    new_transaction: &NewTransaction,
+) -> Result<Vec<Transaction>, HandlerError> {
+    let filter = Filter::new(
+        Some(new_transaction.date - Duration::days(DEFAULT_DAYS)),
+        Some(new_transaction.date + Duration::days(DEFAULT_DAYS)),
+        None,
+        None,
+    );
+    let candidate = Candidate {
+        date: new_transaction.date,
+        amount: new_transaction.amount,
+        transactee: new_transaction.transactee.as_deref(),
+        note: new_transaction.note.as_deref(),
+    };
+
+    let duplicates = transaction_repo
+        .get_all_transactions(user_id, filter, None)
+        .await?
+        .into_iter()
+        .filter(|t| candidate.is_duplicate(&t.into(), DEFAULT_DAYS))
+        .collect();
+    Ok(duplicates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::similar_text;
+
+    #[test]
+    async fn test_similar_text() {
+        assert!(similar_text(Some("Amazon"), Some("AMAZON Marketplace")));
+        assert!(similar_text(
+            Some("Whole Foods Market"),
+            Some("whole foods #123")
+        ));
+        assert!(similar_text(None, Some("Amazon")));
+        assert!(!similar_text(Some("Amazon"), Some("Whole Foods")));
+    }
+}
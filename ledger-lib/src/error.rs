@@ -1,8 +1,22 @@
 use actix_web::http::StatusCode;
 use actix_web::ResponseError;
+use ledger_repo::api_key_repo::ApiKeyRepoError;
+use ledger_repo::category_repo::CategoryRepoError;
+use ledger_repo::duplicate_repo::DuplicateRepoError;
+use ledger_repo::history_repo::HistoryRepoError;
+use ledger_repo::idempotency_repo::IdempotencyRepoError;
+use ledger_repo::ledger_repo::LedgerRepoError;
+use ledger_repo::oidc_repo::OidcRepoError;
+use ledger_repo::rate_limit_repo::RateLimitRepoError;
+use ledger_repo::refresh_token_repo::RefreshTokenRepoError;
+use ledger_repo::rule_repo::RuleRepoError;
+use ledger_repo::session_repo::SessionRepoError;
+use ledger_repo::totp_repo::TotpRepoError;
+use ledger_repo::transactee_repo::TransacteeRepoError;
 use ledger_repo::transaction_repo::TransactionRepoError;
 use ledger_repo::transaction_template_repo::TransactionTemplateRepoError;
 use ledger_repo::user_repo::UserRepoError;
+use ledger_repo::webhook_repo::WebhookRepoError;
 use std::fmt::Debug;
 use thiserror::Error;
 
@@ -17,11 +31,45 @@ pub enum HandlerError {
     #[error(transparent)]
     TemplateNotFoundError(TransactionTemplateRepoError),
     #[error(transparent)]
+    TransactionModifiedError(TransactionRepoError),
+    #[error(transparent)]
+    TemplateModifiedError(TransactionTemplateRepoError),
+    #[error(transparent)]
+    LedgerNotFoundError(LedgerRepoError),
+    #[error(transparent)]
+    CategoryNotFoundError(CategoryRepoError),
+    #[error(transparent)]
+    TransacteeNotFoundError(TransacteeRepoError),
+    #[error(transparent)]
+    RuleNotFoundError(RuleRepoError),
+    #[error(transparent)]
+    ChangeNotFoundError(HistoryRepoError),
+    #[error(transparent)]
+    WebhookNotFoundError(WebhookRepoError),
+    #[error(transparent)]
+    SessionNotFoundError(SessionRepoError),
+    #[error(transparent)]
+    ApiKeyNotFoundError(ApiKeyRepoError),
+    #[error(transparent)]
+    TotpNotFoundError(TotpRepoError),
+    #[error(transparent)]
     UserNotFoundError(UserRepoError),
     #[error(transparent)]
     UserAlreadyExists(UserRepoError),
+    #[error(transparent)]
+    InviteCodeNotFoundError(UserRepoError),
+    #[error(transparent)]
+    InvalidRefreshToken(RefreshTokenRepoError),
     #[error("{0}")]
     BadRequest(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    UnprocessableEntity(String),
+    #[error("{0}")]
+    Gone(String),
 }
 
 impl From<TransactionRepoError> for HandlerError {
@@ -30,6 +78,8 @@ impl From<TransactionRepoError> for HandlerError {
             TransactionRepoError::TransactionNotFound(_) => {
                 HandlerError::TransactionNotFoundError(e)
             }
+            TransactionRepoError::VersionMismatch(_) => HandlerError::TransactionModifiedError(e),
+            TransactionRepoError::NotPermitted => HandlerError::Forbidden(e.to_string()),
             TransactionRepoError::Other(e) => HandlerError::OtherError(e),
         }
     }
@@ -41,16 +91,156 @@ impl From<TransactionTemplateRepoError> for HandlerError {
             TransactionTemplateRepoError::TemplateNotFound(_) => {
                 HandlerError::TemplateNotFoundError(value)
             }
+            TransactionTemplateRepoError::VersionMismatch(_) => {
+                HandlerError::TemplateModifiedError(value)
+            }
+            TransactionTemplateRepoError::NotPermitted => {
+                HandlerError::Forbidden(value.to_string())
+            }
             TransactionTemplateRepoError::Other(e) => HandlerError::OtherError(e),
         }
     }
 }
 
+impl From<LedgerRepoError> for HandlerError {
+    fn from(value: LedgerRepoError) -> Self {
+        match value {
+            LedgerRepoError::LedgerNotFound(_)
+            | LedgerRepoError::MemberNotFound(_)
+            | LedgerRepoError::InvitationNotFound => HandlerError::LedgerNotFoundError(value),
+            LedgerRepoError::AlreadyMember(_) => HandlerError::Conflict(value.to_string()),
+            LedgerRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<CategoryRepoError> for HandlerError {
+    fn from(value: CategoryRepoError) -> Self {
+        match value {
+            CategoryRepoError::CategoryNotFound(_) => HandlerError::CategoryNotFoundError(value),
+            CategoryRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<TransacteeRepoError> for HandlerError {
+    fn from(value: TransacteeRepoError) -> Self {
+        match value {
+            TransacteeRepoError::TransacteeNotFound(_) => {
+                HandlerError::TransacteeNotFoundError(value)
+            }
+            TransacteeRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<RuleRepoError> for HandlerError {
+    fn from(value: RuleRepoError) -> Self {
+        match value {
+            RuleRepoError::RuleNotFound(_) => HandlerError::RuleNotFoundError(value),
+            RuleRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<DuplicateRepoError> for HandlerError {
+    fn from(value: DuplicateRepoError) -> Self {
+        match value {
+            DuplicateRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<HistoryRepoError> for HandlerError {
+    fn from(value: HistoryRepoError) -> Self {
+        match value {
+            HistoryRepoError::ChangeNotFound(_) => HandlerError::ChangeNotFoundError(value),
+            HistoryRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<IdempotencyRepoError> for HandlerError {
+    fn from(value: IdempotencyRepoError) -> Self {
+        match value {
+            IdempotencyRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<WebhookRepoError> for HandlerError {
+    fn from(value: WebhookRepoError) -> Self {
+        match value {
+            WebhookRepoError::WebhookNotFound(_) => HandlerError::WebhookNotFoundError(value),
+            WebhookRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<RateLimitRepoError> for HandlerError {
+    fn from(value: RateLimitRepoError) -> Self {
+        match value {
+            RateLimitRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<OidcRepoError> for HandlerError {
+    fn from(value: OidcRepoError) -> Self {
+        match value {
+            OidcRepoError::IdentityAlreadyLinked(_, _) | OidcRepoError::UserAlreadyExists(_) => {
+                HandlerError::Conflict(value.to_string())
+            }
+            OidcRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<RefreshTokenRepoError> for HandlerError {
+    fn from(value: RefreshTokenRepoError) -> Self {
+        match value {
+            RefreshTokenRepoError::TokenNotFound | RefreshTokenRepoError::TokenReused(_) => {
+                HandlerError::InvalidRefreshToken(value)
+            }
+            RefreshTokenRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<SessionRepoError> for HandlerError {
+    fn from(value: SessionRepoError) -> Self {
+        match value {
+            SessionRepoError::SessionNotFound(_) => HandlerError::SessionNotFoundError(value),
+            SessionRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<ApiKeyRepoError> for HandlerError {
+    fn from(value: ApiKeyRepoError) -> Self {
+        match value {
+            ApiKeyRepoError::ApiKeyNotFound(_) => HandlerError::ApiKeyNotFoundError(value),
+            ApiKeyRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
+impl From<TotpRepoError> for HandlerError {
+    fn from(value: TotpRepoError) -> Self {
+        match value {
+            TotpRepoError::TotpNotFound(_) => HandlerError::TotpNotFoundError(value),
+            TotpRepoError::Other(e) => HandlerError::OtherError(e),
+        }
+    }
+}
+
 impl From<UserRepoError> for HandlerError {
     fn from(e: UserRepoError) -> Self {
         match e {
             UserRepoError::UserNotFound(_) => HandlerError::UserNotFoundError(e),
             UserRepoError::UserAlreadyExists(_) => HandlerError::UserAlreadyExists(e),
+            UserRepoError::InviteCodeNotFound(_) => HandlerError::InviteCodeNotFoundError(e),
+            UserRepoError::InvalidInviteCode => HandlerError::Forbidden(e.to_string()),
             UserRepoError::Other(e) => HandlerError::OtherError(e),
         }
     }
@@ -59,10 +249,27 @@ impl From<UserRepoError> for HandlerError {
 impl ResponseError for HandlerError {
     fn status_code(&self) -> StatusCode {
         match self {
-            HandlerError::TransactionNotFoundError(_) | HandlerError::UserNotFoundError(_) => {
-                StatusCode::NOT_FOUND
+            HandlerError::TransactionNotFoundError(_)
+            | HandlerError::TemplateNotFoundError(_)
+            | HandlerError::LedgerNotFoundError(_)
+            | HandlerError::CategoryNotFoundError(_)
+            | HandlerError::TransacteeNotFoundError(_)
+            | HandlerError::RuleNotFoundError(_)
+            | HandlerError::ChangeNotFoundError(_)
+            | HandlerError::WebhookNotFoundError(_)
+            | HandlerError::SessionNotFoundError(_)
+            | HandlerError::ApiKeyNotFoundError(_)
+            | HandlerError::TotpNotFoundError(_)
+            | HandlerError::UserNotFoundError(_)
+            | HandlerError::InviteCodeNotFoundError(_) => StatusCode::NOT_FOUND,
+            HandlerError::TransactionModifiedError(_) | HandlerError::TemplateModifiedError(_) => {
+                StatusCode::PRECONDITION_FAILED
             }
-            HandlerError::UserAlreadyExists(_) => StatusCode::CONFLICT,
+            HandlerError::Forbidden(_) => StatusCode::FORBIDDEN,
+            HandlerError::UserAlreadyExists(_) | HandlerError::Conflict(_) => StatusCode::CONFLICT,
+            HandlerError::InvalidRefreshToken(_) => StatusCode::UNAUTHORIZED,
+            HandlerError::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            HandlerError::Gone(_) => StatusCode::GONE,
             HandlerError::BadRequest(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
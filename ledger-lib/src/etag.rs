@@ -0,0 +1,83 @@
+//! Optimistic concurrency for transactions and templates. Their version is used as a strong
+//! `ETag`, and writes with an `If-Match` header only go through if it matches the current version.
+
+use actix_web::http::header::{ETag, EntityTag, IfMatch};
+
+pub(crate) fn etag(version: i32) -> ETag {
+    ETag(EntityTag::new_strong(version.to_string()))
+}
+
+/// The `If-Match` precondition of a request did not match the current version
+pub(crate) struct PreconditionFailed;
+
+/// Whether the request has an `If-Match` header. A missing header is parsed as an empty list.
+pub(crate) fn has_precondition(if_match: &IfMatch) -> bool {
+    !matches!(if_match, IfMatch::Items(tags) if tags.is_empty())
+}
+
+/// Checks the request's `If-Match` header against `current_version`. Returns the version the write
+/// has to be made against so that the check is repeated atomically by the repo, or `None` if the
+/// request has no precondition.
+pub(crate) fn expected_version(
+    if_match: &IfMatch,
+    current_version: i32,
+) -> Result<Option<i32>, PreconditionFailed> {
+    match if_match {
+        IfMatch::Any => Ok(Some(current_version)),
+        IfMatch::Items(tags) if tags.is_empty() => Ok(None),
+        IfMatch::Items(tags) => {
+            if tags
+                .iter()
+                .any(|tag| tag.strong_eq(&etag(current_version).0))
+            {
+                Ok(Some(current_version))
+            } else {
+                Err(PreconditionFailed)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expected_version, PreconditionFailed};
+    use actix_web::http::header::{EntityTag, IfMatch};
+
+    #[test]
+    async fn no_precondition() {
+        assert!(matches!(
+            expected_version(&IfMatch::Items(Vec::new()), 3),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    async fn any_matches_current_version() {
+        let if_match = IfMatch::Any;
+        assert!(matches!(expected_version(&if_match, 3), Ok(Some(3))));
+    }
+
+    #[test]
+    async fn matching_tag() {
+        let if_match = IfMatch::Items(vec![
+            EntityTag::new_strong("2".to_string()),
+            EntityTag::new_strong("3".to_string()),
+        ]);
+        assert!(matches!(expected_version(&if_match, 3), Ok(Some(3))));
+    }
+
+    #[test]
+    async fn stale_or_weak_tag() {
+        let if_match = IfMatch::Items(vec![EntityTag::new_strong("2".to_string())]);
+        assert!(matches!(
+            expected_version(&if_match, 3),
+            Err(PreconditionFailed)
+        ));
+
+        let if_match = IfMatch::Items(vec![EntityTag::new_weak("3".to_string())]);
+        assert!(matches!(
+            expected_version(&if_match, 3),
+            Err(PreconditionFailed)
+        ));
+    }
+}
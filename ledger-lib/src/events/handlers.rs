@@ -0,0 +1,178 @@
+use crate::error::HandlerError;
+use crate::events::EventBus;
+use crate::sync::{ensure_not_purged, SyncToken};
+use crate::user::UserId;
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::rt::time::timeout;
+use actix_web::web::Bytes;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use futures_util::stream;
+use ledger_repo::transaction_repo::TransactionRepo;
+use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::error;
+
+/// Comments are sent when nothing has changed for this long, so proxies don't close the stream
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    token: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Action {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl Action {
+    fn of(version: i32, deleted: bool) -> Action {
+        if deleted {
+            Action::Deleted
+        } else if version == 1 {
+            Action::Created
+        } else {
+            Action::Updated
+        }
+    }
+}
+
+/// The event's id is a sync token. Clients that reconnect with it in `Last-Event-ID`, or pass a
+/// token from `GET /sync` as `token`, receive everything that changed after it. Without a token,
+/// only changes made after connecting are sent.
+#[get("")]
+pub async fn stream_events(
+    event_bus: web::Data<EventBus>,
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    user_id: web::ReqData<UserId>,
+    query: web::Query<EventsQuery>,
+    request: HttpRequest,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    // Subscribe before reading the feed, so changes made in between are not missed
+    let receiver = event_bus.subscribe();
+
+    let last_event_id = request
+        .headers()
+        .get(LAST_EVENT_ID_HEADER)
+        .map(|id| id.to_str().unwrap_or_default().to_string());
+    let mut events = EventStream {
+        user_id,
+        since: 0,
+        transaction_repo: transaction_repo.get_ref().clone(),
+        template_repo: template_repo.get_ref().clone(),
+        pending: VecDeque::new(),
+    };
+    match last_event_id.or(query.into_inner().token) {
+        Some(token) => {
+            let SyncToken(since) = SyncToken::decode(&token)?;
+            ensure_not_purged(
+                &*events.transaction_repo,
+                &*events.template_repo,
+                &events.user_id,
+                since,
+            )
+            .await?;
+            events.since = since;
+            events.load_changes().await?;
+        }
+        None => {
+            events.load_changes().await?;
+            events.pending.clear();
+        }
+    }
+
+    let body = stream::unfold(
+        (events, receiver),
+        |(mut events, mut receiver)| async move {
+            loop {
+                if let Some(event) = events.pending.pop_front() {
+                    return Some((Ok::<_, Infallible>(event), (events, receiver)));
+                }
+                match timeout(HEARTBEAT_INTERVAL, receiver.recv()).await {
+                    Err(_) => {
+                        let heartbeat = Bytes::from_static(b": heartbeat\n\n");
+                        return Some((Ok(heartbeat), (events, receiver)));
+                    }
+                    Ok(Ok(user_id)) if user_id != events.user_id => continue,
+                    // A stream that fell behind may have missed its user's changes
+                    Ok(Ok(_)) | Ok(Err(RecvError::Lagged(_))) => {
+                        if let Err(e) = events.load_changes().await {
+                            error!(%e, "Unable to load changes for event stream");
+                            return None;
+                        }
+                    }
+                    Ok(Err(RecvError::Closed)) => return None,
+                }
+            }
+        },
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(body))
+}
+
+struct EventStream {
+    user_id: UserId,
+    /// The latest change that has been queued
+    since: i64,
+    transaction_repo: Arc<dyn TransactionRepo>,
+    template_repo: Arc<dyn TransactionTemplateRepo>,
+    pending: VecDeque<Bytes>,
+}
+
+impl EventStream {
+    /// Queues events for everything that changed after `since`, in the order the changes were
+    /// made
+    async fn load_changes(&mut self) -> Result<(), HandlerError> {
+        let transactions = self
+            .transaction_repo
+            .get_changed_transactions(&self.user_id, self.since)
+            .await?;
+        let templates = self
+            .template_repo
+            .get_changed_templates(&self.user_id, self.since)
+            .await?;
+
+        let mut changes = Vec::with_capacity(transactions.len() + templates.len());
+        for changed in transactions {
+            let data = serde_json::json!({
+                "action": Action::of(changed.transaction.version, changed.deleted),
+                "transaction": changed.transaction,
+            });
+            changes.push((changed.change, "transaction", data));
+        }
+        for changed in templates {
+            let data = serde_json::json!({
+                "action": Action::of(changed.template.version, changed.deleted),
+                "template": changed.template,
+            });
+            changes.push((changed.change, "template", data));
+        }
+        changes.sort_by_key(|(change, _, _)| *change);
+
+        for (change, event, data) in changes {
+            self.pending.push_back(Bytes::from(format!(
+                "id: {}\nevent: {}\ndata: {}\n\n",
+                SyncToken(change).encode(),
+                event,
+                data
+            )));
+            self.since = change;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+mod handlers;
+
+use crate::ledger::ledger_members;
+use crate::user::UserId;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use actix_web::web::Data;
+use actix_web::{web, HttpMessage, Scope};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use ledger_repo::ledger_repo::LedgerRepo;
+use ledger_repo::sqlx_repo::ChangeListener;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::error;
+
+/// Number of changes that can be waiting for a slow stream before it falls behind. A stream that
+/// falls behind reloads its changes from the change feed, so nothing is lost.
+const EVENT_BUS_CAPACITY: usize = 256;
+
+/// Streams create, update and delete events for the user's transactions and templates as they
+/// happen
+pub fn events_service() -> Scope {
+    web::scope("/events").service(handlers::stream_events)
+}
+
+/// Tells open event streams that a user's transactions or templates may have changed. Streams
+/// find out what changed from the change feed, so the bus only carries user ids.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<UserId>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        EventBus { sender }
+    }
+
+    pub fn publish(&self, user_id: UserId) {
+        // Sending only fails if no streams are open
+        let _ = self.sender.send(user_id);
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<UserId> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new()
+    }
+}
+
+/// Publishes changes made through other servers sharing the database, until the listener fails
+pub async fn forward_database_changes(mut listener: ChangeListener, event_bus: EventBus) {
+    loop {
+        match listener.recv().await {
+            Ok(user_id) => event_bus.publish(user_id),
+            Err(e) => {
+                error!(%e, "Stopped forwarding database changes");
+                return;
+            }
+        }
+    }
+}
+
+/// Middleware that publishes to the [EventBus] after each successful request that may have changed
+/// the user's transactions or templates, for every member of the user's current ledger. Must be
+/// wrapped by the authentication middleware.
+pub struct PublishChanges;
+
+impl<S, B> Transform<S, ServiceRequest> for PublishChanges
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = PublishChangesMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(PublishChangesMiddleware { service }))
+    }
+}
+
+pub struct PublishChangesMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for PublishChangesMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            let request = res.request();
+            if request.method() != Method::GET && res.status().is_success() {
+                let event_bus = request.app_data::<Data<EventBus>>().cloned();
+                let ledger_repo = request.app_data::<Data<Arc<dyn LedgerRepo>>>().cloned();
+                let user_id = request.extensions().get::<UserId>().cloned();
+                if let (Some(event_bus), Some(user_id)) = (event_bus, user_id) {
+                    let members = match ledger_repo {
+                        Some(ledger_repo) => {
+                            ledger_members(ledger_repo.as_ref().as_ref(), &user_id)
+                                .await
+                                .unwrap_or_else(|e| {
+                                    error!(%e, "Unable to get ledger members");
+                                    vec![]
+                                })
+                        }
+                        None => vec![],
+                    };
+                    // the user is told even if they have no current ledger, e.g. after leaving it
+                    if !members.contains(&user_id) {
+                        event_bus.publish(user_id);
+                    }
+                    for member in members {
+                        event_bus.publish(member);
+                    }
+                }
+            }
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,84 @@
+use crate::error::HandlerError;
+use crate::history::{record_change, Actor, ChangeRecorder};
+use actix_web::{web, HttpResponse, Responder};
+use ledger_repo::history_repo::{ChangeKind, HistoryRepo};
+use ledger_repo::transaction_repo::{TransactionRepo, TransactionRepoError};
+use std::sync::Arc;
+
+#[get("")]
+pub async fn get_transaction_history(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    history_repo: web::Data<Arc<dyn HistoryRepo>>,
+    actor: Actor,
+    transaction_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    let transaction_id = transaction_id.into_inner();
+    // the history is only shown for transactions in the user's current ledger, including its trash
+    match transaction_repo
+        .get_transaction(&actor.user_id, transaction_id)
+        .await
+    {
+        Ok(_) => {}
+        Err(TransactionRepoError::TransactionNotFound(_)) => {
+            let deleted = transaction_repo
+                .get_deleted_transactions(&actor.user_id)
+                .await?;
+            if !deleted.iter().any(|d| d.transaction.id == transaction_id) {
+                return Err(TransactionRepoError::TransactionNotFound(transaction_id).into());
+            }
+        }
+        Err(e) => return Err(e.into()),
+    }
+    let history = history_repo
+        .get_transaction_history(&actor.user_id, transaction_id)
+        .await?;
+    Ok(HttpResponse::Ok().json(history))
+}
+
+/// Sets the transaction back to how it was after the given change. A transaction in the trash is
+/// restored first.
+#[post("/{change_id}/revert")]
+pub async fn revert_transaction(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    history_repo: web::Data<Arc<dyn HistoryRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
+    path: web::Path<(i32, i32)>,
+) -> Result<impl Responder, HandlerError> {
+    let (transaction_id, change_id) = path.into_inner();
+    let change = history_repo
+        .get_change(&actor.user_id, transaction_id, change_id)
+        .await?;
+    let Some(version) = change.after else {
+        return Err(HandlerError::BadRequest(
+            "Cannot revert to a deleted version, restore the transaction instead".to_string(),
+        ));
+    };
+
+    let current = match transaction_repo
+        .get_transaction(&actor.user_id, transaction_id)
+        .await
+    {
+        Ok(transaction) => transaction,
+        Err(TransactionRepoError::TransactionNotFound(_)) => {
+            transaction_repo
+                .restore_transaction(&actor.user_id, transaction_id)
+                .await?
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let transaction = transaction_repo
+        .update_transaction(&actor.user_id, transaction_id, version.into(), None)
+        .await?;
+    record_change(
+        &changes,
+        &actor,
+        ChangeKind::Revert,
+        Some(current),
+        Some(transaction.clone()),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(transaction))
+}
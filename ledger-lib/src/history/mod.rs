@@ -0,0 +1,130 @@
+mod handlers;
+
+use crate::error::HandlerError;
+use crate::ledger::ledger_members;
+use crate::user::UserId;
+use crate::webhook::queue_transaction_event;
+use actix_web::dev::Payload;
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::header;
+use actix_web::web::Data;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, Scope};
+use ledger_repo::history_repo::{ChangeKind, HistoryRepo, NewTransactionChange};
+use ledger_repo::ledger_repo::LedgerRepo;
+use ledger_repo::transaction_repo::{Transaction, UpdatedTransaction};
+use ledger_repo::webhook_repo::WebhookRepo;
+use std::future::{ready, Ready};
+use std::sync::Arc;
+
+/// Transaction history endpoints, nested under the transaction service
+pub fn history_service() -> Scope {
+    web::scope("/{transaction_id}/history")
+        .service(handlers::get_transaction_history)
+        .service(handlers::revert_transaction)
+}
+
+/// Who is making a request, recorded with every change to a transaction. The client is taken from
+/// the `User-Agent` header.
+pub(crate) struct Actor {
+    pub user_id: UserId,
+    pub client: Option<String>,
+}
+
+impl FromRequest for Actor {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let Some(user_id) = req.extensions().get::<UserId>().cloned() else {
+            return ready(Err(ErrorInternalServerError(
+                "Missing user id for authenticated request",
+            )));
+        };
+        let client = req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|ua| ua.to_str().ok())
+            .map(|ua| ua.to_owned());
+        ready(Ok(Actor { user_id, client }))
+    }
+}
+
+/// Where changes to transactions go: the transaction's history, and the webhooks of the members of
+/// the ledger
+pub(crate) struct ChangeRecorder {
+    history_repo: Arc<dyn HistoryRepo>,
+    webhook_repo: Arc<dyn WebhookRepo>,
+    ledger_repo: Arc<dyn LedgerRepo>,
+}
+
+impl FromRequest for ChangeRecorder {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let (Some(history_repo), Some(webhook_repo), Some(ledger_repo)) = (
+            req.app_data::<Data<Arc<dyn HistoryRepo>>>(),
+            req.app_data::<Data<Arc<dyn WebhookRepo>>>(),
+            req.app_data::<Data<Arc<dyn LedgerRepo>>>(),
+        ) else {
+            return ready(Err(ErrorInternalServerError(
+                "History, webhook or ledger repo is not configured",
+            )));
+        };
+        ready(Ok(ChangeRecorder {
+            history_repo: history_repo.get_ref().clone(),
+            webhook_repo: webhook_repo.get_ref().clone(),
+            ledger_repo: ledger_repo.get_ref().clone(),
+        }))
+    }
+}
+
+pub(crate) async fn record_change(
+    changes: &ChangeRecorder,
+    actor: &Actor,
+    kind: ChangeKind,
+    before: Option<Transaction>,
+    after: Option<Transaction>,
+) -> Result<(), HandlerError> {
+    let transaction = after
+        .as_ref()
+        .or(before.as_ref())
+        .expect("a change should have a transaction before or after it")
+        .clone();
+    let change = NewTransactionChange {
+        transaction_id: transaction.id,
+        kind,
+        before,
+        after,
+        changed_by: actor.user_id.clone(),
+        client: actor.client.clone(),
+    };
+    changes
+        .history_repo
+        .record_change(&actor.user_id, change)
+        .await?;
+    for member in ledger_members(&*changes.ledger_repo, &actor.user_id).await? {
+        queue_transaction_event(&*changes.webhook_repo, &member, kind, &transaction).await?;
+    }
+    Ok(())
+}
+
+/// Records an update for each transaction changed by an operation that changes many transactions
+/// at once
+pub(crate) async fn record_updates(
+    changes: &ChangeRecorder,
+    actor: &Actor,
+    updated: Vec<UpdatedTransaction>,
+) -> Result<(), HandlerError> {
+    for UpdatedTransaction { before, after } in updated {
+        record_change(
+            changes,
+            actor,
+            ChangeKind::Update,
+            Some(before),
+            Some(after),
+        )
+        .await?;
+    }
+    Ok(())
+}
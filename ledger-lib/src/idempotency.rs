@@ -0,0 +1,261 @@
+//! Support for the `Idempotency-Key` header on create endpoints. The response to the first
+//! request with a key is stored, and retries with the same key within the [IdempotencyWindow]
+//! get that response back instead of creating the resource again. Reusing a key for a request
+//! with a different body is an error.
+
+use crate::error::HandlerError;
+use actix_web::dev::Payload;
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError};
+use actix_web::http::StatusCode;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpRequest, HttpResponse};
+use chrono::{Duration, Utc};
+use futures_util::future::{select, Either};
+use futures_util::pin_mut;
+use ledger_repo::idempotency_repo::{IdempotencyRecord, IdempotencyRepo, StoredResponse};
+use ledger_repo::Repos;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::future::{ready, Future, Ready};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use tracing::{error, info};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+/// Set on responses that are replayed from an earlier request with the same key
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+const MAX_KEY_LENGTH: usize = 255;
+
+/// How often stored responses that are past the idempotency window are deleted
+pub const PURGE_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+/// How long a request may go without renewing its claim on a key before the claim is treated as
+/// abandoned, e.g. because the handler panicked or the server handling it died
+const CLAIM_LEASE_SECONDS: i64 = 60;
+
+/// How often a request that is still in progress renews its claim on its key
+const CLAIM_RENEWAL_INTERVAL: StdDuration = StdDuration::from_secs(20);
+
+/// How long the response to a request with an idempotency key is kept
+#[derive(Clone, Copy, Debug)]
+pub struct IdempotencyWindow(pub Duration);
+
+impl IdempotencyWindow {
+    pub fn hours(hours: i64) -> IdempotencyWindow {
+        IdempotencyWindow(Duration::hours(hours))
+    }
+}
+
+impl Default for IdempotencyWindow {
+    fn default() -> Self {
+        IdempotencyWindow::hours(24)
+    }
+}
+
+/// The `Idempotency-Key` header of a request, along with what's needed to store the response to
+/// it
+pub(crate) struct Idempotency {
+    key: Option<String>,
+    request_path: String,
+    repo: Data<Arc<dyn IdempotencyRepo>>,
+    window: IdempotencyWindow,
+}
+
+impl FromRequest for Idempotency {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let (Some(repo), Some(window)) = (
+            req.app_data::<Data<Arc<dyn IdempotencyRepo>>>(),
+            req.app_data::<Data<IdempotencyWindow>>(),
+        ) else {
+            return ready(Err(ErrorInternalServerError(
+                "Idempotency repo is not configured",
+            )));
+        };
+        let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+            None => None,
+            Some(key) => match key.to_str() {
+                Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => Some(key.to_owned()),
+                _ => {
+                    return ready(Err(ErrorBadRequest(format!(
+                        "{} must be between 1 and {} visible ASCII characters",
+                        IDEMPOTENCY_KEY_HEADER, MAX_KEY_LENGTH
+                    ))))
+                }
+            },
+        };
+        ready(Ok(Idempotency {
+            key,
+            request_path: req.path().to_owned(),
+            repo: repo.clone(),
+            window: ***window,
+        }))
+    }
+}
+
+impl Idempotency {
+    /// Identifies the request by its `body` as well as its path
+    pub(crate) fn request<B: Serialize>(self, body: &B) -> Result<IdempotentRequest, HandlerError> {
+        let body = serde_json::to_vec(body).map_err(anyhow::Error::from)?;
+        Ok(IdempotentRequest {
+            idempotency: self,
+            request_hash: hex::encode(Sha256::digest(body)),
+        })
+    }
+}
+
+/// A request that may have an `Idempotency-Key` header, along with the hash of its body
+pub(crate) struct IdempotentRequest {
+    idempotency: Idempotency,
+    request_hash: String,
+}
+
+impl IdempotentRequest {
+    /// Runs `create` unless the request's idempotency key was already used, in which case the
+    /// stored response is returned. Failed requests aren't stored so they can be retried with the
+    /// same key.
+    pub(crate) async fn run<T, F>(
+        self,
+        user_id: &str,
+        create: F,
+    ) -> Result<HttpResponse, HandlerError>
+    where
+        T: Serialize,
+        F: Future<Output = Result<T, HandlerError>>,
+    {
+        let Idempotency {
+            key,
+            request_path,
+            repo,
+            window,
+        } = self.idempotency;
+        let Some(key) = key else {
+            return Ok(HttpResponse::Ok().json(create.await?));
+        };
+
+        let now = Utc::now();
+        match repo
+            .start_request(
+                user_id,
+                &key,
+                &request_path,
+                &self.request_hash,
+                now - window.0,
+                now - Duration::seconds(CLAIM_LEASE_SECONDS),
+            )
+            .await?
+        {
+            None => {}
+            Some(IdempotencyRecord {
+                request_path: previous_path,
+                ..
+            }) if previous_path != request_path => {
+                return Err(HandlerError::UnprocessableEntity(format!(
+                    "{} was already used for a request to {}",
+                    IDEMPOTENCY_KEY_HEADER, previous_path
+                )));
+            }
+            Some(IdempotencyRecord {
+                request_hash: Some(previous_hash),
+                ..
+            }) if previous_hash != self.request_hash => {
+                return Err(HandlerError::UnprocessableEntity(format!(
+                    "{} was already used for a request with a different body",
+                    IDEMPOTENCY_KEY_HEADER
+                )));
+            }
+            Some(IdempotencyRecord { response: None, .. }) => {
+                return Err(HandlerError::Conflict(format!(
+                    "A request with this {} is still being processed",
+                    IDEMPOTENCY_KEY_HEADER
+                )));
+            }
+            Some(IdempotencyRecord {
+                response: Some(response),
+                ..
+            }) => {
+                let status = StatusCode::from_u16(response.status).map_err(anyhow::Error::from)?;
+                return Ok(HttpResponse::build(status)
+                    .content_type("application/json")
+                    .insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"))
+                    .body(response.body));
+            }
+        }
+
+        let created = match renewing_claim(&***repo, user_id, &key, create).await {
+            Ok(created) => created,
+            Err(e) => {
+                repo.abandon_request(user_id, &key).await?;
+                return Err(e);
+            }
+        };
+        let body = serde_json::to_string(&created).map_err(anyhow::Error::from)?;
+        repo.complete_request(
+            user_id,
+            &key,
+            StoredResponse {
+                status: StatusCode::OK.as_u16(),
+                body: body.clone(),
+            },
+        )
+        .await?;
+        Ok(HttpResponse::Ok()
+            .content_type("application/json")
+            .body(body))
+    }
+}
+
+/// Runs `create`, renewing the claim on `key` until it finishes so that slow requests keep their
+/// claim for as long as they are running
+async fn renewing_claim<F: Future>(
+    repo: &dyn IdempotencyRepo,
+    user_id: &str,
+    key: &str,
+    create: F,
+) -> F::Output {
+    let renew = async {
+        let mut interval = actix_web::rt::time::interval(CLAIM_RENEWAL_INTERVAL);
+        // the first tick completes immediately, right after the key was claimed
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = repo.renew_request(user_id, key).await {
+                error!(%e, "Unable to renew idempotency key claim");
+            }
+        }
+    };
+    pin_mut!(create, renew);
+    match select(create, renew).await {
+        Either::Left((created, _)) => created,
+        Either::Right(_) => unreachable!("renewing the claim never finishes"),
+    }
+}
+
+/// Deletes the stored responses that are past the idempotency window
+pub async fn purge_expired_keys(
+    repos: &Repos,
+    window: IdempotencyWindow,
+) -> Result<(), anyhow::Error> {
+    let keys = repos
+        .idempotency_repo
+        .purge_records(Utc::now() - window.0)
+        .await?;
+    info!(keys, "Purged expired idempotency keys");
+
+    Ok(())
+}
+
+/// Purges expired idempotency keys every [PURGE_INTERVAL]. Errors are logged, so this never
+/// returns.
+pub async fn purge_expired_keys_periodically(repos: Repos, window: IdempotencyWindow) {
+    let mut interval = actix_web::rt::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = purge_expired_keys(&repos, window).await {
+            error!(%e, "Unable to purge idempotency keys");
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::INVITATION_LIFETIME_DAYS;
+use crate::auth;
+use crate::auth::refresh_token;
+use crate::error::HandlerError;
+use crate::user::UserId;
+use ledger_repo::ledger_repo::{Ledger, LedgerRepo, LedgerRole};
+
+#[derive(Deserialize)]
+pub struct NewLedger {
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct CurrentLedger {
+    ledger_id: i32,
+}
+
+#[derive(Deserialize)]
+pub struct NewRole {
+    role: LedgerRole,
+}
+
+#[derive(Deserialize)]
+pub struct InvitationCode {
+    code: String,
+}
+
+/// The only response that includes the code, which is stored hashed
+#[derive(Serialize)]
+pub struct CreatedInvitationResponse {
+    code: String,
+    role: LedgerRole,
+    expires_at: DateTime<Utc>,
+}
+
+/// Gets the ledger, failing unless the user is one of its owners
+async fn owned_ledger(
+    ledger_repo: &dyn LedgerRepo,
+    user_id: &str,
+    ledger_id: i32,
+) -> Result<Ledger, HandlerError> {
+    let ledger = ledger_repo.get_ledger(user_id, ledger_id).await?;
+    if ledger.role != LedgerRole::Owner {
+        return Err(HandlerError::Forbidden(
+            "Only owners can manage the ledger".to_string(),
+        ));
+    }
+    Ok(ledger)
+}
+
+/// Fails if the member is the ledger's only owner, who can't leave or stop being an owner
+async fn ensure_other_owner(
+    ledger_repo: &dyn LedgerRepo,
+    ledger_id: i32,
+    user_id: &str,
+) -> Result<(), HandlerError> {
+    let members = ledger_repo.get_members(ledger_id).await?;
+    let is_owner = |id: &str| {
+        members
+            .iter()
+            .any(|m| m.user_id == id && m.role == LedgerRole::Owner)
+    };
+    let other_owner = members
+        .iter()
+        .any(|m| m.user_id != user_id && m.role == LedgerRole::Owner);
+    if is_owner(user_id) && !other_owner {
+        return Err(HandlerError::Conflict(
+            "A ledger must have at least one owner".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[get("")]
+pub async fn get_ledgers(
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let ledgers = ledger_repo.get_ledgers(&user_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ledgers))
+}
+
+#[post("")]
+pub async fn create_ledger(
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+    new_ledger: web::Json<NewLedger>,
+) -> Result<impl Responder, HandlerError> {
+    let name = new_ledger.name.trim();
+    if name.is_empty() {
+        return Err(HandlerError::BadRequest(
+            "Ledger name must not be empty".to_string(),
+        ));
+    }
+    let ledger = ledger_repo
+        .create_ledger(&user_id.into_inner(), name)
+        .await?;
+    Ok(HttpResponse::Ok().json(ledger))
+}
+
+/// Switches the ledger that transaction and template requests act on. Clients have to sync from
+/// the start afterwards.
+#[put("/current")]
+pub async fn set_current_ledger(
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+    current: web::Json<CurrentLedger>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    ledger_repo
+        .set_current_ledger(&user_id, current.ledger_id)
+        .await?;
+    let ledger = ledger_repo.get_ledger(&user_id, current.ledger_id).await?;
+    Ok(HttpResponse::Ok().json(ledger))
+}
+
+#[post("/join")]
+pub async fn join_ledger(
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+    invitation: web::Json<InvitationCode>,
+) -> Result<impl Responder, HandlerError> {
+    let ledger = ledger_repo
+        .accept_invitation(&user_id.into_inner(), &auth::hash_token(&invitation.code))
+        .await?;
+    Ok(HttpResponse::Ok().json(ledger))
+}
+
+#[get("/{ledger_id}")]
+pub async fn get_ledger(
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+    ledger_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    let ledger = ledger_repo
+        .get_ledger(&user_id.into_inner(), ledger_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(ledger))
+}
+
+/// Deletes the ledger along with its transactions and templates. Owners have to switch to another
+/// ledger first, so they aren't left without one.
+#[delete("/{ledger_id}")]
+pub async fn delete_ledger(
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+    ledger_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    let ledger = owned_ledger(
+        ledger_repo.as_ref().as_ref(),
+        &user_id.into_inner(),
+        *ledger_id,
+    )
+    .await?;
+    if ledger.current {
+        return Err(HandlerError::Conflict(
+            "The current ledger can't be deleted".to_string(),
+        ));
+    }
+    ledger_repo.delete_ledger(ledger.id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[get("/{ledger_id}/members")]
+pub async fn get_members(
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+    ledger_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    let ledger = ledger_repo
+        .get_ledger(&user_id.into_inner(), ledger_id.into_inner())
+        .await?;
+    let members = ledger_repo.get_members(ledger.id).await?;
+    Ok(HttpResponse::Ok().json(members))
+}
+
+#[put("/{ledger_id}/members/{member_id}")]
+pub async fn set_role(
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+    path: web::Path<(i32, String)>,
+    new_role: web::Json<NewRole>,
+) -> Result<impl Responder, HandlerError> {
+    let (ledger_id, member_id) = path.into_inner();
+    owned_ledger(
+        ledger_repo.as_ref().as_ref(),
+        &user_id.into_inner(),
+        ledger_id,
+    )
+    .await?;
+    if new_role.role != LedgerRole::Owner {
+        ensure_other_owner(ledger_repo.as_ref().as_ref(), ledger_id, &member_id).await?;
+    }
+    ledger_repo
+        .set_role(ledger_id, &member_id, new_role.role)
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Owners can remove any member, and members can remove themselves to leave the ledger
+#[delete("/{ledger_id}/members/{member_id}")]
+pub async fn remove_member(
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+    path: web::Path<(i32, String)>,
+) -> Result<impl Responder, HandlerError> {
+    let (ledger_id, member_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+    if member_id == user_id {
+        ledger_repo.get_ledger(&user_id, ledger_id).await?;
+    } else {
+        owned_ledger(ledger_repo.as_ref().as_ref(), &user_id, ledger_id).await?;
+    }
+    ensure_other_owner(ledger_repo.as_ref().as_ref(), ledger_id, &member_id).await?;
+    ledger_repo.remove_member(ledger_id, &member_id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Creates a code that lets whoever it is given to join the ledger with `role`
+#[post("/{ledger_id}/invitations")]
+pub async fn create_invitation(
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+    ledger_id: web::Path<i32>,
+    new_role: web::Json<NewRole>,
+) -> Result<impl Responder, HandlerError> {
+    let ledger = owned_ledger(
+        ledger_repo.as_ref().as_ref(),
+        &user_id.into_inner(),
+        *ledger_id,
+    )
+    .await?;
+
+    let code = refresh_token::generate_token();
+    let expires_at = Utc::now() + Duration::days(INVITATION_LIFETIME_DAYS);
+    ledger_repo
+        .create_invitation(
+            ledger.id,
+            &auth::hash_token(&code),
+            new_role.role,
+            expires_at,
+        )
+        .await?;
+    Ok(HttpResponse::Ok().json(CreatedInvitationResponse {
+        code,
+        role: new_role.role,
+        expires_at,
+    }))
+}
@@ -0,0 +1,51 @@
+mod handlers;
+
+use crate::user::UserId;
+use actix_web::{web, Scope};
+use ledger_repo::ledger_repo::{LedgerRepo, LedgerRepoError};
+
+/// Invitations can be accepted for this many days after they are created
+pub const INVITATION_LIFETIME_DAYS: i64 = 7;
+
+/// Ledgers shared with other users. Transaction and template requests act on the user's current
+/// ledger, which members switch between here.
+pub fn ledger_service() -> Scope {
+    web::scope("/ledgers")
+        .service(handlers::get_ledgers)
+        .service(handlers::create_ledger)
+        .service(handlers::set_current_ledger)
+        .service(handlers::join_ledger)
+        .service(handlers::get_ledger)
+        .service(handlers::delete_ledger)
+        .service(handlers::get_members)
+        .service(handlers::set_role)
+        .service(handlers::remove_member)
+        .service(handlers::create_invitation)
+}
+
+/// The members whose current ledger is the user's, who all see changes the user makes to it
+pub(crate) async fn ledger_members(
+    ledger_repo: &dyn LedgerRepo,
+    user_id: &str,
+) -> Result<Vec<UserId>, LedgerRepoError> {
+    let Some(ledger) = ledger_repo
+        .get_ledgers(user_id)
+        .await?
+        .into_iter()
+        .find(|l| l.current)
+    else {
+        return Ok(vec![]);
+    };
+    let mut members = Vec::new();
+    for member in ledger_repo.get_members(ledger.id).await? {
+        let current = member.user_id == user_id
+            || ledger_repo
+                .get_ledger(&member.user_id, ledger.id)
+                .await?
+                .current;
+        if current {
+            members.push(member.user_id);
+        }
+    }
+    Ok(members)
+}
@@ -1,47 +1,173 @@
 #[macro_use]
 extern crate actix_web;
 
+use crate::auth::api_key::RequireScope;
 use crate::auth::jwt::JWTAuth;
+use crate::auth::oidc::OidcProvider;
+use crate::auth::password::Passwords;
+use crate::auth::SignupMode;
+use crate::client_ip::TrustedProxies;
+use crate::events::{EventBus, PublishChanges};
+use crate::idempotency::IdempotencyWindow;
+use crate::rate_limit::RateLimits;
+use crate::webhook::WebhookTargets;
 use ::tracing::error;
 use actix_web::error::JsonPayloadError;
 use actix_web::web::Data;
 use actix_web::{web, HttpResponse};
 use actix_web_httpauth::middleware::HttpAuthentication;
-use ledger_repo::transaction_repo::TransactionRepo;
-use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
-use ledger_repo::user_repo::UserRepo;
-use ledger_repo::HealthCheck;
+use ledger_repo::api_key_repo::ApiKeyScope;
+use ledger_repo::{HealthCheck, Repos};
 use std::sync::Arc;
 
+pub mod admin;
 pub mod auth;
+mod category;
+pub mod client_ip;
 pub mod config;
+mod duplicate;
 mod error;
+mod etag;
+pub mod events;
+mod history;
+pub mod idempotency;
+pub mod ledger;
+mod merge_patch;
+pub mod rate_limit;
+pub mod rule;
+mod suggestion;
+pub mod sync;
+mod tag;
 pub mod tracing;
+mod transactee;
 pub mod transaction;
 pub mod transaction_template;
+pub mod trash;
 pub mod user;
+pub mod webhook;
 
+#[allow(clippy::too_many_arguments)]
 pub fn app_config_func(
     jwt_auth: JWTAuth,
-    user_repo: Arc<dyn UserRepo>,
-    transaction_repo: Arc<dyn TransactionRepo>,
-    template_repo: Arc<dyn TransactionTemplateRepo>,
-    signups_enabled: bool,
+    repos: Repos,
+    signup_mode: SignupMode,
+    idempotency_window: IdempotencyWindow,
+    event_bus: EventBus,
+    rate_limits: RateLimits,
+    oidc_provider: Option<OidcProvider>,
+    passwords: Passwords,
+    trusted_proxies: TrustedProxies,
+    webhook_targets: WebhookTargets,
 ) -> impl FnOnce(&mut web::ServiceConfig) {
     let bearer_auth_middleware = HttpAuthentication::bearer(auth::credentials_validator);
 
     move |cfg| {
         cfg.app_data(jwt_auth)
-            .app_data(Data::new(user_repo))
-            .app_data(Data::new(transaction_repo))
-            .app_data(Data::new(template_repo))
-            .service(transaction::transaction_service().wrap(bearer_auth_middleware.clone()))
+            .app_data(Data::new(repos.user_repo))
+            .app_data(Data::new(repos.ledger_repo))
+            .app_data(Data::new(repos.transaction_repo))
+            .app_data(Data::new(repos.template_repo))
+            .app_data(Data::new(repos.category_repo))
+            .app_data(Data::new(repos.transactee_repo))
+            .app_data(Data::new(repos.rule_repo))
+            .app_data(Data::new(repos.duplicate_repo))
+            .app_data(Data::new(repos.history_repo))
+            .app_data(Data::new(repos.idempotency_repo))
+            .app_data(Data::new(repos.webhook_repo))
+            .app_data(Data::new(repos.refresh_token_repo))
+            .app_data(Data::new(repos.session_repo))
+            .app_data(Data::new(repos.api_key_repo))
+            .app_data(Data::new(repos.totp_repo))
+            .app_data(Data::new(repos.rate_limit_repo))
+            .app_data(Data::new(repos.oidc_repo))
+            .app_data(Data::new(idempotency_window))
+            .app_data(Data::new(event_bus))
+            .app_data(Data::new(passwords))
+            .app_data(Data::new(trusted_proxies))
+            .app_data(Data::new(webhook_targets))
+            .service(
+                transaction::transaction_service()
+                    .wrap(RequireScope::new(
+                        ApiKeyScope::ReadOnly,
+                        &[ApiKeyScope::TransactionsWrite],
+                    ))
+                    .wrap(PublishChanges)
+                    .wrap(rate_limits.limiter("transactions"))
+                    .wrap(bearer_auth_middleware.clone()),
+            )
             .service(
                 transaction_template::transaction_template_service()
+                    .wrap(RequireScope::new(
+                        ApiKeyScope::ReadOnly,
+                        &[ApiKeyScope::TemplatesWrite],
+                    ))
+                    .wrap(PublishChanges)
+                    .wrap(rate_limits.limiter("templates"))
+                    .wrap(bearer_auth_middleware.clone()),
+            )
+            .service(
+                rule::rule_service()
+                    .wrap(RequireScope::new(ApiKeyScope::ReadOnly, &[]))
+                    .wrap(PublishChanges)
+                    .wrap(rate_limits.limiter("rules"))
+                    .wrap(bearer_auth_middleware.clone()),
+            )
+            .service(
+                trash::trash_service()
+                    .wrap(RequireScope::new(
+                        ApiKeyScope::ReadOnly,
+                        &[ApiKeyScope::TransactionsWrite, ApiKeyScope::TemplatesWrite],
+                    ))
+                    .wrap(PublishChanges)
+                    .wrap(rate_limits.limiter("trash"))
+                    .wrap(bearer_auth_middleware.clone()),
+            )
+            .service(
+                // downloading the change feed from the start exports the whole ledger
+                sync::sync_service()
+                    .wrap(RequireScope::new(
+                        ApiKeyScope::Export,
+                        &[ApiKeyScope::TransactionsWrite, ApiKeyScope::TemplatesWrite],
+                    ))
+                    .wrap(PublishChanges)
+                    .wrap(rate_limits.limiter("sync"))
+                    .wrap(bearer_auth_middleware.clone()),
+            )
+            .service(
+                events::events_service()
+                    .wrap(RequireScope::new(ApiKeyScope::ReadOnly, &[]))
+                    .wrap(rate_limits.limiter("events"))
                     .wrap(bearer_auth_middleware.clone()),
             )
-            .service(user::user_service().wrap(bearer_auth_middleware.clone()))
-            .service(auth::auth_service(signups_enabled))
+            .service(
+                webhook::webhook_service()
+                    .wrap(RequireScope::new(ApiKeyScope::ReadOnly, &[]))
+                    .wrap(rate_limits.limiter("webhooks"))
+                    .wrap(bearer_auth_middleware.clone()),
+            )
+            .service(
+                ledger::ledger_service()
+                    .wrap(RequireScope::no_api_keys())
+                    .wrap(PublishChanges)
+                    .wrap(rate_limits.limiter("ledgers"))
+                    .wrap(bearer_auth_middleware.clone()),
+            )
+            .service(
+                admin::admin_service()
+                    .wrap(RequireScope::no_api_keys())
+                    .wrap(rate_limits.limiter("admin"))
+                    .wrap(bearer_auth_middleware.clone()),
+            )
+            .service(
+                user::user_service()
+                    .wrap(RequireScope::no_api_keys())
+                    .wrap(rate_limits.limiter("user"))
+                    .wrap(bearer_auth_middleware.clone()),
+            )
+            .service(
+                auth::auth_service(signup_mode, oidc_provider).wrap(rate_limits.limiter("auth")),
+            )
+            .service(auth::well_known_service())
             .app_data(web::JsonConfig::default().error_handler(|err, req| {
                 error!(req_path = req.path(), %err);
                 match err {
@@ -56,7 +182,7 @@ pub fn app_config_func(
                                 .content_type("application/json")
                                 .body(error_body.to_string()),
                         )
-                            .into()
+                        .into()
                     }
                     _ => err.into(),
                 }
@@ -71,7 +197,7 @@ async fn health_check(repo_health: Data<Arc<dyn HealthCheck>>) -> HttpResponse {
     } else {
         HttpResponse::InternalServerError()
     }
-        .finish()
+    .finish()
 }
 
 pub fn health_check_config_func(
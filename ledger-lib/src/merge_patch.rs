@@ -0,0 +1,88 @@
+//! JSON Merge Patch ([RFC 7396](https://www.rfc-editor.org/rfc/rfc7396)) for partial updates.
+//! A member that is absent from the patch is left unchanged, while an explicit `null` removes it,
+//! which for optional fields means setting them to `None`.
+
+use crate::error::HandlerError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Applies `patch` to `current` by merging it into its JSON representation
+pub(crate) fn apply_merge_patch<T>(current: T, patch: Value) -> Result<T, HandlerError>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut target = serde_json::to_value(current).map_err(anyhow::Error::from)?;
+    merge_patch(&mut target, patch);
+    serde_json::from_value(target)
+        .map_err(|e| HandlerError::BadRequest(format!("Invalid merge patch: {}", e)))
+}
+
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(serde_json::Map::new());
+    }
+    let Value::Object(target) = target else {
+        unreachable!("target was replaced with an object above");
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_patch;
+    use serde_json::json;
+
+    #[test]
+    async fn rfc_example() {
+        let mut target = json!({
+            "title": "Goodbye!",
+            "author": {"givenName": "John", "familyName": "Doe"},
+            "tags": ["example", "sample"],
+            "content": "This will be unchanged"
+        });
+        merge_patch(
+            &mut target,
+            json!({
+                "title": "Hello!",
+                "phoneNumber": "+01-123-456-7890",
+                "author": {"familyName": null},
+                "tags": ["example"]
+            }),
+        );
+        assert_eq!(
+            json!({
+                "title": "Hello!",
+                "author": {"givenName": "John"},
+                "tags": ["example"],
+                "content": "This will be unchanged",
+                "phoneNumber": "+01-123-456-7890"
+            }),
+            target
+        );
+    }
+
+    #[test]
+    async fn absent_and_null_members() {
+        let mut target = json!({"note": "lunch", "transactee": "Bob"});
+        merge_patch(&mut target, json!({"transactee": null}));
+        assert_eq!(json!({"note": "lunch"}), target);
+    }
+
+    #[test]
+    async fn non_object_patch_replaces_target() {
+        let mut target = json!({"a": "b"});
+        merge_patch(&mut target, json!(["c"]));
+        assert_eq!(json!(["c"]), target);
+    }
+}
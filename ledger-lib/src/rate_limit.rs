@@ -0,0 +1,334 @@
+//! Limits how many requests a client can make to a scope in a fixed window of time. Requests over
+//! the limit get a `429 Too Many Requests` response with a `Retry-After` header. The counters are
+//! kept by the [RateLimitRepo], either in memory or in the database so that they are shared
+//! between servers.
+
+use crate::client_ip::client_ip;
+use crate::user::UserId;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::web::Data;
+use actix_web::{HttpMessage, HttpResponse};
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use ledger_repo::rate_limit_repo::RateLimitRepo;
+use ledger_repo::Repos;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use tracing::{error, info, warn};
+
+/// Applies to `/auth` when it isn't configured
+pub const DEFAULT_AUTH_LIMIT: RateLimit = RateLimit {
+    requests: 20,
+    window_seconds: 60,
+};
+/// Counters are kept for a day, so windows can't be longer than that
+pub const COUNTER_RETENTION_HOURS: i64 = 24;
+const PURGE_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(try_from = "UncheckedRateLimit")]
+pub struct RateLimit {
+    pub requests: i64,
+    pub window_seconds: i64,
+}
+
+#[derive(Deserialize)]
+struct UncheckedRateLimit {
+    requests: i64,
+    window_seconds: i64,
+}
+
+/// A limit of no requests would lock the scope out, and a window of no time would never limit
+/// anything, so both have to be positive
+impl TryFrom<UncheckedRateLimit> for RateLimit {
+    type Error = anyhow::Error;
+
+    fn try_from(limit: UncheckedRateLimit) -> Result<Self, Self::Error> {
+        if limit.requests <= 0 {
+            return Err(anyhow!(
+                "Rate limit request count must be positive, not {}",
+                limit.requests
+            ));
+        }
+        if limit.window_seconds <= 0 {
+            return Err(anyhow!(
+                "Rate limit window must be positive, not {}",
+                limit.window_seconds
+            ));
+        }
+        Ok(RateLimit {
+            requests: limit.requests,
+            window_seconds: limit.window_seconds,
+        })
+    }
+}
+
+/// Parses limits written like `20/60`, for 20 requests every 60 seconds
+impl FromStr for RateLimit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (requests, window_seconds) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow!("Rate limit {} is not in the form requests/seconds", s))?;
+        UncheckedRateLimit {
+            requests: requests.trim().parse().context("Invalid request count")?,
+            window_seconds: window_seconds.trim().parse().context("Invalid window")?,
+        }
+        .try_into()
+    }
+}
+
+impl RateLimit {
+    fn window_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let start = now.timestamp().div_euclid(self.window_seconds) * self.window_seconds;
+        Utc.timestamp_opt(start, 0).unwrap()
+    }
+}
+
+/// Where the counters are kept. Counters in memory aren't shared between servers, so each server
+/// allows the full limit.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum CounterStorage {
+    Memory,
+    #[default]
+    Database,
+}
+
+impl FromStr for CounterStorage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "memory" => Ok(CounterStorage::Memory),
+            "database" => Ok(CounterStorage::Database),
+            _ => Err(anyhow!("Unknown counter storage {}", s)),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default, Debug)]
+pub struct RateLimits {
+    #[serde(default)]
+    pub storage: CounterStorage,
+    /// Limits by scope, named like their path without the slash, e.g. `transactions`. Scopes
+    /// without a limit aren't limited, except for `auth` which has [DEFAULT_AUTH_LIMIT].
+    #[serde(default)]
+    pub limits: HashMap<String, RateLimit>,
+}
+
+impl RateLimits {
+    /// Parses limits written like `auth=20/60,transactions=600/60`
+    pub fn parse_limits(limits: &str) -> Result<HashMap<String, RateLimit>, anyhow::Error> {
+        limits
+            .split(',')
+            .filter(|limit| !limit.trim().is_empty())
+            .map(|limit| {
+                let (scope, limit) = limit.split_once('=').ok_or_else(|| {
+                    anyhow!("Rate limit {} is not in the form scope=limit", limit)
+                })?;
+                Ok((scope.trim().to_owned(), limit.parse()?))
+            })
+            .collect()
+    }
+
+    pub fn limit(&self, scope: &str) -> Option<RateLimit> {
+        match self.limits.get(scope) {
+            Some(limit) => Some(*limit),
+            None if scope == "auth" => Some(DEFAULT_AUTH_LIMIT),
+            None => None,
+        }
+    }
+
+    pub fn limiter(&self, scope: &'static str) -> RateLimiter {
+        RateLimiter {
+            scope,
+            limit: self.limit(scope),
+        }
+    }
+}
+
+/// The response to requests over a limit, or to logins while locked out
+pub(crate) fn too_many_requests(retry_after: Duration) -> HttpResponse {
+    let seconds = (retry_after.num_milliseconds() + 999) / 1000;
+    HttpResponse::TooManyRequests()
+        .insert_header((header::RETRY_AFTER, seconds.max(1).to_string()))
+        .body("Too many requests")
+}
+
+/// Limits requests to a scope. Authenticated requests are counted per user, so it has to be
+/// wrapped inside the authentication middleware, and other requests are counted per client
+/// address, which is only taken from forwarding headers set by
+/// [TrustedProxies](crate::client_ip::TrustedProxies).
+#[derive(Clone, Copy)]
+pub struct RateLimiter {
+    scope: &'static str,
+    limit: Option<RateLimit>,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimiterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimiterMiddleware {
+            service: Rc::new(service),
+            limiter: *self,
+        }))
+    }
+}
+
+pub struct RateLimiterMiddleware<S> {
+    service: Rc<S>,
+    limiter: RateLimiter,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let RateLimiter { scope, limit } = self.limiter;
+        let (Some(limit), Some(repo)) = (limit, req.app_data::<Data<Arc<dyn RateLimitRepo>>>())
+        else {
+            let fut = service.call(req);
+            return Box::pin(async move { Ok(fut.await?.map_into_left_body()) });
+        };
+        let repo = repo.clone();
+
+        let user_id = req.extensions().get::<UserId>().cloned();
+        let client = match user_id {
+            Some(user_id) => format!("user:{}", user_id),
+            None => format!(
+                "ip:{}",
+                client_ip(req.request()).as_deref().unwrap_or("unknown")
+            ),
+        };
+        let key = format!("{}:{}", scope, client);
+
+        Box::pin(async move {
+            let now = Utc::now();
+            let window_start = limit.window_start(now);
+            match repo.count_request(&key, window_start).await {
+                Ok(count) if count > limit.requests => {
+                    warn!(key, "Rate limit exceeded");
+                    let window_end = window_start + Duration::seconds(limit.window_seconds);
+                    let response = too_many_requests(window_end - now);
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+                Ok(_) => {}
+                // counters failing shouldn't take the service down with them
+                Err(e) => error!(%e, "Unable to count request"),
+            }
+            Ok(service.call(req).await?.map_into_left_body())
+        })
+    }
+}
+
+/// Deletes request counts and failed logins older than [COUNTER_RETENTION_HOURS]
+pub async fn purge_counters(repos: &Repos) -> Result<(), anyhow::Error> {
+    let counters = repos
+        .rate_limit_repo
+        .purge_counters(Utc::now() - Duration::hours(COUNTER_RETENTION_HOURS))
+        .await?;
+    info!(counters, "Purged rate limit counters");
+
+    Ok(())
+}
+
+/// Purges old counters every [PURGE_INTERVAL]. Errors are logged, so this never returns.
+pub async fn purge_counters_periodically(repos: Repos) {
+    let mut interval = actix_web::rt::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = purge_counters(&repos).await {
+            error!(%e, "Unable to purge rate limit counters");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimit, RateLimits, DEFAULT_AUTH_LIMIT};
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    async fn parse_limits() {
+        let limits = RateLimits {
+            limits: RateLimits::parse_limits("auth=5/10, transactions = 600/60").unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(RateLimit {
+                requests: 5,
+                window_seconds: 10
+            }),
+            limits.limit("auth")
+        );
+        assert_eq!(
+            Some(RateLimit {
+                requests: 600,
+                window_seconds: 60
+            }),
+            limits.limit("transactions")
+        );
+        assert_eq!(None, limits.limit("templates"));
+        assert_eq!(
+            Some(DEFAULT_AUTH_LIMIT),
+            RateLimits::default().limit("auth")
+        );
+
+        assert!(RateLimits::parse_limits("auth=5").is_err());
+        assert!(RateLimits::parse_limits("5/10").is_err());
+    }
+
+    #[test]
+    async fn limits_must_be_positive() {
+        for limit in ["auth=0/60", "auth=-1/60", "auth=5/0", "auth=5/-60"] {
+            assert!(RateLimits::parse_limits(limit).is_err(), "{}", limit);
+        }
+        let config: Result<RateLimits, _> =
+            toml::from_str("[limits]\nauth = { requests = 20, window_seconds = 0 }");
+        assert!(config.is_err());
+        let config: RateLimits =
+            toml::from_str("[limits]\nauth = { requests = 20, window_seconds = 60 }").unwrap();
+        assert_eq!(Some(DEFAULT_AUTH_LIMIT), config.limit("auth"));
+    }
+
+    #[test]
+    async fn window_start() {
+        let limit = RateLimit {
+            requests: 1,
+            window_seconds: 60,
+        };
+        assert_eq!(
+            Utc.timestamp_opt(120, 0).unwrap(),
+            limit.window_start(Utc.timestamp_opt(179, 0).unwrap())
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use crate::error::HandlerError;
+use crate::history::{record_change, Actor, ChangeRecorder};
+use crate::rule::{apply_rules, compile_rules, validate_rule, RuleMatcher};
+use crate::transaction::Filter;
+use crate::user::UserId;
+use actix_web::{web, HttpResponse, Responder};
+use ledger_repo::history_repo::ChangeKind;
+use ledger_repo::rule_repo::{NewRule, RuleRepo};
+use ledger_repo::transaction_repo::{
+    NewTransaction, Transaction, TransactionRepo, TransactionRepoError,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+/// A transaction matched by a rule, along with what it would look like after the rule is applied
+#[derive(Serialize)]
+pub struct RuleMatchResponse {
+    transaction: Transaction,
+    result: Transaction,
+}
+
+#[derive(Serialize)]
+pub struct AppliedRulesResponse {
+    transactions: u64,
+    /// Transactions that were changed by someone else while the rules were being applied, which
+    /// were left as they are
+    skipped: Vec<i32>,
+}
+
+#[get("")]
+pub async fn get_all_rules(
+    rule_repo: web::Data<Arc<dyn RuleRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let rules = rule_repo.get_rules(&user_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(rules))
+}
+
+#[post("")]
+pub async fn create_rule(
+    rule_repo: web::Data<Arc<dyn RuleRepo>>,
+    user_id: web::ReqData<UserId>,
+    new_rule: web::Json<NewRule>,
+) -> Result<impl Responder, HandlerError> {
+    let new_rule = new_rule.into_inner();
+    validate_rule(&new_rule)?;
+
+    let rule = rule_repo
+        .create_rule(&user_id.into_inner(), new_rule)
+        .await?;
+    Ok(HttpResponse::Ok().json(rule))
+}
+
+#[put("/{rule_id}")]
+pub async fn update_rule(
+    rule_repo: web::Data<Arc<dyn RuleRepo>>,
+    user_id: web::ReqData<UserId>,
+    rule_id: web::Path<i32>,
+    updated_rule: web::Json<NewRule>,
+) -> Result<impl Responder, HandlerError> {
+    let updated_rule = updated_rule.into_inner();
+    validate_rule(&updated_rule)?;
+
+    let rule = rule_repo
+        .update_rule(&user_id.into_inner(), rule_id.into_inner(), updated_rule)
+        .await?;
+    Ok(HttpResponse::Ok().json(rule))
+}
+
+#[delete("/{rule_id}")]
+pub async fn delete_rule(
+    rule_repo: web::Data<Arc<dyn RuleRepo>>,
+    user_id: web::ReqData<UserId>,
+    rule_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    let rule = rule_repo
+        .delete_rule(&user_id.into_inner(), rule_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(rule))
+}
+
+/// Shows which existing transactions a rule would match without changing anything
+#[post("/test")]
+pub async fn test_rule(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    user_id: web::ReqData<UserId>,
+    filter: web::Query<Filter>,
+    rule: web::Json<NewRule>,
+) -> Result<impl Responder, HandlerError> {
+    let rule = rule.into_inner();
+    validate_rule(&rule)?;
+    let rule = RuleMatcher::new(&rule.conditions, &rule.actions)
+        .map_err(|e| HandlerError::BadRequest(format!("Invalid rule pattern: {}", e)))?;
+
+    let transactions = transaction_repo
+        .get_all_transactions(&user_id.into_inner(), filter.into_inner().into(), None)
+        .await?;
+
+    let matches: Vec<RuleMatchResponse> = transactions
+        .into_iter()
+        .filter_map(|transaction| {
+            let mut new_transaction: NewTransaction = transaction.clone().into();
+            if !rule.matches(&new_transaction) {
+                return None;
+            }
+            apply_rules(std::slice::from_ref(&rule), &mut new_transaction);
+            let result = new_transaction.to_transaction(transaction.id, transaction.version);
+            Some(RuleMatchResponse {
+                transaction,
+                result,
+            })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(matches))
+}
+
+/// Re-applies all rules to existing transactions matching the filter. Transactions only get updated
+/// if they haven't changed since they were read, so concurrent edits aren't overwritten.
+#[post("/apply")]
+pub async fn apply_rules_to_transactions(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    rule_repo: web::Data<Arc<dyn RuleRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
+    filter: web::Query<Filter>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = actor.user_id.clone();
+    let rules = rule_repo.get_rules(&user_id).await?;
+    let rules = compile_rules(&rules);
+
+    let transactions = transaction_repo
+        .get_all_transactions(&user_id, filter.into_inner().into(), None)
+        .await?;
+
+    let mut count = 0;
+    let mut skipped = Vec::new();
+    for transaction in transactions {
+        let mut new_transaction: NewTransaction = transaction.clone().into();
+        apply_rules(&rules, &mut new_transaction);
+        if new_transaction
+            .clone()
+            .to_transaction(transaction.id, transaction.version)
+            != transaction
+        {
+            let updated = match transaction_repo
+                .update_transaction(
+                    &user_id,
+                    transaction.id,
+                    new_transaction,
+                    Some(transaction.version),
+                )
+                .await
+            {
+                Ok(updated) => updated,
+                Err(TransactionRepoError::VersionMismatch(_)) => {
+                    skipped.push(transaction.id);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            record_change(
+                &changes,
+                &actor,
+                ChangeKind::Update,
+                Some(transaction),
+                Some(updated),
+            )
+            .await?;
+            count += 1;
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(AppliedRulesResponse {
+        transactions: count,
+        skipped,
+    }))
+}
@@ -0,0 +1,213 @@
+mod handlers;
+
+use crate::error::HandlerError;
+use actix_web::{web, Scope};
+use chrono::Datelike;
+use ledger_repo::rule_repo::{AmountSign, NewRule, Rule, RuleActions, RuleConditions, RuleRepo};
+use ledger_repo::transaction_repo::NewTransaction;
+use regex::{Regex, RegexBuilder};
+
+pub fn rule_service() -> Scope {
+    web::scope("/rules")
+        .service(handlers::get_all_rules)
+        .service(handlers::create_rule)
+        .service(handlers::test_rule)
+        .service(handlers::apply_rules_to_transactions)
+        .service(handlers::update_rule)
+        .service(handlers::delete_rule)
+}
+
+/// Rule patterns are regular expressions matched anywhere in the text, ignoring case
+fn pattern_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+/// A rule with its patterns compiled so it can be matched against many transactions
+pub(crate) struct RuleMatcher<'a> {
+    conditions: &'a RuleConditions,
+    actions: &'a RuleActions,
+    transactee: Option<Regex>,
+    note: Option<Regex>,
+}
+
+impl<'a> RuleMatcher<'a> {
+    fn new(
+        conditions: &'a RuleConditions,
+        actions: &'a RuleActions,
+    ) -> Result<RuleMatcher<'a>, regex::Error> {
+        Ok(RuleMatcher {
+            conditions,
+            actions,
+            transactee: conditions
+                .transactee
+                .as_deref()
+                .map(pattern_regex)
+                .transpose()?,
+            note: conditions.note.as_deref().map(pattern_regex).transpose()?,
+        })
+    }
+
+    fn matches(&self, transaction: &NewTransaction) -> bool {
+        fn pattern_matches(regex: &Option<Regex>, value: &Option<String>) -> bool {
+            match (regex, value) {
+                (None, _) => true,
+                (Some(regex), Some(value)) => regex.is_match(value),
+                (Some(_), None) => false,
+            }
+        }
+
+        let conditions = self.conditions;
+        let amount = transaction.amount.abs();
+        pattern_matches(&self.transactee, &transaction.transactee)
+            && pattern_matches(&self.note, &transaction.note)
+            && conditions.min_amount.is_none_or(|min| amount >= min)
+            && conditions.max_amount.is_none_or(|max| amount <= max)
+            && conditions.sign.is_none_or(|sign| match sign {
+                AmountSign::Positive => transaction.amount.is_sign_positive(),
+                AmountSign::Negative => transaction.amount.is_sign_negative(),
+            })
+            && conditions
+                .day_of_month
+                .is_none_or(|day| transaction.date.day() as i32 == day)
+    }
+}
+
+/// Checks that a rule can be compiled and its conditions make sense
+pub(crate) fn validate_rule(rule: &NewRule) -> Result<(), HandlerError> {
+    RuleMatcher::new(&rule.conditions, &rule.actions)
+        .map_err(|e| HandlerError::BadRequest(format!("Invalid rule pattern: {}", e)))?;
+
+    if let Some(day) = rule.conditions.day_of_month {
+        if !(1..=31).contains(&day) {
+            return Err(HandlerError::BadRequest(
+                "Day of month must be between 1 and 31".to_string(),
+            ));
+        }
+    }
+    if let (Some(min), Some(max)) = (rule.conditions.min_amount, rule.conditions.max_amount) {
+        if min > max {
+            return Err(HandlerError::BadRequest(
+                "Minimum amount cannot be more than the maximum amount".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Compiles rules in the order they should be evaluated. Rules are validated when they are saved,
+/// so any that fail to compile are skipped.
+pub(crate) fn compile_rules(rules: &[Rule]) -> Vec<RuleMatcher<'_>> {
+    rules
+        .iter()
+        .filter_map(|r| RuleMatcher::new(&r.conditions, &r.actions).ok())
+        .collect()
+}
+
+/// Applies every matching rule to the transaction. Conditions are checked against the transaction
+/// as it was before any rule was applied. When several rules set the same field the first one
+/// wins, while tags from all of them are added.
+pub(crate) fn apply_rules(rules: &[RuleMatcher], transaction: &mut NewTransaction) {
+    let original = transaction.clone();
+    let mut category = None;
+    let mut transactee = None;
+    let mut note = None;
+
+    for rule in rules.iter().filter(|r| r.matches(&original)) {
+        category = category.or(rule.actions.category.as_ref());
+        transactee = transactee.or(rule.actions.transactee.as_ref());
+        note = note.or(rule.actions.note.as_ref());
+        transaction.tags.extend(rule.actions.tags.iter().cloned());
+    }
+
+    if let Some(category) = category {
+        transaction.category = category.clone();
+    }
+    if let Some(transactee) = transactee {
+        transaction.transactee = Some(transactee.clone());
+    }
+    if let Some(note) = note {
+        transaction.note = Some(note.clone());
+    }
+}
+
+/// Applies the user's rules to a transaction that is being created
+pub(crate) async fn apply_user_rules(
+    rule_repo: &dyn RuleRepo,
+    user_id: &str,
+    transaction: &mut NewTransaction,
+) -> Result<(), HandlerError> {
+    let rules = rule_repo.get_rules(user_id).await?;
+    apply_rules(&compile_rules(&rules), transaction);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_rules, RuleMatcher};
+    use chrono::NaiveDate;
+    use ledger_repo::rule_repo::{AmountSign, RuleActions, RuleConditions};
+    use ledger_repo::transaction_repo::NewTransaction;
+    use rust_decimal::Decimal;
+    use std::collections::HashSet;
+
+    fn transaction(transactee: &str, amount: i64, day: u32) -> NewTransaction {
+        NewTransaction::new(
+            "Uncategorized".to_string(),
+            Some(transactee.to_string()),
+            None,
+            NaiveDate::from_ymd_opt(2023, 5, day).unwrap(),
+            Decimal::from(amount),
+            HashSet::new(),
+        )
+    }
+
+    #[test]
+    async fn test_conditions() {
+        let conditions = RuleConditions {
+            transactee: Some("^amzn".to_string()),
+            min_amount: Some(Decimal::from(10)),
+            max_amount: Some(Decimal::from(100)),
+            sign: Some(AmountSign::Negative),
+            day_of_month: Some(15),
+            ..RuleConditions::default()
+        };
+        let actions = RuleActions::default();
+        let rule = RuleMatcher::new(&conditions, &actions).unwrap();
+
+        assert!(rule.matches(&transaction("AMZN Mktp US", -20, 15)));
+        assert!(!rule.matches(&transaction("Amazon.com", -20, 15)));
+        assert!(!rule.matches(&transaction("AMZN Mktp US", -200, 15)));
+        assert!(!rule.matches(&transaction("AMZN Mktp US", 20, 15)));
+        assert!(!rule.matches(&transaction("AMZN Mktp US", -20, 16)));
+    }
+
+    #[test]
+    async fn test_apply_rules_in_order() {
+        let conditions = RuleConditions::default();
+        let first = RuleActions {
+            category: Some("Shopping".to_string()),
+            tags: HashSet::from(["online".to_string()]),
+            ..RuleActions::default()
+        };
+        let second = RuleActions {
+            category: Some("Misc".to_string()),
+            note: Some("Checked".to_string()),
+            tags: HashSet::from(["amazon".to_string()]),
+            ..RuleActions::default()
+        };
+        let rules = vec![
+            RuleMatcher::new(&conditions, &first).unwrap(),
+            RuleMatcher::new(&conditions, &second).unwrap(),
+        ];
+
+        let mut transaction = transaction("AMZN Mktp US", -20, 15);
+        apply_rules(&rules, &mut transaction);
+
+        assert_eq!("Shopping", transaction.category);
+        assert_eq!(Some("Checked".to_string()), transaction.note);
+        assert_eq!(
+            HashSet::from(["online".to_string(), "amazon".to_string()]),
+            transaction.tags
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use crate::error::HandlerError;
+use crate::suggestion::{suggest, Draft};
+use crate::transactee::normalize_transactee;
+use crate::user::UserId;
+use actix_web::{web, HttpResponse, Responder};
+use ledger_repo::transactee_repo::TransacteeRepo;
+use ledger_repo::transaction_repo::{Filter, TransactionRepo};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct SuggestionQuery {
+    transactee: Option<String>,
+    note: Option<String>,
+    amount: Option<Decimal>,
+    limit: Option<usize>,
+}
+
+#[get("")]
+pub async fn get_suggestions(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    transactee_repo: web::Data<Arc<dyn TransacteeRepo>>,
+    user_id: web::ReqData<UserId>,
+    query: web::Query<SuggestionQuery>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    let query = query.into_inner();
+
+    let draft = Draft {
+        transactee: normalize_transactee(&***transactee_repo, &user_id, query.transactee).await?,
+        note: query.note,
+        amount: query.amount,
+    };
+    let history = transaction_repo
+        .get_all_transactions(&user_id, Filter::NONE, None)
+        .await?;
+
+    let suggestions = suggest(&history, &draft, query.limit.unwrap_or(5));
+    Ok(HttpResponse::Ok().json(suggestions))
+}
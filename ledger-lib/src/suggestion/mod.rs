@@ -0,0 +1,178 @@
+mod handlers;
+mod naive_bayes;
+
+use crate::suggestion::naive_bayes::NaiveBayes;
+use actix_web::{web, Scope};
+use ledger_repo::transaction_repo::Transaction;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// Category and tag suggestions, nested under the transaction service
+pub fn suggestion_service() -> Scope {
+    web::scope("/suggestions").service(handlers::get_suggestions)
+}
+
+/// The parts of a transaction that are known before picking its category and tags
+pub(crate) struct Draft {
+    pub transactee: Option<String>,
+    pub note: Option<String>,
+    pub amount: Option<Decimal>,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub(crate) struct Suggestion {
+    name: String,
+    confidence: f64,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub(crate) struct Suggestions {
+    categories: Vec<Suggestion>,
+    tags: Vec<Suggestion>,
+}
+
+/// Lowercase words from the transactee and note, plus a feature for the sign and magnitude of the
+/// amount so that e.g. small expenses and large incomes are told apart
+fn features(transactee: Option<&str>, note: Option<&str>, amount: Option<Decimal>) -> Vec<String> {
+    let words: BTreeSet<String> = transactee
+        .into_iter()
+        .chain(note)
+        .flat_map(|text| text.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| word.len() > 1 && !word.chars().all(|c| c.is_numeric()))
+        .map(|word| word.to_lowercase())
+        .collect();
+
+    let mut features: Vec<String> = words.into_iter().collect();
+    if let Some(amount) = amount {
+        let sign = if amount.is_sign_negative() { "-" } else { "+" };
+        let digits = amount
+            .abs()
+            .trunc()
+            .to_string()
+            .trim_start_matches('0')
+            .len();
+        features.push(format!("amount:{}{}", sign, digits));
+    }
+    features
+}
+
+fn ranked(confidences: HashMap<String, f64>, limit: usize) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = confidences
+        .into_iter()
+        .map(|(name, confidence)| Suggestion { name, confidence })
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    suggestions.truncate(limit);
+    suggestions
+}
+
+/// Suggests categories and tags for a draft transaction from the user's past transactions. This
+/// averages a naive Bayes prediction over the words and amount with how often past transactions
+/// with the same transactee used each category and tag.
+pub(crate) fn suggest(history: &[Transaction], draft: &Draft, limit: usize) -> Suggestions {
+    let draft_features = features(
+        draft.transactee.as_deref(),
+        draft.note.as_deref(),
+        draft.amount,
+    );
+    let history_features: Vec<Vec<String>> = history
+        .iter()
+        .map(|t| features(t.transactee.as_deref(), t.note.as_deref(), Some(t.amount)))
+        .collect();
+
+    let same_transactee: Vec<&Transaction> = match &draft.transactee {
+        Some(transactee) => history
+            .iter()
+            .filter(|t| {
+                t.transactee
+                    .as_ref()
+                    .is_some_and(|other| other.to_lowercase() == transactee.to_lowercase())
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let blend = |predicted: f64, matching: usize| {
+        if same_transactee.is_empty() {
+            predicted
+        } else {
+            (predicted + matching as f64 / same_transactee.len() as f64) / 2.0
+        }
+    };
+
+    let mut category_classifier = NaiveBayes::new();
+    for (transaction, features) in history.iter().zip(&history_features) {
+        category_classifier.add(transaction.category.clone(), features);
+    }
+    let categories = category_classifier
+        .predict(&draft_features)
+        .into_iter()
+        .map(|(category, predicted)| {
+            let matching = same_transactee
+                .iter()
+                .filter(|t| t.category == category)
+                .count();
+            let confidence = blend(predicted, matching);
+            (category, confidence)
+        })
+        .collect();
+
+    // Transactions can have many tags, so each tag gets its own yes/no classifier
+    let all_tags: BTreeSet<&String> = history.iter().flat_map(|t| &t.tags).collect();
+    let tags = all_tags
+        .into_iter()
+        .map(|tag| {
+            let mut tag_classifier = NaiveBayes::new();
+            for (transaction, features) in history.iter().zip(&history_features) {
+                tag_classifier.add(transaction.tags.contains(tag), features);
+            }
+            let predicted = tag_classifier
+                .predict(&draft_features)
+                .into_iter()
+                .find_map(|(has_tag, p)| has_tag.then_some(p))
+                .unwrap_or(0.0);
+            let matching = same_transactee
+                .iter()
+                .filter(|t| t.tags.contains(tag))
+                .count();
+            (tag.clone(), blend(predicted, matching))
+        })
+        .collect();
+
+    Suggestions {
+        categories: ranked(categories, limit),
+        tags: ranked(tags, limit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::features;
+    use rust_decimal::Decimal;
+
+    #[test]
+    async fn test_features() {
+        assert_eq!(
+            vec![
+                "2k4".to_string(),
+                "amzn".to_string(),
+                "mktp".to_string(),
+                "us".to_string(),
+                "amount:-2".to_string()
+            ],
+            features(
+                Some("AMZN Mktp US*2K4"),
+                Some("#123"),
+                Some(Decimal::from(-25))
+            )
+        );
+        assert_eq!(
+            vec!["amount:+0".to_string()],
+            features(None, None, Some(Decimal::new(5, 1)))
+        );
+    }
+}
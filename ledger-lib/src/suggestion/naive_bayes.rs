@@ -0,0 +1,100 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// Multinomial naive Bayes classifier with Laplace smoothing
+pub struct NaiveBayes<C> {
+    class_docs: HashMap<C, usize>,
+    feature_counts: HashMap<C, HashMap<String, usize>>,
+    feature_totals: HashMap<C, usize>,
+    vocabulary: HashSet<String>,
+    total_docs: usize,
+}
+
+impl<C: Eq + Hash + Clone> NaiveBayes<C> {
+    pub fn new() -> NaiveBayes<C> {
+        NaiveBayes {
+            class_docs: HashMap::new(),
+            feature_counts: HashMap::new(),
+            feature_totals: HashMap::new(),
+            vocabulary: HashSet::new(),
+            total_docs: 0,
+        }
+    }
+
+    pub fn add(&mut self, class: C, features: &[String]) {
+        self.total_docs += 1;
+        *self.class_docs.entry(class.clone()).or_insert(0) += 1;
+        *self.feature_totals.entry(class.clone()).or_insert(0) += features.len();
+
+        let counts = self.feature_counts.entry(class).or_default();
+        for feature in features {
+            *counts.entry(feature.clone()).or_insert(0) += 1;
+            self.vocabulary.insert(feature.clone());
+        }
+    }
+
+    /// Gets the probability of each class given the features. Probabilities add up to 1, unless
+    /// nothing has been learned yet in which case this is empty.
+    pub fn predict(&self, features: &[String]) -> Vec<(C, f64)> {
+        // +1 to leave room for features that have never been seen
+        let vocabulary_size = (self.vocabulary.len() + 1) as f64;
+
+        let log_scores: Vec<(C, f64)> = self
+            .class_docs
+            .iter()
+            .map(|(class, &docs)| {
+                let counts = &self.feature_counts[class];
+                let total = self.feature_totals[class] as f64;
+                let prior = (docs as f64 / self.total_docs as f64).ln();
+                let likelihood: f64 = features
+                    .iter()
+                    .map(|f| {
+                        let count = counts.get(f).copied().unwrap_or(0) as f64;
+                        ((count + 1.0) / (total + vocabulary_size)).ln()
+                    })
+                    .sum();
+                (class.clone(), prior + likelihood)
+            })
+            .collect();
+
+        // Normalize in log space to avoid underflow
+        let max = log_scores
+            .iter()
+            .map(|(_, s)| *s)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let sum: f64 = log_scores.iter().map(|(_, s)| (s - max).exp()).sum();
+        log_scores
+            .into_iter()
+            .map(|(class, s)| (class, (s - max).exp() / sum))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NaiveBayes;
+
+    fn features(features: &[&str]) -> Vec<String> {
+        features.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    async fn test_predict() {
+        let mut classifier = NaiveBayes::new();
+        classifier.add("Groceries", &features(&["whole", "foods"]));
+        classifier.add("Groceries", &features(&["trader", "joes"]));
+        classifier.add("Dining", &features(&["joes", "pizza"]));
+
+        let mut prediction = classifier.predict(&features(&["trader", "joes"]));
+        prediction.sort_by(|a, b| b.1.total_cmp(&a.1));
+        assert_eq!("Groceries", prediction[0].0);
+        assert!(prediction[0].1 > 0.5);
+        assert!((prediction.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    async fn test_predict_empty() {
+        let classifier: NaiveBayes<&str> = NaiveBayes::new();
+        assert!(classifier.predict(&features(&["pizza"])).is_empty());
+    }
+}
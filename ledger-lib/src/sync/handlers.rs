@@ -0,0 +1,451 @@
+use crate::error::HandlerError;
+use crate::history::{record_change, Actor, ChangeRecorder};
+use crate::idempotency::Idempotency;
+use crate::rule::apply_user_rules;
+use crate::sync::{ensure_not_purged, SyncToken};
+use crate::transactee::normalize_transactee;
+use crate::user::UserId;
+use actix_web::{web, HttpResponse, Responder};
+use ledger_repo::history_repo::ChangeKind;
+use ledger_repo::ledger_repo::LedgerRepo;
+use ledger_repo::rule_repo::RuleRepo;
+use ledger_repo::transactee_repo::TransacteeRepo;
+use ledger_repo::transaction_repo::{
+    NewTransaction, Transaction, TransactionRepo, TransactionRepoError,
+};
+use ledger_repo::transaction_template_repo::{
+    NewTransactionTemplate, TransactionTemplate, TransactionTemplateRepo,
+    TransactionTemplateRepoError,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::error;
+
+#[derive(Deserialize)]
+pub struct SyncQuery {
+    token: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SyncResponse {
+    transactions: Vec<Transaction>,
+    deleted_transactions: Vec<i32>,
+    templates: Vec<TransactionTemplate>,
+    deleted_templates: Vec<i32>,
+    sync_token: String,
+}
+
+/// Without a token all transactions and templates are returned, for a client's first sync
+#[get("")]
+pub async fn get_changes(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    ledger_repo: web::Data<Arc<dyn LedgerRepo>>,
+    user_id: web::ReqData<UserId>,
+    query: web::Query<SyncQuery>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    let since = match &query.token {
+        Some(token) => {
+            let SyncToken(since) = SyncToken::decode(token)?;
+            ensure_not_purged(&***transaction_repo, &***template_repo, &user_id, since).await?;
+            Some(since)
+        }
+        None => None,
+    };
+
+    // Changes numbered after this may still be committing alongside ones numbered before them, so
+    // they are left for the next sync
+    let latest_change = ledger_repo
+        .get_last_change(&user_id)
+        .await?
+        .max(since.unwrap_or_default());
+    let mut changed_transactions = transaction_repo
+        .get_changed_transactions(&user_id, since.unwrap_or_default())
+        .await?;
+    changed_transactions.retain(|c| c.change <= latest_change);
+    let mut changed_templates = template_repo
+        .get_changed_templates(&user_id, since.unwrap_or_default())
+        .await?;
+    changed_templates.retain(|c| c.change <= latest_change);
+
+    let mut response = SyncResponse {
+        transactions: Vec::new(),
+        deleted_transactions: Vec::new(),
+        templates: Vec::new(),
+        deleted_templates: Vec::new(),
+        sync_token: SyncToken(latest_change).encode(),
+    };
+    for changed in changed_transactions {
+        if !changed.deleted {
+            response.transactions.push(changed.transaction);
+        } else if since.is_some() {
+            response.deleted_transactions.push(changed.transaction.id);
+        }
+    }
+    for changed in changed_templates {
+        if !changed.deleted {
+            response.templates.push(changed.template);
+        } else if since.is_some() {
+            response
+                .deleted_templates
+                .push(changed.template.template_id);
+        }
+    }
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// A change a client made while offline. Updates and deletes carry the version the client last
+/// saw, and are reported as conflicts if the transaction has changed since.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum TransactionUpload {
+    Create {
+        client_ref: Option<String>,
+        transaction: NewTransaction,
+    },
+    Update {
+        id: i32,
+        base_version: i32,
+        transaction: NewTransaction,
+    },
+    Delete {
+        id: i32,
+        base_version: i32,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum TemplateUpload {
+    Create {
+        client_ref: Option<String>,
+        template: NewTransactionTemplate,
+    },
+    Update {
+        id: i32,
+        base_version: i32,
+        template: NewTransactionTemplate,
+    },
+    Delete {
+        id: i32,
+        base_version: i32,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UploadRequest {
+    #[serde(default)]
+    transactions: Vec<TransactionUpload>,
+    #[serde(default)]
+    templates: Vec<TemplateUpload>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStatus {
+    Applied,
+    Conflict,
+    Error,
+}
+
+/// The outcome of an uploaded change. `current` is the server's version after the change, or the
+/// conflicting version that prevented it. It is `None` when the item no longer exists. Changes
+/// that failed have an `error` instead, and no `id` if they were creates.
+#[derive(Serialize)]
+pub struct UploadResult<T> {
+    status: UploadStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_ref: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    current: Option<T>,
+}
+
+impl<T> UploadResult<T> {
+    fn applied(id: i32, current: Option<T>) -> Self {
+        UploadResult {
+            status: UploadStatus::Applied,
+            id: Some(id),
+            client_ref: None,
+            error: None,
+            current,
+        }
+    }
+
+    fn conflict(id: i32, current: Option<T>) -> Self {
+        UploadResult {
+            status: UploadStatus::Conflict,
+            id: Some(id),
+            client_ref: None,
+            error: None,
+            current,
+        }
+    }
+
+    fn error(id: Option<i32>, client_ref: Option<String>, e: HandlerError) -> Self {
+        if let HandlerError::OtherError(e) = &e {
+            error!(%e, "Unable to apply uploaded change");
+        }
+        UploadResult {
+            status: UploadStatus::Error,
+            id,
+            client_ref,
+            error: Some(e.to_string()),
+            current: None,
+        }
+    }
+}
+
+impl TransactionUpload {
+    /// The id and client reference the change's result is reported with
+    fn reference(&self) -> (Option<i32>, Option<String>) {
+        match self {
+            TransactionUpload::Create { client_ref, .. } => (None, client_ref.clone()),
+            TransactionUpload::Update { id, .. } | TransactionUpload::Delete { id, .. } => {
+                (Some(*id), None)
+            }
+        }
+    }
+}
+
+impl TemplateUpload {
+    /// The id and client reference the change's result is reported with
+    fn reference(&self) -> (Option<i32>, Option<String>) {
+        match self {
+            TemplateUpload::Create { client_ref, .. } => (None, client_ref.clone()),
+            TemplateUpload::Update { id, .. } | TemplateUpload::Delete { id, .. } => {
+                (Some(*id), None)
+            }
+        }
+    }
+}
+
+/// Results are in the same order as the uploaded changes
+#[derive(Serialize)]
+pub struct UploadResponse {
+    transactions: Vec<UploadResult<Transaction>>,
+    templates: Vec<UploadResult<TransactionTemplate>>,
+}
+
+/// Applies changes in the order they were uploaded. A conflict or error doesn't stop the remaining
+/// changes from being applied, and is reported in the change's result rather than failing the
+/// request, since the changes before it have already been applied.
+#[post("")]
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_changes(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    transactee_repo: web::Data<Arc<dyn TransacteeRepo>>,
+    rule_repo: web::Data<Arc<dyn RuleRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
+    idempotency: Idempotency,
+    upload: web::Json<UploadRequest>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = actor.user_id.clone();
+    idempotency
+        .request(&*upload)?
+        .run(&user_id, async {
+            let upload = upload.into_inner();
+            let mut response = UploadResponse {
+                transactions: Vec::new(),
+                templates: Vec::new(),
+            };
+            for change in upload.transactions {
+                let (id, client_ref) = change.reference();
+                let result = apply_transaction_upload(
+                    &***transaction_repo,
+                    &***transactee_repo,
+                    &***rule_repo,
+                    &changes,
+                    &actor,
+                    change,
+                )
+                .await
+                .unwrap_or_else(|e| UploadResult::error(id, client_ref, e));
+                response.transactions.push(result);
+            }
+            for change in upload.templates {
+                let (id, client_ref) = change.reference();
+                let result = apply_template_upload(&***template_repo, &user_id, change)
+                    .await
+                    .unwrap_or_else(|e| UploadResult::error(id, client_ref, e));
+                response.templates.push(result);
+            }
+            Ok(response)
+        })
+        .await
+}
+
+async fn apply_transaction_upload(
+    transaction_repo: &dyn TransactionRepo,
+    transactee_repo: &dyn TransacteeRepo,
+    rule_repo: &dyn RuleRepo,
+    changes: &ChangeRecorder,
+    actor: &Actor,
+    change: TransactionUpload,
+) -> Result<UploadResult<Transaction>, HandlerError> {
+    let user_id = &actor.user_id;
+    match change {
+        TransactionUpload::Create {
+            client_ref,
+            mut transaction,
+        } => {
+            transaction.transactee =
+                normalize_transactee(transactee_repo, user_id, transaction.transactee).await?;
+            apply_user_rules(rule_repo, user_id, &mut transaction).await?;
+            let transaction = transaction_repo
+                .create_new_transaction(user_id, transaction)
+                .await?;
+            record_change(
+                changes,
+                actor,
+                ChangeKind::Create,
+                None,
+                Some(transaction.clone()),
+            )
+            .await?;
+            Ok(UploadResult {
+                client_ref,
+                ..UploadResult::applied(transaction.id, Some(transaction))
+            })
+        }
+        TransactionUpload::Update {
+            id,
+            base_version,
+            mut transaction,
+        } => {
+            let previous = match transaction_repo.get_transaction(user_id, id).await {
+                Ok(previous) => previous,
+                Err(TransactionRepoError::TransactionNotFound(_)) => {
+                    return Ok(UploadResult::conflict(id, None));
+                }
+                Err(e) => return Err(e.into()),
+            };
+            transaction.transactee =
+                normalize_transactee(transactee_repo, user_id, transaction.transactee).await?;
+            match transaction_repo
+                .update_transaction(user_id, id, transaction, Some(base_version))
+                .await
+            {
+                Ok(transaction) => {
+                    record_change(
+                        changes,
+                        actor,
+                        ChangeKind::Update,
+                        Some(previous),
+                        Some(transaction.clone()),
+                    )
+                    .await?;
+                    Ok(UploadResult::applied(id, Some(transaction)))
+                }
+                Err(e) => transaction_conflict(transaction_repo, user_id, id, e).await,
+            }
+        }
+        TransactionUpload::Delete { id, base_version } => {
+            match transaction_repo
+                .delete_transaction(user_id, id, Some(base_version))
+                .await
+            {
+                Ok(transaction) => {
+                    record_change(changes, actor, ChangeKind::Delete, Some(transaction), None)
+                        .await?;
+                    Ok(UploadResult::applied(id, None))
+                }
+                // Already deleted, so there is nothing to conflict with
+                Err(TransactionRepoError::TransactionNotFound(_)) => {
+                    Ok(UploadResult::applied(id, None))
+                }
+                Err(e) => transaction_conflict(transaction_repo, user_id, id, e).await,
+            }
+        }
+    }
+}
+
+async fn transaction_conflict(
+    transaction_repo: &dyn TransactionRepo,
+    user_id: &str,
+    id: i32,
+    error: TransactionRepoError,
+) -> Result<UploadResult<Transaction>, HandlerError> {
+    match error {
+        TransactionRepoError::VersionMismatch(_) | TransactionRepoError::TransactionNotFound(_) => {
+            match transaction_repo.get_transaction(user_id, id).await {
+                Ok(current) => Ok(UploadResult::conflict(id, Some(current))),
+                Err(TransactionRepoError::TransactionNotFound(_)) => {
+                    Ok(UploadResult::conflict(id, None))
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+        e => Err(e.into()),
+    }
+}
+
+async fn apply_template_upload(
+    template_repo: &dyn TransactionTemplateRepo,
+    user_id: &str,
+    change: TemplateUpload,
+) -> Result<UploadResult<TransactionTemplate>, HandlerError> {
+    match change {
+        TemplateUpload::Create {
+            client_ref,
+            template,
+        } => {
+            let template = template_repo.create_template(user_id, template).await?;
+            Ok(UploadResult {
+                client_ref,
+                ..UploadResult::applied(template.template_id, Some(template))
+            })
+        }
+        TemplateUpload::Update {
+            id,
+            base_version,
+            template,
+        } => {
+            match template_repo
+                .update_template(user_id, id, template, Some(base_version))
+                .await
+            {
+                Ok(template) => Ok(UploadResult::applied(id, Some(template))),
+                Err(e) => template_conflict(template_repo, user_id, id, e).await,
+            }
+        }
+        TemplateUpload::Delete { id, base_version } => {
+            match template_repo
+                .delete_template(user_id, id, Some(base_version))
+                .await
+            {
+                // Already deleted, so there is nothing to conflict with
+                Ok(_) | Err(TransactionTemplateRepoError::TemplateNotFound(_)) => {
+                    Ok(UploadResult::applied(id, None))
+                }
+                Err(e) => template_conflict(template_repo, user_id, id, e).await,
+            }
+        }
+    }
+}
+
+async fn template_conflict(
+    template_repo: &dyn TransactionTemplateRepo,
+    user_id: &str,
+    id: i32,
+    error: TransactionTemplateRepoError,
+) -> Result<UploadResult<TransactionTemplate>, HandlerError> {
+    match error {
+        TransactionTemplateRepoError::VersionMismatch(_)
+        | TransactionTemplateRepoError::TemplateNotFound(_) => {
+            match template_repo.get_template(user_id, id).await {
+                Ok(current) => Ok(UploadResult::conflict(id, Some(current))),
+                Err(TransactionTemplateRepoError::TemplateNotFound(_)) => {
+                    Ok(UploadResult::conflict(id, None))
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+        e => Err(e.into()),
+    }
+}
@@ -0,0 +1,77 @@
+mod handlers;
+
+use crate::error::HandlerError;
+use actix_web::{web, Scope};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ledger_repo::transaction_repo::TransactionRepo;
+use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
+
+/// Incremental sync for offline clients. `GET /sync` returns everything that changed since the
+/// client's sync token along with a new token, and `POST /sync` applies changes the client made
+/// while offline.
+pub fn sync_service() -> Scope {
+    web::scope("/sync")
+        .service(handlers::get_changes)
+        .service(handlers::upload_changes)
+}
+
+/// The latest change a client has seen. Clients treat it as opaque, which leaves room to change
+/// what it contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SyncToken(pub(crate) i64);
+
+impl SyncToken {
+    pub(crate) fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("v1:{}", self.0))
+    }
+
+    pub(crate) fn decode(token: &str) -> Result<SyncToken, HandlerError> {
+        let invalid = || HandlerError::BadRequest("Invalid sync token".to_string());
+        let decoded = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let change = decoded
+            .strip_prefix("v1:")
+            .and_then(|change| change.parse().ok())
+            .ok_or_else(invalid)?;
+        Ok(SyncToken(change))
+    }
+}
+
+/// Fails with [HandlerError::Gone] if deleted items were purged after `since`, as the client can no
+/// longer find out about them
+pub(crate) async fn ensure_not_purged(
+    transaction_repo: &dyn TransactionRepo,
+    template_repo: &dyn TransactionTemplateRepo,
+    user_id: &str,
+    since: i64,
+) -> Result<(), HandlerError> {
+    let last_purged = transaction_repo
+        .get_last_purged_change(user_id)
+        .await?
+        .max(template_repo.get_last_purged_change(user_id).await?);
+    if since < last_purged {
+        return Err(HandlerError::Gone(
+            "Deleted items have been purged since the last sync, sync again without a token"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyncToken;
+
+    #[test]
+    async fn token_round_trip() {
+        let token = SyncToken(1234);
+        assert_eq!(token, SyncToken::decode(&token.encode()).unwrap());
+    }
+
+    #[test]
+    async fn invalid_tokens() {
+        assert!(SyncToken::decode("not a token").is_err());
+        assert!(SyncToken::decode("MTIzNA").is_err());
+    }
+}
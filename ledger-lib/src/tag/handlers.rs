@@ -0,0 +1,151 @@
+use crate::error::HandlerError;
+use crate::history::{record_updates, Actor, ChangeRecorder};
+use crate::user::UserId;
+use actix_web::{web, HttpResponse, Responder};
+use ledger_repo::transaction_repo::TransactionRepo;
+use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct RenameTag {
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct MergeTags {
+    tags: Vec<String>,
+    into: String,
+}
+
+#[derive(Serialize)]
+pub struct TagStatsResponse {
+    tag: String,
+    count: i64,
+    total: Decimal,
+}
+
+/// Number of transactions and templates changed by a tag operation
+#[derive(Serialize)]
+pub struct TagChangesResponse {
+    transactions: u64,
+    templates: u64,
+}
+
+async fn merge(
+    transaction_repo: &Arc<dyn TransactionRepo>,
+    template_repo: &Arc<dyn TransactionTemplateRepo>,
+    changes: &ChangeRecorder,
+    actor: &Actor,
+    tags: &[String],
+    new_tag: &str,
+) -> Result<TagChangesResponse, HandlerError> {
+    if new_tag.is_empty() {
+        return Err(HandlerError::BadRequest(
+            "Tag name cannot be empty".to_string(),
+        ));
+    }
+
+    let user_id = &actor.user_id;
+    let updated = transaction_repo.merge_tags(user_id, tags, new_tag).await?;
+    let transactions = updated.len() as u64;
+    record_updates(changes, actor, updated).await?;
+
+    let templates = template_repo.merge_tags(user_id, tags, new_tag).await?;
+    Ok(TagChangesResponse {
+        transactions,
+        templates,
+    })
+}
+
+#[get("")]
+pub async fn get_all_tags(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let tags = transaction_repo.get_all_tags(&user_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+#[get("/stats")]
+pub async fn get_tag_stats(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let tag_stats: Vec<TagStatsResponse> = transaction_repo
+        .get_tag_stats(&user_id.into_inner())
+        .await?
+        .into_iter()
+        .map(|ts| TagStatsResponse {
+            tag: ts.tag,
+            count: ts.count,
+            total: ts.total,
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(tag_stats))
+}
+
+#[put("/{tag}")]
+pub async fn rename_tag(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
+    tag: web::Path<String>,
+    rename: web::Json<RenameTag>,
+) -> Result<impl Responder, HandlerError> {
+    let changes = merge(
+        &transaction_repo,
+        &template_repo,
+        &changes,
+        &actor,
+        &[tag.into_inner()],
+        &rename.into_inner().name,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(changes))
+}
+
+#[post("/merge")]
+pub async fn merge_tags(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
+    merge_tags: web::Json<MergeTags>,
+) -> Result<impl Responder, HandlerError> {
+    let merge_tags = merge_tags.into_inner();
+    let changes = merge(
+        &transaction_repo,
+        &template_repo,
+        &changes,
+        &actor,
+        &merge_tags.tags,
+        &merge_tags.into,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(changes))
+}
+
+#[delete("/{tag}")]
+pub async fn delete_tag(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
+    tag: web::Path<String>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = actor.user_id.clone();
+    let tag = tag.into_inner();
+
+    let updated = transaction_repo.delete_tag(&user_id, &tag).await?;
+    let transactions = updated.len() as u64;
+    record_updates(&changes, &actor, updated).await?;
+
+    let templates = template_repo.delete_tag(&user_id, &tag).await?;
+    Ok(HttpResponse::Ok().json(TagChangesResponse {
+        transactions,
+        templates,
+    }))
+}
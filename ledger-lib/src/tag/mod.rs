@@ -0,0 +1,14 @@
+mod handlers;
+
+use actix_web::{web, Scope};
+
+/// Tag endpoints. Tags are stored on transactions and templates, so changes made here are applied
+/// to both.
+pub fn tag_service() -> Scope {
+    web::scope("/tags")
+        .service(handlers::get_all_tags)
+        .service(handlers::get_tag_stats)
+        .service(handlers::merge_tags)
+        .service(handlers::rename_tag)
+        .service(handlers::delete_tag)
+}
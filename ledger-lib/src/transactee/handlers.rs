@@ -0,0 +1,179 @@
+use crate::error::HandlerError;
+use crate::history::{record_updates, Actor, ChangeRecorder};
+use crate::transactee::{alias_regex, canonical_name};
+use crate::user::UserId;
+use actix_web::{web, HttpResponse, Responder};
+use ledger_repo::transactee_repo::{Transactee, TransacteeRepo, TransacteeRepoError};
+use ledger_repo::transaction_repo::TransactionRepo;
+use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct TransacteesOption {
+    category: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TransacteeAliases {
+    aliases: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MergeTransactees {
+    transactees: Vec<String>,
+    into: String,
+}
+
+/// A canonical transactee with the number of transactions for it. Transactees that are not in the
+/// directory have no aliases.
+#[derive(Serialize)]
+pub struct TransacteeResponse {
+    name: String,
+    count: i64,
+    aliases: Vec<String>,
+}
+
+/// Number of transactions and templates changed by a merge
+#[derive(Serialize)]
+pub struct TransacteeChangesResponse {
+    transactions: u64,
+    templates: u64,
+}
+
+#[get("")]
+pub async fn get_all_transactees(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    transactee_repo: web::Data<Arc<dyn TransacteeRepo>>,
+    user_id: web::ReqData<UserId>,
+    options: web::Query<TransacteesOption>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    let directory = transactee_repo.get_transactees(&user_id).await?;
+
+    let mut transactees: HashMap<String, TransacteeResponse> = directory
+        .iter()
+        .map(|t| {
+            let response = TransacteeResponse {
+                name: t.name.clone(),
+                count: 0,
+                aliases: t.aliases.clone(),
+            };
+            (t.name.clone(), response)
+        })
+        .collect();
+    // Transactions from before an alias was added may still have the original name
+    for tc in transaction_repo
+        .get_all_transactees(&user_id, options.into_inner().category)
+        .await?
+    {
+        let name = canonical_name(&directory, &tc.transactee)
+            .map(str::to_owned)
+            .unwrap_or(tc.transactee);
+        transactees
+            .entry(name.clone())
+            .or_insert_with(|| TransacteeResponse {
+                name,
+                count: 0,
+                aliases: Vec::new(),
+            })
+            .count += tc.count;
+    }
+
+    let mut transactees: Vec<TransacteeResponse> = transactees.into_values().collect();
+    transactees.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    Ok(HttpResponse::Ok().json(transactees))
+}
+
+#[put("/{name}")]
+pub async fn set_transactee(
+    transactee_repo: web::Data<Arc<dyn TransacteeRepo>>,
+    user_id: web::ReqData<UserId>,
+    name: web::Path<String>,
+    aliases: web::Json<TransacteeAliases>,
+) -> Result<impl Responder, HandlerError> {
+    let aliases = aliases.into_inner().aliases;
+    for alias in &aliases {
+        alias_regex(alias).map_err(|e| {
+            HandlerError::BadRequest(format!("Invalid alias pattern '{}': {}", alias, e))
+        })?;
+    }
+
+    let transactee = transactee_repo
+        .set_transactee(
+            &user_id.into_inner(),
+            Transactee::new(name.into_inner(), aliases),
+        )
+        .await?;
+    Ok(HttpResponse::Ok().json(transactee))
+}
+
+#[delete("/{name}")]
+pub async fn delete_transactee(
+    transactee_repo: web::Data<Arc<dyn TransacteeRepo>>,
+    user_id: web::ReqData<UserId>,
+    name: web::Path<String>,
+) -> Result<impl Responder, HandlerError> {
+    let transactee = transactee_repo
+        .delete_transactee(&user_id.into_inner(), &name.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(transactee))
+}
+
+/// Merges transactees into another one. Transactions and templates are updated to use the new
+/// name, and the merged names become aliases of it so that they are normalized from now on.
+#[post("/merge")]
+pub async fn merge_transactees(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    transactee_repo: web::Data<Arc<dyn TransacteeRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
+    merge: web::Json<MergeTransactees>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = actor.user_id.clone();
+    let merge = merge.into_inner();
+    if merge.into.is_empty() {
+        return Err(HandlerError::BadRequest(
+            "Transactee name cannot be empty".to_string(),
+        ));
+    }
+
+    let updated = transaction_repo
+        .merge_transactees(&user_id, &merge.transactees, &merge.into)
+        .await?;
+    let transactions = updated.len() as u64;
+    record_updates(&changes, &actor, updated).await?;
+
+    let templates = template_repo
+        .merge_transactees(&user_id, &merge.transactees, &merge.into)
+        .await?;
+
+    let mut target = match transactee_repo.get_transactee(&user_id, &merge.into).await {
+        Ok(transactee) => transactee,
+        Err(TransacteeRepoError::TransacteeNotFound(_)) => {
+            Transactee::new(merge.into.clone(), Vec::new())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    for name in merge.transactees.iter().filter(|&n| n != &merge.into) {
+        let aliases = match transactee_repo.delete_transactee(&user_id, name).await {
+            Ok(transactee) => transactee.aliases,
+            Err(TransacteeRepoError::TransacteeNotFound(_)) => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let name_alias = format!("^{}$", regex::escape(name));
+        for alias in std::iter::once(name_alias).chain(aliases) {
+            if !target.aliases.contains(&alias) {
+                target.aliases.push(alias);
+            }
+        }
+    }
+    transactee_repo.set_transactee(&user_id, target).await?;
+
+    Ok(HttpResponse::Ok().json(TransacteeChangesResponse {
+        transactions,
+        templates,
+    }))
+}
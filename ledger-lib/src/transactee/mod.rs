@@ -0,0 +1,82 @@
+mod handlers;
+
+use crate::error::HandlerError;
+use actix_web::{web, Scope};
+use ledger_repo::transactee_repo::{Transactee, TransacteeRepo};
+use regex::{Regex, RegexBuilder};
+
+/// Transactee directory endpoints, nested under the transaction service
+pub fn transactee_service() -> Scope {
+    web::scope("/transactees")
+        .service(handlers::get_all_transactees)
+        .service(handlers::merge_transactees)
+        .service(handlers::set_transactee)
+        .service(handlers::delete_transactee)
+}
+
+/// Alias patterns are regular expressions matched anywhere in the name, ignoring case
+fn alias_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+/// Finds the canonical name for `name` in the transactee directory. A transactee matches if its
+/// canonical name is the same ignoring case, or any of its alias patterns match.
+fn canonical_name<'a>(transactees: &'a [Transactee], name: &str) -> Option<&'a str> {
+    transactees
+        .iter()
+        .find(|t| t.name.to_lowercase() == name.to_lowercase())
+        .or_else(|| {
+            transactees.iter().find(|t| {
+                t.aliases
+                    .iter()
+                    .filter_map(|alias| alias_regex(alias).ok())
+                    .any(|regex| regex.is_match(name))
+            })
+        })
+        .map(|t| t.name.as_str())
+}
+
+/// Replaces `transactee` with its canonical name if it matches an entry in the user's transactee
+/// directory
+pub(crate) async fn normalize_transactee(
+    transactee_repo: &dyn TransacteeRepo,
+    user_id: &str,
+    transactee: Option<String>,
+) -> Result<Option<String>, HandlerError> {
+    let Some(transactee) = transactee else {
+        return Ok(None);
+    };
+
+    let transactees = transactee_repo.get_transactees(user_id).await?;
+    let transactee = match canonical_name(&transactees, &transactee) {
+        Some(name) => name.to_owned(),
+        None => transactee,
+    };
+    Ok(Some(transactee))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::canonical_name;
+    use ledger_repo::transactee_repo::Transactee;
+
+    #[test]
+    async fn test_canonical_name() {
+        let transactees = vec![
+            Transactee::new(
+                "Amazon".to_string(),
+                vec!["^AMZN Mktp".to_string(), "amazon\\.com".to_string()],
+            ),
+            Transactee::new("Alice".to_string(), Vec::new()),
+        ];
+
+        assert_eq!(
+            Some("Amazon"),
+            canonical_name(&transactees, "AMZN Mktp US*2K4")
+        );
+        assert_eq!(Some("Amazon"), canonical_name(&transactees, "Amazon.com"));
+        assert_eq!(Some("Amazon"), canonical_name(&transactees, "AMAZON"));
+        assert_eq!(Some("Alice"), canonical_name(&transactees, "alice"));
+        assert_eq!(None, canonical_name(&transactees, "Bob"));
+    }
+}
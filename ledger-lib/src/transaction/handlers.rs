@@ -1,3 +1,4 @@
+use actix_web::http::header::IfMatch;
 use actix_web::web;
 use actix_web::HttpResponse;
 use actix_web::Responder;
@@ -6,11 +7,21 @@ use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use crate::duplicate::find_possible_duplicates;
 use crate::error::HandlerError;
+use crate::etag::{etag, expected_version, has_precondition};
+use crate::history::{record_change, Actor, ChangeRecorder};
+use crate::idempotency::Idempotency;
+use crate::merge_patch::apply_merge_patch;
+use crate::rule::apply_user_rules;
+use crate::transactee::normalize_transactee;
 use crate::user::UserId;
 
-use ledger_repo::transaction_repo::TransactionRepo;
-use ledger_repo::transaction_repo::{NewTransaction, PageOptions};
+use ledger_repo::history_repo::ChangeKind;
+use ledger_repo::rule_repo::RuleRepo;
+use ledger_repo::transactee_repo::TransacteeRepo;
+use ledger_repo::transaction_repo::{NewTransaction, PageOptions, Transaction};
+use ledger_repo::transaction_repo::{TransactionRepo, TransactionRepoError};
 
 #[derive(Deserialize)]
 pub struct Filter {
@@ -38,8 +49,19 @@ pub struct PageQueryParameters {
 }
 
 #[derive(Deserialize)]
-pub struct TransacteesOption {
-    category: Option<String>,
+pub struct CreateOptions {
+    #[serde(default)]
+    check_duplicates: bool,
+}
+
+/// The created transaction. When duplicates were checked for, also has the existing transactions
+/// it is likely a duplicate of.
+#[derive(Serialize)]
+pub struct CreatedTransactionResponse {
+    #[serde(flatten)]
+    transaction: Transaction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    possible_duplicates: Option<Vec<Transaction>>,
 }
 
 #[derive(Serialize)]
@@ -63,7 +85,9 @@ pub async fn get_transaction(
     let transaction = transaction_repo
         .get_transaction(&user_id.into_inner(), transaction_id.into_inner())
         .await?;
-    Ok(HttpResponse::Ok().json(transaction))
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(transaction.version))
+        .json(transaction))
 }
 
 #[get("")]
@@ -80,8 +104,8 @@ pub async fn get_transactions(
     }
 
     let filter = filter.into_inner();
-    let page_options = if page.offset.is_some() && page.limit.is_some() {
-        Some(PageOptions::new(page.offset.unwrap(), page.limit.unwrap()))
+    let page_options = if let (Some(offset), Some(limit)) = (page.offset, page.limit) {
+        Some(PageOptions::new(offset, limit))
     } else {
         None
     };
@@ -93,43 +117,184 @@ pub async fn get_transactions(
 }
 
 #[post("")]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_new_transaction(
     transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
-    user_id: web::ReqData<UserId>,
+    transactee_repo: web::Data<Arc<dyn TransacteeRepo>>,
+    rule_repo: web::Data<Arc<dyn RuleRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
+    idempotency: Idempotency,
+    options: web::Query<CreateOptions>,
     new_transaction: web::Json<NewTransaction>,
 ) -> Result<impl Responder, HandlerError> {
-    let transaction = transaction_repo
-        .create_new_transaction(&user_id.into_inner(), new_transaction.into_inner())
-        .await?;
-    Ok(HttpResponse::Ok().json(transaction))
+    let user_id = actor.user_id.clone();
+    idempotency
+        .request(&*new_transaction)?
+        .run(&user_id, async {
+            let mut new_transaction = new_transaction.into_inner();
+            new_transaction.transactee =
+                normalize_transactee(&***transactee_repo, &user_id, new_transaction.transactee)
+                    .await?;
+            apply_user_rules(&***rule_repo, &user_id, &mut new_transaction).await?;
+
+            let possible_duplicates = if options.check_duplicates {
+                Some(
+                    find_possible_duplicates(&***transaction_repo, &user_id, &new_transaction)
+                        .await?,
+                )
+            } else {
+                None
+            };
+
+            let transaction = transaction_repo
+                .create_new_transaction(&user_id, new_transaction)
+                .await?;
+            record_change(
+                &changes,
+                &actor,
+                ChangeKind::Create,
+                None,
+                Some(transaction.clone()),
+            )
+            .await?;
+            Ok(CreatedTransactionResponse {
+                transaction,
+                possible_duplicates,
+            })
+        })
+        .await
 }
 
 #[put("/{transaction_id}")]
 pub async fn update_transaction(
     transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
-    user_id: web::ReqData<UserId>,
+    transactee_repo: web::Data<Arc<dyn TransacteeRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
     transaction_id: web::Path<i32>,
+    if_match: web::Header<IfMatch>,
     updated_transaction: web::Json<NewTransaction>,
 ) -> Result<impl Responder, HandlerError> {
+    let transaction_id = transaction_id.into_inner();
+    let previous = transaction_repo
+        .get_transaction(&actor.user_id, transaction_id)
+        .await?;
+    let expected_version = expected_version(&if_match, previous.version)
+        .map_err(|_| TransactionRepoError::VersionMismatch(transaction_id))?;
+
+    save_update(
+        &***transaction_repo,
+        &***transactee_repo,
+        &changes,
+        &actor,
+        previous,
+        updated_transaction.into_inner(),
+        expected_version,
+    )
+    .await
+}
+
+/// Partially updates a transaction with a JSON Merge Patch
+#[patch("/{transaction_id}")]
+pub async fn patch_transaction(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    transactee_repo: web::Data<Arc<dyn TransacteeRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
+    transaction_id: web::Path<i32>,
+    if_match: web::Header<IfMatch>,
+    patch: web::Json<serde_json::Value>,
+) -> Result<impl Responder, HandlerError> {
+    let transaction_id = transaction_id.into_inner();
+    let previous = transaction_repo
+        .get_transaction(&actor.user_id, transaction_id)
+        .await?;
+    expected_version(&if_match, previous.version)
+        .map_err(|_| TransactionRepoError::VersionMismatch(transaction_id))?;
+    let updated_transaction =
+        apply_merge_patch(NewTransaction::from(previous.clone()), patch.into_inner())?;
+
+    // The patch was applied to the version read above, so it must not overwrite a newer one
+    let expected_version = Some(previous.version);
+    save_update(
+        &***transaction_repo,
+        &***transactee_repo,
+        &changes,
+        &actor,
+        previous,
+        updated_transaction,
+        expected_version,
+    )
+    .await
+}
+
+async fn save_update(
+    transaction_repo: &dyn TransactionRepo,
+    transactee_repo: &dyn TransacteeRepo,
+    changes: &ChangeRecorder,
+    actor: &Actor,
+    previous: Transaction,
+    mut updated_transaction: NewTransaction,
+    expected_version: Option<i32>,
+) -> Result<HttpResponse, HandlerError> {
+    updated_transaction.transactee = normalize_transactee(
+        transactee_repo,
+        &actor.user_id,
+        updated_transaction.transactee,
+    )
+    .await?;
+
     let transaction = transaction_repo
         .update_transaction(
-            &user_id.into_inner(),
-            transaction_id.into_inner(),
-            updated_transaction.into_inner(),
+            &actor.user_id,
+            previous.id,
+            updated_transaction,
+            expected_version,
         )
         .await?;
-    Ok(HttpResponse::Ok().json(transaction))
+    record_change(
+        changes,
+        actor,
+        ChangeKind::Update,
+        Some(previous),
+        Some(transaction.clone()),
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(transaction.version))
+        .json(transaction))
 }
 
 #[delete("/{transaction_id}")]
 pub async fn delete_transaction(
     transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
-    user_id: web::ReqData<UserId>,
+    changes: ChangeRecorder,
+    actor: Actor,
     transaction_id: web::Path<i32>,
+    if_match: web::Header<IfMatch>,
 ) -> Result<impl Responder, HandlerError> {
+    let transaction_id = transaction_id.into_inner();
+    let expected_version = if has_precondition(&if_match) {
+        let current = transaction_repo
+            .get_transaction(&actor.user_id, transaction_id)
+            .await?;
+        expected_version(&if_match, current.version)
+            .map_err(|_| TransactionRepoError::VersionMismatch(transaction_id))?
+    } else {
+        None
+    };
     let transaction = transaction_repo
-        .delete_transaction(&user_id.into_inner(), transaction_id.into_inner())
+        .delete_transaction(&actor.user_id, transaction_id, expected_version)
         .await?;
+    record_change(
+        &changes,
+        &actor,
+        ChangeKind::Delete,
+        Some(transaction.clone()),
+        None,
+    )
+    .await?;
     Ok(HttpResponse::Ok().json(transaction))
 }
 
@@ -153,38 +318,6 @@ pub async fn get_monthly_totals(
     Ok(HttpResponse::Ok().json(monthly_totals))
 }
 
-#[get("/categories")]
-pub async fn get_all_categories(
-    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
-    user_id: web::ReqData<UserId>,
-) -> Result<impl Responder, HandlerError> {
-    let categories = transaction_repo
-        .get_all_categories(&user_id.into_inner())
-        .await?;
-    Ok(HttpResponse::Ok().json(categories))
-}
-
-#[get("/tags")]
-pub async fn get_all_tags(
-    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
-    user_id: web::ReqData<UserId>,
-) -> Result<impl Responder, HandlerError> {
-    let tags = transaction_repo.get_all_tags(&user_id.into_inner()).await?;
-    Ok(HttpResponse::Ok().json(tags))
-}
-
-#[get("/transactees")]
-pub async fn get_all_transactees(
-    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
-    user_id: web::ReqData<UserId>,
-    options: web::Query<TransacteesOption>,
-) -> Result<impl Responder, HandlerError> {
-    let transactees = transaction_repo
-        .get_all_transactees(&user_id.into_inner(), options.into_inner().category)
-        .await?;
-    Ok(HttpResponse::Ok().json(transactees))
-}
-
 #[get("/balance")]
 pub async fn get_balance(
     transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
@@ -1,17 +1,24 @@
 mod handlers;
 
+pub(crate) use handlers::Filter;
+
+use crate::{category, duplicate, history, suggestion, tag, transactee};
 use actix_web::{web, Scope};
 
 pub fn transaction_service() -> Scope {
     web::scope("/transactions")
-        .service(handlers::get_all_categories)
-        .service(handlers::get_all_tags)
-        .service(handlers::get_all_transactees)
+        .service(category::category_service())
+        .service(duplicate::duplicate_service())
+        .service(history::history_service())
+        .service(suggestion::suggestion_service())
+        .service(tag::tag_service())
+        .service(transactee::transactee_service())
         .service(handlers::get_balance)
         .service(handlers::get_monthly_totals)
         .service(handlers::get_transaction)
         .service(handlers::get_transactions)
         .service(handlers::create_new_transaction)
         .service(handlers::update_transaction)
+        .service(handlers::patch_transaction)
         .service(handlers::delete_transaction)
 }
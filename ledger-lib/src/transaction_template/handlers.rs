@@ -1,19 +1,49 @@
 use crate::error::HandlerError;
+use crate::etag::{etag, expected_version, has_precondition};
+use crate::idempotency::Idempotency;
+use crate::merge_patch::apply_merge_patch;
 use crate::user::UserId;
+use actix_web::http::header::IfMatch;
 use actix_web::{web, HttpResponse, Responder};
-use ledger_repo::transaction_template_repo::{NewTransactionTemplate, TransactionTemplateRepo};
+use ledger_repo::transaction_template_repo::{
+    NewTransactionTemplate, TransactionTemplateRepo, TransactionTemplateRepoError,
+};
 use std::sync::Arc;
 
+/// Checks the `If-Match` header against the current version of the template, returning the version
+/// the write has to be made against
+async fn check_if_match(
+    template_repo: &dyn TransactionTemplateRepo,
+    user_id: &str,
+    template_id: i32,
+    if_match: &IfMatch,
+) -> Result<Option<i32>, HandlerError> {
+    if !has_precondition(if_match) {
+        return Ok(None);
+    }
+    let current = template_repo.get_template(user_id, template_id).await?;
+    let expected_version = expected_version(if_match, current.version)
+        .map_err(|_| TransactionTemplateRepoError::VersionMismatch(template_id))?;
+    Ok(expected_version)
+}
+
 #[post("")]
 pub async fn create_template(
     template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
     user_id: web::ReqData<UserId>,
+    idempotency: Idempotency,
     new_template: web::Json<NewTransactionTemplate>,
 ) -> Result<impl Responder, HandlerError> {
-    let template = template_repo
-        .create_template(&user_id.into_inner(), new_template.into_inner())
-        .await?;
-    Ok(HttpResponse::Ok().json(template))
+    let user_id = user_id.into_inner();
+    idempotency
+        .request(&*new_template)?
+        .run(&user_id, async {
+            let template = template_repo
+                .create_template(&user_id, new_template.into_inner())
+                .await?;
+            Ok(template)
+        })
+        .await
 }
 
 #[put("/{template_id}")]
@@ -21,16 +51,65 @@ pub async fn update_template(
     template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
     user_id: web::ReqData<UserId>,
     template_id: web::Path<i32>,
+    if_match: web::Header<IfMatch>,
     updated_template: web::Json<NewTransactionTemplate>,
 ) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    let template_id = template_id.into_inner();
+    let expected_version =
+        check_if_match(&***template_repo, &user_id, template_id, &if_match).await?;
+
     let template = template_repo
         .update_template(
-            &user_id.into_inner(),
-            template_id.into_inner(),
+            &user_id,
+            template_id,
             updated_template.into_inner(),
+            expected_version,
         )
         .await?;
-    Ok(HttpResponse::Ok().json(template))
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(template.version))
+        .json(template))
+}
+
+/// Partially updates a template with a JSON Merge Patch
+#[patch("/{template_id}")]
+pub async fn patch_template(
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    user_id: web::ReqData<UserId>,
+    template_id: web::Path<i32>,
+    if_match: web::Header<IfMatch>,
+    patch: web::Json<serde_json::Value>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    let template_id = template_id.into_inner();
+    let current = template_repo.get_template(&user_id, template_id).await?;
+    expected_version(&if_match, current.version)
+        .map_err(|_| TransactionTemplateRepoError::VersionMismatch(template_id))?;
+
+    let version = current.version;
+    let updated_template =
+        apply_merge_patch(NewTransactionTemplate::from(current), patch.into_inner())?;
+    let template = template_repo
+        .update_template(&user_id, template_id, updated_template, Some(version))
+        .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(template.version))
+        .json(template))
+}
+
+#[get("/{template_id}")]
+pub async fn get_template(
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    user_id: web::ReqData<UserId>,
+    template_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    let template = template_repo
+        .get_template(&user_id.into_inner(), template_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(template.version))
+        .json(template))
 }
 
 #[get("")]
@@ -47,9 +126,15 @@ pub async fn delete_template(
     template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
     user_id: web::ReqData<UserId>,
     template_id: web::Path<i32>,
+    if_match: web::Header<IfMatch>,
 ) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    let template_id = template_id.into_inner();
+    let expected_version =
+        check_if_match(&***template_repo, &user_id, template_id, &if_match).await?;
+
     let template = template_repo
-        .delete_template(&user_id.into_inner(), template_id.into_inner())
+        .delete_template(&user_id, template_id, expected_version)
         .await?;
     Ok(HttpResponse::Ok().json(template))
 }
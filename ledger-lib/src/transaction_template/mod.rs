@@ -6,6 +6,8 @@ pub fn transaction_template_service() -> Scope {
     web::scope("/templates")
         .service(handlers::create_template)
         .service(handlers::get_all_templates)
+        .service(handlers::get_template)
         .service(handlers::update_template)
+        .service(handlers::patch_template)
         .service(handlers::delete_template)
 }
@@ -0,0 +1,63 @@
+use crate::error::HandlerError;
+use crate::history::{record_change, Actor, ChangeRecorder};
+use crate::user::UserId;
+use actix_web::{web, HttpResponse, Responder};
+use ledger_repo::history_repo::ChangeKind;
+use ledger_repo::transaction_repo::TransactionRepo;
+use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
+use std::sync::Arc;
+
+#[get("/transactions")]
+pub async fn get_deleted_transactions(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let transactions = transaction_repo
+        .get_deleted_transactions(&user_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(transactions))
+}
+
+#[post("/transactions/{transaction_id}/restore")]
+pub async fn restore_transaction(
+    transaction_repo: web::Data<Arc<dyn TransactionRepo>>,
+    changes: ChangeRecorder,
+    actor: Actor,
+    transaction_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    let transaction = transaction_repo
+        .restore_transaction(&actor.user_id, transaction_id.into_inner())
+        .await?;
+    record_change(
+        &changes,
+        &actor,
+        ChangeKind::Restore,
+        None,
+        Some(transaction.clone()),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(transaction))
+}
+
+#[get("/templates")]
+pub async fn get_deleted_templates(
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let templates = template_repo
+        .get_deleted_templates(&user_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(templates))
+}
+
+#[post("/templates/{template_id}/restore")]
+pub async fn restore_template(
+    template_repo: web::Data<Arc<dyn TransactionTemplateRepo>>,
+    user_id: web::ReqData<UserId>,
+    template_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    let template = template_repo
+        .restore_template(&user_id.into_inner(), template_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(template))
+}
@@ -0,0 +1,47 @@
+mod handlers;
+
+use actix_web::{web, Scope};
+use chrono::{Duration, Utc};
+use ledger_repo::Repos;
+use std::time::Duration as StdDuration;
+use tracing::{error, info};
+
+/// How often the trash is checked for items that are past the retention period
+pub const PURGE_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60 * 24);
+
+pub fn trash_service() -> Scope {
+    web::scope("/trash")
+        .service(handlers::get_deleted_transactions)
+        .service(handlers::restore_transaction)
+        .service(handlers::get_deleted_templates)
+        .service(handlers::restore_template)
+}
+
+/// Permanently deletes the transactions and templates that have been in the trash for longer than
+/// `retention_days`
+pub async fn purge_trash(repos: &Repos, retention_days: i64) -> Result<(), anyhow::Error> {
+    let deleted_before = Utc::now() - Duration::days(retention_days);
+
+    let transactions = repos
+        .transaction_repo
+        .purge_deleted_transactions(deleted_before)
+        .await?;
+    let templates = repos
+        .template_repo
+        .purge_deleted_templates(deleted_before)
+        .await?;
+    info!(transactions, templates, "Purged trash");
+
+    Ok(())
+}
+
+/// Purges the trash every [PURGE_INTERVAL]. Errors are logged, so this never returns.
+pub async fn purge_trash_periodically(repos: Repos, retention_days: i64) {
+    let mut interval = actix_web::rt::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = purge_trash(&repos, retention_days).await {
+            error!(%e, "Unable to purge trash");
+        }
+    }
+}
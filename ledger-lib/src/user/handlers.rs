@@ -1,20 +1,104 @@
 use actix_web::{web, HttpResponse, Responder};
-use serde::Deserialize;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 use super::UserId;
 use crate::auth;
+use crate::auth::api_key;
+use crate::auth::invite_code;
+use crate::auth::password::Passwords;
+use crate::auth::refresh_token;
+use crate::auth::totp;
+use crate::auth::totp::SecondFactor;
 use crate::error::HandlerError;
-use ledger_repo::user_repo::UserRepo;
+use ledger_repo::api_key_repo::{ApiKey, ApiKeyRepo, NewApiKey};
+use ledger_repo::refresh_token_repo::RefreshTokenRepo;
+use ledger_repo::session_repo::SessionRepo;
+use ledger_repo::totp_repo::{TotpRepo, TotpRepoError};
+use ledger_repo::user_repo::{InviteCode, NewInviteCode, UserRepo};
 
 #[derive(Deserialize)]
 pub struct NewPassword {
     new_password: String,
 }
 
+/// The only response that includes the key, which can't be recovered from its hash
+#[derive(Serialize)]
+pub struct CreatedApiKeyResponse {
+    #[serde(flatten)]
+    api_key: ApiKey,
+    key: String,
+}
+
+/// What an authenticator app needs. The secret is shown for entering by hand.
+#[derive(Serialize)]
+pub struct TotpEnrollment {
+    secret: String,
+    otpauth_uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct TotpCode {
+    code: String,
+}
+
+/// The only response that includes the recovery codes, which are stored hashed
+#[derive(Serialize)]
+pub struct RecoveryCodesResponse {
+    recovery_codes: Vec<String>,
+}
+
+fn validate_api_key(api_key: &NewApiKey) -> Result<(), HandlerError> {
+    if api_key.name.trim().is_empty() {
+        return Err(HandlerError::BadRequest(
+            "API key name must not be empty".to_string(),
+        ));
+    }
+    if api_key.scopes.is_empty() {
+        return Err(HandlerError::BadRequest(
+            "API key must have at least one scope".to_string(),
+        ));
+    }
+    if api_key
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(HandlerError::BadRequest(
+            "API key expiry must be in the future".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The only response that includes the code, which can't be recovered from its hash
+#[derive(Serialize)]
+pub struct CreatedInviteCodeResponse {
+    #[serde(flatten)]
+    invite_code: InviteCode,
+    code: String,
+}
+
+fn validate_invite_code(invite_code: &NewInviteCode) -> Result<(), HandlerError> {
+    if invite_code.max_uses < 1 {
+        return Err(HandlerError::BadRequest(
+            "Invite code must allow at least one use".to_string(),
+        ));
+    }
+    if invite_code.expires_at <= Utc::now() {
+        return Err(HandlerError::BadRequest(
+            "Invite code expiry must be in the future".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Changes the password and logs out every session, including the current one
 #[put("/password")]
 pub async fn update_password(
     user_repo: web::Data<Arc<dyn UserRepo>>,
+    refresh_token_repo: web::Data<Arc<dyn RefreshTokenRepo>>,
+    passwords: web::Data<Passwords>,
     user_id: Option<web::ReqData<UserId>>,
     credentials: web::Json<NewPassword>,
 ) -> Result<impl Responder, HandlerError> {
@@ -22,10 +106,14 @@ pub async fn update_password(
         return Ok(HttpResponse::Unauthorized().finish());
     }
 
-    let password_hash = auth::password::encode_password(credentials.into_inner().new_password)?;
+    let user_id = user_id.unwrap().into_inner();
+    let new_password = credentials.into_inner().new_password;
+    passwords.check_policy(&new_password)?;
+    let password_hash = passwords.encode_password(&new_password)?;
     user_repo
-        .update_password_hash(&user_id.unwrap().into_inner(), &password_hash)
+        .update_password_hash(&user_id, &password_hash)
         .await?;
+    refresh_token_repo.revoke_all_families(&user_id).await?;
 
     Ok(HttpResponse::Ok().finish())
 }
@@ -45,3 +133,199 @@ pub async fn delete_user(
 
     Ok(HttpResponse::Ok().finish())
 }
+
+/// The devices the user is logged in on, most recently used first
+#[get("/sessions")]
+pub async fn get_sessions(
+    session_repo: web::Data<Arc<dyn SessionRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let sessions = session_repo
+        .get_sessions(&user_id.into_inner(), refresh_token::issued_after())
+        .await?;
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+/// Logs a device out. Its access token stays valid until it expires.
+#[delete("/sessions/{session_id}")]
+pub async fn revoke_session(
+    session_repo: web::Data<Arc<dyn SessionRepo>>,
+    user_id: web::ReqData<UserId>,
+    session_id: web::Path<String>,
+) -> Result<impl Responder, HandlerError> {
+    session_repo
+        .revoke_session(&user_id.into_inner(), &session_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[get("/api_keys")]
+pub async fn get_api_keys(
+    api_key_repo: web::Data<Arc<dyn ApiKeyRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let api_keys = api_key_repo.get_api_keys(&user_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(api_keys))
+}
+
+#[post("/api_keys")]
+pub async fn create_api_key(
+    api_key_repo: web::Data<Arc<dyn ApiKeyRepo>>,
+    user_id: web::ReqData<UserId>,
+    new_api_key: web::Json<NewApiKey>,
+) -> Result<impl Responder, HandlerError> {
+    let new_api_key = new_api_key.into_inner();
+    validate_api_key(&new_api_key)?;
+
+    let key = api_key::generate_key();
+    let api_key = api_key_repo
+        .create_api_key(&user_id.into_inner(), new_api_key, &auth::hash_token(&key))
+        .await?;
+    Ok(HttpResponse::Ok().json(CreatedApiKeyResponse { api_key, key }))
+}
+
+/// Revokes the key. Requests using it fail from then on.
+#[delete("/api_keys/{api_key_id}")]
+pub async fn delete_api_key(
+    api_key_repo: web::Data<Arc<dyn ApiKeyRepo>>,
+    user_id: web::ReqData<UserId>,
+    api_key_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    api_key_repo
+        .delete_api_key(&user_id.into_inner(), api_key_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Starts setting up two-factor authentication with a new secret. It isn't required when logging
+/// in until it is confirmed.
+#[post("/totp")]
+pub async fn enroll_totp(
+    totp_repo: web::Data<Arc<dyn TotpRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    if totp_repo
+        .get_totp(&user_id)
+        .await?
+        .is_some_and(|totp| totp.enabled)
+    {
+        return Err(HandlerError::Conflict(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+
+    let secret = totp::generate_secret();
+    totp_repo.create_totp(&user_id, secret.clone()).await?;
+    Ok(HttpResponse::Ok().json(TotpEnrollment {
+        secret: totp::encode_secret(&secret),
+        otpauth_uri: totp::otpauth_uri(&secret, &user_id),
+    }))
+}
+
+/// Enables two-factor authentication once the user shows that their authenticator app generates
+/// valid codes. Responds with the recovery codes.
+#[post("/totp/confirm")]
+pub async fn confirm_totp(
+    totp_repo: web::Data<Arc<dyn TotpRepo>>,
+    user_id: web::ReqData<UserId>,
+    code: web::Json<TotpCode>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    let totp = totp_repo
+        .get_totp(&user_id)
+        .await?
+        .ok_or_else(|| TotpRepoError::TotpNotFound(user_id.clone()))?;
+    if totp.enabled {
+        return Err(HandlerError::Conflict(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+
+    let step = totp::verify_code(&totp.secret, &code.code, Utc::now())
+        .ok_or_else(|| HandlerError::BadRequest("Invalid code".to_string()))?;
+    totp_repo.use_step(&user_id, step).await?;
+
+    let recovery_codes = totp::generate_recovery_codes();
+    totp_repo
+        .enable_totp(
+            &user_id,
+            recovery_codes
+                .iter()
+                .map(|code| totp::hash_recovery_code(code))
+                .collect(),
+        )
+        .await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// Turns two-factor authentication off, which needs a code if it is enabled
+#[delete("/totp")]
+pub async fn disable_totp(
+    totp_repo: web::Data<Arc<dyn TotpRepo>>,
+    user_id: web::ReqData<UserId>,
+    second_factor: web::Json<SecondFactor>,
+) -> Result<impl Responder, HandlerError> {
+    let user_id = user_id.into_inner();
+    let totp = totp_repo
+        .get_totp(&user_id)
+        .await?
+        .ok_or_else(|| TotpRepoError::TotpNotFound(user_id.clone()))?;
+    if totp.enabled
+        && !totp::verify_second_factor(
+            totp_repo.as_ref().as_ref(),
+            &user_id,
+            &totp.secret,
+            &second_factor,
+        )
+        .await?
+    {
+        return Err(HandlerError::BadRequest("Invalid code".to_string()));
+    }
+
+    totp_repo.delete_totp(&user_id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[get("/invite_codes")]
+pub async fn get_invite_codes(
+    user_repo: web::Data<Arc<dyn UserRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let invite_codes = user_repo.get_invite_codes(&user_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(invite_codes))
+}
+
+/// Creates a code that lets people sign up when signups need an invite
+#[post("/invite_codes")]
+pub async fn create_invite_code(
+    user_repo: web::Data<Arc<dyn UserRepo>>,
+    user_id: web::ReqData<UserId>,
+    new_invite_code: web::Json<NewInviteCode>,
+) -> Result<impl Responder, HandlerError> {
+    let new_invite_code = new_invite_code.into_inner();
+    validate_invite_code(&new_invite_code)?;
+
+    let code = invite_code::generate_code();
+    let invite_code = user_repo
+        .create_invite_code(
+            &user_id.into_inner(),
+            new_invite_code,
+            &invite_code::hash_code(&code),
+        )
+        .await?;
+    Ok(HttpResponse::Ok().json(CreatedInviteCodeResponse { invite_code, code }))
+}
+
+/// Revokes the code. Accounts already created with it are kept.
+#[delete("/invite_codes/{invite_code_id}")]
+pub async fn delete_invite_code(
+    user_repo: web::Data<Arc<dyn UserRepo>>,
+    user_id: web::ReqData<UserId>,
+    invite_code_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    user_repo
+        .delete_invite_code(&user_id.into_inner(), invite_code_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
@@ -8,4 +8,15 @@ pub fn user_service() -> Scope {
     web::scope("/user")
         .service(handlers::update_password)
         .service(handlers::delete_user)
+        .service(handlers::get_sessions)
+        .service(handlers::revoke_session)
+        .service(handlers::get_api_keys)
+        .service(handlers::create_api_key)
+        .service(handlers::delete_api_key)
+        .service(handlers::enroll_totp)
+        .service(handlers::confirm_totp)
+        .service(handlers::disable_totp)
+        .service(handlers::get_invite_codes)
+        .service(handlers::create_invite_code)
+        .service(handlers::delete_invite_code)
 }
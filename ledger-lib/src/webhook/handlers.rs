@@ -0,0 +1,111 @@
+use crate::error::HandlerError;
+use crate::user::UserId;
+use crate::webhook::WebhookTargets;
+use actix_web::{web, HttpResponse, Responder};
+use ledger_repo::webhook_repo::{NewWebhook, Webhook, WebhookRepo};
+use rand::Rng;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Number of deliveries shown in a webhook's delivery log
+const DELIVERY_LOG_LIMIT: i64 = 100;
+
+/// The only response that includes the secret, which is needed to check signatures
+#[derive(Serialize)]
+pub struct CreatedWebhookResponse {
+    #[serde(flatten)]
+    webhook: Webhook,
+    secret: String,
+}
+
+async fn validate_webhook(
+    webhook: &NewWebhook,
+    targets: &WebhookTargets,
+) -> Result<(), HandlerError> {
+    let url = reqwest::Url::parse(&webhook.url)
+        .map_err(|e| HandlerError::BadRequest(format!("Invalid webhook url: {}", e)))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(HandlerError::BadRequest(
+            "Webhook url must be http or https".to_string(),
+        ));
+    }
+    if webhook.events.is_empty() {
+        return Err(HandlerError::BadRequest(
+            "Webhook must subscribe to at least one event".to_string(),
+        ));
+    }
+    // Checked again when deliveries are sent, as the host can resolve to other addresses later
+    targets
+        .check_url(&url)
+        .await
+        .map_err(HandlerError::BadRequest)?;
+    Ok(())
+}
+
+#[get("")]
+pub async fn get_webhooks(
+    webhook_repo: web::Data<Arc<dyn WebhookRepo>>,
+    user_id: web::ReqData<UserId>,
+) -> Result<impl Responder, HandlerError> {
+    let webhooks = webhook_repo.get_webhooks(&user_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+#[post("")]
+pub async fn create_webhook(
+    webhook_repo: web::Data<Arc<dyn WebhookRepo>>,
+    targets: web::Data<WebhookTargets>,
+    user_id: web::ReqData<UserId>,
+    new_webhook: web::Json<NewWebhook>,
+) -> Result<impl Responder, HandlerError> {
+    let new_webhook = new_webhook.into_inner();
+    validate_webhook(&new_webhook, &targets).await?;
+
+    let secret: [u8; 32] = rand::thread_rng().gen();
+    let secret = hex::encode(secret);
+    let webhook = webhook_repo
+        .create_webhook(&user_id.into_inner(), new_webhook, secret.clone())
+        .await?;
+    Ok(HttpResponse::Ok().json(CreatedWebhookResponse { webhook, secret }))
+}
+
+#[get("/{webhook_id}")]
+pub async fn get_webhook(
+    webhook_repo: web::Data<Arc<dyn WebhookRepo>>,
+    user_id: web::ReqData<UserId>,
+    webhook_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    let webhook = webhook_repo
+        .get_webhook(&user_id.into_inner(), webhook_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+#[delete("/{webhook_id}")]
+pub async fn delete_webhook(
+    webhook_repo: web::Data<Arc<dyn WebhookRepo>>,
+    user_id: web::ReqData<UserId>,
+    webhook_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    webhook_repo
+        .delete_webhook(&user_id.into_inner(), webhook_id.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// The latest deliveries to the webhook and the result of their last attempt, newest first
+#[get("/{webhook_id}/deliveries")]
+pub async fn get_deliveries(
+    webhook_repo: web::Data<Arc<dyn WebhookRepo>>,
+    user_id: web::ReqData<UserId>,
+    webhook_id: web::Path<i32>,
+) -> Result<impl Responder, HandlerError> {
+    let deliveries = webhook_repo
+        .get_deliveries(
+            &user_id.into_inner(),
+            webhook_id.into_inner(),
+            DELIVERY_LOG_LIMIT,
+        )
+        .await?;
+    Ok(HttpResponse::Ok().json(deliveries))
+}
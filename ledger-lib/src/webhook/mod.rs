@@ -0,0 +1,228 @@
+mod handlers;
+mod target;
+
+use crate::error::HandlerError;
+use actix_web::{web, Scope};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use ledger_repo::history_repo::ChangeKind;
+use ledger_repo::transaction_repo::Transaction;
+use ledger_repo::webhook_repo::{
+    DeliveryAttempt, DeliveryStatus, DueDelivery, Webhook, WebhookEvent, WebhookRepo,
+    WebhookRepoError,
+};
+use ledger_repo::Repos;
+use sha2::Sha256;
+use std::sync::Arc;
+use target::TargetResolver;
+use tracing::{error, warn};
+
+pub use target::WebhookTargets;
+
+pub const SIGNATURE_HEADER: &str = "X-Ledger-Signature";
+pub const EVENT_HEADER: &str = "X-Ledger-Event";
+pub const DELIVERY_HEADER: &str = "X-Ledger-Delivery";
+
+/// How often [deliver_webhooks_periodically] checks for due deliveries
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Deliveries are claimed for this many minutes, which has to be longer than a request can take
+const CLAIM_MINUTES: i64 = 1;
+const BATCH_SIZE: i64 = 50;
+
+pub fn webhook_service() -> Scope {
+    web::scope("/webhooks")
+        .service(handlers::get_webhooks)
+        .service(handlers::create_webhook)
+        .service(handlers::get_webhook)
+        .service(handlers::delete_webhook)
+        .service(handlers::get_deliveries)
+}
+
+/// Signs a payload the way receivers are expected to check it: the hex encoded HMAC-SHA256 of the
+/// body, keyed with the webhook's secret and prefixed with `sha256=`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn matches(webhook: &Webhook, event: WebhookEvent, transaction: &Transaction) -> bool {
+    let filter = &webhook.filter;
+    webhook.events.contains(&event)
+        && filter
+            .category
+            .as_ref()
+            .is_none_or(|category| *category == transaction.category)
+        && filter
+            .min_expense
+            .is_none_or(|min_expense| -transaction.amount >= min_expense)
+}
+
+/// Queues a delivery to each of the user's webhooks that is interested in the change
+pub(crate) async fn queue_transaction_event(
+    webhook_repo: &dyn WebhookRepo,
+    user_id: &str,
+    kind: ChangeKind,
+    transaction: &Transaction,
+) -> Result<(), HandlerError> {
+    let event = match kind {
+        ChangeKind::Create => WebhookEvent::TransactionCreated,
+        ChangeKind::Update | ChangeKind::Revert => WebhookEvent::TransactionUpdated,
+        ChangeKind::Delete => WebhookEvent::TransactionDeleted,
+        ChangeKind::Restore => WebhookEvent::TransactionRestored,
+    };
+
+    for webhook in webhook_repo.get_webhooks(user_id).await? {
+        if !matches(&webhook, event, transaction) {
+            continue;
+        }
+        let payload = serde_json::json!({
+            "event": event,
+            "created_at": Utc::now(),
+            "transaction": transaction,
+        });
+        webhook_repo
+            .queue_delivery(webhook.id, event, payload)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Sends queued deliveries. Deliveries that fail are retried with exponential backoff, starting
+/// at `retry_delay`, until they have been attempted `max_attempts` times. Deliveries are only
+/// sent to addresses `targets` allows, and redirects aren't followed, as they could lead anywhere.
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+    targets: WebhookTargets,
+    max_attempts: i32,
+    retry_delay: Duration,
+}
+
+impl WebhookDispatcher {
+    pub fn new(
+        targets: WebhookTargets,
+        max_attempts: i32,
+        retry_delay: Duration,
+    ) -> WebhookDispatcher {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(TargetResolver(targets)))
+            .build()
+            .expect("HTTP client should be built");
+        WebhookDispatcher {
+            client,
+            targets,
+            max_attempts,
+            retry_delay,
+        }
+    }
+
+    /// Retries for a little over an hour
+    pub fn with_targets(targets: WebhookTargets) -> WebhookDispatcher {
+        WebhookDispatcher::new(targets, 8, Duration::seconds(30))
+    }
+
+    /// Sends the deliveries that are due, returning how many were attempted
+    pub async fn deliver_due(
+        &self,
+        webhook_repo: &dyn WebhookRepo,
+    ) -> Result<usize, WebhookRepoError> {
+        let mut attempted = 0;
+        loop {
+            let now = Utc::now();
+            let due = webhook_repo
+                .claim_due_deliveries(now, now + Duration::minutes(CLAIM_MINUTES), BATCH_SIZE)
+                .await?;
+            if due.is_empty() {
+                return Ok(attempted);
+            }
+            for due in due {
+                let attempt = self.attempt(&due).await;
+                webhook_repo
+                    .record_attempt(due.delivery.id, attempt)
+                    .await?;
+                attempted += 1;
+            }
+        }
+    }
+
+    async fn send(&self, due: &DueDelivery) -> Result<reqwest::Response, String> {
+        let url = reqwest::Url::parse(&due.url).map_err(|e| e.to_string())?;
+        // Hosts that are names are checked as the client resolves them
+        self.targets.check_ip_host(&url)?;
+        let body = due.delivery.payload.to_string();
+        self.client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, sign(&due.secret, body.as_bytes()))
+            .header(EVENT_HEADER, due.delivery.event.to_string())
+            .header(DELIVERY_HEADER, due.delivery.id.to_string())
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn attempt(&self, due: &DueDelivery) -> DeliveryAttempt {
+        let response = self.send(due).await;
+
+        let (response_status, error) = match response {
+            Ok(response) if response.status().is_success() => {
+                return DeliveryAttempt {
+                    status: DeliveryStatus::Succeeded,
+                    response_status: Some(response.status().as_u16() as i32),
+                    error: None,
+                    next_attempt_at: Utc::now(),
+                };
+            }
+            Ok(response) => (
+                Some(response.status().as_u16() as i32),
+                format!("Unexpected response status {}", response.status()),
+            ),
+            Err(error) => (None, error),
+        };
+        warn!(delivery_id = due.delivery.id, %error, "Webhook delivery failed");
+
+        let attempts = due.delivery.attempts + 1;
+        let status = if attempts >= self.max_attempts {
+            DeliveryStatus::Failed
+        } else {
+            DeliveryStatus::Pending
+        };
+        DeliveryAttempt {
+            status,
+            response_status,
+            error: Some(error),
+            next_attempt_at: Utc::now() + self.retry_delay * 2i32.pow(attempts as u32 - 1),
+        }
+    }
+}
+
+/// Sends due deliveries every [POLL_INTERVAL]. Errors are logged, so this never returns.
+pub async fn deliver_webhooks_periodically(repos: Repos, dispatcher: WebhookDispatcher) {
+    let mut interval = actix_web::rt::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = dispatcher.deliver_due(&*repos.webhook_repo).await {
+            error!(%e, "Unable to deliver webhooks");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign;
+
+    #[test]
+    async fn hmac_sha256_signature() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            sign("Jefe", b"what do ya want for nothing?")
+        );
+    }
+}
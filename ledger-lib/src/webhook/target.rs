@@ -0,0 +1,152 @@
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::Url;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Which addresses webhooks can be sent to. Unless private targets are allowed, only public
+/// addresses are, so a webhook can't be used to make requests into the server's own network.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct WebhookTargets {
+    /// Whether loopback, private and link-local addresses can be sent to as well
+    pub allow_private: bool,
+}
+
+impl WebhookTargets {
+    fn check_ip(&self, ip: IpAddr) -> Result<(), String> {
+        if self.allow_private || is_public(ip) {
+            Ok(())
+        } else {
+            Err(format!("Webhooks can't be sent to {}", ip))
+        }
+    }
+
+    /// Resolves a host name, failing if any of its addresses can't be sent to
+    async fn resolve(&self, host: &str) -> Result<Vec<SocketAddr>, String> {
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+            .await
+            .map_err(|e| format!("Unable to resolve {}: {}", host, e))?
+            .collect();
+        for addr in &addrs {
+            self.check_ip(addr.ip())?;
+        }
+        Ok(addrs)
+    }
+
+    /// Checks the url's host is an address that can be sent to, resolving it if it's a name
+    pub(crate) async fn check_url(&self, url: &Url) -> Result<(), String> {
+        match (host_ip(url), url.host_str()) {
+            (Some(ip), _) => self.check_ip(ip),
+            (None, Some(host)) => self.resolve(host).await.map(|_| ()),
+            (None, None) => Err("Webhook url must have a host".to_string()),
+        }
+    }
+
+    /// Checks the url's host if it's an address. Names are checked as they're resolved by
+    /// [TargetResolver], so the addresses that were checked are the ones connected to.
+    pub(crate) fn check_ip_host(&self, url: &Url) -> Result<(), String> {
+        match host_ip(url) {
+            Some(ip) => self.check_ip(ip),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Resolves the names of webhook urls for the delivery client, refusing ones that resolve to
+/// addresses that can't be sent to
+pub(crate) struct TargetResolver(pub WebhookTargets);
+
+impl Resolve for TargetResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let targets = self.0;
+        Box::pin(async move {
+            let addrs = targets.resolve(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// The url's host, if it's an address rather than a name
+fn host_ip(url: &Url) -> Option<IpAddr> {
+    let host = url.host_str()?;
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+        .parse()
+        .ok()
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    // 0.0.0.0/8 is "this network" and 100.64.0.0/10 is shared address space for carrier NAT
+    !(first == 0
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || (first == 100 && second & 0xc0 == 64))
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    // fc00::/7 is unique local addresses and fe80::/10 is link-local addresses
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || first & 0xfe00 == 0xfc00
+        || first & 0xffc0 == 0xfe80)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_public, WebhookTargets};
+    use reqwest::Url;
+    use std::net::IpAddr;
+
+    fn public(ip: &str) -> bool {
+        is_public(ip.parse::<IpAddr>().unwrap())
+    }
+
+    #[test]
+    async fn public_addresses() {
+        assert!(public("93.184.216.34"));
+        assert!(public("100.128.0.1"));
+        assert!(public("2606:2800:220:1::"));
+        assert!(!public("127.0.0.1"));
+        assert!(!public("10.1.2.3"));
+        assert!(!public("172.16.0.1"));
+        assert!(!public("192.168.1.1"));
+        assert!(!public("169.254.169.254"));
+        assert!(!public("100.64.0.1"));
+        assert!(!public("0.0.0.0"));
+        assert!(!public("255.255.255.255"));
+        assert!(!public("::"));
+        assert!(!public("::1"));
+        assert!(!public("fd00::1"));
+        assert!(!public("fe80::1"));
+        assert!(!public("::ffff:127.0.0.1"));
+    }
+
+    #[test]
+    async fn private_targets() {
+        let url = Url::parse("http://localhost:8080/hook").unwrap();
+        assert!(WebhookTargets::default().check_url(&url).await.is_err());
+        let targets = WebhookTargets {
+            allow_private: true,
+        };
+        assert_eq!(Ok(()), targets.check_url(&url).await);
+
+        let url = Url::parse("http://[::1]/hook").unwrap();
+        assert!(WebhookTargets::default().check_ip_host(&url).is_err());
+        let url = Url::parse("https://93.184.216.34/hook").unwrap();
+        assert_eq!(Ok(()), WebhookTargets::default().check_ip_host(&url));
+    }
+}
@@ -0,0 +1,179 @@
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web::{http, test, App};
+use chrono::{Duration, Utc};
+use rstest::rstest;
+use serde_json::{json, Value};
+
+use ledger_lib::auth::jwt::JWTAuth;
+use ledger_lib::auth::password::Passwords;
+use ledger_lib::auth::SignupMode;
+use ledger_lib::client_ip::TrustedProxies;
+use ledger_lib::events::EventBus;
+use ledger_lib::idempotency::IdempotencyWindow;
+use ledger_lib::rate_limit::RateLimits;
+use ledger_lib::webhook::WebhookTargets;
+use ledger_repo::user_repo::UserRepoError;
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+macro_rules! login {
+    (&$service:ident, $body:expr) => {{
+        let request = TestRequest::post()
+            .uri("/auth/get_token")
+            .set_json($body)
+            .to_request();
+        test::call_service(&$service, request).await
+    }};
+}
+
+macro_rules! call_as {
+    (&$service:ident, $access_token:expr, $request:expr) => {{
+        let request = $request
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", $access_token),
+            ))
+            .to_request();
+        test::call_service(&$service, request).await
+    }};
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_admin(_tracing_setup: &(), repos: Repos) {
+    let admin = TestUser::new(repos.user_repo.clone()).await;
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    ledger_lib::admin::grant_admins(&*repos.user_repo, std::slice::from_ref(&admin.user_id))
+        .await
+        .unwrap();
+    let secret: [u8; 32] = rand::random();
+    let service = test::init_service(App::new().configure(ledger_lib::app_config_func(
+        JWTAuth::from_secret(secret.to_vec()),
+        repos.clone(),
+        SignupMode::Open,
+        IdempotencyWindow::default(),
+        EventBus::new(),
+        RateLimits::default(),
+        None,
+        Passwords::default(),
+        TrustedProxies::default(),
+        WebhookTargets::default(),
+    )))
+    .await;
+
+    let response = login!(&service, json!({"id": admin.user_id, "password": "pass"}));
+    let admin_token: Value = test::read_body_json(response).await;
+    let admin_token = admin_token["access_token"].as_str().unwrap().to_owned();
+    let response = login!(&service, json!({"id": user.user_id, "password": "pass"}));
+    let user_tokens: Value = test::read_body_json(response).await;
+    let user_token = user_tokens["access_token"].as_str().unwrap().to_owned();
+
+    // only admins can manage users
+    let response = call_as!(&service, user_token, TestRequest::get().uri("/admin/users"));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+    let request = TestRequest::post()
+        .uri("/transactions")
+        .set_json(json!({"category": "Misc", "date": "2021-06-09", "amount": "-5", "tags": []}));
+    let response = call_as!(&service, user_token, request);
+    assert!(response.status().is_success());
+    let response = call_as!(
+        &service,
+        admin_token,
+        TestRequest::get().uri("/admin/users")
+    );
+    let users: Vec<Value> = test::read_body_json(response).await;
+    let summary = users.iter().find(|u| u["id"] == user.user_id).unwrap();
+    assert_eq!(false, summary["admin"]);
+    assert_eq!(1, summary["transaction_count"]);
+    assert!(summary["last_login_at"].is_string());
+    let summary = users.iter().find(|u| u["id"] == admin.user_id).unwrap();
+    assert_eq!(true, summary["admin"]);
+
+    // admins see everyone's invite codes
+    let request = TestRequest::post()
+        .uri("/user/invite_codes")
+        .set_json(json!({"expires_at": Utc::now() + Duration::days(1)}));
+    let response = call_as!(&service, user_token, request);
+    assert!(response.status().is_success());
+    let response = call_as!(
+        &service,
+        admin_token,
+        TestRequest::get().uri("/admin/invite_codes")
+    );
+    let invite_codes: Vec<Value> = test::read_body_json(response).await;
+    assert!(invite_codes.iter().any(|c| c["created_by"] == user.user_id));
+    let response = call_as!(
+        &service,
+        user_token,
+        TestRequest::get().uri("/admin/invite_codes")
+    );
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+    // disabled users can't log in or use the credentials they already have
+    let uri = format!("/admin/users/{}/disable", user.user_id);
+    let response = call_as!(&service, admin_token, TestRequest::post().uri(&uri));
+    assert!(response.status().is_success());
+    let response = call_as!(
+        &service,
+        user_token,
+        TestRequest::get().uri("/transactions")
+    );
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    let response = login!(&service, json!({"id": user.user_id, "password": "pass"}));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+    let request = TestRequest::post()
+        .uri("/auth/refresh")
+        .set_json(json!({"refresh_token": user_tokens["refresh_token"]}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+    let uri = format!("/admin/users/{}/enable", user.user_id);
+    let response = call_as!(&service, admin_token, TestRequest::post().uri(&uri));
+    assert!(response.status().is_success());
+    let response = login!(&service, json!({"id": user.user_id, "password": "pass"}));
+    assert_eq!(StatusCode::OK, response.status());
+
+    // a forced reset needs a new password at the next login
+    let uri = format!("/admin/users/{}/reset_password", user.user_id);
+    let response = call_as!(&service, admin_token, TestRequest::post().uri(&uri));
+    assert!(response.status().is_success());
+    let response = login!(&service, json!({"id": user.user_id, "password": "pass"}));
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(json!({"password_reset_required": true}), body);
+    let response = login!(
+        &service,
+        json!({"id": user.user_id, "password": "pass", "new_password": "new pass"})
+    );
+    assert_eq!(StatusCode::OK, response.status());
+    let response = login!(
+        &service,
+        json!({"id": user.user_id, "password": "new pass"})
+    );
+    assert_eq!(StatusCode::OK, response.status());
+
+    // admins can't lock themselves out
+    let uri = format!("/admin/users/{}/disable", admin.user_id);
+    let response = call_as!(&service, admin_token, TestRequest::post().uri(&uri));
+    assert_eq!(StatusCode::CONFLICT, response.status());
+
+    let uri = format!("/admin/users/{}", user.user_id);
+    let response = call_as!(&service, admin_token, TestRequest::delete().uri(&uri));
+    assert!(response.status().is_success());
+    assert!(matches!(
+        repos.user_repo.get_user(&user.user_id).await,
+        Err(UserRepoError::UserNotFound(_))
+    ));
+    let response = call_as!(&service, admin_token, TestRequest::delete().uri(&uri));
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+    admin.delete().await;
+}
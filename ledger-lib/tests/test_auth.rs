@@ -0,0 +1,804 @@
+use std::collections::HashSet;
+
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web::{http, test, App};
+use chrono::{Duration, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use rstest::rstest;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use ledger_lib::auth::jwt::{JWTAuth, KeyAlgorithm};
+use ledger_lib::auth::password::Passwords;
+use ledger_lib::auth::totp;
+use ledger_lib::auth::SignupMode;
+use ledger_lib::client_ip::TrustedProxies;
+use ledger_lib::config::{JwtConfig, JwtKeyConfig, PasswordConfig};
+use ledger_lib::events::EventBus;
+use ledger_lib::idempotency::IdempotencyWindow;
+use ledger_lib::rate_limit::RateLimits;
+use ledger_lib::webhook::WebhookTargets;
+use ledger_repo::api_key_repo::{ApiKeyScope, NewApiKey};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+macro_rules! build_auth_app {
+    ($repos:ident) => {{
+        build_auth_app!($repos, RateLimits::default())
+    }};
+    ($repos:ident, $rate_limits:expr) => {{
+        let secret: [u8; 32] = rand::random();
+        build_auth_app!($repos, $rate_limits, JWTAuth::from_secret(secret.to_vec()))
+    }};
+    ($repos:ident, $rate_limits:expr, $jwt_auth:expr) => {{
+        test::init_service(App::new().configure(ledger_lib::app_config_func(
+            $jwt_auth,
+            $repos.clone(),
+            SignupMode::Open,
+            IdempotencyWindow::default(),
+            EventBus::new(),
+            $rate_limits,
+            None,
+            Passwords::default(),
+            TrustedProxies::default(),
+            WebhookTargets::default(),
+        )))
+        .await
+    }};
+}
+
+macro_rules! get_token {
+    (&$service:ident, $user:ident, $password:expr) => {{
+        let request = TestRequest::post()
+            .uri("/auth/get_token")
+            .set_json(json!({"id": $user.user_id, "password": $password}))
+            .to_request();
+        let response = test::call_service(&$service, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let tokens: TokenResponse = test::read_body_json(response).await;
+        tokens
+    }};
+}
+
+macro_rules! refresh {
+    (&$service:ident, $refresh_token:expr) => {{
+        let request = TestRequest::post()
+            .uri("/auth/refresh")
+            .set_json(json!({ "refresh_token": $refresh_token }))
+            .to_request();
+        test::call_service(&$service, request).await
+    }};
+}
+
+macro_rules! get_transactions {
+    (&$service:ident, $access_token:expr) => {{
+        let request = TestRequest::get()
+            .uri("/transactions")
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", $access_token),
+            ))
+            .to_request();
+        test::call_service(&$service, request).await.status()
+    }};
+}
+
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    token_type: String,
+    expires_in: u64,
+    refresh_token: String,
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_refresh_token_rotation(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let service = build_auth_app!(repos);
+
+    let tokens = get_token!(&service, user, "pass");
+    assert_eq!("Bearer", tokens.token_type);
+    assert_eq!(JWTAuth::ACCESS_TOKEN_LIFETIME, tokens.expires_in);
+    assert_eq!(
+        StatusCode::OK,
+        get_transactions!(&service, tokens.access_token)
+    );
+
+    let response = refresh!(&service, tokens.refresh_token);
+    assert_eq!(response.status(), StatusCode::OK);
+    let refreshed: TokenResponse = test::read_body_json(response).await;
+    assert_ne!(tokens.refresh_token, refreshed.refresh_token);
+    assert_eq!(
+        StatusCode::OK,
+        get_transactions!(&service, refreshed.access_token)
+    );
+
+    // reusing a rotated token looks like theft, so the whole family is revoked
+    let response = refresh!(&service, tokens.refresh_token);
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = refresh!(&service, refreshed.refresh_token);
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = refresh!(&service, "not a token");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_logout(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let service = build_auth_app!(repos);
+
+    let laptop = get_token!(&service, user, "pass");
+    let phone = get_token!(&service, user, "pass");
+
+    let request = TestRequest::post()
+        .uri("/auth/logout")
+        .set_json(json!({ "refresh_token": laptop.refresh_token }))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = refresh!(&service, laptop.refresh_token);
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    // other sessions stay logged in
+    let response = refresh!(&service, phone.refresh_token);
+    assert_eq!(response.status(), StatusCode::OK);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_password_change_revokes_sessions(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let service = build_auth_app!(repos);
+
+    let laptop = get_token!(&service, user, "pass");
+    let phone = get_token!(&service, user, "pass");
+
+    let request = TestRequest::put()
+        .uri("/user/password")
+        .insert_header((
+            http::header::AUTHORIZATION,
+            format!("Bearer {}", laptop.access_token),
+        ))
+        .set_json(json!({"new_password": "new pass"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for session in [laptop, phone] {
+        let response = refresh!(&service, session.refresh_token);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let tokens = get_token!(&service, user, "new pass");
+    let response = refresh!(&service, tokens.refresh_token);
+    assert_eq!(response.status(), StatusCode::OK);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_password_policy_and_rehash(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let breached_passwords_file =
+        std::env::temp_dir().join(format!("breached-{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&breached_passwords_file, "password123\n").unwrap();
+    let passwords = Passwords::from_config(&PasswordConfig {
+        memory_kib: 8192,
+        breached_passwords_file: Some(breached_passwords_file.clone()),
+        ..PasswordConfig::default()
+    })
+    .unwrap();
+    std::fs::remove_file(breached_passwords_file).unwrap();
+    let secret: [u8; 32] = rand::random();
+    let service = test::init_service(App::new().configure(ledger_lib::app_config_func(
+        JWTAuth::from_secret(secret.to_vec()),
+        repos.clone(),
+        SignupMode::Open,
+        IdempotencyWindow::default(),
+        EventBus::new(),
+        RateLimits::default(),
+        None,
+        passwords.clone(),
+        TrustedProxies::default(),
+        WebhookTargets::default(),
+    )))
+    .await;
+
+    let new_user_id = uuid::Uuid::new_v4().to_string();
+    for (password, status) in [
+        ("", StatusCode::UNPROCESSABLE_ENTITY),
+        ("short", StatusCode::UNPROCESSABLE_ENTITY),
+        ("password123", StatusCode::UNPROCESSABLE_ENTITY),
+        ("correct horse", StatusCode::OK),
+    ] {
+        let request = TestRequest::post()
+            .uri("/auth/signup")
+            .set_json(json!({"id": new_user_id, "password": password}))
+            .to_request();
+        let response = test::call_service(&service, request).await;
+        assert_eq!(status, response.status(), "{:?}", password);
+    }
+    let new_user = repos.user_repo.get_user(&new_user_id).await.unwrap();
+    assert!(!passwords.needs_rehash(&new_user.password_hash));
+    repos.user_repo.delete_user(&new_user_id).await.unwrap();
+
+    // the password was hashed with the old parameters, so logging in hashes it again
+    let old_hash = repos
+        .user_repo
+        .get_user(&user.user_id)
+        .await
+        .unwrap()
+        .password_hash;
+    assert!(passwords.needs_rehash(&old_hash));
+    let tokens = get_token!(&service, user, "pass");
+    let new_hash = repos
+        .user_repo
+        .get_user(&user.user_id)
+        .await
+        .unwrap()
+        .password_hash;
+    assert!(!passwords.needs_rehash(&new_hash));
+    let _ = get_token!(&service, user, "pass");
+
+    let request = TestRequest::put()
+        .uri("/user/password")
+        .insert_header((
+            http::header::AUTHORIZATION,
+            format!("Bearer {}", tokens.access_token),
+        ))
+        .set_json(json!({"new_password": "password123"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status());
+
+    user.delete().await;
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_sessions(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let service = build_auth_app!(repos);
+
+    let request = TestRequest::post()
+        .uri("/auth/get_token")
+        .peer_addr("192.0.2.1:4000".parse().unwrap())
+        .set_json(json!({"id": user.user_id, "password": "pass", "device_name": "Laptop"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let laptop: TokenResponse = test::read_body_json(response).await;
+    let request = TestRequest::post()
+        .uri("/auth/get_token")
+        .insert_header((http::header::USER_AGENT, "Ledger for Android"))
+        .set_json(json!({"id": user.user_id, "password": "pass"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let phone: TokenResponse = test::read_body_json(response).await;
+
+    let request = TestRequest::post()
+        .uri("/auth/refresh")
+        .peer_addr("192.0.2.2:4000".parse().unwrap())
+        .set_json(json!({ "refresh_token": laptop.refresh_token }))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let laptop: TokenResponse = test::read_body_json(response).await;
+
+    let request = TestRequest::get()
+        .uri("/user/sessions")
+        .insert_header((
+            http::header::AUTHORIZATION,
+            format!("Bearer {}", phone.access_token),
+        ))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let sessions: Value = test::read_body_json(response).await;
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(2, sessions.len());
+    assert_eq!("Laptop", sessions[0]["device_name"]);
+    assert_eq!("192.0.2.2", sessions[0]["ip_address"]);
+    assert_eq!("Ledger for Android", sessions[1]["device_name"]);
+
+    let laptop_session = sessions[0]["id"].as_str().unwrap();
+    for expected_status in [StatusCode::OK, StatusCode::NOT_FOUND] {
+        let request = TestRequest::delete()
+            .uri(&format!("/user/sessions/{}", laptop_session))
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", phone.access_token),
+            ))
+            .to_request();
+        let response = test::call_service(&service, request).await;
+        assert_eq!(response.status(), expected_status);
+    }
+
+    // the laptop is logged out, the phone isn't
+    let response = refresh!(&service, laptop.refresh_token);
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = refresh!(&service, phone.refresh_token);
+    assert_eq!(response.status(), StatusCode::OK);
+
+    user.delete().await;
+}
+
+macro_rules! create_api_key {
+    (&$service:ident, $access_token:expr, $new_api_key:expr) => {{
+        let request = TestRequest::post()
+            .uri("/user/api_keys")
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", $access_token),
+            ))
+            .set_json($new_api_key)
+            .to_request();
+        test::call_service(&$service, request).await
+    }};
+}
+
+macro_rules! post_with_key {
+    (&$service:ident, $uri:expr, $key:expr, $body:expr) => {{
+        let request = TestRequest::post()
+            .uri($uri)
+            .insert_header((http::header::AUTHORIZATION, format!("Bearer {}", $key)))
+            .set_json($body)
+            .to_request();
+        test::call_service(&$service, request).await.status()
+    }};
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_api_key_scopes(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let service = build_auth_app!(repos);
+    let tokens = get_token!(&service, user, "pass");
+
+    let response = create_api_key!(
+        &service,
+        tokens.access_token,
+        json!({"name": "Reports", "scopes": ["read-only"]})
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    let read_only: Value = test::read_body_json(response).await;
+    let read_only_key = read_only["key"].as_str().unwrap();
+    assert!(read_only_key.starts_with(ledger_lib::auth::api_key::API_KEY_PREFIX));
+
+    let response = create_api_key!(
+        &service,
+        tokens.access_token,
+        json!({"name": "Import", "scopes": ["transactions:write"]})
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    let import: Value = test::read_body_json(response).await;
+    let import_key = import["key"].as_str().unwrap();
+
+    let transaction = json!({
+        "category": "Groceries",
+        "transactee": "Market",
+        "date": "2021-06-09",
+        "amount": "-12.34",
+        "tags": [],
+    });
+    let template = json!({"name": "Groceries", "category": "Groceries", "tags": []});
+
+    assert_eq!(StatusCode::OK, get_transactions!(&service, read_only_key));
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        post_with_key!(&service, "/transactions", read_only_key, &transaction)
+    );
+
+    // transactions:write allows writing transactions, but not reading them or writing templates
+    assert_eq!(
+        StatusCode::OK,
+        post_with_key!(&service, "/transactions", import_key, &transaction)
+    );
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        get_transactions!(&service, import_key)
+    );
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        post_with_key!(&service, "/templates", import_key, &template)
+    );
+
+    // keys can't manage the account, which includes minting more keys
+    let response = create_api_key!(
+        &service,
+        read_only_key,
+        json!({"name": "Escalated", "scopes": ["export"]})
+    );
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_api_key_lifecycle(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let service = build_auth_app!(repos);
+    let tokens = get_token!(&service, user, "pass");
+
+    let response = create_api_key!(
+        &service,
+        tokens.access_token,
+        json!({"name": "Old", "scopes": ["read-only"], "expires_at": "2020-01-01T00:00:00Z"})
+    );
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = create_api_key!(
+        &service,
+        tokens.access_token,
+        json!({"name": "Nothing", "scopes": []})
+    );
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = create_api_key!(
+        &service,
+        tokens.access_token,
+        json!({"name": "Reports", "scopes": ["read-only"], "expires_at": "2999-01-01T00:00:00Z"})
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    let created: Value = test::read_body_json(response).await;
+    let key = created["key"].as_str().unwrap();
+    assert_eq!(StatusCode::OK, get_transactions!(&service, key));
+
+    // the key is never shown again
+    let request = TestRequest::get()
+        .uri("/user/api_keys")
+        .insert_header((
+            http::header::AUTHORIZATION,
+            format!("Bearer {}", tokens.access_token),
+        ))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let api_keys: Value = test::read_body_json(response).await;
+    assert_eq!(1, api_keys.as_array().unwrap().len());
+    assert_eq!("Reports", api_keys[0]["name"]);
+    assert_eq!(json!(["read-only"]), api_keys[0]["scopes"]);
+    assert!(api_keys[0].get("key").is_none());
+
+    let request = TestRequest::delete()
+        .uri(&format!("/user/api_keys/{}", created["id"]))
+        .insert_header((
+            http::header::AUTHORIZATION,
+            format!("Bearer {}", tokens.access_token),
+        ))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(StatusCode::UNAUTHORIZED, get_transactions!(&service, key));
+
+    // expired keys are rejected
+    let expired_key = format!("{}expired", ledger_lib::auth::api_key::API_KEY_PREFIX);
+    repos
+        .api_key_repo
+        .create_api_key(
+            &user.user_id,
+            NewApiKey {
+                name: "Expired".to_string(),
+                scopes: HashSet::from([ApiKeyScope::ReadOnly]),
+                expires_at: Some(Utc::now() - Duration::minutes(1)),
+            },
+            &hex::encode(Sha256::digest(expired_key.as_bytes())),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        StatusCode::UNAUTHORIZED,
+        get_transactions!(&service, expired_key)
+    );
+
+    let unknown_key = format!("{}unknown", ledger_lib::auth::api_key::API_KEY_PREFIX);
+    assert_eq!(
+        StatusCode::UNAUTHORIZED,
+        get_transactions!(&service, unknown_key)
+    );
+
+    user.delete().await;
+}
+
+macro_rules! login {
+    (&$service:ident, $credentials:expr) => {{
+        let request = TestRequest::post()
+            .uri("/auth/get_token")
+            .set_json($credentials)
+            .to_request();
+        test::call_service(&$service, request).await
+    }};
+}
+
+macro_rules! call_with_token {
+    (&$service:ident, $request:expr, $access_token:expr, $body:expr) => {{
+        let request = $request
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", $access_token),
+            ))
+            .set_json($body)
+            .to_request();
+        test::call_service(&$service, request).await
+    }};
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_totp(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let service = build_auth_app!(repos);
+    let tokens = get_token!(&service, user, "pass");
+
+    let response = call_with_token!(
+        &service,
+        TestRequest::post().uri("/user/totp"),
+        tokens.access_token,
+        json!({})
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    let enrollment: Value = test::read_body_json(response).await;
+    let encoded_secret = enrollment["secret"].as_str().unwrap();
+    assert!(enrollment["otpauth_uri"]
+        .as_str()
+        .unwrap()
+        .contains(&format!("secret={encoded_secret}")));
+    let secret = totp::decode_secret(encoded_secret).unwrap();
+
+    // codes aren't needed until enrollment is confirmed
+    get_token!(&service, user, "pass");
+
+    let response = call_with_token!(
+        &service,
+        TestRequest::post().uri("/user/totp/confirm"),
+        tokens.access_token,
+        json!({ "code": "not a code" })
+    );
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = call_with_token!(
+        &service,
+        TestRequest::post().uri("/user/totp/confirm"),
+        tokens.access_token,
+        json!({ "code": totp::code_at(&secret, Utc::now()) })
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let recovery_codes: Vec<String> =
+        serde_json::from_value(body["recovery_codes"].clone()).unwrap();
+    assert_eq!(10, recovery_codes.len());
+
+    let response = call_with_token!(
+        &service,
+        TestRequest::post().uri("/user/totp"),
+        tokens.access_token,
+        json!({})
+    );
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = login!(&service, json!({"id": user.user_id, "password": "pass"}));
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(json!({ "two_factor_required": true }), body);
+
+    // the code from confirming was used, so this uses the next step's code
+    let code = totp::code_at(&secret, Utc::now() + Duration::seconds(30));
+    let credentials = json!({"id": user.user_id, "password": "pass", "totp_code": code});
+    let response = login!(&service, credentials.clone());
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = login!(&service, credentials);
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = login!(
+        &service,
+        json!({"id": user.user_id, "password": "wrong", "totp_code": code})
+    );
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let credentials = json!({
+        "id": user.user_id,
+        "password": "pass",
+        "recovery_code": recovery_codes[0].to_uppercase(),
+    });
+    let response = login!(&service, credentials.clone());
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = login!(&service, credentials);
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = call_with_token!(
+        &service,
+        TestRequest::delete().uri("/user/totp"),
+        tokens.access_token,
+        json!({ "recovery_code": recovery_codes[0] })
+    );
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = call_with_token!(
+        &service,
+        TestRequest::delete().uri("/user/totp"),
+        tokens.access_token,
+        json!({ "recovery_code": recovery_codes[1] })
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    get_token!(&service, user, "pass");
+
+    user.delete().await;
+}
+
+macro_rules! login_from {
+    (&$service:ident, $ip:expr, $user_id:expr, $password:expr) => {{
+        let request = TestRequest::post()
+            .uri("/auth/get_token")
+            .peer_addr(format!("{}:443", $ip).parse().unwrap())
+            .set_json(json!({"id": $user_id, "password": $password}))
+            .to_request();
+        test::call_service(&$service, request).await
+    }};
+}
+
+fn retry_after(response: &actix_web::dev::ServiceResponse) -> i64 {
+    response
+        .headers()
+        .get(http::header::RETRY_AFTER)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_login_lockout(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let other_user = TestUser::new(repos.user_repo.clone()).await;
+    let service = build_auth_app!(repos);
+
+    // a successful login clears the user's failures
+    for _ in 0..4 {
+        let response = login_from!(&service, "10.0.0.1", user.user_id, "wrong");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = login_from!(&service, "10.0.0.2", user.user_id, "pass");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for _ in 0..5 {
+        let response = login_from!(&service, "10.0.0.3", user.user_id, "wrong");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    // locked out even with the right password, from anywhere
+    let response = login_from!(&service, "10.0.0.4", user.user_id, "pass");
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let seconds = retry_after(&response);
+    assert!(0 < seconds && seconds <= 30, "Retry-After was {}", seconds);
+
+    // the address that was guessing is locked out for other users too
+    let response = login_from!(&service, "10.0.0.3", other_user.user_id, "pass");
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = login_from!(&service, "10.0.0.4", other_user.user_id, "pass");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    user.delete().await;
+    other_user.delete().await;
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_rate_limits(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let other_user = TestUser::new(repos.user_repo.clone()).await;
+    let rate_limits = RateLimits {
+        limits: RateLimits::parse_limits("auth=3/60,transactions=2/60").unwrap(),
+        ..Default::default()
+    };
+    let service = build_auth_app!(repos, rate_limits);
+
+    let tokens = get_token!(&service, user, "pass");
+    let other_tokens = get_token!(&service, other_user, "pass");
+
+    // authenticated requests are limited per user
+    assert_eq!(
+        StatusCode::OK,
+        get_transactions!(&service, tokens.access_token)
+    );
+    assert_eq!(
+        StatusCode::OK,
+        get_transactions!(&service, tokens.access_token)
+    );
+    assert_eq!(
+        StatusCode::TOO_MANY_REQUESTS,
+        get_transactions!(&service, tokens.access_token)
+    );
+    assert_eq!(
+        StatusCode::OK,
+        get_transactions!(&service, other_tokens.access_token)
+    );
+
+    // other requests are limited per client address
+    let response = refresh!(&service, tokens.refresh_token);
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = refresh!(&service, other_tokens.refresh_token);
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let seconds = retry_after(&response);
+    assert!(0 < seconds && seconds <= 60, "Retry-After was {}", seconds);
+    // forwarding headers from clients that aren't trusted proxies don't change their address
+    let request = TestRequest::post()
+        .uri("/auth/refresh")
+        .insert_header(("X-Forwarded-For", "198.51.100.7"))
+        .set_json(json!({"refresh_token": other_tokens.refresh_token}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = login_from!(&service, "10.0.0.1", user.user_id, "pass");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    user.delete().await;
+    other_user.delete().await;
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_jwks(_tracing_setup: &(), repos: Repos) {
+    let user = TestUser::new(repos.user_repo.clone()).await;
+    let jwt_config = JwtConfig {
+        signing_key: "new".into(),
+        keys: vec![
+            JwtKeyConfig {
+                kid: "old".into(),
+                algorithm: KeyAlgorithm::EdDSA,
+                public_key_file: "tests/keys/ed25519-old.pub.pem".into(),
+                private_key_file: None,
+            },
+            JwtKeyConfig {
+                kid: "new".into(),
+                algorithm: KeyAlgorithm::RS256,
+                public_key_file: "tests/keys/rsa.pub.pem".into(),
+                private_key_file: Some("tests/keys/rsa.pem".into()),
+            },
+        ],
+    };
+    let jwt_auth = JWTAuth::from_config(&jwt_config).unwrap();
+    let service = build_auth_app!(repos, RateLimits::default(), jwt_auth);
+
+    let request = TestRequest::get()
+        .uri("/.well-known/jwks.json")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let jwks: JwkSet = test::read_body_json(response).await;
+    assert!(jwks.find("old").is_some());
+
+    // tokens can be verified with the published key, without sharing a secret
+    let tokens = get_token!(&service, user, "pass");
+    let header = jsonwebtoken::decode_header(&tokens.access_token).unwrap();
+    assert_eq!(Some("new".to_string()), header.kid);
+    let jwk = jwks.find("new").unwrap();
+    let claims = jsonwebtoken::decode::<Value>(
+        &tokens.access_token,
+        &DecodingKey::from_jwk(jwk).unwrap(),
+        &Validation::new(Algorithm::RS256),
+    )
+    .unwrap()
+    .claims;
+    assert_eq!(json!(user.user_id), claims["sub"]);
+    assert_eq!(
+        StatusCode::OK,
+        get_transactions!(&service, tokens.access_token)
+    );
+
+    user.delete().await;
+}
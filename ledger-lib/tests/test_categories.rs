@@ -0,0 +1,87 @@
+extern crate futures_util;
+extern crate serde_json;
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_get_categories_with_metadata(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = NewTransaction::new(
+        "Misc".to_string(),
+        Some("Alice".to_string()),
+        None,
+        NaiveDate::from_str("2021-07-01").unwrap(),
+        Decimal::from(-20),
+        HashSet::new(),
+    );
+    let _transaction: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::put()
+        .uri("/transactions/categories/Salary")
+        .set_json(json!({"kind": "income", "color": "#00ff00", "icon": null}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let request = TestRequest::get()
+        .uri("/transactions/categories")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let categories: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(
+        json!([
+            {"name": "Misc", "kind": null, "color": null, "icon": null, "archived": false},
+            {"name": "Salary", "kind": "income", "color": "#00ff00", "icon": null, "archived": false},
+        ]),
+        categories
+    );
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_delete_invalid_category(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::delete()
+        .uri("/transactions/categories/Misc")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    test_user.delete().await
+}
@@ -0,0 +1,106 @@
+extern crate futures_util;
+extern crate serde_json;
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+fn build_transaction(transactee: &str, date: &str, amount: i64) -> NewTransaction {
+    NewTransaction::new(
+        "Shopping".to_string(),
+        Some(transactee.to_string()),
+        None,
+        NaiveDate::from_str(date).unwrap(),
+        Decimal::from(amount),
+        HashSet::new(),
+    )
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_find_and_dismiss_duplicates(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = build_transaction("Amazon", "2021-07-01", -20);
+    let amazon: Transaction = create_transaction!(&service, new_transaction);
+    let new_transaction = build_transaction("AMAZON Marketplace", "2021-07-02", -20);
+    let duplicate: Transaction = create_transaction!(&service, new_transaction);
+    // Different transactee, amount or too far apart
+    let new_transaction = build_transaction("Alice", "2021-07-01", -20);
+    let _transaction: Transaction = create_transaction!(&service, new_transaction);
+    let new_transaction = build_transaction("Amazon", "2021-07-01", -30);
+    let _transaction: Transaction = create_transaction!(&service, new_transaction);
+    let new_transaction = build_transaction("Amazon", "2021-07-20", -20);
+    let _transaction: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::get()
+        .uri("/transactions/duplicates")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    let groups: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(json!([[amazon, duplicate]]), groups);
+
+    let request = TestRequest::post()
+        .uri("/transactions/duplicates/dismiss")
+        .set_json(json!({"transactions": [duplicate.id, amazon.id]}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let request = TestRequest::get()
+        .uri("/transactions/duplicates")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    let groups: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(json!([]), groups);
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_create_transaction_checks_duplicates(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = build_transaction("Amazon", "2021-07-01", -20);
+    let amazon: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::post()
+        .uri("/transactions?check_duplicates=true")
+        .set_json(build_transaction("Amazon.com", "2021-07-03", -20))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let created: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!("Amazon.com", created["transactee"]);
+    assert_eq!(json!([amazon]), created["possible_duplicates"]);
+
+    test_user.delete().await
+}
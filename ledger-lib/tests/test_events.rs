@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::time::Duration;
+
+use actix_web::body::MessageBody;
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+fn build_transaction(amount: &str) -> NewTransaction {
+    NewTransaction::new(
+        "Groceries".to_string(),
+        Some("Market".to_string()),
+        None,
+        NaiveDate::from_str("2021-06-09").unwrap(),
+        Decimal::from_str(amount).unwrap(),
+        HashSet::new(),
+    )
+}
+
+struct Event {
+    id: String,
+    event: String,
+    data: Value,
+}
+
+/// Reads the next event from the stream, skipping heartbeats
+async fn next_event(body: &mut Pin<Box<impl MessageBody>>) -> Event {
+    loop {
+        let chunk = actix_web::rt::time::timeout(
+            Duration::from_secs(5),
+            std::future::poll_fn(|cx| body.as_mut().poll_next(cx)),
+        )
+        .await
+        .expect("No event received")
+        .expect("Event stream ended")
+        .ok()
+        .unwrap();
+        let chunk = String::from_utf8(chunk.to_vec()).unwrap();
+        if chunk.starts_with(':') {
+            continue;
+        }
+
+        let field = |name: &str| {
+            chunk
+                .lines()
+                .find_map(|line| line.strip_prefix(&format!("{}: ", name)))
+                .unwrap()
+                .to_string()
+        };
+        return Event {
+            id: field("id"),
+            event: field("event"),
+            data: serde_json::from_str(&field("data")).unwrap(),
+        };
+    }
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_stream_events(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::get().uri("/events").to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(
+        "text/event-stream",
+        response.headers().get("Content-Type").unwrap()
+    );
+    let mut body = Box::pin(response.into_body());
+
+    let new_transaction = build_transaction("-10");
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+    let event = next_event(&mut body).await;
+    assert_eq!("transaction", event.event);
+    assert_eq!(
+        json!({"action": "created", "transaction": transaction}),
+        event.data
+    );
+
+    let request = TestRequest::put()
+        .uri(&format!("/transactions/{}", transaction.id))
+        .set_json(build_transaction("-25"))
+        .to_request();
+    let updated: Transaction = test::call_and_read_body_json(&service, request).await;
+    let event = next_event(&mut body).await;
+    assert_eq!(
+        json!({"action": "updated", "transaction": updated}),
+        event.data
+    );
+
+    let request = TestRequest::delete()
+        .uri(&format!("/transactions/{}", transaction.id))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    let event = next_event(&mut body).await;
+    assert_eq!("deleted", event.data["action"]);
+    assert_eq!(transaction.id, event.data["transaction"]["id"]);
+
+    let template = json!({"name": "Rent", "amount": "-1000", "tags": []});
+    let request = TestRequest::post()
+        .uri("/templates")
+        .set_json(&template)
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    let event = next_event(&mut body).await;
+    assert_eq!("template", event.event);
+    assert_eq!("created", event.data["action"]);
+    assert_eq!("Rent", event.data["template"]["name"]);
+
+    test_user.delete().await;
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_resume_events(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = build_transaction("-10");
+    let _: Transaction = create_transaction!(&service, new_transaction);
+    let request = TestRequest::get().uri("/sync").to_request();
+    let changes: Value = test::call_and_read_body_json(&service, request).await;
+    let token = changes["sync_token"].as_str().unwrap().to_string();
+
+    let new_transaction = build_transaction("-20");
+    let second: Transaction = create_transaction!(&service, new_transaction);
+    let new_transaction = build_transaction("-30");
+    let third: Transaction = create_transaction!(&service, new_transaction);
+
+    // Events missed while disconnected are sent straight away
+    let request = TestRequest::get()
+        .uri(&format!("/events?token={}", token))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::OK, response.status());
+    let mut body = Box::pin(response.into_body());
+    let event = next_event(&mut body).await;
+    assert_eq!(second.id, event.data["transaction"]["id"]);
+    let event = next_event(&mut body).await;
+    assert_eq!(third.id, event.data["transaction"]["id"]);
+
+    // Last-Event-ID takes precedence over the token
+    let request = TestRequest::get()
+        .uri(&format!("/events?token={}", token))
+        .insert_header(("Last-Event-ID", event.id))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    let mut body = Box::pin(response.into_body());
+    let request = TestRequest::delete()
+        .uri(&format!("/transactions/{}", second.id))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    let event = next_event(&mut body).await;
+    assert_eq!("deleted", event.data["action"]);
+    assert_eq!(second.id, event.data["transaction"]["id"]);
+
+    let request = TestRequest::get().uri("/events?token=invalid").to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+    test_user.delete().await;
+}
@@ -3,7 +3,6 @@ extern crate serde_json;
 
 use std::collections::HashSet;
 use std::str::FromStr;
-use std::sync::Arc;
 
 use actix_web::http::StatusCode;
 use actix_web::test;
@@ -16,9 +15,8 @@ use rust_decimal::Decimal;
 use tracing::instrument;
 
 use crate::utils::mock::MockAuthentication;
-use ledger_repo::transaction_repo::{NewTransaction, Transaction, TransactionRepo};
-use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
-use ledger_repo::user_repo::UserRepo;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
 use utils::repos;
 use utils::tracing_setup;
 use utils::TestUser;
@@ -29,17 +27,9 @@ mod utils;
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_get_transaction(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_get_transaction(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let new_transaction = NewTransaction::new(
@@ -67,17 +57,9 @@ async fn test_get_transaction(
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_get_invalid_transaction(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_get_invalid_transaction(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let request = TestRequest::get()
@@ -3,7 +3,6 @@ extern crate serde_json;
 
 use std::collections::HashSet;
 use std::str::FromStr;
-use std::sync::Arc;
 
 use actix_web::test;
 use actix_web::test::TestRequest;
@@ -15,9 +14,8 @@ use rust_decimal::Decimal;
 use tracing::instrument;
 
 use crate::utils::mock::MockAuthentication;
-use ledger_repo::transaction_repo::{NewTransaction, Transaction, TransactionRepo};
-use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
-use ledger_repo::user_repo::UserRepo;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
 use utils::repos;
 use utils::tracing_setup;
 use utils::TestUser;
@@ -28,17 +26,9 @@ mod utils;
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_get_all_transactions(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_get_all_transactions(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let new_transactions = vec![
@@ -79,17 +69,9 @@ async fn test_get_all_transactions(
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_transactions_sorted(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_transactions_sorted(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let new_transactions = vec![
@@ -139,17 +121,9 @@ async fn test_transactions_sorted(
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_get_transactions_filter_category(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_get_transactions_filter_category(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let new_transactions = vec![
@@ -192,17 +166,9 @@ async fn test_get_transactions_filter_category(
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_get_transactions_filter_transactee(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_get_transactions_filter_transactee(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let new_transactions = vec![
@@ -247,17 +213,9 @@ async fn test_get_transactions_filter_transactee(
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_get_transactions_filter_from(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_get_transactions_filter_from(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let new_transactions = vec![
@@ -302,17 +260,9 @@ async fn test_get_transactions_filter_from(
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_get_transactions_filter_until(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_get_transactions_filter_until(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let new_transactions = vec![
@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::history_repo::{ChangeKind, TransactionChange};
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+fn build_transaction(amount: &str) -> NewTransaction {
+    NewTransaction::new(
+        "Misc".to_string(),
+        Some("Bob".to_string()),
+        None,
+        NaiveDate::from_str("2021-06-09").unwrap(),
+        Decimal::from_str(amount).unwrap(),
+        HashSet::from(["groceries".to_string()]),
+    )
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_transaction_history(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = build_transaction("5.10");
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::put()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .insert_header((header::USER_AGENT, "ledger-app/1.0"))
+        .set_json(build_transaction("7.50"))
+        .to_request();
+    let updated: Transaction = test::call_and_read_body_json(&service, request).await;
+
+    let request = TestRequest::put()
+        .uri("/transactions/tags/groceries")
+        .set_json(json!({"name": "food"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let request = TestRequest::delete()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}/history", transaction.id).as_str())
+        .to_request();
+    let history: Vec<TransactionChange> = test::call_and_read_body_json(&service, request).await;
+
+    let kinds: Vec<ChangeKind> = history.iter().map(|c| c.kind).collect();
+    assert_eq!(
+        vec![
+            ChangeKind::Create,
+            ChangeKind::Update,
+            ChangeKind::Update,
+            ChangeKind::Delete
+        ],
+        kinds
+    );
+    assert!(history
+        .iter()
+        .all(|c| c.changed_by == test_user.user_id && c.transaction_id == transaction.id));
+    assert_eq!(None, history[0].before);
+    assert_eq!(Some(transaction.clone()), history[0].after);
+    assert_eq!(Some(transaction), history[1].before);
+    assert_eq!(Some(updated.clone()), history[1].after);
+    assert_eq!(Some("ledger-app/1.0".to_string()), history[1].client);
+    assert_eq!(
+        HashSet::from(["food".to_string()]),
+        history[2].after.as_ref().unwrap().tags
+    );
+    assert_eq!(None, history[3].after);
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_revert_transaction(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = build_transaction("5.10");
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::put()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .set_json(build_transaction("7.50"))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let request = TestRequest::delete()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}/history", transaction.id).as_str())
+        .to_request();
+    let history: Vec<TransactionChange> = test::call_and_read_body_json(&service, request).await;
+
+    // Reverting to the deleted version isn't possible
+    let request = TestRequest::post()
+        .uri(
+            format!(
+                "/transactions/{}/history/{}/revert",
+                transaction.id, history[2].id
+            )
+            .as_str(),
+        )
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+    // Reverting to the created version restores it from the trash
+    let request = TestRequest::post()
+        .uri(
+            format!(
+                "/transactions/{}/history/{}/revert",
+                transaction.id, history[0].id
+            )
+            .as_str(),
+        )
+        .to_request();
+    let reverted: Transaction = test::call_and_read_body_json(&service, request).await;
+    // the update, restore and revert each bumped the version
+    let transaction = Transaction {
+        version: 4,
+        ..transaction
+    };
+    assert_eq!(transaction, reverted);
+
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .to_request();
+    let current: Transaction = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(transaction, current);
+
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}/history", transaction.id).as_str())
+        .to_request();
+    let history: Vec<TransactionChange> = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(ChangeKind::Revert, history.last().unwrap().kind);
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_history_invalid_transaction(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::get()
+        .uri("/transactions/1234/history")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+    let request = TestRequest::post()
+        .uri("/transactions/1234/history/1234/revert")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_shared_history(_tracing_setup: &(), repos: Repos) {
+    let owner = TestUser::new(repos.user_repo.clone()).await;
+    let member = TestUser::new(repos.user_repo.clone()).await;
+    let outsider = TestUser::new(repos.user_repo.clone()).await;
+    let owner_service = test::init_service(build_app!(repos, owner.user_id.clone())).await;
+    let member_service = test::init_service(build_app!(repos, member.user_id.clone())).await;
+    let outsider_service = test::init_service(build_app!(repos, outsider.user_id.clone())).await;
+
+    let request = TestRequest::get().uri("/ledgers").to_request();
+    let ledgers: Value = test::call_and_read_body_json(&owner_service, request).await;
+    let ledger_id = ledgers[0]["id"].clone();
+    let request = TestRequest::post()
+        .uri(format!("/ledgers/{}/invitations", ledger_id).as_str())
+        .set_json(json!({"role": "editor"}))
+        .to_request();
+    let invitation: Value = test::call_and_read_body_json(&owner_service, request).await;
+    let request = TestRequest::post()
+        .uri("/ledgers/join")
+        .set_json(json!({"code": invitation["code"]}))
+        .to_request();
+    let response = test::call_service(&member_service, request).await;
+    assert!(response.status().is_success());
+    let request = TestRequest::put()
+        .uri("/ledgers/current")
+        .set_json(json!({"ledger_id": ledger_id}))
+        .to_request();
+    let response = test::call_service(&member_service, request).await;
+    assert!(response.status().is_success());
+
+    let request = TestRequest::post()
+        .uri("/webhooks")
+        .set_json(json!({
+            "url": "http://localhost:9/hook",
+            "events": ["transaction.created"],
+        }))
+        .to_request();
+    let webhook: Value = test::call_and_read_body_json(&owner_service, request).await;
+
+    let new_transaction = build_transaction("5.10");
+    let transaction: Transaction = create_transaction!(&member_service, new_transaction);
+
+    // the owner sees the member's change and is told about it
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}/history", transaction.id).as_str())
+        .to_request();
+    let history: Vec<TransactionChange> =
+        test::call_and_read_body_json(&owner_service, request).await;
+    assert_eq!(1, history.len());
+    assert_eq!(ChangeKind::Create, history[0].kind);
+    assert_eq!(member.user_id, history[0].changed_by);
+    let request = TestRequest::get()
+        .uri(&format!("/webhooks/{}/deliveries", webhook["id"]))
+        .to_request();
+    let deliveries: Value = test::call_and_read_body_json(&owner_service, request).await;
+    assert_eq!(1, deliveries.as_array().unwrap().len());
+    assert_eq!("transaction.created", deliveries[0]["event"]);
+
+    // users outside the ledger can't see or revert its history
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}/history", transaction.id).as_str())
+        .to_request();
+    let response = test::call_service(&outsider_service, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+    let request = TestRequest::post()
+        .uri(
+            format!(
+                "/transactions/{}/history/{}/revert",
+                transaction.id, history[0].id
+            )
+            .as_str(),
+        )
+        .to_request();
+    let response = test::call_service(&outsider_service, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+    owner.delete().await;
+    member.delete().await;
+    outsider.delete().await;
+}
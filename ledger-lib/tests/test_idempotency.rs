@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_lib::idempotency::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAYED_HEADER};
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::transaction_template_repo::{NewTransactionTemplate, TransactionTemplate};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+fn build_transaction(amount: &str) -> NewTransaction {
+    NewTransaction::new(
+        "Groceries".to_string(),
+        Some("Market".to_string()),
+        None,
+        NaiveDate::from_str("2021-06-09").unwrap(),
+        Decimal::from_str(amount).unwrap(),
+        HashSet::new(),
+    )
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_retried_transaction_creation(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::post()
+        .uri("/transactions")
+        .insert_header((IDEMPOTENCY_KEY_HEADER, "retry-1"))
+        .set_json(build_transaction("-12.50"))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    assert!(response.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+    let created: Transaction = test::read_body_json(response).await;
+
+    let request = TestRequest::post()
+        .uri("/transactions")
+        .insert_header((IDEMPOTENCY_KEY_HEADER, "retry-1"))
+        .set_json(build_transaction("-12.50"))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    assert_eq!(
+        "true",
+        response.headers().get(IDEMPOTENT_REPLAYED_HEADER).unwrap()
+    );
+    let replayed: Transaction = test::read_body_json(response).await;
+    assert_eq!(created, replayed);
+
+    let request = TestRequest::get().uri("/transactions").to_request();
+    let transactions: Vec<Transaction> = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(vec![created], transactions);
+
+    // the same key can't be used for a different transaction
+    let request = TestRequest::post()
+        .uri("/transactions")
+        .insert_header((IDEMPOTENCY_KEY_HEADER, "retry-1"))
+        .set_json(build_transaction("-13.50"))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status());
+
+    // or for another endpoint
+    let request = TestRequest::post()
+        .uri("/templates")
+        .insert_header((IDEMPOTENCY_KEY_HEADER, "retry-1"))
+        .set_json(NewTransactionTemplate::new(
+            "Groceries".to_string(),
+            None,
+            None,
+            None,
+            None,
+            HashSet::new(),
+        ))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, response.status());
+
+    // requests without a key are never deduplicated
+    for _ in 0..2 {
+        let new_transaction = build_transaction("-12.50");
+        let _: Transaction = create_transaction!(&service, new_transaction);
+    }
+    let request = TestRequest::get().uri("/transactions").to_request();
+    let transactions: Vec<Transaction> = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(3, transactions.len());
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_retried_template_creation(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    // the tags are serialized in any order, but are still the same request
+    let new_template = NewTransactionTemplate::new(
+        "Rent".to_string(),
+        Some("Housing".to_string()),
+        None,
+        None,
+        None,
+        HashSet::from(["home", "monthly", "fixed", "rent"].map(String::from)),
+    );
+    let mut templates = Vec::new();
+    for _ in 0..2 {
+        let request = TestRequest::post()
+            .uri("/templates")
+            .insert_header((IDEMPOTENCY_KEY_HEADER, "template-1"))
+            .set_json(&new_template)
+            .to_request();
+        let template: TransactionTemplate = test::call_and_read_body_json(&service, request).await;
+        templates.push(template.template_id);
+    }
+    assert_eq!(templates[0], templates[1]);
+
+    let request = TestRequest::get().uri("/templates").to_request();
+    let all: Vec<TransactionTemplate> = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(1, all.len());
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_invalid_idempotency_key(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::post()
+        .uri("/transactions")
+        .insert_header((IDEMPOTENCY_KEY_HEADER, ""))
+        .set_json(build_transaction("1"))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+    let request = TestRequest::post()
+        .uri("/transactions")
+        .insert_header((IDEMPOTENCY_KEY_HEADER, "k".repeat(256)))
+        .set_json(build_transaction("1"))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+    let request = TestRequest::get().uri("/transactions").to_request();
+    let transactions: Vec<Transaction> = test::call_and_read_body_json(&service, request).await;
+    assert!(transactions.is_empty());
+
+    test_user.delete().await
+}
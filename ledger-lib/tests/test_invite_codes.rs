@@ -0,0 +1,156 @@
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web::{http, test, App};
+use chrono::{Duration, Utc};
+use rstest::rstest;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use ledger_lib::auth::jwt::JWTAuth;
+use ledger_lib::auth::password::Passwords;
+use ledger_lib::auth::SignupMode;
+use ledger_lib::client_ip::TrustedProxies;
+use ledger_lib::events::EventBus;
+use ledger_lib::idempotency::IdempotencyWindow;
+use ledger_lib::rate_limit::RateLimits;
+use ledger_lib::webhook::WebhookTargets;
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+macro_rules! login {
+    (&$service:ident, $user_id:expr, $password:expr) => {{
+        let request = TestRequest::post()
+            .uri("/auth/get_token")
+            .set_json(json!({"id": $user_id, "password": $password}))
+            .to_request();
+        let tokens: Value = test::call_and_read_body_json(&$service, request).await;
+        tokens["access_token"].as_str().unwrap().to_owned()
+    }};
+}
+
+macro_rules! call_as {
+    (&$service:ident, $access_token:expr, $request:expr) => {{
+        let request = $request
+            .insert_header((
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", $access_token),
+            ))
+            .to_request();
+        test::call_service(&$service, request).await
+    }};
+}
+
+macro_rules! signup {
+    (&$service:ident, $body:expr) => {{
+        let request = TestRequest::post()
+            .uri("/auth/signup")
+            .set_json($body)
+            .to_request();
+        test::call_service(&$service, request).await
+    }};
+}
+
+const PASSWORD: &str = "correct horse";
+
+#[rstest]
+#[actix_rt::test]
+async fn test_invite_only_signup(_tracing_setup: &(), repos: Repos) {
+    let inviter = TestUser::new(repos.user_repo.clone()).await;
+    let secret: [u8; 32] = rand::random();
+    let service = test::init_service(App::new().configure(ledger_lib::app_config_func(
+        JWTAuth::from_secret(secret.to_vec()),
+        repos.clone(),
+        SignupMode::InviteOnly,
+        IdempotencyWindow::default(),
+        EventBus::new(),
+        RateLimits::default(),
+        None,
+        Passwords::default(),
+        TrustedProxies::default(),
+        WebhookTargets::default(),
+    )))
+    .await;
+    let access_token = login!(&service, inviter.user_id, "pass");
+
+    let expires_at = Utc::now() + Duration::days(7);
+    let request = TestRequest::post()
+        .uri("/user/invite_codes")
+        .set_json(json!({"max_uses": 2, "expires_at": expires_at}));
+    let response = call_as!(&service, access_token, request);
+    assert_eq!(StatusCode::OK, response.status());
+    let created: Value = test::read_body_json(response).await;
+    let code = created["code"].as_str().unwrap().to_owned();
+    assert_eq!(0, created["uses"]);
+
+    let request = TestRequest::post()
+        .uri("/user/invite_codes")
+        .set_json(json!({"expires_at": Utc::now() - Duration::days(1)}));
+    let response = call_as!(&service, access_token, request);
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+    let first = Uuid::new_v4().to_string();
+    let response = signup!(&service, json!({"id": first, "password": PASSWORD}));
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+    let response = signup!(
+        &service,
+        json!({"id": first, "password": PASSWORD, "invite_code": "0000-0000-0000-0000"})
+    );
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+    // codes are accepted without caring about case
+    let response = signup!(
+        &service,
+        json!({"id": first, "password": PASSWORD, "invite_code": code.to_uppercase()})
+    );
+    assert_eq!(StatusCode::OK, response.status());
+    // signing up as an existing user doesn't use the code up
+    let response = signup!(
+        &service,
+        json!({"id": first, "password": PASSWORD, "invite_code": code})
+    );
+    assert_eq!(StatusCode::CONFLICT, response.status());
+    let second = Uuid::new_v4().to_string();
+    let response = signup!(
+        &service,
+        json!({"id": second, "password": PASSWORD, "invite_code": code})
+    );
+    assert_eq!(StatusCode::OK, response.status());
+    let third = Uuid::new_v4().to_string();
+    let response = signup!(
+        &service,
+        json!({"id": third, "password": PASSWORD, "invite_code": code})
+    );
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+    let response = call_as!(
+        &service,
+        access_token,
+        TestRequest::get().uri("/user/invite_codes")
+    );
+    let invite_codes: Vec<Value> = test::read_body_json(response).await;
+    assert_eq!(1, invite_codes.len());
+    assert_eq!(2, invite_codes[0]["uses"]);
+    assert!(invite_codes[0].get("code").is_none());
+
+    // users who signed up with an invite can invite others too, and only delete their own codes
+    let invitee_token = login!(&service, first, PASSWORD);
+    let uri = format!("/user/invite_codes/{}", created["id"]);
+    let response = call_as!(&service, invitee_token, TestRequest::delete().uri(&uri));
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+    let request = TestRequest::post()
+        .uri("/user/invite_codes")
+        .set_json(json!({"expires_at": expires_at}));
+    let response = call_as!(&service, invitee_token, request);
+    assert_eq!(StatusCode::OK, response.status());
+    let response = call_as!(&service, access_token, TestRequest::delete().uri(&uri));
+    assert_eq!(StatusCode::OK, response.status());
+
+    for user_id in [&first, &second] {
+        repos.user_repo.delete_user(user_id).await.unwrap();
+    }
+    inviter.delete().await;
+}
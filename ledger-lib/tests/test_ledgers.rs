@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+fn build_transaction() -> NewTransaction {
+    NewTransaction::new(
+        "Groceries".to_string(),
+        Some("Market".to_string()),
+        None,
+        NaiveDate::from_str("2021-06-09").unwrap(),
+        Decimal::from_str("-12.30").unwrap(),
+        HashSet::new(),
+    )
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_share_ledger(_tracing_setup: &(), repos: Repos) {
+    let owner = TestUser::new(repos.user_repo.clone()).await;
+    let member = TestUser::new(repos.user_repo.clone()).await;
+    let owner_service = test::init_service(build_app!(repos, owner.user_id.clone())).await;
+    let member_service = test::init_service(build_app!(repos, member.user_id.clone())).await;
+
+    let request = TestRequest::get().uri("/ledgers").to_request();
+    let ledgers: Value = test::call_and_read_body_json(&owner_service, request).await;
+    assert_eq!(
+        json!([{"id": ledgers[0]["id"], "name": owner.user_id, "role": "owner", "current": true}]),
+        ledgers
+    );
+
+    let request = TestRequest::post()
+        .uri("/ledgers")
+        .set_json(json!({"name": "Household"}))
+        .to_request();
+    let household: Value = test::call_and_read_body_json(&owner_service, request).await;
+    assert_eq!("Household", household["name"]);
+    assert_eq!(false, household["current"]);
+    let request = TestRequest::put()
+        .uri("/ledgers/current")
+        .set_json(json!({"ledger_id": household["id"]}))
+        .to_request();
+    let household: Value = test::call_and_read_body_json(&owner_service, request).await;
+    assert_eq!(true, household["current"]);
+
+    let request = TestRequest::post()
+        .uri(format!("/ledgers/{}/invitations", household["id"]).as_str())
+        .set_json(json!({"role": "editor"}))
+        .to_request();
+    let invitation: Value = test::call_and_read_body_json(&owner_service, request).await;
+    assert_eq!("editor", invitation["role"]);
+
+    let request = TestRequest::post()
+        .uri("/ledgers/join")
+        .set_json(json!({"code": invitation["code"]}))
+        .to_request();
+    let joined: Value = test::call_and_read_body_json(&member_service, request).await;
+    assert_eq!(household["id"], joined["id"]);
+    assert_eq!("editor", joined["role"]);
+    // the code can only be used once
+    let request = TestRequest::post()
+        .uri("/ledgers/join")
+        .set_json(json!({"code": invitation["code"]}))
+        .to_request();
+    let response = test::call_service(&member_service, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+    let request = TestRequest::put()
+        .uri("/ledgers/current")
+        .set_json(json!({"ledger_id": household["id"]}))
+        .to_request();
+    let response = test::call_service(&member_service, request).await;
+    assert!(response.status().is_success());
+
+    let new_transaction = build_transaction();
+    let transaction: Transaction = create_transaction!(&member_service, new_transaction);
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .to_request();
+    let seen: Transaction = test::call_and_read_body_json(&owner_service, request).await;
+    assert_eq!(transaction, seen);
+
+    let request = TestRequest::get()
+        .uri(format!("/ledgers/{}/members", household["id"]).as_str())
+        .to_request();
+    let members: Value = test::call_and_read_body_json(&member_service, request).await;
+    assert_eq!(
+        json!([
+            {"user_id": owner.user_id, "role": "owner"},
+            {"user_id": member.user_id, "role": "editor"},
+        ]),
+        members
+    );
+
+    owner.delete().await;
+    member.delete().await;
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_member_permissions(_tracing_setup: &(), repos: Repos) {
+    let owner = TestUser::new(repos.user_repo.clone()).await;
+    let viewer = TestUser::new(repos.user_repo.clone()).await;
+    let owner_service = test::init_service(build_app!(repos, owner.user_id.clone())).await;
+    let viewer_service = test::init_service(build_app!(repos, viewer.user_id.clone())).await;
+
+    let request = TestRequest::get().uri("/ledgers").to_request();
+    let ledgers: Value = test::call_and_read_body_json(&owner_service, request).await;
+    let ledger_id = ledgers[0]["id"].clone();
+
+    let request = TestRequest::post()
+        .uri(format!("/ledgers/{}/invitations", ledger_id).as_str())
+        .set_json(json!({"role": "viewer"}))
+        .to_request();
+    let invitation: Value = test::call_and_read_body_json(&owner_service, request).await;
+    let request = TestRequest::post()
+        .uri("/ledgers/join")
+        .set_json(json!({"code": invitation["code"]}))
+        .to_request();
+    let response = test::call_service(&viewer_service, request).await;
+    assert!(response.status().is_success());
+    let request = TestRequest::put()
+        .uri("/ledgers/current")
+        .set_json(json!({"ledger_id": ledger_id}))
+        .to_request();
+    let response = test::call_service(&viewer_service, request).await;
+    assert!(response.status().is_success());
+
+    // viewers can read the ledger but not change it or manage its members
+    let request = TestRequest::get().uri("/transactions").to_request();
+    let response = test::call_service(&viewer_service, request).await;
+    assert!(response.status().is_success());
+    let request = TestRequest::post()
+        .uri("/transactions")
+        .set_json(build_transaction())
+        .to_request();
+    let response = test::call_service(&viewer_service, request).await;
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+    let request = TestRequest::post()
+        .uri(format!("/ledgers/{}/invitations", ledger_id).as_str())
+        .set_json(json!({"role": "owner"}))
+        .to_request();
+    let response = test::call_service(&viewer_service, request).await;
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+    // the only owner can neither stop being one nor leave, and the current ledger can't be deleted
+    let request = TestRequest::put()
+        .uri(format!("/ledgers/{}/members/{}", ledger_id, owner.user_id).as_str())
+        .set_json(json!({"role": "editor"}))
+        .to_request();
+    let response = test::call_service(&owner_service, request).await;
+    assert_eq!(StatusCode::CONFLICT, response.status());
+    let request = TestRequest::delete()
+        .uri(format!("/ledgers/{}/members/{}", ledger_id, owner.user_id).as_str())
+        .to_request();
+    let response = test::call_service(&owner_service, request).await;
+    assert_eq!(StatusCode::CONFLICT, response.status());
+    let request = TestRequest::delete()
+        .uri(format!("/ledgers/{}", ledger_id).as_str())
+        .to_request();
+    let response = test::call_service(&owner_service, request).await;
+    assert_eq!(StatusCode::CONFLICT, response.status());
+
+    let request = TestRequest::put()
+        .uri(format!("/ledgers/{}/members/{}", ledger_id, viewer.user_id).as_str())
+        .set_json(json!({"role": "editor"}))
+        .to_request();
+    let response = test::call_service(&owner_service, request).await;
+    assert!(response.status().is_success());
+    let new_transaction = build_transaction();
+    let _: Transaction = create_transaction!(&viewer_service, new_transaction);
+
+    // leaving moves the member back to their own ledger
+    let request = TestRequest::delete()
+        .uri(format!("/ledgers/{}/members/{}", ledger_id, viewer.user_id).as_str())
+        .to_request();
+    let response = test::call_service(&viewer_service, request).await;
+    assert!(response.status().is_success());
+    let request = TestRequest::get()
+        .uri(format!("/ledgers/{}", ledger_id).as_str())
+        .to_request();
+    let response = test::call_service(&viewer_service, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+    let request = TestRequest::get().uri("/transactions").to_request();
+    let transactions: Vec<Transaction> =
+        test::call_and_read_body_json(&viewer_service, request).await;
+    assert!(transactions.is_empty());
+
+    owner.delete().await;
+    viewer.delete().await;
+}
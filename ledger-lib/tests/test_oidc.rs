@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use actix_web::http::{header, StatusCode};
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::{test, web, App, HttpResponse, HttpServer};
+use chrono::Utc;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{EncodingKey, Header};
+use reqwest::Url;
+use rstest::rstest;
+use serde::Deserialize;
+use serde_json::json;
+
+use ledger_lib::auth::jwt::{JWTAuth, KeyAlgorithm, PublicKey};
+use ledger_lib::auth::oidc::{code_challenge, OidcProvider};
+use ledger_lib::auth::password::Passwords;
+use ledger_lib::auth::SignupMode;
+use ledger_lib::client_ip::TrustedProxies;
+use ledger_lib::config::OidcConfig;
+use ledger_lib::events::EventBus;
+use ledger_lib::idempotency::IdempotencyWindow;
+use ledger_lib::rate_limit::RateLimits;
+use ledger_lib::webhook::WebhookTargets;
+use ledger_repo::user_repo::UserRepoError;
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+use uuid::Uuid;
+
+#[macro_use]
+mod utils;
+
+const CLIENT_ID: &str = "ledger";
+const REDIRECT_URI: &str = "http://localhost/auth/oidc/callback";
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+struct IssuedCode {
+    client_id: String,
+    redirect_uri: String,
+    code_challenge: String,
+    nonce: String,
+    subject: String,
+    username: Option<String>,
+}
+
+/// A local stand-in for an OpenID Connect provider. Instead of asking users to log in, it logs in
+/// whoever is named by the `login_hint` parameter.
+#[derive(Clone)]
+struct MockIdp {
+    issuer: String,
+    codes: Arc<Mutex<HashMap<String, IssuedCode>>>,
+    jwks: JwkSet,
+}
+
+async fn discovery(idp: Data<MockIdp>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "issuer": idp.issuer,
+        "authorization_endpoint": format!("{}/authorize", idp.issuer),
+        "token_endpoint": format!("{}/token", idp.issuer),
+        "jwks_uri": format!("{}/jwks", idp.issuer),
+    }))
+}
+
+async fn jwks(idp: Data<MockIdp>) -> HttpResponse {
+    HttpResponse::Ok().json(&idp.jwks)
+}
+
+async fn authorize(
+    idp: Data<MockIdp>,
+    params: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    assert_eq!("code", params["response_type"]);
+    assert_eq!("S256", params["code_challenge_method"]);
+    let code = Uuid::new_v4().to_string();
+    idp.codes.lock().unwrap().insert(
+        code.clone(),
+        IssuedCode {
+            client_id: params["client_id"].clone(),
+            redirect_uri: params["redirect_uri"].clone(),
+            code_challenge: params["code_challenge"].clone(),
+            nonce: params["nonce"].clone(),
+            subject: params["login_hint"].clone(),
+            username: params.get("username").cloned(),
+        },
+    );
+    let redirect = Url::parse_with_params(
+        &params["redirect_uri"],
+        [("code", &code), ("state", &params["state"])],
+    )
+    .unwrap();
+    HttpResponse::Found()
+        .insert_header((header::LOCATION, redirect.as_str()))
+        .finish()
+}
+
+async fn token(idp: Data<MockIdp>, form: web::Form<HashMap<String, String>>) -> HttpResponse {
+    let invalid_grant = HttpResponse::BadRequest().json(json!({"error": "invalid_grant"}));
+    let Some(issued) = idp.codes.lock().unwrap().remove(&form["code"]) else {
+        return invalid_grant;
+    };
+    if form["grant_type"] != "authorization_code"
+        || form["client_id"] != issued.client_id
+        || form["redirect_uri"] != issued.redirect_uri
+        || code_challenge(&form["code_verifier"]) != issued.code_challenge
+    {
+        return invalid_grant;
+    }
+
+    let now = Utc::now().timestamp();
+    let claims = json!({
+        "iss": idp.issuer,
+        "sub": issued.subject,
+        "aud": issued.client_id,
+        "iat": now,
+        "exp": now + 300,
+        "nonce": issued.nonce,
+        "preferred_username": issued.username,
+    });
+    let mut header = Header::new(jsonwebtoken::Algorithm::EdDSA);
+    header.kid = Some("idp".to_string());
+    let encoding_key = EncodingKey::from_ed_pem(include_bytes!("keys/ed25519-old.pem")).unwrap();
+    let id_token = jsonwebtoken::encode(&header, &claims, &encoding_key).unwrap();
+    HttpResponse::Ok().json(json!({
+        "access_token": "unused",
+        "token_type": "Bearer",
+        "id_token": id_token,
+    }))
+}
+
+fn start_idp() -> MockIdp {
+    let public_key = PublicKey::from_pem(
+        "idp",
+        KeyAlgorithm::EdDSA,
+        include_bytes!("keys/ed25519-old.pub.pem"),
+    )
+    .unwrap();
+    let signing_keys = JWTAuth::from_keys(
+        "idp",
+        include_bytes!("keys/ed25519-old.pem"),
+        vec![public_key],
+    )
+    .unwrap();
+    let mut idp = MockIdp {
+        issuer: String::new(),
+        codes: Arc::new(Mutex::new(HashMap::new())),
+        jwks: signing_keys.jwks().clone(),
+    };
+
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    idp.issuer = format!("http://{}", listener.local_addr().unwrap());
+    let state = idp.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(Data::new(state.clone()))
+            .route(
+                "/.well-known/openid-configuration",
+                web::get().to(discovery),
+            )
+            .route("/jwks", web::get().to(jwks))
+            .route("/authorize", web::get().to(authorize))
+            .route("/token", web::post().to(token))
+    })
+    .workers(1)
+    .listen(listener)
+    .unwrap();
+    actix_web::rt::spawn(server.run());
+    idp
+}
+
+macro_rules! build_oidc_app {
+    ($repos:ident, $jwt_auth:expr, $idp:expr, $signup_mode:expr) => {{
+        let config = OidcConfig {
+            issuer: $idp.issuer.clone(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_uri: REDIRECT_URI.to_string(),
+        };
+        test::init_service(App::new().configure(ledger_lib::app_config_func(
+            $jwt_auth.clone(),
+            $repos.clone(),
+            $signup_mode,
+            IdempotencyWindow::default(),
+            EventBus::new(),
+            RateLimits::default(),
+            Some(OidcProvider::new(config)),
+            Passwords::default(),
+            TrustedProxies::default(),
+            WebhookTargets::default(),
+        )))
+        .await
+    }};
+}
+
+/// Starts a login, has the provider log in `subject`, and returns the callback the provider
+/// redirects back to
+macro_rules! authorize {
+    (&$service:ident, $subject:expr, $username:expr) => {{
+        let request = TestRequest::get().uri("/auth/oidc/authorize").to_request();
+        let response = test::call_service(&$service, request).await;
+        assert_eq!(response.status(), StatusCode::FOUND);
+        let location = response.headers().get(header::LOCATION).unwrap();
+        let mut url = Url::parse(location.to_str().unwrap()).unwrap();
+        url.query_pairs_mut()
+            .append_pair("login_hint", $subject)
+            .append_pair("username", $username);
+
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let response = client.get(url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FOUND);
+        let callback = Url::parse(
+            response.headers()[reqwest::header::LOCATION]
+                .to_str()
+                .unwrap(),
+        )
+        .unwrap();
+        format!("{}?{}", callback.path(), callback.query().unwrap())
+    }};
+}
+
+macro_rules! callback {
+    (&$service:ident, $callback:expr) => {{
+        let request = TestRequest::get().uri($callback).to_request();
+        test::call_service(&$service, request).await
+    }};
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_oidc_login(_tracing_setup: &(), repos: Repos) {
+    let idp = start_idp();
+    let secret: [u8; 32] = rand::random();
+    let jwt_auth = JWTAuth::from_secret(secret.to_vec());
+    let service = build_oidc_app!(repos, jwt_auth, idp, SignupMode::Open);
+    let subject = Uuid::new_v4().to_string();
+    let username = format!("oidc-user-{}", subject);
+
+    // the first login provisions a user
+    let callback = authorize!(&service, &subject, &username);
+    let response = callback!(&service, &callback);
+    assert_eq!(response.status(), StatusCode::OK);
+    let tokens: TokenResponse = test::read_body_json(response).await;
+    assert_eq!(
+        Ok(username.clone()),
+        jwt_auth.validate_token(&tokens.access_token)
+    );
+    assert!(repos.user_repo.get_user(&username).await.is_ok());
+
+    let request = TestRequest::get()
+        .uri("/transactions")
+        .insert_header((
+            header::AUTHORIZATION,
+            format!("Bearer {}", tokens.access_token),
+        ))
+        .to_request();
+    assert_eq!(
+        StatusCode::OK,
+        test::call_service(&service, request).await.status()
+    );
+
+    // a login can't be finished twice
+    let response = callback!(&service, &callback);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // later logins are linked by subject, even if the username changes
+    let callback = authorize!(&service, &subject, "renamed");
+    let response = callback!(&service, &callback);
+    assert_eq!(response.status(), StatusCode::OK);
+    let tokens: TokenResponse = test::read_body_json(response).await;
+    assert_eq!(
+        Ok(username.clone()),
+        jwt_auth.validate_token(&tokens.access_token)
+    );
+    assert!(matches!(
+        repos.user_repo.get_user("renamed").await,
+        Err(UserRepoError::UserNotFound(_))
+    ));
+
+    // provisioned users have no password to log in with
+    let request = TestRequest::post()
+        .uri("/auth/get_token")
+        .set_json(json!({"id": username, "password": ""}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // a new subject isn't linked to a local user that has its username, but gets a user of its own
+    let local_user = TestUser::new(repos.user_repo.clone()).await;
+    let another_subject = Uuid::new_v4().to_string();
+    let callback = authorize!(&service, &another_subject, &local_user.user_id);
+    let response = callback!(&service, &callback);
+    assert_eq!(response.status(), StatusCode::OK);
+    let tokens: TokenResponse = test::read_body_json(response).await;
+    let suffixed = format!("{}-2", local_user.user_id);
+    assert_eq!(
+        Ok(suffixed.clone()),
+        jwt_auth.validate_token(&tokens.access_token)
+    );
+    assert_eq!(
+        Some(suffixed.clone()),
+        repos
+            .oidc_repo
+            .get_linked_user(&idp.issuer, &another_subject)
+            .await
+            .unwrap()
+    );
+
+    local_user.delete().await;
+    repos.user_repo.delete_user(&suffixed).await.unwrap();
+    repos.user_repo.delete_user(&username).await.unwrap();
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_oidc_login_without_signups(_tracing_setup: &(), repos: Repos) {
+    let idp = start_idp();
+    let secret: [u8; 32] = rand::random();
+    let service = build_oidc_app!(
+        repos,
+        JWTAuth::from_secret(secret.to_vec()),
+        idp,
+        SignupMode::Closed
+    );
+    let subject = Uuid::new_v4().to_string();
+    let username = format!("oidc-user-{}", subject);
+
+    let callback = authorize!(&service, &subject, &username);
+    let response = callback!(&service, &callback);
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(matches!(
+        repos.user_repo.get_user(&username).await,
+        Err(UserRepoError::UserNotFound(_))
+    ));
+}
+
+#[rstest]
+#[actix_rt::test]
+async fn test_oidc_rejected(_tracing_setup: &(), repos: Repos) {
+    let idp = start_idp();
+    let secret: [u8; 32] = rand::random();
+    let service = build_oidc_app!(
+        repos,
+        JWTAuth::from_secret(secret.to_vec()),
+        idp,
+        SignupMode::Open
+    );
+    let subject = Uuid::new_v4().to_string();
+
+    // a code the provider didn't issue is refused by it
+    let callback = authorize!(&service, &subject, "unused");
+    let mut url = Url::parse(&format!("http://localhost{}", callback)).unwrap();
+    let state = url
+        .query_pairs()
+        .find(|(name, _)| name == "state")
+        .unwrap()
+        .1
+        .into_owned();
+    url.query_pairs_mut()
+        .clear()
+        .append_pair("code", "forged")
+        .append_pair("state", &state);
+    let response = callback!(
+        &service,
+        &format!("/auth/oidc/callback?{}", url.query().unwrap())
+    );
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // the provider reporting an error
+    let callback = authorize!(&service, &subject, "unused");
+    let url = Url::parse(&format!("http://localhost{}", callback)).unwrap();
+    let (_, state) = url.query_pairs().find(|(name, _)| name == "state").unwrap();
+    let response = callback!(
+        &service,
+        &format!("/auth/oidc/callback?error=access_denied&state={}", state)
+    );
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = callback!(&service, "/auth/oidc/callback?code=code&state=unknown");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::transaction_template_repo::{NewTransactionTemplate, TransactionTemplate};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_patch_transaction(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = NewTransaction::new(
+        "Misc".to_string(),
+        Some("Bob".to_string()),
+        Some("lunch".to_string()),
+        NaiveDate::from_str("2021-06-09").unwrap(),
+        Decimal::from_str("5.10").unwrap(),
+        HashSet::from(["food".to_string()]),
+    );
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+
+    // absent members are left unchanged
+    let request = TestRequest::patch()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .insert_header((header::CONTENT_TYPE, "application/merge-patch+json"))
+        .set_payload(json!({"note": "dinner"}).to_string())
+        .to_request();
+    let patched: Transaction = test::call_and_read_body_json(&service, request).await;
+    let mut expected = transaction.clone();
+    expected.note = Some("dinner".to_string());
+    expected.version = 2;
+    assert_eq!(expected, patched);
+
+    // null clears optional members
+    let request = TestRequest::patch()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .set_json(json!({"transactee": null, "tags": []}))
+        .to_request();
+    let patched: Transaction = test::call_and_read_body_json(&service, request).await;
+    expected.transactee = None;
+    expected.tags = HashSet::new();
+    expected.version = 3;
+    assert_eq!(expected, patched);
+
+    // but required members can't be removed
+    let request = TestRequest::patch()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .set_json(json!({"category": null}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+    let request = TestRequest::patch()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .insert_header((header::IF_MATCH, "\"1\""))
+        .set_json(json!({"note": "breakfast"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::PRECONDITION_FAILED, response.status());
+
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .to_request();
+    let current: Transaction = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(expected, current);
+
+    let request = TestRequest::patch()
+        .uri(format!("/transactions/{}", transaction.id + 1000).as_str())
+        .set_json(json!({"note": "breakfast"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_patch_template(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_template = NewTransactionTemplate::new(
+        "Rent".to_string(),
+        Some("Housing".to_string()),
+        Some("Landlord".to_string()),
+        Some(Decimal::from_str("-1200").unwrap()),
+        None,
+        HashSet::new(),
+    );
+    let request = TestRequest::post()
+        .uri("/templates")
+        .set_json(&new_template)
+        .to_request();
+    let template: TransactionTemplate = test::call_and_read_body_json(&service, request).await;
+
+    let request = TestRequest::patch()
+        .uri(format!("/templates/{}", template.template_id).as_str())
+        .set_json(json!({"amount": null, "note": "varies"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!("\"2\"", response.headers().get(header::ETAG).unwrap());
+    let patched: TransactionTemplate = test::read_body_json(response).await;
+    assert_eq!("Rent", patched.name);
+    assert_eq!(Some("Landlord".to_string()), patched.transactee);
+    assert_eq!(None, patched.amount);
+    assert_eq!(Some("varies".to_string()), patched.note);
+
+    let request = TestRequest::patch()
+        .uri(format!("/templates/{}", template.template_id).as_str())
+        .insert_header((header::IF_MATCH, "\"1\""))
+        .set_json(json!({"name": "Mortgage"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::PRECONDITION_FAILED, response.status());
+
+    test_user.delete().await
+}
@@ -0,0 +1,150 @@
+extern crate futures_util;
+extern crate serde_json;
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+fn build_transaction(transactee: &str, amount: i64) -> NewTransaction {
+    NewTransaction::new(
+        "Uncategorized".to_string(),
+        Some(transactee.to_string()),
+        None,
+        NaiveDate::from_str("2021-07-01").unwrap(),
+        Decimal::from(amount),
+        HashSet::new(),
+    )
+}
+
+fn shopping_rule() -> serde_json::Value {
+    json!({
+        "name": "Amazon",
+        "conditions": {"transactee": "^AMZN", "sign": "negative"},
+        "actions": {"category": "Shopping", "tags": ["online"]},
+    })
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_create_transaction_applies_rules(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::post()
+        .uri("/rules")
+        .set_json(shopping_rule())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let new_transaction = build_transaction("AMZN Mktp US", -20);
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+    assert_eq!("Shopping", transaction.category);
+    assert_eq!(HashSet::from(["online".to_string()]), transaction.tags);
+
+    // Refunds don't match the rule
+    let new_transaction = build_transaction("AMZN Mktp US", 20);
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+    assert_eq!("Uncategorized", transaction.category);
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_test_and_apply_rules(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = build_transaction("AMZN Mktp US", -20);
+    let amazon: Transaction = create_transaction!(&service, new_transaction);
+    let new_transaction = build_transaction("Alice", -20);
+    let alice: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::post()
+        .uri("/rules/test")
+        .set_json(shopping_rule())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    let matches: serde_json::Value = test::read_body_json(response).await;
+    let mut result = amazon.clone();
+    result.category = "Shopping".to_string();
+    result.tags = HashSet::from(["online".to_string()]);
+    assert_eq!(json!([{"transaction": amazon, "result": result}]), matches);
+
+    let request = TestRequest::post()
+        .uri("/rules")
+        .set_json(shopping_rule())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let request = TestRequest::post()
+        .uri("/rules/apply?from=2021-07-01")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    let applied: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(json!({"transactions": 1, "skipped": []}), applied);
+
+    let request = TestRequest::get()
+        .uri(&format!("/transactions/{}", amazon.id))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    let transaction: Transaction = test::read_body_json(response).await;
+    result.version += 1;
+    assert_eq!(result, transaction);
+
+    let request = TestRequest::get()
+        .uri(&format!("/transactions/{}", alice.id))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    let transaction: Transaction = test::read_body_json(response).await;
+    assert_eq!(alice, transaction);
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_create_invalid_rule(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::post()
+        .uri("/rules")
+        .set_json(json!({"name": "Invalid", "conditions": {"note": "gift("}}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    test_user.delete().await
+}
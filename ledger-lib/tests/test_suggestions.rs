@@ -0,0 +1,74 @@
+extern crate futures_util;
+extern crate serde_json;
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_get_suggestions(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let history = [
+        ("Groceries", "Whole Foods", -45, vec!["food"]),
+        ("Groceries", "Whole Foods Market", -30, vec!["food"]),
+        ("Groceries", "Trader Joes", -25, vec!["food"]),
+        ("Transport", "Shell", -40, vec!["car"]),
+        ("Salary", "Employer", 2000, vec![]),
+    ];
+    for (category, transactee, amount, tags) in history {
+        let new_transaction = NewTransaction::new(
+            category.to_string(),
+            Some(transactee.to_string()),
+            None,
+            NaiveDate::from_str("2021-07-01").unwrap(),
+            Decimal::from(amount),
+            tags.into_iter()
+                .map(|t| t.to_string())
+                .collect::<HashSet<_>>(),
+        );
+        let _transaction: Transaction = create_transaction!(&service, new_transaction);
+    }
+
+    let request = TestRequest::get()
+        .uri("/transactions/suggestions?transactee=Whole%20Foods&amount=-20&limit=2")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let suggestions: serde_json::Value = test::read_body_json(response).await;
+    let categories = suggestions["categories"].as_array().unwrap();
+    assert_eq!(2, categories.len());
+    assert_eq!("Groceries", categories[0]["name"]);
+    assert!(categories[0]["confidence"].as_f64().unwrap() > 0.8);
+
+    let tags = suggestions["tags"].as_array().unwrap();
+    assert_eq!("food", tags[0]["name"]);
+    assert!(tags[0]["confidence"].as_f64().unwrap() > 0.8);
+    assert_eq!("car", tags[1]["name"]);
+    assert!(tags[1]["confidence"].as_f64().unwrap() < 0.5);
+
+    test_user.delete().await
+}
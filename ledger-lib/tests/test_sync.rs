@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_lib::idempotency::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAYED_HEADER};
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+fn build_transaction(amount: &str) -> NewTransaction {
+    NewTransaction::new(
+        "Groceries".to_string(),
+        Some("Market".to_string()),
+        None,
+        NaiveDate::from_str("2021-06-09").unwrap(),
+        Decimal::from_str(amount).unwrap(),
+        HashSet::new(),
+    )
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_get_changes(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = build_transaction("-10");
+    let first: Transaction = create_transaction!(&service, new_transaction);
+    let new_transaction = build_transaction("-20");
+    let second: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::get().uri("/sync").to_request();
+    let changes: Value = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(json!([first, second]), changes["transactions"]);
+    assert_eq!(json!([]), changes["deleted_transactions"]);
+    let token = changes["sync_token"].as_str().unwrap().to_string();
+
+    let request = TestRequest::get()
+        .uri(&format!("/sync?token={}", token))
+        .to_request();
+    let changes: Value = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(json!([]), changes["transactions"]);
+    assert_eq!(token, changes["sync_token"]);
+
+    let request = TestRequest::delete()
+        .uri(&format!("/transactions/{}", first.id))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    let request = TestRequest::put()
+        .uri(&format!("/transactions/{}", second.id))
+        .set_json(build_transaction("-25"))
+        .to_request();
+    let updated: Transaction = test::call_and_read_body_json(&service, request).await;
+
+    let request = TestRequest::get()
+        .uri(&format!("/sync?token={}", token))
+        .to_request();
+    let changes: Value = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(json!([updated]), changes["transactions"]);
+    assert_eq!(json!([first.id]), changes["deleted_transactions"]);
+    assert_ne!(token, changes["sync_token"]);
+
+    // once the deleted transaction is purged the old token can't be used anymore
+    ledger_lib::trash::purge_trash(&repos, -1).await.unwrap();
+    let request = TestRequest::get()
+        .uri(&format!("/sync?token={}", token))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::GONE, response.status());
+
+    let request = TestRequest::get().uri("/sync?token=invalid").to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_upload_changes(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = build_transaction("-10");
+    let edited: Transaction = create_transaction!(&service, new_transaction);
+    let new_transaction = build_transaction("-20");
+    let deleted: Transaction = create_transaction!(&service, new_transaction);
+
+    // another device edits the transaction while this one is offline
+    let request = TestRequest::put()
+        .uri(&format!("/transactions/{}", edited.id))
+        .set_json(build_transaction("-15"))
+        .to_request();
+    let current: Transaction = test::call_and_read_body_json(&service, request).await;
+
+    let request = TestRequest::post()
+        .uri("/sync")
+        .set_json(json!({
+            "transactions": [
+                {"op": "create", "client_ref": "local-1", "transaction": build_transaction("-30")},
+                {"op": "update", "id": edited.id, "base_version": edited.version, "transaction": build_transaction("-12")},
+                {"op": "delete", "id": deleted.id, "base_version": deleted.version},
+                {"op": "delete", "id": deleted.id, "base_version": deleted.version},
+            ],
+            "templates": [
+                {"op": "create", "client_ref": "local-2", "template": {"name": "Rent", "category": null, "transactee": null, "amount": null, "note": null, "tags": []}},
+            ],
+        }))
+        .to_request();
+    let results: Value = test::call_and_read_body_json(&service, request).await;
+
+    let created = &results["transactions"][0];
+    assert_eq!("applied", created["status"]);
+    assert_eq!("local-1", created["client_ref"]);
+    assert_eq!("-30", created["current"]["amount"]);
+    assert_eq!(
+        json!({"status": "conflict", "id": edited.id, "current": current}),
+        results["transactions"][1]
+    );
+    assert_eq!(
+        json!({"status": "applied", "id": deleted.id, "current": null}),
+        results["transactions"][2]
+    );
+    assert_eq!("applied", results["transactions"][3]["status"]);
+    assert_eq!("applied", results["templates"][0]["status"]);
+    assert_eq!("Rent", results["templates"][0]["current"]["name"]);
+
+    let request = TestRequest::get().uri("/transactions").to_request();
+    let transactions: Vec<Transaction> = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(2, transactions.len());
+    assert!(transactions.contains(&current));
+
+    let request = TestRequest::post()
+        .uri("/sync")
+        .set_json(json!({
+            "transactions": [
+                {"op": "update", "id": edited.id, "base_version": current.version, "transaction": build_transaction("-12")},
+            ],
+        }))
+        .to_request();
+    let results: Value = test::call_and_read_body_json(&service, request).await;
+    assert_eq!("applied", results["transactions"][0]["status"]);
+    assert_eq!("-12", results["transactions"][0]["current"]["amount"]);
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_upload_errors(_tracing_setup: &(), repos: Repos) {
+    let owner = TestUser::new(repos.user_repo.clone()).await;
+    let viewer = TestUser::new(repos.user_repo.clone()).await;
+    let owner_service = test::init_service(build_app!(repos, owner.user_id.clone())).await;
+    let viewer_service = test::init_service(build_app!(repos, viewer.user_id.clone())).await;
+
+    let request = TestRequest::get().uri("/ledgers").to_request();
+    let ledgers: Value = test::call_and_read_body_json(&owner_service, request).await;
+    let ledger_id = ledgers[0]["id"].clone();
+    let request = TestRequest::post()
+        .uri(format!("/ledgers/{}/invitations", ledger_id).as_str())
+        .set_json(json!({"role": "viewer"}))
+        .to_request();
+    let invitation: Value = test::call_and_read_body_json(&owner_service, request).await;
+    let request = TestRequest::post()
+        .uri("/ledgers/join")
+        .set_json(json!({"code": invitation["code"]}))
+        .to_request();
+    let response = test::call_service(&viewer_service, request).await;
+    assert!(response.status().is_success());
+    let request = TestRequest::put()
+        .uri("/ledgers/current")
+        .set_json(json!({"ledger_id": ledger_id}))
+        .to_request();
+    let response = test::call_service(&viewer_service, request).await;
+    assert!(response.status().is_success());
+
+    let new_transaction = build_transaction("-10");
+    let transaction: Transaction = create_transaction!(&owner_service, new_transaction);
+
+    // viewers can't change anything, which is reported per change rather than failing the upload
+    let upload = json!({
+        "transactions": [
+            {"op": "update", "id": transaction.id + 1000, "base_version": 1, "transaction": build_transaction("-12")},
+            {"op": "create", "client_ref": "local-1", "transaction": build_transaction("-30")},
+        ],
+    });
+    let request = TestRequest::post()
+        .uri("/sync")
+        .insert_header((IDEMPOTENCY_KEY_HEADER, "upload-1"))
+        .set_json(&upload)
+        .to_request();
+    let results: Value = test::call_and_read_body_json(&viewer_service, request).await;
+    assert_eq!(
+        json!({"status": "conflict", "id": transaction.id + 1000, "current": null}),
+        results["transactions"][0]
+    );
+    assert_eq!("error", results["transactions"][1]["status"]);
+    assert_eq!("local-1", results["transactions"][1]["client_ref"]);
+    assert!(results["transactions"][1]["error"].is_string());
+
+    // so the response is kept for retries with the same key
+    let request = TestRequest::post()
+        .uri("/sync")
+        .insert_header((IDEMPOTENCY_KEY_HEADER, "upload-1"))
+        .set_json(&upload)
+        .to_request();
+    let response = test::call_service(&viewer_service, request).await;
+    assert!(response.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+    let replayed: Value = test::read_body_json(response).await;
+    assert_eq!(results, replayed);
+
+    owner.delete().await;
+    viewer.delete().await;
+}
@@ -0,0 +1,99 @@
+extern crate futures_util;
+extern crate serde_json;
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_rename_tag(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = NewTransaction::new(
+        "Misc".to_string(),
+        None,
+        None,
+        NaiveDate::from_str("2021-07-01").unwrap(),
+        Decimal::from(-20),
+        HashSet::from(["grocery".to_string(), "food".to_string()]),
+    );
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::put()
+        .uri("/transactions/tags/grocery")
+        .set_json(json!({"name": "groceries"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let changes: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(json!({"transactions": 1, "templates": 0}), changes);
+
+    let request = TestRequest::get()
+        .uri(&format!("/transactions/{}", transaction.id))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    let transaction: Transaction = test::read_body_json(response).await;
+    assert_eq!(
+        HashSet::from(["groceries".to_string(), "food".to_string()]),
+        transaction.tags
+    );
+
+    let request = TestRequest::get()
+        .uri("/transactions/tags/stats")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    let stats: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(
+        json!([
+            {"tag": "food", "count": 1, "total": "-20"},
+            {"tag": "groceries", "count": 1, "total": "-20"},
+        ]),
+        stats
+    );
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_rename_tag_to_empty(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::put()
+        .uri("/transactions/tags/food")
+        .set_json(json!({"name": ""}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    test_user.delete().await
+}
@@ -0,0 +1,137 @@
+extern crate futures_util;
+extern crate serde_json;
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+fn new_transaction(transactee: &str) -> NewTransaction {
+    NewTransaction::new(
+        "Shopping".to_string(),
+        Some(transactee.to_string()),
+        None,
+        NaiveDate::from_str("2021-07-01").unwrap(),
+        Decimal::from(-20),
+        HashSet::new(),
+    )
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_create_transaction_normalizes_transactee(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::put()
+        .uri("/transactions/transactees/Amazon")
+        .set_json(json!({"aliases": ["^AMZN Mktp", "amazon\\.com"]}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    for transactee in ["AMZN Mktp US*2K4", "Amazon.com", "AMAZON"] {
+        let new_transaction = new_transaction(transactee);
+        let transaction: Transaction = create_transaction!(&service, new_transaction);
+        assert_eq!(Some("Amazon".to_string()), transaction.transactee);
+    }
+    let new_transaction = new_transaction("Alice");
+    let _transaction: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::get()
+        .uri("/transactions/transactees")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    let transactees: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(
+        json!([
+            {"name": "Amazon", "count": 3, "aliases": ["^AMZN Mktp", "amazon\\.com"]},
+            {"name": "Alice", "count": 1, "aliases": []},
+        ]),
+        transactees
+    );
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_merge_transactees(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    for transactee in ["AMZN Mktp US*2K4", "Amazon.com"] {
+        let new_transaction = new_transaction(transactee);
+        let _transaction: Transaction = create_transaction!(&service, new_transaction);
+    }
+
+    let request = TestRequest::post()
+        .uri("/transactions/transactees/merge")
+        .set_json(json!({"transactees": ["AMZN Mktp US*2K4", "Amazon.com"], "into": "Amazon"}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    let changes: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(json!({"transactions": 2, "templates": 0}), changes);
+
+    // Merged names are normalized from now on
+    let new_transaction = new_transaction("amazon.com");
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+    assert_eq!(Some("Amazon".to_string()), transaction.transactee);
+
+    let request = TestRequest::get()
+        .uri("/transactions/transactees")
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    let transactees: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(
+        json!([
+            {"name": "Amazon", "count": 3, "aliases": ["^AMZN Mktp US\\*2K4$", "^Amazon\\.com$"]},
+        ]),
+        transactees
+    );
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_set_invalid_alias(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::put()
+        .uri("/transactions/transactees/Amazon")
+        .set_json(json!({"aliases": ["AMZN("]}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    test_user.delete().await
+}
@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::NaiveDate;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::transaction_template_repo::NewTransactionTemplate;
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_trash_and_restore_transaction(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = NewTransaction::new(
+        "Misc".to_string(),
+        Some("Bob".to_string()),
+        None,
+        NaiveDate::from_str("2021-06-09").unwrap(),
+        Decimal::from_str("5.10").unwrap(),
+        HashSet::new(),
+    );
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::delete()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+    let request = TestRequest::get().uri("/trash/transactions").to_request();
+    let trash: Vec<Value> = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(1, trash.len());
+    assert_eq!(transaction.id, trash[0]["id"]);
+    assert!(trash[0]["deleted_at"].is_string());
+
+    let request = TestRequest::post()
+        .uri(format!("/trash/transactions/{}/restore", transaction.id).as_str())
+        .to_request();
+    let restored: Transaction = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(transaction.version + 1, restored.version);
+    assert_eq!(
+        Transaction {
+            version: restored.version,
+            ..transaction
+        },
+        restored
+    );
+
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let request = TestRequest::post()
+        .uri(format!("/trash/transactions/{}/restore", transaction.id).as_str())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+    test_user.delete().await
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_trash_and_restore_template(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let template = repos
+        .template_repo
+        .create_template(
+            &test_user.user_id,
+            NewTransactionTemplate::new(
+                "Rent".to_string(),
+                Some("Housing".to_string()),
+                None,
+                None,
+                None,
+                HashSet::new(),
+            ),
+        )
+        .await
+        .unwrap();
+    repos
+        .template_repo
+        .delete_template(&test_user.user_id, template.template_id, None)
+        .await
+        .unwrap();
+
+    let request = TestRequest::get().uri("/trash/templates").to_request();
+    let trash: Vec<Value> = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(1, trash.len());
+    assert_eq!(template.template_id, trash[0]["template_id"]);
+
+    let request = TestRequest::post()
+        .uri(format!("/trash/templates/{}/restore", template.template_id).as_str())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    let templates = repos
+        .template_repo
+        .get_templates(&test_user.user_id)
+        .await
+        .unwrap();
+    assert_eq!(1, templates.len());
+
+    test_user.delete().await
+}
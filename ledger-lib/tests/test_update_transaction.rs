@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 use std::str::FromStr;
-use std::sync::Arc;
 
+use actix_web::http::header;
 use actix_web::http::StatusCode;
 use actix_web::test;
 use actix_web::test::{read_body_json, TestRequest};
@@ -13,9 +13,8 @@ use rust_decimal::Decimal;
 use tracing::instrument;
 
 use crate::utils::mock::MockAuthentication;
-use ledger_repo::transaction_repo::{NewTransaction, Transaction, TransactionRepo};
-use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
-use ledger_repo::user_repo::UserRepo;
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
 use utils::repos;
 use utils::tracing_setup;
 use utils::TestUser;
@@ -26,17 +25,9 @@ mod utils;
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_update_transaction(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_update_transaction(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let new_transaction = NewTransaction::new(
@@ -82,17 +73,9 @@ async fn test_update_transaction(
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_update_tags(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_update_tags(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let new_transaction = NewTransaction::new(
@@ -131,17 +114,9 @@ async fn test_update_tags(
 #[instrument(skip(repos))]
 #[rstest]
 #[actix_rt::test]
-async fn test_update_invalid_transaction(
-    _tracing_setup: &(),
-    repos: (
-        Arc<dyn UserRepo>,
-        Arc<dyn TransactionRepo>,
-        Arc<dyn TransactionTemplateRepo>,
-    ),
-) {
-    let (user_repo, transaction_repo, _template_repo) = repos;
-    let test_user = TestUser::new(user_repo).await;
-    let app = build_app!(transaction_repo, test_user.user_id.clone());
+async fn test_update_invalid_transaction(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
     let service = test::init_service(app).await;
 
     let update = NewTransaction::new(
@@ -162,3 +137,72 @@ async fn test_update_invalid_transaction(
 
     test_user.delete().await
 }
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_update_with_if_match(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let new_transaction = NewTransaction::new(
+        "Misc".to_string(),
+        Some("Bob".to_string()),
+        None,
+        NaiveDate::from_str("2021-06-09").unwrap(),
+        Decimal::from_str("5.10").unwrap(),
+        HashSet::new(),
+    );
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    let etag = response.headers().get(header::ETAG).unwrap().clone();
+    assert_eq!("\"1\"", etag);
+
+    let mut update = new_transaction.clone();
+    update.note = Some("first device".to_string());
+    let request = TestRequest::put()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .insert_header((header::IF_MATCH, etag.clone()))
+        .set_json(&update)
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+    assert_eq!("\"2\"", response.headers().get(header::ETAG).unwrap());
+
+    // the second device still has the old version
+    update.note = Some("second device".to_string());
+    let request = TestRequest::put()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .insert_header((header::IF_MATCH, etag.clone()))
+        .set_json(&update)
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::PRECONDITION_FAILED, response.status());
+
+    let request = TestRequest::delete()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .insert_header((header::IF_MATCH, etag))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::PRECONDITION_FAILED, response.status());
+
+    let request = TestRequest::get()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .to_request();
+    let current: Transaction = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(Some("first device".to_string()), current.note);
+
+    let request = TestRequest::delete()
+        .uri(format!("/transactions/{}", transaction.id).as_str())
+        .insert_header((header::IF_MATCH, "\"2\""))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    test_user.delete().await
+}
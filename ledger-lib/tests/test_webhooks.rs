@@ -0,0 +1,314 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::test::TestRequest;
+use actix_web::web::{Bytes, Data};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use chrono::{Duration, NaiveDate, Utc};
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use tracing::instrument;
+
+use crate::utils::mock::MockAuthentication;
+use ledger_lib::webhook::{sign, WebhookDispatcher, WebhookTargets};
+use ledger_repo::transaction_repo::{NewTransaction, Transaction};
+use ledger_repo::Repos;
+use utils::repos;
+use utils::tracing_setup;
+use utils::TestUser;
+
+#[macro_use]
+mod utils;
+
+fn build_transaction(category: &str, amount: &str) -> NewTransaction {
+    NewTransaction::new(
+        category.to_string(),
+        Some("Market".to_string()),
+        None,
+        NaiveDate::from_str("2021-06-09").unwrap(),
+        Decimal::from_str(amount).unwrap(),
+        HashSet::new(),
+    )
+}
+
+/// The receivers are local, so private targets have to be allowed
+const LOCAL_TARGETS: WebhookTargets = WebhookTargets {
+    allow_private: true,
+};
+
+struct ReceivedRequest {
+    event: String,
+    signature: String,
+    body: Bytes,
+}
+
+/// A local stand-in for a webhook receiver, which records the requests it gets and responds with
+/// `status`
+#[derive(Clone)]
+struct Receiver {
+    url: String,
+    requests: Arc<Mutex<Vec<ReceivedRequest>>>,
+    status: Arc<AtomicU16>,
+}
+
+async fn receive(receiver: Data<Receiver>, request: HttpRequest, body: Bytes) -> HttpResponse {
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap().to_string())
+            .unwrap_or_default()
+    };
+    receiver.requests.lock().unwrap().push(ReceivedRequest {
+        event: header("X-Ledger-Event"),
+        signature: header("X-Ledger-Signature"),
+        body,
+    });
+    let status = StatusCode::from_u16(receiver.status.load(Ordering::SeqCst)).unwrap();
+    HttpResponse::build(status).finish()
+}
+
+fn start_receiver() -> Receiver {
+    let mut receiver = Receiver {
+        url: String::new(),
+        requests: Arc::new(Mutex::new(Vec::new())),
+        status: Arc::new(AtomicU16::new(200)),
+    };
+    let state = receiver.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(Data::new(state.clone()))
+            .default_service(web::to(receive))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    receiver.url = format!("http://{}/hook", server.addrs()[0]);
+    actix_web::rt::spawn(server.run());
+    receiver
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_webhook_delivery(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+    let receiver = start_receiver();
+
+    let request = TestRequest::post()
+        .uri("/webhooks")
+        .set_json(json!({
+            "url": receiver.url,
+            "events": ["transaction.created"],
+            "filter": {"min_expense": "100"},
+        }))
+        .to_request();
+    let webhook: Value = test::call_and_read_body_json(&service, request).await;
+    let secret = webhook["secret"].as_str().unwrap().to_string();
+
+    // the secret is only shown when the webhook is created
+    let request = TestRequest::get()
+        .uri(&format!("/webhooks/{}", webhook["id"]))
+        .to_request();
+    let fetched: Value = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(webhook["url"], fetched["url"]);
+    assert_eq!(None, fetched.get("secret"));
+
+    let new_transaction = build_transaction("Groceries", "-150");
+    let large_expense: Transaction = create_transaction!(&service, new_transaction);
+    let new_transaction = build_transaction("Groceries", "-50");
+    let _: Transaction = create_transaction!(&service, new_transaction);
+
+    let delivered = WebhookDispatcher::with_targets(LOCAL_TARGETS)
+        .deliver_due(&*repos.webhook_repo)
+        .await
+        .unwrap();
+    assert_eq!(1, delivered);
+
+    {
+        let requests = receiver.requests.lock().unwrap();
+        assert_eq!(1, requests.len());
+        let request = &requests[0];
+        assert_eq!("transaction.created", request.event);
+        assert_eq!(sign(&secret, &request.body), request.signature);
+        let payload: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!("transaction.created", payload["event"]);
+        assert_eq!(json!(large_expense), payload["transaction"]);
+    }
+
+    let request = TestRequest::get()
+        .uri(&format!("/webhooks/{}/deliveries", webhook["id"]))
+        .to_request();
+    let deliveries: Value = test::call_and_read_body_json(&service, request).await;
+    let deliveries = deliveries.as_array().unwrap();
+    assert_eq!(1, deliveries.len());
+    assert_eq!("succeeded", deliveries[0]["status"]);
+    assert_eq!(1, deliveries[0]["attempts"]);
+    assert_eq!(200, deliveries[0]["last_response_status"]);
+
+    test_user.delete().await;
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_webhook_retries(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+    let receiver = start_receiver();
+    receiver.status.store(500, Ordering::SeqCst);
+
+    let request = TestRequest::post()
+        .uri("/webhooks")
+        .set_json(json!({
+            "url": receiver.url,
+            "events": ["transaction.created", "transaction.deleted"],
+            "filter": {"category": "Rent"},
+        }))
+        .to_request();
+    let webhook: Value = test::call_and_read_body_json(&service, request).await;
+    let new_transaction = build_transaction("Rent", "-1000");
+    let transaction: Transaction = create_transaction!(&service, new_transaction);
+
+    // retried straight away until it has been attempted twice
+    let delivered = WebhookDispatcher::new(LOCAL_TARGETS, 2, Duration::zero())
+        .deliver_due(&*repos.webhook_repo)
+        .await
+        .unwrap();
+    assert_eq!(2, delivered);
+    assert_eq!(2, receiver.requests.lock().unwrap().len());
+
+    let request = TestRequest::delete()
+        .uri(&format!("/transactions/{}", transaction.id))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert!(response.status().is_success());
+
+    // the next attempt is delayed
+    let before = Utc::now();
+    let delivered = WebhookDispatcher::new(LOCAL_TARGETS, 3, Duration::hours(1))
+        .deliver_due(&*repos.webhook_repo)
+        .await
+        .unwrap();
+    assert_eq!(1, delivered);
+
+    let request = TestRequest::get()
+        .uri(&format!("/webhooks/{}/deliveries", webhook["id"]))
+        .to_request();
+    let deliveries: Value = test::call_and_read_body_json(&service, request).await;
+    let deliveries = deliveries.as_array().unwrap();
+    assert_eq!(2, deliveries.len());
+    assert_eq!("transaction.deleted", deliveries[0]["event"]);
+    assert_eq!("pending", deliveries[0]["status"]);
+    assert_eq!(1, deliveries[0]["attempts"]);
+    let next_attempt_at: chrono::DateTime<Utc> =
+        serde_json::from_value(deliveries[0]["next_attempt_at"].clone()).unwrap();
+    assert!(next_attempt_at >= before + Duration::hours(1));
+    assert_eq!("transaction.created", deliveries[1]["event"]);
+    assert_eq!("failed", deliveries[1]["status"]);
+    assert_eq!(2, deliveries[1]["attempts"]);
+    assert_eq!(500, deliveries[1]["last_response_status"]);
+
+    test_user.delete().await;
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_invalid_webhooks(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+
+    let request = TestRequest::post()
+        .uri("/webhooks")
+        .set_json(json!({"url": "ftp://localhost/hook", "events": ["transaction.created"]}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+    let request = TestRequest::post()
+        .uri("/webhooks")
+        .set_json(json!({"url": "http://localhost/hook", "events": []}))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+    let request = TestRequest::get().uri("/webhooks/1000").to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+    let request = TestRequest::get().uri("/webhooks").to_request();
+    let webhooks: Value = test::call_and_read_body_json(&service, request).await;
+    assert_eq!(json!([]), webhooks);
+
+    test_user.delete().await;
+}
+
+#[instrument(skip(repos))]
+#[rstest]
+#[actix_rt::test]
+async fn test_private_webhook_targets(_tracing_setup: &(), repos: Repos) {
+    let test_user = TestUser::new(repos.user_repo.clone()).await;
+    let receiver = start_receiver();
+
+    // without allowing private targets, local receivers can't be registered
+    let app = App::new()
+        .app_data(Data::new(repos.webhook_repo.clone()))
+        .app_data(Data::new(WebhookTargets::default()))
+        .service(
+            ledger_lib::webhook::webhook_service().wrap(MockAuthentication {
+                user_id: test_user.user_id.clone(),
+            }),
+        );
+    let service = test::init_service(app).await;
+    for url in [
+        receiver.url.as_str(),
+        "http://localhost/hook",
+        "http://169.254.169.254/latest/meta-data",
+        "http://[::1]/hook",
+        "http://10.0.0.1/hook",
+    ] {
+        let request = TestRequest::post()
+            .uri("/webhooks")
+            .set_json(json!({"url": url, "events": ["transaction.created"]}))
+            .to_request();
+        let response = test::call_service(&service, request).await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status(), "{}", url);
+    }
+
+    // or delivered to, in case the host resolved to a public address when it was registered
+    let app = build_app!(repos, test_user.user_id.clone());
+    let service = test::init_service(app).await;
+    let request = TestRequest::post()
+        .uri("/webhooks")
+        .set_json(json!({"url": receiver.url, "events": ["transaction.created"]}))
+        .to_request();
+    let webhook: Value = test::call_and_read_body_json(&service, request).await;
+    let new_transaction = build_transaction("Groceries", "-10");
+    let _: Transaction = create_transaction!(&service, new_transaction);
+
+    let delivered = WebhookDispatcher::with_targets(WebhookTargets::default())
+        .deliver_due(&*repos.webhook_repo)
+        .await
+        .unwrap();
+    assert_eq!(1, delivered);
+    assert!(receiver.requests.lock().unwrap().is_empty());
+
+    let request = TestRequest::get()
+        .uri(&format!("/webhooks/{}/deliveries", webhook["id"]))
+        .to_request();
+    let deliveries: Value = test::call_and_read_body_json(&service, request).await;
+    assert_eq!("pending", deliveries[0]["status"]);
+    assert_eq!(Value::Null, deliveries[0]["last_response_status"]);
+
+    test_user.delete().await;
+}
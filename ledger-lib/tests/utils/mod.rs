@@ -6,27 +6,99 @@ use tracing::info;
 use tracing::Level;
 use uuid::Uuid;
 
-use ledger_repo::transaction_repo::TransactionRepo;
-use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
 use ledger_repo::user_repo::User;
 use ledger_repo::user_repo::UserRepo;
+use ledger_repo::Repos;
 
+#[allow(dead_code)]
 pub mod mock;
 
+#[allow(unused_macros)]
 macro_rules! build_app {
-    ($transaction_repo:ident, $user_id:expr) => {{
+    ($repos:ident, $user_id:expr) => {{
+        let user_id = $user_id;
         let app = App::new()
-            .app_data(Data::new($transaction_repo))
+            .app_data(Data::new($repos.ledger_repo.clone()))
+            .app_data(Data::new($repos.transaction_repo.clone()))
+            .app_data(Data::new($repos.template_repo.clone()))
+            .app_data(Data::new($repos.category_repo.clone()))
+            .app_data(Data::new($repos.transactee_repo.clone()))
+            .app_data(Data::new($repos.rule_repo.clone()))
+            .app_data(Data::new($repos.duplicate_repo.clone()))
+            .app_data(Data::new($repos.history_repo.clone()))
+            .app_data(Data::new($repos.idempotency_repo.clone()))
+            .app_data(Data::new($repos.webhook_repo.clone()))
+            .app_data(Data::new($repos.refresh_token_repo.clone()))
+            .app_data(Data::new($repos.session_repo.clone()))
+            .app_data(Data::new($repos.api_key_repo.clone()))
+            .app_data(Data::new($repos.totp_repo.clone()))
+            .app_data(Data::new($repos.rate_limit_repo.clone()))
+            .app_data(Data::new($repos.oidc_repo.clone()))
+            .app_data(Data::new(
+                ledger_lib::idempotency::IdempotencyWindow::default(),
+            ))
+            .app_data(Data::new(ledger_lib::events::EventBus::new()))
+            // webhooks are sent to local receivers
+            .app_data(Data::new(ledger_lib::webhook::WebhookTargets {
+                allow_private: true,
+            }))
             .wrap(ledger_lib::tracing::create_middleware())
             .service(
                 ledger_lib::transaction::transaction_service()
-                    .wrap(MockAuthentication { user_id: $user_id }),
+                    .wrap(ledger_lib::events::PublishChanges)
+                    .wrap(MockAuthentication {
+                        user_id: user_id.clone(),
+                    }),
+            )
+            .service(
+                ledger_lib::transaction_template::transaction_template_service()
+                    .wrap(ledger_lib::events::PublishChanges)
+                    .wrap(MockAuthentication {
+                        user_id: user_id.clone(),
+                    }),
+            )
+            .service(
+                ledger_lib::rule::rule_service()
+                    .wrap(ledger_lib::events::PublishChanges)
+                    .wrap(MockAuthentication {
+                        user_id: user_id.clone(),
+                    }),
+            )
+            .service(
+                ledger_lib::trash::trash_service()
+                    .wrap(ledger_lib::events::PublishChanges)
+                    .wrap(MockAuthentication {
+                        user_id: user_id.clone(),
+                    }),
+            )
+            .service(
+                ledger_lib::sync::sync_service()
+                    .wrap(ledger_lib::events::PublishChanges)
+                    .wrap(MockAuthentication {
+                        user_id: user_id.clone(),
+                    }),
+            )
+            .service(
+                ledger_lib::events::events_service().wrap(MockAuthentication {
+                    user_id: user_id.clone(),
+                }),
+            )
+            .service(
+                ledger_lib::webhook::webhook_service().wrap(MockAuthentication {
+                    user_id: user_id.clone(),
+                }),
+            )
+            .service(
+                ledger_lib::ledger::ledger_service()
+                    .wrap(ledger_lib::events::PublishChanges)
+                    .wrap(MockAuthentication { user_id }),
             );
         tracing::info!("Built app");
         app
     }};
 }
 
+#[allow(unused_macros)]
 macro_rules! create_transaction {
     (&$service:ident, $new_transaction:ident) => {{
         let request = TestRequest::post()
@@ -53,7 +125,9 @@ impl TestUser {
         let user_id = "test-user-".to_owned() + &Uuid::new_v4().to_string();
         let user = User::new(
             user_id.to_string(),
-            ledger_lib::auth::password::encode_password("pass".to_string()).unwrap(),
+            ledger_lib::auth::password::Passwords::default()
+                .encode_password("pass")
+                .unwrap(),
         );
         user_repo.create_user(user).await.unwrap();
         info!(%user_id, "Created user");
@@ -79,10 +153,6 @@ pub fn tracing_setup() -> () {
 }
 
 #[fixture]
-pub fn repos() -> (
-    Arc<dyn UserRepo>,
-    Arc<dyn TransactionRepo>,
-    Arc<dyn TransactionTemplateRepo>,
-) {
+pub fn repos() -> Repos {
     ledger_repo::mem_repo::create_repos()
 }
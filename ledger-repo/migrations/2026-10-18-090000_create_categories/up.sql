@@ -0,0 +1,10 @@
+CREATE TABLE categories
+(
+    user_id  VARCHAR NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name     VARCHAR NOT NULL,
+    kind     VARCHAR NOT NULL CHECK (kind IN ('income', 'expense', 'transfer')),
+    color    VARCHAR,
+    icon     VARCHAR,
+    archived BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (user_id, name)
+);
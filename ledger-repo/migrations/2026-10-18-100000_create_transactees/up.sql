@@ -0,0 +1,7 @@
+CREATE TABLE transactees
+(
+    user_id VARCHAR NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name    VARCHAR NOT NULL,
+    aliases VARCHAR[] NOT NULL DEFAULT '{}',
+    PRIMARY KEY (user_id, name)
+);
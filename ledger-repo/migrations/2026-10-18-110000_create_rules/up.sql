@@ -0,0 +1,17 @@
+CREATE TABLE rules
+(
+    id                 SERIAL PRIMARY KEY,
+    user_id            VARCHAR             NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name               VARCHAR             NOT NULL,
+    priority           INTEGER DEFAULT 0   NOT NULL,
+    transactee_pattern VARCHAR,
+    note_pattern       VARCHAR,
+    min_amount         NUMERIC,
+    max_amount         NUMERIC,
+    sign               VARCHAR CHECK (sign IN ('positive', 'negative')),
+    day_of_month       INTEGER CHECK (day_of_month BETWEEN 1 AND 31),
+    set_category       VARCHAR,
+    set_transactee     VARCHAR,
+    add_tags           TEXT[] DEFAULT '{}' NOT NULL,
+    set_note           VARCHAR
+);
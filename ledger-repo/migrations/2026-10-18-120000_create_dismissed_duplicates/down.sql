@@ -0,0 +1 @@
+DROP TABLE dismissed_duplicates;
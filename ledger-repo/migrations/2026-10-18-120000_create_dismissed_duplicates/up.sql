@@ -0,0 +1,8 @@
+CREATE TABLE dismissed_duplicates
+(
+    user_id              VARCHAR NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    transaction_id       INTEGER NOT NULL REFERENCES transactions (id) ON DELETE CASCADE,
+    other_transaction_id INTEGER NOT NULL REFERENCES transactions (id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, transaction_id, other_transaction_id),
+    CHECK (transaction_id < other_transaction_id)
+);
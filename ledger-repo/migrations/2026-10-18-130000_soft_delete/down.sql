@@ -0,0 +1,10 @@
+DELETE FROM transactions WHERE deleted_at IS NOT NULL;
+DELETE FROM transaction_templates WHERE deleted_at IS NOT NULL;
+
+DROP INDEX transaction_templates_deleted_at;
+DROP INDEX transactions_deleted_at;
+
+ALTER TABLE transaction_templates
+    DROP COLUMN deleted_at;
+ALTER TABLE transactions
+    DROP COLUMN deleted_at;
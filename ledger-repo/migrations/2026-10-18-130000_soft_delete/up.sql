@@ -0,0 +1,7 @@
+ALTER TABLE transactions
+    ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE transaction_templates
+    ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX transactions_deleted_at ON transactions (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX transaction_templates_deleted_at ON transaction_templates (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,14 @@
+CREATE TABLE transaction_history
+(
+    id             SERIAL PRIMARY KEY,
+    user_id        VARCHAR                   NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    transaction_id INTEGER                   NOT NULL,
+    kind           VARCHAR                   NOT NULL CHECK (kind IN ('create', 'update', 'delete', 'restore', 'revert')),
+    before         JSONB,
+    after          JSONB,
+    changed_at     TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    changed_by     VARCHAR                   NOT NULL,
+    client         VARCHAR
+);
+
+CREATE INDEX transaction_history_transaction ON transaction_history (user_id, transaction_id);
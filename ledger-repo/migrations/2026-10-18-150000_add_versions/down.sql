@@ -0,0 +1,11 @@
+UPDATE transaction_history
+SET before = before - 'version'
+WHERE before IS NOT NULL;
+UPDATE transaction_history
+SET after = after - 'version'
+WHERE after IS NOT NULL;
+
+ALTER TABLE transaction_templates
+    DROP COLUMN version;
+ALTER TABLE transactions
+    DROP COLUMN version;
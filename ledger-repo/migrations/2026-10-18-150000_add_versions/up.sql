@@ -0,0 +1,14 @@
+ALTER TABLE transactions
+    ADD COLUMN version INTEGER DEFAULT 1 NOT NULL;
+ALTER TABLE transaction_templates
+    ADD COLUMN version INTEGER DEFAULT 1 NOT NULL;
+
+-- snapshots recorded before versions existed
+UPDATE transaction_history
+SET before = before || '{"version": 1}'
+WHERE before IS NOT NULL
+  AND NOT before ? 'version';
+UPDATE transaction_history
+SET after = after || '{"version": 1}'
+WHERE after IS NOT NULL
+  AND NOT after ? 'version';
@@ -0,0 +1,12 @@
+CREATE TABLE idempotency_keys
+(
+    user_id         VARCHAR     NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    key             VARCHAR     NOT NULL,
+    request_path    VARCHAR     NOT NULL,
+    response_status INTEGER,
+    response_body   TEXT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, key)
+);
+
+CREATE INDEX idempotency_keys_created_at_idx ON idempotency_keys (created_at);
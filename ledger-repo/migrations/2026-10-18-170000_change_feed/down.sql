@@ -0,0 +1,12 @@
+DROP TABLE purged_changes;
+
+DROP TRIGGER transaction_templates_change_seq ON transaction_templates;
+DROP TRIGGER transactions_change_seq ON transactions;
+DROP FUNCTION next_change_seq();
+
+ALTER TABLE transaction_templates
+    DROP COLUMN change_seq;
+ALTER TABLE transactions
+    DROP COLUMN change_seq;
+
+DROP SEQUENCE change_seq;
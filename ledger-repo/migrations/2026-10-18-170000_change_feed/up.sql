@@ -0,0 +1,40 @@
+-- Every insert and update of a transaction or template takes the next number from change_seq, so
+-- clients can ask for everything that changed after the last change they have seen
+CREATE SEQUENCE change_seq;
+
+ALTER TABLE transactions
+    ADD COLUMN change_seq BIGINT NOT NULL DEFAULT nextval('change_seq');
+ALTER TABLE transaction_templates
+    ADD COLUMN change_seq BIGINT NOT NULL DEFAULT nextval('change_seq');
+
+CREATE INDEX transactions_change_seq_idx ON transactions (user_id, change_seq);
+CREATE INDEX transaction_templates_change_seq_idx ON transaction_templates (user_id, change_seq);
+
+CREATE FUNCTION next_change_seq() RETURNS TRIGGER AS
+$$
+BEGIN
+    NEW.change_seq := nextval('change_seq');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_change_seq
+    BEFORE UPDATE
+    ON transactions
+    FOR EACH ROW
+EXECUTE FUNCTION next_change_seq();
+
+CREATE TRIGGER transaction_templates_change_seq
+    BEFORE UPDATE
+    ON transaction_templates
+    FOR EACH ROW
+EXECUTE FUNCTION next_change_seq();
+
+-- The latest change of each user's transactions and templates that were purged from the trash.
+-- Clients that last synced before it have missed the deletion and need to sync from scratch.
+CREATE TABLE purged_changes
+(
+    user_id                     VARCHAR PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    transactions_purged_through BIGINT NOT NULL DEFAULT 0,
+    templates_purged_through    BIGINT NOT NULL DEFAULT 0
+);
@@ -0,0 +1,3 @@
+DROP TRIGGER transaction_templates_notify_change ON transaction_templates;
+DROP TRIGGER transactions_notify_change ON transactions;
+DROP FUNCTION notify_change();
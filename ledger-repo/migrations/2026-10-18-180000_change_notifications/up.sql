@@ -0,0 +1,22 @@
+-- Tells every server listening on ledger_changes whose transactions or templates changed, so
+-- servers sharing the database can push the change to their own clients. Notifications with the
+-- same user id are merged by Postgres within a database transaction.
+CREATE FUNCTION notify_change() RETURNS TRIGGER AS
+$$
+BEGIN
+    PERFORM pg_notify('ledger_changes', NEW.user_id);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_notify_change
+    AFTER INSERT OR UPDATE
+    ON transactions
+    FOR EACH ROW
+EXECUTE FUNCTION notify_change();
+
+CREATE TRIGGER transaction_templates_notify_change
+    AFTER INSERT OR UPDATE
+    ON transaction_templates
+    FOR EACH ROW
+EXECUTE FUNCTION notify_change();
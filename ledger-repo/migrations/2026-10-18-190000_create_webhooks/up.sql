@@ -0,0 +1,30 @@
+CREATE TABLE webhooks
+(
+    id          SERIAL PRIMARY KEY,
+    user_id     VARCHAR     NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    url         VARCHAR     NOT NULL,
+    secret      VARCHAR     NOT NULL,
+    events      VARCHAR[]   NOT NULL,
+    category    VARCHAR,
+    min_expense NUMERIC,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX webhooks_user_id_idx ON webhooks (user_id);
+
+CREATE TABLE webhook_deliveries
+(
+    id                   BIGSERIAL PRIMARY KEY,
+    webhook_id           INTEGER     NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+    event                VARCHAR     NOT NULL,
+    payload              JSONB       NOT NULL,
+    status               VARCHAR     NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'succeeded', 'failed')),
+    attempts             INTEGER     NOT NULL DEFAULT 0,
+    next_attempt_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_response_status INTEGER,
+    last_error           VARCHAR,
+    created_at           TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX webhook_deliveries_webhook_id_idx ON webhook_deliveries (webhook_id, id);
+CREATE INDEX webhook_deliveries_due_idx ON webhook_deliveries (next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1,20 @@
+CREATE TABLE refresh_token_families
+(
+    id         VARCHAR PRIMARY KEY,
+    user_id    VARCHAR     NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX refresh_token_families_user_id_idx ON refresh_token_families (user_id);
+
+CREATE TABLE refresh_tokens
+(
+    token_hash VARCHAR PRIMARY KEY,
+    family_id  VARCHAR     NOT NULL REFERENCES refresh_token_families (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    used_at    TIMESTAMPTZ
+);
+
+CREATE INDEX refresh_tokens_family_id_idx ON refresh_tokens (family_id);
+CREATE INDEX refresh_tokens_created_at_idx ON refresh_tokens (created_at);
@@ -0,0 +1,4 @@
+ALTER TABLE refresh_token_families
+    DROP COLUMN device_name,
+    DROP COLUMN ip_address,
+    DROP COLUMN last_used_at;
@@ -0,0 +1,4 @@
+ALTER TABLE refresh_token_families
+    ADD COLUMN device_name  VARCHAR,
+    ADD COLUMN ip_address   VARCHAR,
+    ADD COLUMN last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
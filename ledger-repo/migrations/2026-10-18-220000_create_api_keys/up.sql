@@ -0,0 +1,12 @@
+CREATE TABLE api_keys
+(
+    id         SERIAL PRIMARY KEY,
+    user_id    VARCHAR     NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name       VARCHAR     NOT NULL,
+    key_hash   VARCHAR     NOT NULL UNIQUE,
+    scopes     VARCHAR[]   NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
@@ -0,0 +1,16 @@
+CREATE TABLE totp_credentials
+(
+    user_id        VARCHAR PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    secret         BYTEA       NOT NULL,
+    enabled        BOOLEAN     NOT NULL DEFAULT FALSE,
+    last_used_step BIGINT,
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE recovery_codes
+(
+    user_id   VARCHAR NOT NULL REFERENCES totp_credentials (user_id) ON DELETE CASCADE,
+    code_hash VARCHAR NOT NULL,
+    used_at   TIMESTAMPTZ,
+    PRIMARY KEY (user_id, code_hash)
+);
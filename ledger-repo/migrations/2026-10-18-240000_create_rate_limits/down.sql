@@ -0,0 +1,2 @@
+DROP TABLE failed_logins;
+DROP TABLE rate_limit_counters;
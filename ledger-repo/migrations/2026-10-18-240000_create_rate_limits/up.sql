@@ -0,0 +1,16 @@
+CREATE TABLE rate_limit_counters
+(
+    key          VARCHAR     NOT NULL,
+    window_start TIMESTAMPTZ NOT NULL,
+    count        BIGINT      NOT NULL,
+    PRIMARY KEY (key, window_start)
+);
+
+CREATE INDEX rate_limit_counters_window_start_idx ON rate_limit_counters (window_start);
+
+CREATE TABLE failed_logins
+(
+    key             VARCHAR PRIMARY KEY,
+    failures        INTEGER     NOT NULL,
+    last_failure_at TIMESTAMPTZ NOT NULL
+);
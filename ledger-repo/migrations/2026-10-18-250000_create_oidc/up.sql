@@ -0,0 +1,18 @@
+CREATE TABLE oidc_logins
+(
+    state         VARCHAR PRIMARY KEY,
+    code_verifier VARCHAR     NOT NULL,
+    nonce         VARCHAR     NOT NULL,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX oidc_logins_created_at ON oidc_logins (created_at);
+
+CREATE TABLE oidc_identities
+(
+    issuer     VARCHAR     NOT NULL,
+    subject    VARCHAR     NOT NULL,
+    user_id    VARCHAR     NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (issuer, subject)
+);
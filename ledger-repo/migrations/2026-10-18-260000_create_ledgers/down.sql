@@ -0,0 +1,80 @@
+CREATE OR REPLACE FUNCTION notify_change() RETURNS TRIGGER AS
+$$
+BEGIN
+    PERFORM pg_notify('ledger_changes', NEW.user_id);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- rows go back to the first owner of their ledger
+CREATE TEMPORARY TABLE ledger_owners AS
+SELECT DISTINCT ON (ledger_id) ledger_id, user_id
+FROM ledger_members
+WHERE role = 'owner'
+ORDER BY ledger_id, joined_at;
+
+ALTER TABLE purged_changes
+    ADD COLUMN user_id VARCHAR REFERENCES users (id) ON DELETE CASCADE;
+UPDATE purged_changes p
+SET user_id = o.user_id
+FROM ledger_owners o
+WHERE o.ledger_id = p.ledger_id;
+DELETE
+FROM purged_changes
+WHERE user_id IS NULL;
+ALTER TABLE purged_changes
+    DROP COLUMN ledger_id;
+-- an owner of several ledgers keeps one of their markers
+DELETE
+FROM purged_changes p
+    USING purged_changes other
+WHERE p.user_id = other.user_id
+  AND p.ctid < other.ctid;
+ALTER TABLE purged_changes
+    ADD PRIMARY KEY (user_id);
+
+ALTER TABLE transactions
+    DISABLE TRIGGER USER;
+ALTER TABLE transaction_templates
+    DISABLE TRIGGER USER;
+
+UPDATE transactions t
+SET user_id = o.user_id
+FROM ledger_owners o
+WHERE o.ledger_id = t.ledger_id;
+DELETE
+FROM transactions
+WHERE ledger_id NOT IN (SELECT ledger_id FROM ledger_owners);
+UPDATE transaction_templates t
+SET user_id = o.user_id
+FROM ledger_owners o
+WHERE o.ledger_id = t.ledger_id;
+DELETE
+FROM transaction_templates
+WHERE ledger_id NOT IN (SELECT ledger_id FROM ledger_owners);
+
+ALTER TABLE transactions
+    ENABLE TRIGGER USER;
+ALTER TABLE transaction_templates
+    ENABLE TRIGGER USER;
+
+DROP INDEX transactions_change_seq_idx;
+DROP INDEX transaction_templates_change_seq_idx;
+
+ALTER TABLE transactions
+    DROP COLUMN ledger_id,
+    ALTER COLUMN user_id SET NOT NULL,
+    DROP CONSTRAINT transactions_user_id_fkey,
+    ADD CONSTRAINT transactions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE transaction_templates
+    DROP COLUMN ledger_id,
+    ALTER COLUMN user_id SET NOT NULL,
+    DROP CONSTRAINT transaction_templates_user_id_fkey,
+    ADD CONSTRAINT transaction_templates_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+
+CREATE INDEX transactions_change_seq_idx ON transactions (user_id, change_seq);
+CREATE INDEX transaction_templates_change_seq_idx ON transaction_templates (user_id, change_seq);
+
+DROP TABLE ledger_invitations;
+DROP TABLE ledger_members;
+DROP TABLE ledgers;
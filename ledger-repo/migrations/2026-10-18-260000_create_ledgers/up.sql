@@ -0,0 +1,113 @@
+-- Transactions and templates belong to a ledger, which is shared by its members. Requests act on
+-- the member's current ledger.
+CREATE TABLE ledgers
+(
+    id         SERIAL PRIMARY KEY,
+    name       VARCHAR                   NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE ledger_members
+(
+    ledger_id     INTEGER                   NOT NULL REFERENCES ledgers (id) ON DELETE CASCADE,
+    user_id       VARCHAR                   NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role          VARCHAR                   NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    current       BOOLEAN     DEFAULT FALSE NOT NULL,
+    -- the last change before the ledger became current, clients that synced before it have synced
+    -- another ledger
+    current_since BIGINT      DEFAULT 0     NOT NULL,
+    joined_at     TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (ledger_id, user_id)
+);
+
+CREATE INDEX ledger_members_user ON ledger_members (user_id);
+CREATE UNIQUE INDEX ledger_members_current ON ledger_members (user_id) WHERE current;
+
+CREATE TABLE ledger_invitations
+(
+    code_hash  VARCHAR PRIMARY KEY,
+    ledger_id  INTEGER                   NOT NULL REFERENCES ledgers (id) ON DELETE CASCADE,
+    role       VARCHAR                   NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    expires_at TIMESTAMPTZ               NOT NULL
+);
+
+-- every existing user gets a ledger of their own with their transactions and templates
+ALTER TABLE ledgers
+    ADD COLUMN migrated_user VARCHAR;
+INSERT INTO ledgers(name, migrated_user)
+SELECT id, id
+FROM users;
+INSERT INTO ledger_members(ledger_id, user_id, role, current)
+SELECT id, migrated_user, 'owner', TRUE
+FROM ledgers;
+
+-- moving rows to their ledger isn't a change that clients need to sync
+ALTER TABLE transactions
+    DISABLE TRIGGER USER;
+ALTER TABLE transaction_templates
+    DISABLE TRIGGER USER;
+
+ALTER TABLE transactions
+    ADD COLUMN ledger_id INTEGER REFERENCES ledgers (id) ON DELETE CASCADE;
+UPDATE transactions t
+SET ledger_id = l.id
+FROM ledgers l
+WHERE l.migrated_user = t.user_id;
+ALTER TABLE transaction_templates
+    ADD COLUMN ledger_id INTEGER REFERENCES ledgers (id) ON DELETE CASCADE;
+UPDATE transaction_templates t
+SET ledger_id = l.id
+FROM ledgers l
+WHERE l.migrated_user = t.user_id;
+
+ALTER TABLE transactions
+    ENABLE TRIGGER USER;
+ALTER TABLE transaction_templates
+    ENABLE TRIGGER USER;
+
+-- user_id is kept as the member that created the row, which stays in the ledger when they leave
+ALTER TABLE transactions
+    ALTER COLUMN ledger_id SET NOT NULL,
+    ALTER COLUMN user_id DROP NOT NULL,
+    DROP CONSTRAINT transactions_user_id_fkey,
+    ADD CONSTRAINT transactions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL;
+ALTER TABLE transaction_templates
+    ALTER COLUMN ledger_id SET NOT NULL,
+    ALTER COLUMN user_id DROP NOT NULL,
+    DROP CONSTRAINT transaction_templates_user_id_fkey,
+    ADD CONSTRAINT transaction_templates_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL;
+
+DROP INDEX transactions_change_seq_idx;
+DROP INDEX transaction_templates_change_seq_idx;
+CREATE INDEX transactions_change_seq_idx ON transactions (ledger_id, change_seq);
+CREATE INDEX transaction_templates_change_seq_idx ON transaction_templates (ledger_id, change_seq);
+
+ALTER TABLE purged_changes
+    ADD COLUMN ledger_id INTEGER REFERENCES ledgers (id) ON DELETE CASCADE;
+UPDATE purged_changes p
+SET ledger_id = l.id
+FROM ledgers l
+WHERE l.migrated_user = p.user_id;
+ALTER TABLE purged_changes
+    DROP COLUMN user_id;
+ALTER TABLE purged_changes
+    ALTER COLUMN ledger_id SET NOT NULL,
+    ADD PRIMARY KEY (ledger_id);
+
+ALTER TABLE ledgers
+    DROP COLUMN migrated_user;
+
+-- every member the ledger is current for is told about its changes
+CREATE OR REPLACE FUNCTION notify_change() RETURNS TRIGGER AS
+$$
+DECLARE
+    member VARCHAR;
+BEGIN
+    FOR member IN SELECT user_id FROM ledger_members WHERE ledger_id = NEW.ledger_id AND current
+        LOOP
+            PERFORM pg_notify('ledger_changes', member);
+        END LOOP;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,7 @@
+DROP INDEX transactions_user_id;
+
+ALTER TABLE users
+    DROP COLUMN admin,
+    DROP COLUMN disabled,
+    DROP COLUMN password_reset_required,
+    DROP COLUMN last_login_at;
@@ -0,0 +1,7 @@
+ALTER TABLE users
+    ADD COLUMN admin                   BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN disabled                BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN password_reset_required BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN last_login_at           TIMESTAMPTZ;
+
+CREATE INDEX transactions_user_id ON transactions (user_id);
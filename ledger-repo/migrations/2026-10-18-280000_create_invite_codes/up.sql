@@ -0,0 +1,12 @@
+CREATE TABLE invite_codes
+(
+    id         SERIAL PRIMARY KEY,
+    code_hash  VARCHAR     NOT NULL UNIQUE,
+    created_by VARCHAR     NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    max_uses   INTEGER     NOT NULL CHECK (max_uses > 0),
+    uses       INTEGER     NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX invite_codes_created_by ON invite_codes (created_by);
@@ -0,0 +1,13 @@
+-- changes made by members that have since been deleted go with them
+DELETE
+FROM transaction_history
+WHERE user_id IS NULL;
+
+DROP INDEX transaction_history_transaction;
+CREATE INDEX transaction_history_transaction ON transaction_history (user_id, transaction_id);
+
+ALTER TABLE transaction_history
+    DROP COLUMN ledger_id,
+    ALTER COLUMN user_id SET NOT NULL,
+    DROP CONSTRAINT transaction_history_user_id_fkey,
+    ADD CONSTRAINT transaction_history_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
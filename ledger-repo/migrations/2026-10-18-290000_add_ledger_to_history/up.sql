@@ -0,0 +1,28 @@
+-- History belongs to the ledger of its transaction, so every member of the ledger sees it.
+-- user_id is kept as the member that made the change, and the history stays when they leave.
+ALTER TABLE transaction_history
+    ADD COLUMN ledger_id INTEGER REFERENCES ledgers (id) ON DELETE CASCADE;
+UPDATE transaction_history h
+SET ledger_id = t.ledger_id
+FROM transactions t
+WHERE t.id = h.transaction_id;
+-- transactions that were purged from the trash have no ledger left, their history goes to the
+-- current ledger of the user that recorded it
+UPDATE transaction_history h
+SET ledger_id = m.ledger_id
+FROM ledger_members m
+WHERE h.ledger_id IS NULL
+  AND m.user_id = h.user_id
+  AND m.current;
+DELETE
+FROM transaction_history
+WHERE ledger_id IS NULL;
+
+ALTER TABLE transaction_history
+    ALTER COLUMN ledger_id SET NOT NULL,
+    ALTER COLUMN user_id DROP NOT NULL,
+    DROP CONSTRAINT transaction_history_user_id_fkey,
+    ADD CONSTRAINT transaction_history_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL;
+
+DROP INDEX transaction_history_transaction;
+CREATE INDEX transaction_history_transaction ON transaction_history (ledger_id, transaction_id);
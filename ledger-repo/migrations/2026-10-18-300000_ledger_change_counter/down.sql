@@ -0,0 +1,26 @@
+DROP TRIGGER transaction_templates_change_seq ON transaction_templates;
+CREATE TRIGGER transaction_templates_change_seq
+    BEFORE UPDATE
+    ON transaction_templates
+    FOR EACH ROW
+EXECUTE FUNCTION next_change_seq();
+
+DROP TRIGGER transactions_change_seq ON transactions;
+CREATE TRIGGER transactions_change_seq
+    BEFORE UPDATE
+    ON transactions
+    FOR EACH ROW
+EXECUTE FUNCTION next_change_seq();
+
+CREATE OR REPLACE FUNCTION next_change_seq() RETURNS TRIGGER AS
+$$
+BEGIN
+    NEW.change_seq := nextval('change_seq');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP FUNCTION next_ledger_change(INTEGER);
+
+ALTER TABLE ledgers
+    DROP COLUMN last_change;
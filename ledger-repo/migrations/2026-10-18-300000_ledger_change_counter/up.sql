@@ -0,0 +1,44 @@
+-- Change numbers are taken while holding a lock on the ledger's row until the change commits, so
+-- within a ledger they are handed out in commit order. last_change is then a point that clients can
+-- safely sync up to: every change numbered before it has been committed.
+ALTER TABLE ledgers
+    ADD COLUMN last_change BIGINT NOT NULL DEFAULT 0;
+UPDATE ledgers l
+SET last_change = GREATEST(
+        (SELECT COALESCE(MAX(change_seq), 0) FROM transactions t WHERE t.ledger_id = l.id),
+        (SELECT COALESCE(MAX(change_seq), 0) FROM transaction_templates t WHERE t.ledger_id = l.id),
+        (SELECT COALESCE(MAX(current_since), 0) FROM ledger_members m WHERE m.ledger_id = l.id));
+
+CREATE FUNCTION next_ledger_change(ledger INTEGER) RETURNS BIGINT AS
+$$
+DECLARE
+    change BIGINT;
+BEGIN
+    PERFORM 1 FROM ledgers WHERE id = ledger FOR UPDATE;
+    change := nextval('change_seq');
+    UPDATE ledgers SET last_change = change WHERE id = ledger;
+    RETURN change;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION next_change_seq() RETURNS TRIGGER AS
+$$
+BEGIN
+    NEW.change_seq := next_ledger_change(NEW.ledger_id);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER transactions_change_seq ON transactions;
+CREATE TRIGGER transactions_change_seq
+    BEFORE INSERT OR UPDATE
+    ON transactions
+    FOR EACH ROW
+EXECUTE FUNCTION next_change_seq();
+
+DROP TRIGGER transaction_templates_change_seq ON transaction_templates;
+CREATE TRIGGER transaction_templates_change_seq
+    BEFORE INSERT OR UPDATE
+    ON transaction_templates
+    FOR EACH ROW
+EXECUTE FUNCTION next_change_seq();
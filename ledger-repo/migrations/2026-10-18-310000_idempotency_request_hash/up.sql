@@ -0,0 +1,3 @@
+-- NULL for keys stored before request bodies were hashed, which are replayed for any body
+ALTER TABLE idempotency_keys
+    ADD COLUMN request_hash VARCHAR;
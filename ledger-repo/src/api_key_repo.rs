@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// What an API key is allowed to do. Requests authenticated with a password login can do
+/// everything.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ApiKeyScope {
+    #[serde(rename = "read-only")]
+    ReadOnly,
+    #[serde(rename = "transactions:write")]
+    TransactionsWrite,
+    #[serde(rename = "templates:write")]
+    TemplatesWrite,
+    #[serde(rename = "export")]
+    Export,
+}
+
+impl Display for ApiKeyScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let scope = match self {
+            ApiKeyScope::ReadOnly => "read-only",
+            ApiKeyScope::TransactionsWrite => "transactions:write",
+            ApiKeyScope::TemplatesWrite => "templates:write",
+            ApiKeyScope::Export => "export",
+        };
+        f.write_str(scope)
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read-only" => Ok(ApiKeyScope::ReadOnly),
+            "transactions:write" => Ok(ApiKeyScope::TransactionsWrite),
+            "templates:write" => Ok(ApiKeyScope::TemplatesWrite),
+            "export" => Ok(ApiKeyScope::Export),
+            _ => Err(anyhow::anyhow!("Invalid API key scope {}", s)),
+        }
+    }
+}
+
+/// The key itself is only shown when it is created, and only its hash is stored
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub scopes: HashSet<ApiKeyScope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: HashSet<ApiKeyScope>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// An API key along with the user it acts for
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ApiKeyOwner {
+    pub user_id: String,
+    pub api_key: ApiKey,
+}
+
+#[derive(Error, Debug)]
+pub enum ApiKeyRepoError {
+    #[error("API key with id {0} not found")]
+    ApiKeyNotFound(i32),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[async_trait]
+pub trait ApiKeyRepo: Sync + Send {
+    async fn create_api_key(
+        &self,
+        user_id: &str,
+        new_api_key: NewApiKey,
+        key_hash: &str,
+    ) -> Result<ApiKey, ApiKeyRepoError>;
+
+    async fn get_api_keys(&self, user_id: &str) -> Result<Vec<ApiKey>, ApiKeyRepoError>;
+
+    /// Finds the key with the given hash, including expired keys
+    async fn find_api_key(&self, key_hash: &str) -> Result<Option<ApiKeyOwner>, ApiKeyRepoError>;
+
+    async fn delete_api_key(&self, user_id: &str, api_key_id: i32) -> Result<(), ApiKeyRepoError>;
+}
@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum CategoryKind {
+    Income,
+    Expense,
+    Transfer,
+}
+
+impl Display for CategoryKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            CategoryKind::Income => "income",
+            CategoryKind::Expense => "expense",
+            CategoryKind::Transfer => "transfer",
+        };
+        f.write_str(kind)
+    }
+}
+
+impl FromStr for CategoryKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "income" => Ok(CategoryKind::Income),
+            "expense" => Ok(CategoryKind::Expense),
+            "transfer" => Ok(CategoryKind::Transfer),
+            _ => Err(anyhow::anyhow!("Invalid category kind {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Category {
+    pub name: String,
+    pub kind: CategoryKind,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub archived: bool,
+}
+
+impl Category {
+    pub fn new(
+        name: String,
+        kind: CategoryKind,
+        color: Option<String>,
+        icon: Option<String>,
+        archived: bool,
+    ) -> Category {
+        Category {
+            name,
+            kind,
+            color,
+            icon,
+            archived,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CategoryRepoError {
+    #[error("Category {0} not found")]
+    CategoryNotFound(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Stores metadata for categories. Categories themselves are still plain strings on transactions,
+/// so a category may be used by transactions without having any metadata stored here.
+#[async_trait]
+pub trait CategoryRepo: Sync + Send {
+    async fn get_categories(&self, user_id: &str) -> Result<Vec<Category>, CategoryRepoError>;
+
+    async fn get_category(&self, user_id: &str, name: &str) -> Result<Category, CategoryRepoError>;
+
+    /// Creates the category metadata, or replaces it if it already exists
+    async fn set_category(
+        &self,
+        user_id: &str,
+        category: Category,
+    ) -> Result<Category, CategoryRepoError>;
+
+    async fn delete_category(
+        &self,
+        user_id: &str,
+        name: &str,
+    ) -> Result<Category, CategoryRepoError>;
+}
@@ -0,0 +1,25 @@
+use async_trait::async_trait;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DuplicateRepoError {
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Stores pairs of transactions the user has said are not duplicates of each other. Pairs are
+/// unordered, so implementations store them with the smaller id first.
+#[async_trait]
+pub trait DuplicateRepo: Sync + Send {
+    async fn get_dismissed_pairs(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<(i32, i32)>, DuplicateRepoError>;
+
+    async fn dismiss_pair(
+        &self,
+        user_id: &str,
+        transaction_id: i32,
+        other_transaction_id: i32,
+    ) -> Result<(), DuplicateRepoError>;
+}
@@ -0,0 +1,118 @@
+use crate::transaction_repo::Transaction;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    Revert,
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            ChangeKind::Create => "create",
+            ChangeKind::Update => "update",
+            ChangeKind::Delete => "delete",
+            ChangeKind::Restore => "restore",
+            ChangeKind::Revert => "revert",
+        };
+        f.write_str(kind)
+    }
+}
+
+impl FromStr for ChangeKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(ChangeKind::Create),
+            "update" => Ok(ChangeKind::Update),
+            "delete" => Ok(ChangeKind::Delete),
+            "restore" => Ok(ChangeKind::Restore),
+            "revert" => Ok(ChangeKind::Revert),
+            _ => Err(anyhow::anyhow!("Invalid change kind {}", s)),
+        }
+    }
+}
+
+/// A single change to a transaction. `before` is empty for creates and `after` is empty for
+/// deletes. `changed_by` is the user that made the change and `client` identifies the API client
+/// it was made through, if known.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct TransactionChange {
+    pub id: i32,
+    pub transaction_id: i32,
+    pub kind: ChangeKind,
+    pub before: Option<Transaction>,
+    pub after: Option<Transaction>,
+    pub changed_at: DateTime<Utc>,
+    pub changed_by: String,
+    pub client: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewTransactionChange {
+    pub transaction_id: i32,
+    pub kind: ChangeKind,
+    pub before: Option<Transaction>,
+    pub after: Option<Transaction>,
+    pub changed_by: String,
+    pub client: Option<String>,
+}
+
+impl NewTransactionChange {
+    pub fn to_transaction_change(self, id: i32, changed_at: DateTime<Utc>) -> TransactionChange {
+        TransactionChange {
+            id,
+            transaction_id: self.transaction_id,
+            kind: self.kind,
+            before: self.before,
+            after: self.after,
+            changed_at,
+            changed_by: self.changed_by,
+            client: self.client,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HistoryRepoError {
+    #[error("Change with id {0} not found")]
+    ChangeNotFound(i32),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Append-only log of the changes made to each transaction. Changes are kept with the user's current
+/// ledger, so every member of the ledger sees the same history.
+#[async_trait]
+pub trait HistoryRepo: Sync + Send {
+    async fn record_change(
+        &self,
+        user_id: &str,
+        change: NewTransactionChange,
+    ) -> Result<TransactionChange, HistoryRepoError>;
+
+    /// Gets the changes made to a transaction, oldest first
+    async fn get_transaction_history(
+        &self,
+        user_id: &str,
+        transaction_id: i32,
+    ) -> Result<Vec<TransactionChange>, HistoryRepoError>;
+
+    async fn get_change(
+        &self,
+        user_id: &str,
+        transaction_id: i32,
+        change_id: i32,
+    ) -> Result<TransactionChange, HistoryRepoError>;
+}
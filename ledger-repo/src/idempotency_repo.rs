@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum IdempotencyRepoError {
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// The response that was sent for a request with an idempotency key
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// An earlier request made with the same idempotency key
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdempotencyRecord {
+    pub request_path: String,
+    /// Hash of the request body, `None` for requests stored before bodies were hashed
+    pub request_hash: Option<String>,
+    /// `None` while the request is still being processed
+    pub response: Option<StoredResponse>,
+}
+
+/// Stores the responses to requests that had an `Idempotency-Key` header, so that retries of the
+/// same request can be answered without repeating it. Keys are scoped to a user.
+#[async_trait]
+pub trait IdempotencyRepo: Sync + Send {
+    /// Claims `key` for a new request, returning `None`. If the key was already used by a request
+    /// made at or after `expired_before`, that request's record is returned instead and the key
+    /// is not claimed. Records older than that are replaced, as are claims still in progress that
+    /// were last renewed before `abandoned_before`, since the request that made them has likely
+    /// died.
+    async fn start_request(
+        &self,
+        user_id: &str,
+        key: &str,
+        request_path: &str,
+        request_hash: &str,
+        expired_before: DateTime<Utc>,
+        abandoned_before: DateTime<Utc>,
+    ) -> Result<Option<IdempotencyRecord>, IdempotencyRepoError>;
+
+    /// Renews the claim on `key` of a request that is still being processed, so that it isn't
+    /// treated as abandoned
+    async fn renew_request(&self, user_id: &str, key: &str) -> Result<(), IdempotencyRepoError>;
+
+    /// Stores the response for a request claimed with [IdempotencyRepo::start_request]
+    async fn complete_request(
+        &self,
+        user_id: &str,
+        key: &str,
+        response: StoredResponse,
+    ) -> Result<(), IdempotencyRepoError>;
+
+    /// Releases a claimed key without storing a response, so the request can be retried
+    async fn abandon_request(&self, user_id: &str, key: &str) -> Result<(), IdempotencyRepoError>;
+
+    /// Deletes the records of requests made before `created_before`, returning how many were
+    /// deleted
+    async fn purge_records(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<u64, IdempotencyRepoError>;
+}
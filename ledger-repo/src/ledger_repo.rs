@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// What a member can do in a ledger. Owners also manage its members and invitations.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LedgerRole {
+    Owner,
+    Editor,
+    Viewer,
+}
+
+impl LedgerRole {
+    /// Whether the member can change the ledger's transactions and templates
+    pub fn can_write(self) -> bool {
+        self != LedgerRole::Viewer
+    }
+}
+
+impl Display for LedgerRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let role = match self {
+            LedgerRole::Owner => "owner",
+            LedgerRole::Editor => "editor",
+            LedgerRole::Viewer => "viewer",
+        };
+        f.write_str(role)
+    }
+}
+
+impl FromStr for LedgerRole {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "owner" => Ok(LedgerRole::Owner),
+            "editor" => Ok(LedgerRole::Editor),
+            "viewer" => Ok(LedgerRole::Viewer),
+            _ => Err(anyhow::anyhow!("Invalid ledger role {}", s)),
+        }
+    }
+}
+
+/// A ledger as seen by one of its members
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct Ledger {
+    pub id: i32,
+    pub name: String,
+    pub role: LedgerRole,
+    /// Whether it is the ledger that the member's requests act on
+    pub current: bool,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct LedgerMember {
+    pub user_id: String,
+    pub role: LedgerRole,
+}
+
+#[derive(Error, Debug)]
+pub enum LedgerRepoError {
+    #[error("Ledger with id {0} not found")]
+    LedgerNotFound(i32),
+    #[error("Member {0} not found")]
+    MemberNotFound(String),
+    #[error("Invitation not found")]
+    InvitationNotFound,
+    #[error("User {0} is already a member")]
+    AlreadyMember(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Ledgers own transactions and templates, and are shared by their members. Every user gets a
+/// ledger of their own when they are created, and each member has one current ledger that the
+/// transaction and template repos act on.
+#[async_trait]
+pub trait LedgerRepo: Sync + Send {
+    /// Gets the ledgers the user is a member of, ordered by id
+    async fn get_ledgers(&self, user_id: &str) -> Result<Vec<Ledger>, LedgerRepoError>;
+
+    /// Gets the ledger if the user is a member of it, otherwise fails with
+    /// [LedgerRepoError::LedgerNotFound]
+    async fn get_ledger(&self, user_id: &str, ledger_id: i32) -> Result<Ledger, LedgerRepoError>;
+
+    /// Creates a ledger owned by the user. It becomes their current ledger if they don't have one.
+    async fn create_ledger(&self, user_id: &str, name: &str) -> Result<Ledger, LedgerRepoError>;
+
+    /// Deletes the ledger along with its transactions and templates. Members it was current for
+    /// are moved to another of their ledgers.
+    async fn delete_ledger(&self, ledger_id: i32) -> Result<(), LedgerRepoError>;
+
+    /// Makes the ledger the one the user's requests act on. Changes from before the switch can no
+    /// longer be synced incrementally, so clients start over.
+    async fn set_current_ledger(
+        &self,
+        user_id: &str,
+        ledger_id: i32,
+    ) -> Result<(), LedgerRepoError>;
+
+    /// The latest change to the user's current ledger, or 0 if they don't have one. Every change up
+    /// to it is seen by reads made after this returns, so clients can safely sync up to it.
+    async fn get_last_change(&self, user_id: &str) -> Result<i64, LedgerRepoError>;
+
+    /// Gets the members of the ledger, in the order they joined
+    async fn get_members(&self, ledger_id: i32) -> Result<Vec<LedgerMember>, LedgerRepoError>;
+
+    async fn set_role(
+        &self,
+        ledger_id: i32,
+        user_id: &str,
+        role: LedgerRole,
+    ) -> Result<(), LedgerRepoError>;
+
+    /// Removes the member. If it was their current ledger they are moved to another of theirs.
+    async fn remove_member(&self, ledger_id: i32, user_id: &str) -> Result<(), LedgerRepoError>;
+
+    /// Saves an invitation to join the ledger with `role`. Only the hash of its code is stored.
+    /// Invitations that have expired are deleted.
+    async fn create_invitation(
+        &self,
+        ledger_id: i32,
+        code_hash: &str,
+        role: LedgerRole,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), LedgerRepoError>;
+
+    /// Adds the user to the ledger of the invitation, which can't be used again. Fails with
+    /// [LedgerRepoError::InvitationNotFound] if it is unknown or has expired.
+    async fn accept_invitation(
+        &self,
+        user_id: &str,
+        code_hash: &str,
+    ) -> Result<Ledger, LedgerRepoError>;
+}
@@ -1,8 +1,40 @@
+use crate::api_key_repo::ApiKeyRepo;
+use crate::category_repo::CategoryRepo;
+use crate::duplicate_repo::DuplicateRepo;
+use crate::history_repo::HistoryRepo;
+use crate::idempotency_repo::IdempotencyRepo;
+use crate::ledger_repo::LedgerRepo;
+use crate::oidc_repo::OidcRepo;
+use crate::rate_limit_repo::RateLimitRepo;
+use crate::refresh_token_repo::RefreshTokenRepo;
+use crate::rule_repo::RuleRepo;
+use crate::session_repo::SessionRepo;
+use crate::totp_repo::TotpRepo;
+use crate::transactee_repo::TransacteeRepo;
+use crate::transaction_repo::TransactionRepo;
+use crate::transaction_template_repo::TransactionTemplateRepo;
+use crate::user_repo::UserRepo;
+use crate::webhook_repo::WebhookRepo;
 use async_trait::async_trait;
+use std::sync::Arc;
 
+pub mod api_key_repo;
+pub mod category_repo;
+pub mod duplicate_repo;
+pub mod history_repo;
+pub mod idempotency_repo;
+pub mod ledger_repo;
+pub mod oidc_repo;
+pub mod rate_limit_repo;
+pub mod refresh_token_repo;
+pub mod rule_repo;
+pub mod session_repo;
+pub mod totp_repo;
+pub mod transactee_repo;
 pub mod transaction_repo;
 pub mod transaction_template_repo;
 pub mod user_repo;
+pub mod webhook_repo;
 
 // implementation modules
 pub mod mem_repo;
@@ -12,3 +44,24 @@ pub mod sqlx_repo;
 pub trait HealthCheck: Send + Sync {
     async fn check(&self) -> bool;
 }
+
+#[derive(Clone)]
+pub struct Repos {
+    pub user_repo: Arc<dyn UserRepo>,
+    pub transaction_repo: Arc<dyn TransactionRepo>,
+    pub template_repo: Arc<dyn TransactionTemplateRepo>,
+    pub category_repo: Arc<dyn CategoryRepo>,
+    pub transactee_repo: Arc<dyn TransacteeRepo>,
+    pub rule_repo: Arc<dyn RuleRepo>,
+    pub duplicate_repo: Arc<dyn DuplicateRepo>,
+    pub history_repo: Arc<dyn HistoryRepo>,
+    pub idempotency_repo: Arc<dyn IdempotencyRepo>,
+    pub webhook_repo: Arc<dyn WebhookRepo>,
+    pub refresh_token_repo: Arc<dyn RefreshTokenRepo>,
+    pub session_repo: Arc<dyn SessionRepo>,
+    pub api_key_repo: Arc<dyn ApiKeyRepo>,
+    pub totp_repo: Arc<dyn TotpRepo>,
+    pub rate_limit_repo: Arc<dyn RateLimitRepo>,
+    pub oidc_repo: Arc<dyn OidcRepo>,
+    pub ledger_repo: Arc<dyn LedgerRepo>,
+}
@@ -0,0 +1,113 @@
+use crate::api_key_repo::{ApiKey, ApiKeyOwner, ApiKeyRepo, ApiKeyRepoError, NewApiKey};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+struct StoredApiKey {
+    user_id: String,
+    key_hash: String,
+    api_key: ApiKey,
+}
+
+struct State {
+    api_keys: BTreeMap<i32, StoredApiKey>,
+    next_id: i32,
+}
+
+pub struct MemApiKeyRepo {
+    state: RwLock<State>,
+}
+
+impl MemApiKeyRepo {
+    pub fn new() -> MemApiKeyRepo {
+        let state = State {
+            api_keys: BTreeMap::new(),
+            next_id: 0,
+        };
+        MemApiKeyRepo {
+            state: RwLock::new(state),
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.state
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.state
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepo for MemApiKeyRepo {
+    async fn create_api_key(
+        &self,
+        user_id: &str,
+        new_api_key: NewApiKey,
+        key_hash: &str,
+    ) -> Result<ApiKey, ApiKeyRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let id = write_guard.next_id;
+        write_guard.next_id += 1;
+
+        let api_key = ApiKey {
+            id,
+            name: new_api_key.name,
+            scopes: new_api_key.scopes,
+            created_at: Utc::now(),
+            expires_at: new_api_key.expires_at,
+        };
+        write_guard.api_keys.insert(
+            id,
+            StoredApiKey {
+                user_id: user_id.to_owned(),
+                key_hash: key_hash.to_owned(),
+                api_key: api_key.clone(),
+            },
+        );
+        Ok(api_key)
+    }
+
+    async fn get_api_keys(&self, user_id: &str) -> Result<Vec<ApiKey>, ApiKeyRepoError> {
+        let read_guard = self.read_lock()?;
+
+        Ok(read_guard
+            .api_keys
+            .values()
+            .filter(|stored| stored.user_id == user_id)
+            .map(|stored| stored.api_key.clone())
+            .collect())
+    }
+
+    async fn find_api_key(&self, key_hash: &str) -> Result<Option<ApiKeyOwner>, ApiKeyRepoError> {
+        let read_guard = self.read_lock()?;
+
+        Ok(read_guard
+            .api_keys
+            .values()
+            .find(|stored| stored.key_hash == key_hash)
+            .map(|stored| ApiKeyOwner {
+                user_id: stored.user_id.clone(),
+                api_key: stored.api_key.clone(),
+            }))
+    }
+
+    async fn delete_api_key(&self, user_id: &str, api_key_id: i32) -> Result<(), ApiKeyRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        match write_guard.api_keys.get(&api_key_id) {
+            Some(stored) if stored.user_id == user_id => {
+                write_guard.api_keys.remove(&api_key_id);
+                Ok(())
+            }
+            _ => Err(ApiKeyRepoError::ApiKeyNotFound(api_key_id)),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::category_repo::CategoryRepoError::CategoryNotFound;
+use crate::category_repo::{Category, CategoryRepo, CategoryRepoError};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+type State = HashMap<String, HashMap<String, Category>>;
+
+pub struct MemCategoryRepo {
+    user_categories: RwLock<State>,
+}
+
+impl MemCategoryRepo {
+    pub fn new() -> MemCategoryRepo {
+        MemCategoryRepo {
+            user_categories: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.user_categories
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.user_categories
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl CategoryRepo for MemCategoryRepo {
+    async fn get_categories(&self, user_id: &str) -> Result<Vec<Category>, CategoryRepoError> {
+        let read_guard = self.read_lock()?;
+
+        let Some(categories) = read_guard.get(user_id) else {
+            return Ok(Vec::new());
+        };
+
+        let mut categories: Vec<Category> = categories.values().cloned().collect();
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(categories)
+    }
+
+    async fn get_category(&self, user_id: &str, name: &str) -> Result<Category, CategoryRepoError> {
+        let read_guard = self.read_lock()?;
+
+        read_guard
+            .get(user_id)
+            .and_then(|categories| categories.get(name))
+            .cloned()
+            .ok_or_else(|| CategoryNotFound(name.to_owned()))
+    }
+
+    async fn set_category(
+        &self,
+        user_id: &str,
+        category: Category,
+    ) -> Result<Category, CategoryRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        write_guard
+            .entry(user_id.to_owned())
+            .or_default()
+            .insert(category.name.clone(), category.clone());
+        Ok(category)
+    }
+
+    async fn delete_category(
+        &self,
+        user_id: &str,
+        name: &str,
+    ) -> Result<Category, CategoryRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        write_guard
+            .get_mut(user_id)
+            .and_then(|categories| categories.remove(name))
+            .ok_or_else(|| CategoryNotFound(name.to_owned()))
+    }
+}
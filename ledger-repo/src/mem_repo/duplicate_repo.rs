@@ -0,0 +1,67 @@
+use crate::duplicate_repo::{DuplicateRepo, DuplicateRepoError};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+type State = HashMap<String, HashSet<(i32, i32)>>;
+
+pub struct MemDuplicateRepo {
+    user_dismissed_pairs: RwLock<State>,
+}
+
+impl MemDuplicateRepo {
+    pub fn new() -> MemDuplicateRepo {
+        MemDuplicateRepo {
+            user_dismissed_pairs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.user_dismissed_pairs
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.user_dismissed_pairs
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl DuplicateRepo for MemDuplicateRepo {
+    async fn get_dismissed_pairs(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<(i32, i32)>, DuplicateRepoError> {
+        let read_guard = self.read_lock()?;
+
+        let mut pairs: Vec<(i32, i32)> = read_guard
+            .get(user_id)
+            .map(|pairs| pairs.iter().copied().collect())
+            .unwrap_or_default();
+        pairs.sort();
+        Ok(pairs)
+    }
+
+    async fn dismiss_pair(
+        &self,
+        user_id: &str,
+        transaction_id: i32,
+        other_transaction_id: i32,
+    ) -> Result<(), DuplicateRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let pair = (
+            transaction_id.min(other_transaction_id),
+            transaction_id.max(other_transaction_id),
+        );
+        write_guard
+            .entry(user_id.to_owned())
+            .or_default()
+            .insert(pair);
+        Ok(())
+    }
+}
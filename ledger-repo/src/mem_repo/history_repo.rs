@@ -0,0 +1,122 @@
+use crate::history_repo::{HistoryRepo, HistoryRepoError, NewTransactionChange, TransactionChange};
+use crate::mem_repo::ledger_repo::MemLedgerRepo;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+struct State {
+    ledger_changes: HashMap<i32, Vec<TransactionChange>>,
+    next_id: i32,
+}
+
+pub struct MemHistoryRepo {
+    state: RwLock<State>,
+    ledger_repo: Arc<MemLedgerRepo>,
+}
+
+impl MemHistoryRepo {
+    pub fn new(ledger_repo: Arc<MemLedgerRepo>) -> MemHistoryRepo {
+        let state = State {
+            ledger_changes: HashMap::new(),
+            next_id: 0,
+        };
+        MemHistoryRepo {
+            state: RwLock::new(state),
+            ledger_repo,
+        }
+    }
+
+    fn current_ledger(&self, user: &str) -> Result<Option<i32>, anyhow::Error> {
+        Ok(self
+            .ledger_repo
+            .current_ledger(user)?
+            .map(|ledger| ledger.id))
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.state
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.state
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl HistoryRepo for MemHistoryRepo {
+    async fn record_change(
+        &self,
+        user_id: &str,
+        change: NewTransactionChange,
+    ) -> Result<TransactionChange, HistoryRepoError> {
+        let ledger_id = self
+            .current_ledger(user_id)?
+            .ok_or_else(|| anyhow!("User {} has no current ledger", user_id))?;
+        let mut write_guard = self.write_lock()?;
+
+        let id = write_guard.next_id;
+        write_guard.next_id += 1;
+
+        let change = change.to_transaction_change(id, Utc::now());
+        write_guard
+            .ledger_changes
+            .entry(ledger_id)
+            .or_default()
+            .push(change.clone());
+
+        Ok(change)
+    }
+
+    async fn get_transaction_history(
+        &self,
+        user_id: &str,
+        transaction_id: i32,
+    ) -> Result<Vec<TransactionChange>, HistoryRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id)? else {
+            return Ok(vec![]);
+        };
+        let read_guard = self.read_lock()?;
+
+        let history = read_guard
+            .ledger_changes
+            .get(&ledger_id)
+            .map(|changes| {
+                changes
+                    .iter()
+                    .filter(|c| c.transaction_id == transaction_id)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(history)
+    }
+
+    async fn get_change(
+        &self,
+        user_id: &str,
+        transaction_id: i32,
+        change_id: i32,
+    ) -> Result<TransactionChange, HistoryRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id)? else {
+            return Err(HistoryRepoError::ChangeNotFound(change_id));
+        };
+        let read_guard = self.read_lock()?;
+
+        read_guard
+            .ledger_changes
+            .get(&ledger_id)
+            .and_then(|changes| {
+                changes
+                    .iter()
+                    .find(|c| c.id == change_id && c.transaction_id == transaction_id)
+            })
+            .cloned()
+            .ok_or(HistoryRepoError::ChangeNotFound(change_id))
+    }
+}
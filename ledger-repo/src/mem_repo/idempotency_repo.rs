@@ -0,0 +1,118 @@
+use crate::idempotency_repo::{
+    IdempotencyRecord, IdempotencyRepo, IdempotencyRepoError, StoredResponse,
+};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockWriteGuard};
+
+struct Entry {
+    record: IdempotencyRecord,
+    created_at: DateTime<Utc>,
+}
+
+/// Entries by user id and key
+type State = HashMap<(String, String), Entry>;
+
+pub struct MemIdempotencyRepo {
+    entries: RwLock<State>,
+}
+
+impl MemIdempotencyRepo {
+    pub fn new() -> MemIdempotencyRepo {
+        MemIdempotencyRepo {
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.entries
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepo for MemIdempotencyRepo {
+    async fn start_request(
+        &self,
+        user_id: &str,
+        key: &str,
+        request_path: &str,
+        request_hash: &str,
+        expired_before: DateTime<Utc>,
+        abandoned_before: DateTime<Utc>,
+    ) -> Result<Option<IdempotencyRecord>, IdempotencyRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let entry_key = (user_id.to_owned(), key.to_owned());
+        if let Some(entry) = write_guard.get(&entry_key) {
+            let abandoned = entry.record.response.is_none() && entry.created_at < abandoned_before;
+            if entry.created_at >= expired_before && !abandoned {
+                return Ok(Some(entry.record.clone()));
+            }
+        }
+        write_guard.insert(
+            entry_key,
+            Entry {
+                record: IdempotencyRecord {
+                    request_path: request_path.to_owned(),
+                    request_hash: Some(request_hash.to_owned()),
+                    response: None,
+                },
+                created_at: Utc::now(),
+            },
+        );
+        Ok(None)
+    }
+
+    async fn renew_request(&self, user_id: &str, key: &str) -> Result<(), IdempotencyRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        if let Some(entry) = write_guard.get_mut(&(user_id.to_owned(), key.to_owned())) {
+            if entry.record.response.is_none() {
+                entry.created_at = Utc::now();
+            }
+        }
+        Ok(())
+    }
+
+    async fn complete_request(
+        &self,
+        user_id: &str,
+        key: &str,
+        response: StoredResponse,
+    ) -> Result<(), IdempotencyRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        if let Some(entry) = write_guard.get_mut(&(user_id.to_owned(), key.to_owned())) {
+            entry.record.response = Some(response);
+        }
+        Ok(())
+    }
+
+    async fn abandon_request(&self, user_id: &str, key: &str) -> Result<(), IdempotencyRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let entry_key = (user_id.to_owned(), key.to_owned());
+        if write_guard
+            .get(&entry_key)
+            .is_some_and(|entry| entry.record.response.is_none())
+        {
+            write_guard.remove(&entry_key);
+        }
+        Ok(())
+    }
+
+    async fn purge_records(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<u64, IdempotencyRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let count = write_guard.len();
+        write_guard.retain(|_, entry| entry.created_at >= created_before);
+        Ok((count - write_guard.len()) as u64)
+    }
+}
@@ -0,0 +1,387 @@
+use crate::ledger_repo::LedgerRepoError::{
+    AlreadyMember, InvitationNotFound, LedgerNotFound, MemberNotFound,
+};
+use crate::ledger_repo::{Ledger, LedgerMember, LedgerRepo, LedgerRepoError, LedgerRole};
+use crate::mem_repo::ChangeSequence;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+struct Member {
+    user_id: String,
+    role: LedgerRole,
+    current: bool,
+    current_since: i64,
+}
+
+struct LedgerEntry {
+    name: String,
+    /// In the order they joined
+    members: Vec<Member>,
+}
+
+struct Invitation {
+    ledger_id: i32,
+    role: LedgerRole,
+    expires_at: DateTime<Utc>,
+}
+
+struct State {
+    ledgers: BTreeMap<i32, LedgerEntry>,
+    invitations: HashMap<String, Invitation>,
+    next_id: i32,
+}
+
+impl State {
+    fn member(&self, ledger_id: i32, user_id: &str) -> Option<&Member> {
+        self.ledgers
+            .get(&ledger_id)?
+            .members
+            .iter()
+            .find(|m| m.user_id == user_id)
+    }
+
+    fn member_mut(&mut self, ledger_id: i32, user_id: &str) -> Option<&mut Member> {
+        self.ledgers
+            .get_mut(&ledger_id)?
+            .members
+            .iter_mut()
+            .find(|m| m.user_id == user_id)
+    }
+
+    fn ledger(&self, ledger_id: i32, user_id: &str) -> Option<Ledger> {
+        let member = self.member(ledger_id, user_id)?;
+        Some(Ledger {
+            id: ledger_id,
+            name: self.ledgers[&ledger_id].name.clone(),
+            role: member.role,
+            current: member.current,
+        })
+    }
+
+    fn current(&self, user_id: &str) -> Option<(i32, &Member)> {
+        self.ledgers.iter().find_map(|(id, ledger)| {
+            ledger
+                .members
+                .iter()
+                .find(|m| m.user_id == user_id && m.current)
+                .map(|m| (*id, m))
+        })
+    }
+
+    /// The user has nothing to sync from before a first ledger, so it is current since the start
+    fn insert_ledger(&mut self, user_id: &str, name: &str) -> Ledger {
+        let id = self.next_id;
+        self.next_id += 1;
+        let current = self.current(user_id).is_none();
+        self.ledgers.insert(
+            id,
+            LedgerEntry {
+                name: name.to_owned(),
+                members: vec![Member {
+                    user_id: user_id.to_owned(),
+                    role: LedgerRole::Owner,
+                    current,
+                    current_since: 0,
+                }],
+            },
+        );
+        Ledger {
+            id,
+            name: name.to_owned(),
+            role: LedgerRole::Owner,
+            current,
+        }
+    }
+
+    /// Makes the user's first ledger current if they don't have a current one
+    fn ensure_current(&mut self, user_id: &str, change: i64) {
+        if self.current(user_id).is_some() {
+            return;
+        }
+        let first = self
+            .ledgers
+            .values_mut()
+            .find_map(|l| l.members.iter_mut().find(|m| m.user_id == user_id));
+        if let Some(member) = first {
+            member.current = true;
+            member.current_since = change;
+        }
+    }
+}
+
+/// The ledger that a member's requests act on
+pub(crate) struct CurrentLedger {
+    pub(crate) id: i32,
+    pub(crate) role: LedgerRole,
+    pub(crate) since: i64,
+}
+
+/// Shared with the user repo, which creates each user's own ledger, and the transaction and
+/// template repos, which find the ledger that a user's requests act on
+pub struct MemLedgerRepo {
+    state: RwLock<State>,
+    change_sequence: Arc<ChangeSequence>,
+}
+
+impl MemLedgerRepo {
+    pub(crate) fn new(change_sequence: Arc<ChangeSequence>) -> MemLedgerRepo {
+        let state = State {
+            ledgers: BTreeMap::new(),
+            invitations: HashMap::new(),
+            next_id: 1,
+        };
+        MemLedgerRepo {
+            state: RwLock::new(state),
+            change_sequence,
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.state
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.state
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    pub(crate) fn current_ledger(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<CurrentLedger>, anyhow::Error> {
+        let read_guard = self.read_lock()?;
+        Ok(read_guard
+            .current(user_id)
+            .map(|(id, member)| CurrentLedger {
+                id,
+                role: member.role,
+                since: member.current_since,
+            }))
+    }
+
+    /// Creates the ledger a new user starts with
+    pub(crate) fn create_own_ledger(&self, user_id: &str) -> Result<(), anyhow::Error> {
+        let mut write_guard = self.write_lock()?;
+        write_guard.insert_ledger(user_id, user_id);
+        Ok(())
+    }
+
+    /// Removes a deleted user from their ledgers. Ledgers without other members are deleted, and
+    /// the earliest remaining member of a ledger left without an owner becomes its owner.
+    pub(crate) fn remove_user(&self, user_id: &str) -> Result<(), anyhow::Error> {
+        let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
+
+        state.ledgers.retain(|_, ledger| {
+            ledger.members.retain(|m| m.user_id != user_id);
+            if !ledger.members.iter().any(|m| m.role == LedgerRole::Owner) {
+                if let Some(first) = ledger.members.first_mut() {
+                    first.role = LedgerRole::Owner;
+                }
+            }
+            !ledger.members.is_empty()
+        });
+        let ledgers = &state.ledgers;
+        state
+            .invitations
+            .retain(|_, invitation| ledgers.contains_key(&invitation.ledger_id));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LedgerRepo for MemLedgerRepo {
+    async fn get_ledgers(&self, user_id: &str) -> Result<Vec<Ledger>, LedgerRepoError> {
+        let read_guard = self.read_lock()?;
+
+        Ok(read_guard
+            .ledgers
+            .keys()
+            .filter_map(|id| read_guard.ledger(*id, user_id))
+            .collect())
+    }
+
+    async fn get_ledger(&self, user_id: &str, ledger_id: i32) -> Result<Ledger, LedgerRepoError> {
+        let read_guard = self.read_lock()?;
+
+        read_guard
+            .ledger(ledger_id, user_id)
+            .ok_or(LedgerNotFound(ledger_id))
+    }
+
+    async fn create_ledger(&self, user_id: &str, name: &str) -> Result<Ledger, LedgerRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        Ok(write_guard.insert_ledger(user_id, name))
+    }
+
+    async fn delete_ledger(&self, ledger_id: i32) -> Result<(), LedgerRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let ledger = write_guard
+            .ledgers
+            .remove(&ledger_id)
+            .ok_or(LedgerNotFound(ledger_id))?;
+        write_guard
+            .invitations
+            .retain(|_, invitation| invitation.ledger_id != ledger_id);
+        for member in ledger.members.iter().filter(|m| m.current) {
+            write_guard.ensure_current(&member.user_id, self.change_sequence.next());
+        }
+        Ok(())
+    }
+
+    async fn set_current_ledger(
+        &self,
+        user_id: &str,
+        ledger_id: i32,
+    ) -> Result<(), LedgerRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        if write_guard.member(ledger_id, user_id).is_none() {
+            return Err(LedgerNotFound(ledger_id));
+        }
+        if let Some((current_id, _)) = write_guard.current(user_id) {
+            if current_id == ledger_id {
+                return Ok(());
+            }
+            write_guard
+                .member_mut(current_id, user_id)
+                .expect("current member should exist")
+                .current = false;
+        }
+        let member = write_guard
+            .member_mut(ledger_id, user_id)
+            .expect("member was checked above");
+        member.current = true;
+        member.current_since = self.change_sequence.next();
+        Ok(())
+    }
+
+    async fn get_last_change(&self, _user_id: &str) -> Result<i64, LedgerRepoError> {
+        // changes are numbered while their repo's lock is held, so a read made after this waits
+        // for any change that has a number but isn't stored yet
+        Ok(self.change_sequence.current())
+    }
+
+    async fn get_members(&self, ledger_id: i32) -> Result<Vec<LedgerMember>, LedgerRepoError> {
+        let read_guard = self.read_lock()?;
+
+        let ledger = read_guard
+            .ledgers
+            .get(&ledger_id)
+            .ok_or(LedgerNotFound(ledger_id))?;
+        Ok(ledger
+            .members
+            .iter()
+            .map(|m| LedgerMember {
+                user_id: m.user_id.clone(),
+                role: m.role,
+            })
+            .collect())
+    }
+
+    async fn set_role(
+        &self,
+        ledger_id: i32,
+        user_id: &str,
+        role: LedgerRole,
+    ) -> Result<(), LedgerRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let member = write_guard
+            .member_mut(ledger_id, user_id)
+            .ok_or_else(|| MemberNotFound(user_id.to_owned()))?;
+        member.role = role;
+        Ok(())
+    }
+
+    async fn remove_member(&self, ledger_id: i32, user_id: &str) -> Result<(), LedgerRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let ledger = write_guard
+            .ledgers
+            .get_mut(&ledger_id)
+            .ok_or_else(|| MemberNotFound(user_id.to_owned()))?;
+        let position = ledger
+            .members
+            .iter()
+            .position(|m| m.user_id == user_id)
+            .ok_or_else(|| MemberNotFound(user_id.to_owned()))?;
+        let member = ledger.members.remove(position);
+        if member.current {
+            write_guard.ensure_current(user_id, self.change_sequence.next());
+        }
+        Ok(())
+    }
+
+    async fn create_invitation(
+        &self,
+        ledger_id: i32,
+        code_hash: &str,
+        role: LedgerRole,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), LedgerRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let now = Utc::now();
+        write_guard
+            .invitations
+            .retain(|_, invitation| invitation.expires_at > now);
+        if !write_guard.ledgers.contains_key(&ledger_id) {
+            return Err(LedgerNotFound(ledger_id));
+        }
+        write_guard.invitations.insert(
+            code_hash.to_owned(),
+            Invitation {
+                ledger_id,
+                role,
+                expires_at,
+            },
+        );
+        Ok(())
+    }
+
+    async fn accept_invitation(
+        &self,
+        user_id: &str,
+        code_hash: &str,
+    ) -> Result<Ledger, LedgerRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let Some(invitation) = write_guard
+            .invitations
+            .get(code_hash)
+            .filter(|i| i.expires_at > Utc::now())
+        else {
+            return Err(InvitationNotFound);
+        };
+        let (ledger_id, role) = (invitation.ledger_id, invitation.role);
+        if write_guard.member(ledger_id, user_id).is_some() {
+            return Err(AlreadyMember(user_id.to_owned()));
+        }
+
+        write_guard.invitations.remove(code_hash);
+        write_guard
+            .ledgers
+            .get_mut(&ledger_id)
+            .expect("invitations are deleted with their ledger")
+            .members
+            .push(Member {
+                user_id: user_id.to_owned(),
+                role,
+                current: false,
+                current_since: 0,
+            });
+        write_guard.ensure_current(user_id, self.change_sequence.next());
+        Ok(write_guard
+            .ledger(ledger_id, user_id)
+            .expect("member was just added"))
+    }
+}
@@ -1,24 +1,94 @@
-use crate::transaction_repo::TransactionRepo;
-use crate::transaction_template_repo::TransactionTemplateRepo;
-use crate::user_repo::UserRepo;
+use crate::rate_limit_repo::RateLimitRepo;
+use crate::Repos;
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
 
+mod api_key_repo;
+mod category_repo;
+mod duplicate_repo;
+mod history_repo;
+mod idempotency_repo;
+mod ledger_repo;
+mod oidc_repo;
+mod rate_limit_repo;
+mod refresh_token_repo;
+mod rule_repo;
+mod session_repo;
+mod totp_repo;
+mod transactee_repo;
 mod transaction_repo;
 mod transaction_template_repo;
 mod user_repo;
+mod webhook_repo;
 
-pub fn create_repos() -> (
-    Arc<dyn UserRepo>,
-    Arc<dyn TransactionRepo>,
-    Arc<dyn TransactionTemplateRepo>,
-) {
-    let user_repo = user_repo::MemUserRepo::new();
-    let transaction_repo = transaction_repo::MemTransactionRepo::new();
-    let transaction_template_repo = transaction_template_repo::MemTransactionTemplateRepo::new();
-
-    (
-        Arc::new(user_repo),
-        Arc::new(transaction_repo),
-        Arc::new(transaction_template_repo),
-    )
+/// Numbers changes to transactions and templates. It is shared between their repos so that changes
+/// to both are ordered, like the database's change sequence.
+pub(crate) struct ChangeSequence(AtomicI64);
+
+impl ChangeSequence {
+    fn new() -> ChangeSequence {
+        ChangeSequence(AtomicI64::new(0))
+    }
+
+    pub(crate) fn next(&self) -> i64 {
+        self.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// The number of the latest change
+    pub(crate) fn current(&self) -> i64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+pub fn create_repos() -> Repos {
+    let change_sequence = Arc::new(ChangeSequence::new());
+    let ledger_repo = Arc::new(ledger_repo::MemLedgerRepo::new(change_sequence.clone()));
+    let transaction_repo = Arc::new(transaction_repo::MemTransactionRepo::new(
+        change_sequence.clone(),
+        ledger_repo.clone(),
+    ));
+    let user_repo = Arc::new(user_repo::MemUserRepo::new(
+        ledger_repo.clone(),
+        transaction_repo.clone(),
+    ));
+    let transaction_template_repo = transaction_template_repo::MemTransactionTemplateRepo::new(
+        change_sequence,
+        ledger_repo.clone(),
+    );
+    let category_repo = category_repo::MemCategoryRepo::new();
+    let transactee_repo = transactee_repo::MemTransacteeRepo::new();
+    let rule_repo = rule_repo::MemRuleRepo::new();
+    let duplicate_repo = duplicate_repo::MemDuplicateRepo::new();
+    let history_repo = history_repo::MemHistoryRepo::new(ledger_repo.clone());
+    let idempotency_repo = idempotency_repo::MemIdempotencyRepo::new();
+    let webhook_repo = webhook_repo::MemWebhookRepo::new();
+    let api_key_repo = api_key_repo::MemApiKeyRepo::new();
+    let totp_repo = totp_repo::MemTotpRepo::new();
+    let refresh_token_repo = Arc::new(refresh_token_repo::MemRefreshTokenRepo::new());
+
+    Repos {
+        user_repo: user_repo.clone(),
+        transaction_repo,
+        template_repo: Arc::new(transaction_template_repo),
+        category_repo: Arc::new(category_repo),
+        transactee_repo: Arc::new(transactee_repo),
+        rule_repo: Arc::new(rule_repo),
+        duplicate_repo: Arc::new(duplicate_repo),
+        history_repo: Arc::new(history_repo),
+        idempotency_repo: Arc::new(idempotency_repo),
+        webhook_repo: Arc::new(webhook_repo),
+        refresh_token_repo: refresh_token_repo.clone(),
+        session_repo: refresh_token_repo,
+        api_key_repo: Arc::new(api_key_repo),
+        totp_repo: Arc::new(totp_repo),
+        rate_limit_repo: create_rate_limit_repo(),
+        oidc_repo: Arc::new(oidc_repo::MemOidcRepo::new(user_repo)),
+        ledger_repo,
+    }
+}
+
+/// Keeps rate limiting counters in memory, which is cheaper than the database when there is only
+/// one server
+pub fn create_rate_limit_repo() -> Arc<dyn RateLimitRepo> {
+    Arc::new(rate_limit_repo::MemRateLimitRepo::new())
 }
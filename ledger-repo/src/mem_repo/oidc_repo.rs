@@ -0,0 +1,115 @@
+use crate::mem_repo::user_repo::MemUserRepo;
+use crate::oidc_repo::{OidcLogin, OidcRepo, OidcRepoError};
+use crate::user_repo::{User, UserRepoError};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[derive(Default)]
+struct State {
+    /// Logins by state, along with when they were started
+    logins: HashMap<String, (OidcLogin, DateTime<Utc>)>,
+    /// User ids by issuer and subject
+    identities: HashMap<(String, String), String>,
+}
+
+/// Locks are taken identities first, then users
+pub struct MemOidcRepo {
+    state: RwLock<State>,
+    user_repo: Arc<MemUserRepo>,
+}
+
+impl MemOidcRepo {
+    pub(crate) fn new(user_repo: Arc<MemUserRepo>) -> MemOidcRepo {
+        MemOidcRepo {
+            state: RwLock::new(State::default()),
+            user_repo,
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.state
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.state
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl OidcRepo for MemOidcRepo {
+    async fn create_login(
+        &self,
+        login: OidcLogin,
+        expire_before: DateTime<Utc>,
+    ) -> Result<(), OidcRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        write_guard
+            .logins
+            .retain(|_, (_, created_at)| *created_at >= expire_before);
+        write_guard
+            .logins
+            .insert(login.state.clone(), (login, Utc::now()));
+
+        Ok(())
+    }
+
+    async fn take_login(
+        &self,
+        state: &str,
+        expire_before: DateTime<Utc>,
+    ) -> Result<Option<OidcLogin>, OidcRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        Ok(write_guard
+            .logins
+            .remove(state)
+            .filter(|(_, created_at)| *created_at >= expire_before)
+            .map(|(login, _)| login))
+    }
+
+    async fn get_linked_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<String>, OidcRepoError> {
+        let read_guard = self.read_lock()?;
+
+        Ok(read_guard
+            .identities
+            .get(&(issuer.to_owned(), subject.to_owned()))
+            .cloned())
+    }
+
+    async fn create_linked_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+        user: User,
+    ) -> Result<(), OidcRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let key = (issuer.to_owned(), subject.to_owned());
+        if write_guard.identities.contains_key(&key) {
+            return Err(OidcRepoError::IdentityAlreadyLinked(
+                issuer.to_owned(),
+                subject.to_owned(),
+            ));
+        }
+        let user_id = user.id.clone();
+        self.user_repo.insert_user(user).map_err(|e| match e {
+            UserRepoError::UserAlreadyExists(user_id) => OidcRepoError::UserAlreadyExists(user_id),
+            e => OidcRepoError::Other(e.into()),
+        })?;
+        write_guard.identities.insert(key, user_id);
+
+        Ok(())
+    }
+}
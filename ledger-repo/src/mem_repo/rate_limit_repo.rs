@@ -0,0 +1,113 @@
+use crate::rate_limit_repo::{FailedLogins, RateLimitRepo, RateLimitRepoError};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+struct State {
+    request_counts: HashMap<(String, DateTime<Utc>), i64>,
+    failed_logins: HashMap<String, FailedLogins>,
+}
+
+pub struct MemRateLimitRepo {
+    state: RwLock<State>,
+}
+
+impl MemRateLimitRepo {
+    pub fn new() -> MemRateLimitRepo {
+        let state = State {
+            request_counts: HashMap::new(),
+            failed_logins: HashMap::new(),
+        };
+        MemRateLimitRepo {
+            state: RwLock::new(state),
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.state
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.state
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl RateLimitRepo for MemRateLimitRepo {
+    async fn count_request(
+        &self,
+        key: &str,
+        window_start: DateTime<Utc>,
+    ) -> Result<i64, RateLimitRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let count = write_guard
+            .request_counts
+            .entry((key.to_owned(), window_start))
+            .or_insert(0);
+        *count += 1;
+        Ok(*count)
+    }
+
+    async fn get_failed_logins(
+        &self,
+        key: &str,
+    ) -> Result<Option<FailedLogins>, RateLimitRepoError> {
+        let read_guard = self.read_lock()?;
+
+        Ok(read_guard.failed_logins.get(key).cloned())
+    }
+
+    async fn record_failed_login(
+        &self,
+        key: &str,
+        reset_before: DateTime<Utc>,
+    ) -> Result<FailedLogins, RateLimitRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let now = Utc::now();
+        let failed_logins = write_guard
+            .failed_logins
+            .entry(key.to_owned())
+            .and_modify(|failed_logins| {
+                if failed_logins.last_failure_at < reset_before {
+                    failed_logins.failures = 1;
+                } else {
+                    failed_logins.failures += 1;
+                }
+                failed_logins.last_failure_at = now;
+            })
+            .or_insert(FailedLogins {
+                failures: 1,
+                last_failure_at: now,
+            });
+        Ok(failed_logins.clone())
+    }
+
+    async fn clear_failed_logins(&self, key: &str) -> Result<(), RateLimitRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        write_guard.failed_logins.remove(key);
+        Ok(())
+    }
+
+    async fn purge_counters(&self, before: DateTime<Utc>) -> Result<u64, RateLimitRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let count = write_guard.request_counts.len() + write_guard.failed_logins.len();
+        write_guard
+            .request_counts
+            .retain(|(_, window_start), _| *window_start >= before);
+        write_guard
+            .failed_logins
+            .retain(|_, failed_logins| failed_logins.last_failure_at >= before);
+        let remaining = write_guard.request_counts.len() + write_guard.failed_logins.len();
+        Ok((count - remaining) as u64)
+    }
+}
@@ -0,0 +1,175 @@
+use crate::refresh_token_repo::{RefreshTokenOwner, RefreshTokenRepo, RefreshTokenRepoError};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Families also hold the details of the session they belong to, which are managed through the
+/// SessionRepo implementation
+pub(super) struct Family {
+    pub(super) user_id: String,
+    pub(super) created_at: DateTime<Utc>,
+    pub(super) revoked_at: Option<DateTime<Utc>>,
+    pub(super) device_name: Option<String>,
+    pub(super) ip_address: Option<String>,
+    pub(super) last_used_at: DateTime<Utc>,
+}
+
+struct Token {
+    family_id: String,
+    created_at: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+pub(super) struct State {
+    /// Families by id
+    pub(super) families: HashMap<String, Family>,
+    /// Tokens by hash
+    tokens: HashMap<String, Token>,
+}
+
+pub struct MemRefreshTokenRepo {
+    state: RwLock<State>,
+}
+
+impl MemRefreshTokenRepo {
+    pub fn new() -> MemRefreshTokenRepo {
+        MemRefreshTokenRepo {
+            state: RwLock::new(State::default()),
+        }
+    }
+
+    pub(super) fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.state
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    pub(super) fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.state
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepo for MemRefreshTokenRepo {
+    async fn create_family(
+        &self,
+        user_id: &str,
+        family_id: &str,
+        token_hash: &str,
+    ) -> Result<(), RefreshTokenRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let now = Utc::now();
+        write_guard.families.insert(
+            family_id.to_owned(),
+            Family {
+                user_id: user_id.to_owned(),
+                created_at: now,
+                revoked_at: None,
+                device_name: None,
+                ip_address: None,
+                last_used_at: now,
+            },
+        );
+        write_guard.tokens.insert(
+            token_hash.to_owned(),
+            Token {
+                family_id: family_id.to_owned(),
+                created_at: now,
+                used_at: None,
+            },
+        );
+        Ok(())
+    }
+
+    async fn rotate_token(
+        &self,
+        token_hash: &str,
+        new_token_hash: &str,
+        issued_after: DateTime<Utc>,
+    ) -> Result<RefreshTokenOwner, RefreshTokenRepoError> {
+        let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
+
+        let token = state
+            .tokens
+            .get_mut(token_hash)
+            .filter(|token| token.created_at >= issued_after)
+            .ok_or(RefreshTokenRepoError::TokenNotFound)?;
+        let family = state
+            .families
+            .get_mut(&token.family_id)
+            .filter(|family| family.revoked_at.is_none())
+            .ok_or(RefreshTokenRepoError::TokenNotFound)?;
+
+        let now = Utc::now();
+        if token.used_at.is_some() {
+            family.revoked_at = Some(now);
+            return Err(RefreshTokenRepoError::TokenReused(token.family_id.clone()));
+        }
+        token.used_at = Some(now);
+
+        let owner = RefreshTokenOwner {
+            user_id: family.user_id.clone(),
+            family_id: token.family_id.clone(),
+        };
+        state.tokens.insert(
+            new_token_hash.to_owned(),
+            Token {
+                family_id: owner.family_id.clone(),
+                created_at: now,
+                used_at: None,
+            },
+        );
+        Ok(owner)
+    }
+
+    async fn revoke_family(&self, token_hash: &str) -> Result<(), RefreshTokenRepoError> {
+        let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
+
+        if let Some(token) = state.tokens.get(token_hash) {
+            if let Some(family) = state.families.get_mut(&token.family_id) {
+                family.revoked_at.get_or_insert_with(Utc::now);
+            }
+        }
+        Ok(())
+    }
+
+    async fn revoke_all_families(&self, user_id: &str) -> Result<(), RefreshTokenRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let now = Utc::now();
+        for family in write_guard.families.values_mut() {
+            if family.user_id == user_id {
+                family.revoked_at.get_or_insert(now);
+            }
+        }
+        Ok(())
+    }
+
+    async fn purge_tokens(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<u64, RefreshTokenRepoError> {
+        let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
+
+        let count = state.tokens.len();
+        state
+            .tokens
+            .retain(|_, token| token.created_at >= created_before);
+        let purged = count - state.tokens.len();
+
+        let tokens = &state.tokens;
+        state
+            .families
+            .retain(|family_id, _| tokens.values().any(|token| token.family_id == *family_id));
+        Ok(purged as u64)
+    }
+}
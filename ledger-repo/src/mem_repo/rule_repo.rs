@@ -0,0 +1,97 @@
+use crate::rule_repo::{NewRule, Rule, RuleRepo, RuleRepoError};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+struct State {
+    user_rules: HashMap<String, HashMap<i32, Rule>>,
+    next_id: i32,
+}
+
+pub struct MemRuleRepo {
+    state: RwLock<State>,
+}
+
+impl MemRuleRepo {
+    pub fn new() -> MemRuleRepo {
+        let state = State {
+            user_rules: HashMap::new(),
+            next_id: 0,
+        };
+        MemRuleRepo {
+            state: RwLock::new(state),
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.state
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.state
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl RuleRepo for MemRuleRepo {
+    async fn get_rules(&self, user_id: &str) -> Result<Vec<Rule>, RuleRepoError> {
+        let read_guard = self.read_lock()?;
+
+        let Some(rules) = read_guard.user_rules.get(user_id) else {
+            return Ok(Vec::new());
+        };
+
+        let mut rules: Vec<Rule> = rules.values().cloned().collect();
+        rules.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.id.cmp(&b.id)));
+        Ok(rules)
+    }
+
+    async fn create_rule(&self, user_id: &str, new_rule: NewRule) -> Result<Rule, RuleRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let id = write_guard.next_id;
+        write_guard.next_id += 1;
+
+        let rule = new_rule.to_rule(id);
+        write_guard
+            .user_rules
+            .entry(user_id.to_owned())
+            .or_default()
+            .insert(id, rule.clone());
+
+        Ok(rule)
+    }
+
+    async fn update_rule(
+        &self,
+        user_id: &str,
+        rule_id: i32,
+        rule: NewRule,
+    ) -> Result<Rule, RuleRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let existing = write_guard
+            .user_rules
+            .get_mut(user_id)
+            .and_then(|rules| rules.get_mut(&rule_id))
+            .ok_or(RuleRepoError::RuleNotFound(rule_id))?;
+        *existing = rule.to_rule(rule_id);
+
+        Ok(existing.clone())
+    }
+
+    async fn delete_rule(&self, user_id: &str, rule_id: i32) -> Result<Rule, RuleRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        write_guard
+            .user_rules
+            .get_mut(user_id)
+            .and_then(|rules| rules.remove(&rule_id))
+            .ok_or(RuleRepoError::RuleNotFound(rule_id))
+    }
+}
@@ -0,0 +1,86 @@
+use crate::mem_repo::refresh_token_repo::MemRefreshTokenRepo;
+use crate::session_repo::{Session, SessionRepo, SessionRepoError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+
+/// Sessions are token families, so they are kept by the refresh token repo
+#[async_trait]
+impl SessionRepo for MemRefreshTokenRepo {
+    async fn start_session(
+        &self,
+        session_id: &str,
+        device_name: Option<String>,
+        ip_address: Option<String>,
+    ) -> Result<(), SessionRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let family = write_guard
+            .families
+            .get_mut(session_id)
+            .ok_or_else(|| SessionRepoError::SessionNotFound(session_id.to_owned()))?;
+        family.device_name = device_name;
+        family.ip_address = ip_address;
+        family.last_used_at = Utc::now();
+        Ok(())
+    }
+
+    async fn touch_session(
+        &self,
+        session_id: &str,
+        ip_address: Option<String>,
+    ) -> Result<(), SessionRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let family = write_guard
+            .families
+            .get_mut(session_id)
+            .ok_or_else(|| SessionRepoError::SessionNotFound(session_id.to_owned()))?;
+        family.ip_address = ip_address;
+        family.last_used_at = Utc::now();
+        Ok(())
+    }
+
+    async fn get_sessions(
+        &self,
+        user_id: &str,
+        used_after: DateTime<Utc>,
+    ) -> Result<Vec<Session>, SessionRepoError> {
+        let read_guard = self.read_lock()?;
+
+        let mut sessions: Vec<Session> = read_guard
+            .families
+            .iter()
+            .filter(|(_, family)| {
+                family.user_id == user_id
+                    && family.revoked_at.is_none()
+                    && family.last_used_at >= used_after
+            })
+            .map(|(id, family)| Session {
+                id: id.clone(),
+                device_name: family.device_name.clone(),
+                ip_address: family.ip_address.clone(),
+                created_at: family.created_at,
+                last_used_at: family.last_used_at,
+            })
+            .collect();
+        sessions.sort_by_key(|session| Reverse(session.last_used_at));
+        Ok(sessions)
+    }
+
+    async fn revoke_session(
+        &self,
+        user_id: &str,
+        session_id: &str,
+    ) -> Result<(), SessionRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let family = write_guard
+            .families
+            .get_mut(session_id)
+            .filter(|family| family.user_id == user_id && family.revoked_at.is_none())
+            .ok_or_else(|| SessionRepoError::SessionNotFound(session_id.to_owned()))?;
+        family.revoked_at = Some(Utc::now());
+        Ok(())
+    }
+}
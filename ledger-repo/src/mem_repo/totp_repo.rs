@@ -0,0 +1,120 @@
+use crate::totp_repo::{TotpCredential, TotpRepo, TotpRepoError};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+struct Entry {
+    credential: TotpCredential,
+    /// Unused recovery codes
+    recovery_code_hashes: Vec<String>,
+}
+
+/// Entries by user id
+type State = HashMap<String, Entry>;
+
+pub struct MemTotpRepo {
+    entries: RwLock<State>,
+}
+
+impl MemTotpRepo {
+    pub fn new() -> MemTotpRepo {
+        MemTotpRepo {
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.entries
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.entries
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl TotpRepo for MemTotpRepo {
+    async fn get_totp(&self, user_id: &str) -> Result<Option<TotpCredential>, TotpRepoError> {
+        let read_guard = self.read_lock()?;
+
+        Ok(read_guard
+            .get(user_id)
+            .map(|entry| entry.credential.clone()))
+    }
+
+    async fn create_totp(&self, user_id: &str, secret: Vec<u8>) -> Result<(), TotpRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        write_guard.insert(
+            user_id.to_owned(),
+            Entry {
+                credential: TotpCredential {
+                    secret,
+                    enabled: false,
+                    last_used_step: None,
+                },
+                recovery_code_hashes: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn enable_totp(
+        &self,
+        user_id: &str,
+        recovery_code_hashes: Vec<String>,
+    ) -> Result<(), TotpRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let entry = write_guard
+            .get_mut(user_id)
+            .ok_or_else(|| TotpRepoError::TotpNotFound(user_id.to_owned()))?;
+        entry.credential.enabled = true;
+        entry.recovery_code_hashes = recovery_code_hashes;
+        Ok(())
+    }
+
+    async fn delete_totp(&self, user_id: &str) -> Result<(), TotpRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        write_guard.remove(user_id);
+        Ok(())
+    }
+
+    async fn use_step(&self, user_id: &str, step: i64) -> Result<bool, TotpRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let entry = write_guard
+            .get_mut(user_id)
+            .ok_or_else(|| TotpRepoError::TotpNotFound(user_id.to_owned()))?;
+        if entry
+            .credential
+            .last_used_step
+            .is_some_and(|last_used_step| last_used_step >= step)
+        {
+            return Ok(false);
+        }
+        entry.credential.last_used_step = Some(step);
+        Ok(true)
+    }
+
+    async fn use_recovery_code(
+        &self,
+        user_id: &str,
+        code_hash: &str,
+    ) -> Result<bool, TotpRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let Some(entry) = write_guard.get_mut(user_id) else {
+            return Ok(false);
+        };
+        let count = entry.recovery_code_hashes.len();
+        entry.recovery_code_hashes.retain(|hash| hash != code_hash);
+        Ok(entry.recovery_code_hashes.len() < count)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::transactee_repo::TransacteeRepoError::TransacteeNotFound;
+use crate::transactee_repo::{Transactee, TransacteeRepo, TransacteeRepoError};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+type State = HashMap<String, HashMap<String, Transactee>>;
+
+pub struct MemTransacteeRepo {
+    user_transactees: RwLock<State>,
+}
+
+impl MemTransacteeRepo {
+    pub fn new() -> MemTransacteeRepo {
+        MemTransacteeRepo {
+            user_transactees: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.user_transactees
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.user_transactees
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl TransacteeRepo for MemTransacteeRepo {
+    async fn get_transactees(&self, user_id: &str) -> Result<Vec<Transactee>, TransacteeRepoError> {
+        let read_guard = self.read_lock()?;
+
+        let Some(transactees) = read_guard.get(user_id) else {
+            return Ok(Vec::new());
+        };
+
+        let mut transactees: Vec<Transactee> = transactees.values().cloned().collect();
+        transactees.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(transactees)
+    }
+
+    async fn get_transactee(
+        &self,
+        user_id: &str,
+        name: &str,
+    ) -> Result<Transactee, TransacteeRepoError> {
+        let read_guard = self.read_lock()?;
+
+        read_guard
+            .get(user_id)
+            .and_then(|transactees| transactees.get(name))
+            .cloned()
+            .ok_or_else(|| TransacteeNotFound(name.to_owned()))
+    }
+
+    async fn set_transactee(
+        &self,
+        user_id: &str,
+        transactee: Transactee,
+    ) -> Result<Transactee, TransacteeRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        write_guard
+            .entry(user_id.to_owned())
+            .or_default()
+            .insert(transactee.name.clone(), transactee.clone());
+        Ok(transactee)
+    }
+
+    async fn delete_transactee(
+        &self,
+        user_id: &str,
+        name: &str,
+    ) -> Result<Transactee, TransacteeRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        write_guard
+            .get_mut(user_id)
+            .and_then(|transactees| transactees.remove(name))
+            .ok_or_else(|| TransacteeNotFound(name.to_owned()))
+    }
+}
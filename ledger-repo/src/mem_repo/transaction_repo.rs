@@ -1,49 +1,140 @@
-use crate::transaction_repo::TransactionRepoError::TransactionNotFound;
+use crate::mem_repo::ledger_repo::MemLedgerRepo;
+use crate::mem_repo::ChangeSequence;
+use crate::transaction_repo::TransactionRepoError::{
+    NotPermitted, TransactionNotFound, VersionMismatch,
+};
 use crate::transaction_repo::{
-    Filter, MonthlyTotal, NewTransaction, PageOptions, Transaction, TransactionRepo,
-    TransactionRepoError,
+    ChangedTransaction, DeletedTransaction, Filter, MonthlyTotal, NewTransaction, PageOptions,
+    TagStats, TransacteeCount, Transaction, TransactionRepo, TransactionRepoError,
+    UpdatedTransaction,
 };
 use anyhow::anyhow;
 use async_trait::async_trait;
-use chrono::{Datelike, NaiveDate};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
 use rust_decimal::Decimal;
+use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
-use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 struct State {
     transactions: HashMap<i32, Transaction>,
-    user_transactions: HashMap<String, HashSet<i32>>,
+    ledger_transactions: HashMap<i32, HashSet<i32>>,
+    deleted_transactions: HashMap<i32, HashMap<i32, DeletedTransaction>>,
+    /// The latest change of each transaction, including deleted ones
+    changes: HashMap<i32, i64>,
+    last_purged_changes: HashMap<i32, i64>,
+    /// The user who created each transaction, including deleted ones
+    creators: HashMap<i32, String>,
     next_id: i32,
 }
 
 pub struct MemTransactionRepo {
     state: RwLock<State>,
+    change_sequence: Arc<ChangeSequence>,
+    ledger_repo: Arc<MemLedgerRepo>,
 }
 
 impl MemTransactionRepo {
-    pub fn new() -> MemTransactionRepo {
+    pub(crate) fn new(
+        change_sequence: Arc<ChangeSequence>,
+        ledger_repo: Arc<MemLedgerRepo>,
+    ) -> MemTransactionRepo {
         let state = State {
             transactions: HashMap::new(),
-            user_transactions: HashMap::new(),
+            ledger_transactions: HashMap::new(),
+            deleted_transactions: HashMap::new(),
+            changes: HashMap::new(),
+            last_purged_changes: HashMap::new(),
+            creators: HashMap::new(),
             next_id: 0,
         };
         MemTransactionRepo {
             state: RwLock::new(state),
+            change_sequence,
+            ledger_repo,
         }
     }
 
-    fn read_lock(&self) -> Result<RwLockReadGuard<State>, anyhow::Error> {
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
         self.state
             .read()
             .map_err(|_| anyhow!("Unable to acquire lock"))
     }
 
-    fn write_lock(&self) -> Result<RwLockWriteGuard<State>, anyhow::Error> {
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
         self.state
             .write()
             .map_err(|_| anyhow!("Unable to acquire lock"))
     }
+
+    fn current_ledger(&self, user: &str) -> Result<Option<i32>, TransactionRepoError> {
+        Ok(self
+            .ledger_repo
+            .current_ledger(user)?
+            .map(|ledger| ledger.id))
+    }
+
+    /// The user's current ledger, if they can change its transactions
+    fn writable_ledger(&self, user: &str) -> Result<i32, TransactionRepoError> {
+        match self.ledger_repo.current_ledger(user)? {
+            Some(ledger) if ledger.role.can_write() => Ok(ledger.id),
+            _ => Err(NotPermitted),
+        }
+    }
+
+    /// Applies `update` to each transaction of the ledger, counting it as changed when `update`
+    /// returns true
+    fn update_ledger_transactions(
+        &self,
+        ledger_id: i32,
+        mut update: impl FnMut(&mut Transaction) -> bool,
+    ) -> Result<Vec<UpdatedTransaction>, TransactionRepoError> {
+        let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
+
+        let Some(transaction_ids) = state.ledger_transactions.get(&ledger_id) else {
+            return Ok(vec![]);
+        };
+
+        let mut updated = vec![];
+        for id in transaction_ids {
+            let transaction = state
+                .transactions
+                .get_mut(id)
+                .expect("transactions should have all the ids from ledger_transactions");
+            let before = transaction.clone();
+            if update(transaction) {
+                transaction.version += 1;
+                state.changes.insert(*id, self.change_sequence.next());
+                updated.push(UpdatedTransaction {
+                    before,
+                    after: transaction.clone(),
+                });
+            }
+        }
+        updated.sort_by_key(|u| u.after.id);
+
+        Ok(updated)
+    }
+
+    /// Number of transactions the user created that aren't in the trash
+    pub(crate) fn count_created(&self, user: &str) -> Result<i64, anyhow::Error> {
+        let read_guard = self.read_lock()?;
+        let count = read_guard
+            .creators
+            .iter()
+            .filter(|(id, creator)| *creator == user && read_guard.transactions.contains_key(id))
+            .count();
+        Ok(count as i64)
+    }
+
+    /// Forgets that a deleted user created their transactions, which stay in their ledgers
+    pub(crate) fn remove_creator(&self, user: &str) -> Result<(), anyhow::Error> {
+        let mut write_guard = self.write_lock()?;
+        write_guard.creators.retain(|_, creator| creator != user);
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -53,9 +144,12 @@ impl TransactionRepo for MemTransactionRepo {
         user: &str,
         transaction_id: i32,
     ) -> Result<Transaction, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user)? else {
+            return Err(TransactionNotFound(transaction_id));
+        };
         let read_guard = self.read_lock()?;
 
-        let Some(transaction_ids) = read_guard.user_transactions.get(user) else {
+        let Some(transaction_ids) = read_guard.ledger_transactions.get(&ledger_id) else {
             return Err(TransactionNotFound(transaction_id));
         };
         if !transaction_ids.contains(&transaction_id) {
@@ -65,7 +159,7 @@ impl TransactionRepo for MemTransactionRepo {
         let transaction = read_guard
             .transactions
             .get(&transaction_id)
-            .expect("transactions should contain same ids as user_transactions")
+            .expect("transactions should contain same ids as ledger_transactions")
             .clone();
         Ok(transaction)
     }
@@ -76,9 +170,12 @@ impl TransactionRepo for MemTransactionRepo {
         filter: Filter,
         page_options: Option<PageOptions>,
     ) -> Result<Vec<Transaction>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user)? else {
+            return Ok(Vec::new());
+        };
         let read_guard = self.read_lock()?;
 
-        let Some(transaction_ids) = read_guard.user_transactions.get(user) else {
+        let Some(transaction_ids) = read_guard.ledger_transactions.get(&ledger_id) else {
             return Ok(Vec::new());
         };
 
@@ -88,7 +185,7 @@ impl TransactionRepo for MemTransactionRepo {
                 read_guard
                     .transactions
                     .get(id)
-                    .expect("transactions should have all the ids from user_transactions")
+                    .expect("transactions should have all the ids from ledger_transactions")
             })
             .cloned()
             .collect();
@@ -131,17 +228,20 @@ impl TransactionRepo for MemTransactionRepo {
         user: &str,
         new_transaction: NewTransaction,
     ) -> Result<Transaction, TransactionRepoError> {
+        let ledger_id = self.writable_ledger(user)?;
         let mut write_guard = self.write_lock()?;
 
         let id = write_guard.next_id;
         write_guard.next_id += 1;
 
-        let transaction = new_transaction.to_transaction(id);
+        let transaction = new_transaction.to_transaction(id, 1);
 
         write_guard.transactions.insert(id, transaction.clone());
+        write_guard.changes.insert(id, self.change_sequence.next());
+        write_guard.creators.insert(id, user.to_owned());
         write_guard
-            .user_transactions
-            .entry(user.to_owned())
+            .ledger_transactions
+            .entry(ledger_id)
             .or_insert_with(HashSet::new)
             .insert(id);
 
@@ -153,10 +253,12 @@ impl TransactionRepo for MemTransactionRepo {
         user: &str,
         transaction_id: i32,
         updated_transaction: NewTransaction,
+        expected_version: Option<i32>,
     ) -> Result<Transaction, TransactionRepoError> {
+        let ledger_id = self.writable_ledger(user)?;
         let mut write_guard = self.write_lock()?;
 
-        let Some(transaction_ids) = write_guard.user_transactions.get(user) else {
+        let Some(transaction_ids) = write_guard.ledger_transactions.get(&ledger_id) else {
             return Err(TransactionNotFound(transaction_id));
         };
         if !transaction_ids.contains(&transaction_id) {
@@ -164,32 +266,207 @@ impl TransactionRepo for MemTransactionRepo {
         };
 
         let entry = write_guard.transactions.entry(transaction_id);
-        if let Entry::Occupied(mut e) = entry {
-            let transaction = updated_transaction.to_transaction(transaction_id);
-            e.insert(transaction.clone());
-            Ok(transaction)
-        } else {
-            Err(TransactionNotFound(transaction_id))
+        let Entry::Occupied(mut e) = entry else {
+            return Err(TransactionNotFound(transaction_id));
+        };
+        let version = e.get().version;
+        if expected_version.is_some_and(|v| v != version) {
+            return Err(VersionMismatch(transaction_id));
         }
+        let transaction = updated_transaction.to_transaction(transaction_id, version + 1);
+        e.insert(transaction.clone());
+        write_guard
+            .changes
+            .insert(transaction_id, self.change_sequence.next());
+        Ok(transaction)
     }
 
     async fn delete_transaction(
         &self,
         user: &str,
         transaction_id: i32,
+        expected_version: Option<i32>,
+    ) -> Result<Transaction, TransactionRepoError> {
+        let ledger_id = self.writable_ledger(user)?;
+        let mut write_guard = self.write_lock()?;
+
+        let Some(transaction_ids) = write_guard.ledger_transactions.get(&ledger_id) else {
+            return Err(TransactionNotFound(transaction_id));
+        };
+        if !transaction_ids.contains(&transaction_id) {
+            return Err(TransactionNotFound(transaction_id));
+        }
+        let version = write_guard
+            .transactions
+            .get(&transaction_id)
+            .expect("transactions should contain same ids as ledger_transactions")
+            .version;
+        if expected_version.is_some_and(|v| v != version) {
+            return Err(VersionMismatch(transaction_id));
+        }
+
+        write_guard
+            .ledger_transactions
+            .get_mut(&ledger_id)
+            .expect("ledger_transactions was checked above")
+            .remove(&transaction_id);
+        let transaction = write_guard
+            .transactions
+            .remove(&transaction_id)
+            .expect("transactions should contain same ids as ledger_transactions");
+        write_guard
+            .deleted_transactions
+            .entry(ledger_id)
+            .or_default()
+            .insert(
+                transaction_id,
+                DeletedTransaction::new(transaction.clone(), Utc::now()),
+            );
+        write_guard
+            .changes
+            .insert(transaction_id, self.change_sequence.next());
+
+        Ok(transaction)
+    }
+
+    async fn get_deleted_transactions(
+        &self,
+        user: &str,
+    ) -> Result<Vec<DeletedTransaction>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user)? else {
+            return Ok(Vec::new());
+        };
+        let read_guard = self.read_lock()?;
+
+        let mut deleted_transactions: Vec<DeletedTransaction> = read_guard
+            .deleted_transactions
+            .get(&ledger_id)
+            .map(|deleted| deleted.values().cloned().collect())
+            .unwrap_or_default();
+        deleted_transactions.sort_by(|a, b| {
+            b.deleted_at
+                .cmp(&a.deleted_at)
+                .then_with(|| b.transaction.id.cmp(&a.transaction.id))
+        });
+
+        Ok(deleted_transactions)
+    }
+
+    async fn restore_transaction(
+        &self,
+        user: &str,
+        transaction_id: i32,
     ) -> Result<Transaction, TransactionRepoError> {
+        let ledger_id = self.writable_ledger(user)?;
+        let mut write_guard = self.write_lock()?;
+
+        let Some(deleted) = write_guard
+            .deleted_transactions
+            .get_mut(&ledger_id)
+            .and_then(|deleted| deleted.remove(&transaction_id))
+        else {
+            return Err(TransactionNotFound(transaction_id));
+        };
+
+        let mut transaction = deleted.transaction;
+        transaction.version += 1;
+        write_guard
+            .transactions
+            .insert(transaction_id, transaction.clone());
+        write_guard
+            .changes
+            .insert(transaction_id, self.change_sequence.next());
+        write_guard
+            .ledger_transactions
+            .entry(ledger_id)
+            .or_insert_with(HashSet::new)
+            .insert(transaction_id);
+
+        Ok(transaction)
+    }
+
+    async fn purge_deleted_transactions(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<u64, TransactionRepoError> {
         let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
 
-        if let Some(t) = write_guard.transactions.remove(&transaction_id) {
-            write_guard
-                .user_transactions
-                .get_mut(user)
-                .expect("ids in transactions should be present in user_transactions")
-                .remove(&transaction_id);
-            Ok(t)
-        } else {
-            Err(TransactionNotFound(transaction_id))
+        let mut count = 0;
+        for (ledger_id, deleted) in state.deleted_transactions.iter_mut() {
+            deleted.retain(|id, d| {
+                if d.deleted_at >= deleted_before {
+                    return true;
+                }
+                let change = state
+                    .changes
+                    .remove(id)
+                    .expect("changes should have all transaction ids");
+                state.creators.remove(id);
+                let last_purged = state.last_purged_changes.entry(*ledger_id).or_default();
+                *last_purged = change.max(*last_purged);
+                count += 1;
+                false
+            });
         }
+
+        Ok(count)
+    }
+
+    async fn get_changed_transactions(
+        &self,
+        user: &str,
+        since: i64,
+    ) -> Result<Vec<ChangedTransaction>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user)? else {
+            return Ok(Vec::new());
+        };
+        let read_guard = self.read_lock()?;
+
+        let live = read_guard
+            .ledger_transactions
+            .get(&ledger_id)
+            .into_iter()
+            .flatten()
+            .map(|id| {
+                let transaction = read_guard
+                    .transactions
+                    .get(id)
+                    .expect("transactions should contain same ids as ledger_transactions");
+                (transaction, false)
+            });
+        let deleted = read_guard
+            .deleted_transactions
+            .get(&ledger_id)
+            .into_iter()
+            .flat_map(|deleted| deleted.values())
+            .map(|d| (&d.transaction, true));
+        let mut changed: Vec<ChangedTransaction> = live
+            .chain(deleted)
+            .map(|(transaction, deleted)| ChangedTransaction {
+                transaction: transaction.clone(),
+                deleted,
+                change: read_guard.changes[&transaction.id],
+            })
+            .filter(|c| c.change > since)
+            .collect();
+        changed.sort_by_key(|c| c.change);
+
+        Ok(changed)
+    }
+
+    async fn get_last_purged_change(&self, user: &str) -> Result<i64, TransactionRepoError> {
+        let Some(ledger) = self.ledger_repo.current_ledger(user)? else {
+            return Ok(0);
+        };
+        let read_guard = self.read_lock()?;
+
+        let last_purged = read_guard
+            .last_purged_changes
+            .get(&ledger.id)
+            .copied()
+            .unwrap_or_default();
+        Ok(last_purged.max(ledger.since))
     }
 
     async fn get_monthly_totals(
@@ -214,7 +491,7 @@ impl TransactionRepo for MemTransactionRepo {
         }
 
         let mut monthly_totals: Vec<MonthlyTotal> = monthly_totals.into_values().collect();
-        monthly_totals.sort_by(|a, b| b.month.cmp(&a.month));
+        monthly_totals.sort_by_key(|mt| Reverse(mt.month));
 
         Ok(monthly_totals)
     }
@@ -239,11 +516,57 @@ impl TransactionRepo for MemTransactionRepo {
         Ok(tags.into_iter().collect())
     }
 
+    async fn get_tag_stats(&self, user: &str) -> Result<Vec<TagStats>, TransactionRepoError> {
+        let mut tag_stats: HashMap<String, TagStats> = HashMap::new();
+        for t in self.get_all_transactions(user, Filter::NONE, None).await? {
+            for tag in t.tags {
+                let stats = tag_stats
+                    .entry(tag.clone())
+                    .or_insert_with(|| TagStats::new(tag, 0, Decimal::ZERO));
+                stats.count += 1;
+                stats.total += t.amount;
+            }
+        }
+
+        let mut tag_stats: Vec<TagStats> = tag_stats.into_values().collect();
+        tag_stats.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+
+        Ok(tag_stats)
+    }
+
+    async fn merge_tags(
+        &self,
+        user: &str,
+        tags: &[String],
+        new_tag: &str,
+    ) -> Result<Vec<UpdatedTransaction>, TransactionRepoError> {
+        let ledger_id = self.writable_ledger(user)?;
+        self.update_ledger_transactions(ledger_id, |transaction| {
+            let mut changed = false;
+            for tag in tags {
+                changed |= transaction.tags.remove(tag);
+            }
+            if changed {
+                transaction.tags.insert(new_tag.to_owned());
+            }
+            changed
+        })
+    }
+
+    async fn delete_tag(
+        &self,
+        user: &str,
+        tag: &str,
+    ) -> Result<Vec<UpdatedTransaction>, TransactionRepoError> {
+        let ledger_id = self.writable_ledger(user)?;
+        self.update_ledger_transactions(ledger_id, |transaction| transaction.tags.remove(tag))
+    }
+
     async fn get_all_transactees(
         &self,
         user: &str,
         category: Option<String>,
-    ) -> Result<Vec<String>, TransactionRepoError> {
+    ) -> Result<Vec<TransacteeCount>, TransactionRepoError> {
         let mut transactee_counts = HashMap::new();
 
         let transactions = self.get_all_transactions(user, Filter::NONE, None).await?;
@@ -263,12 +586,38 @@ impl TransactionRepo for MemTransactionRepo {
             }
         }
 
-        let mut transactees: Vec<String> = transactee_counts.keys().cloned().collect();
-        transactees.sort_by(|a, b| transactee_counts.get(b).cmp(&transactee_counts.get(a)));
+        let mut transactees: Vec<TransacteeCount> = transactee_counts
+            .into_iter()
+            .map(|(transactee, count)| TransacteeCount::new(transactee, count))
+            .collect();
+        transactees.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.transactee.cmp(&b.transactee))
+        });
 
         Ok(transactees)
     }
 
+    async fn merge_transactees(
+        &self,
+        user: &str,
+        transactees: &[String],
+        new_transactee: &str,
+    ) -> Result<Vec<UpdatedTransaction>, TransactionRepoError> {
+        let ledger_id = self.writable_ledger(user)?;
+        self.update_ledger_transactions(ledger_id, |transaction| {
+            let matches = transaction
+                .transactee
+                .as_ref()
+                .is_some_and(|t| transactees.contains(t));
+            if matches {
+                transaction.transactee = Some(new_transactee.to_owned());
+            }
+            matches
+        })
+    }
+
     async fn get_balance(&self, user: &str) -> Result<Decimal, TransactionRepoError> {
         let sum = self
             .get_all_transactions(user, Filter::NONE, None)
@@ -1,45 +1,96 @@
+use crate::mem_repo::ledger_repo::MemLedgerRepo;
+use crate::mem_repo::ChangeSequence;
 use crate::transaction_template_repo::{
-    NewTransactionTemplate, TransactionTemplate, TransactionTemplateRepo,
-    TransactionTemplateRepoError,
+    ChangedTemplate, DeletedTransactionTemplate, NewTransactionTemplate, TransactionTemplate,
+    TransactionTemplateRepo, TransactionTemplateRepoError,
 };
 use anyhow::anyhow;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use std::collections::{HashMap, HashSet};
-use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 struct State {
     templates: HashMap<i32, TransactionTemplate>,
-    user_templates: HashMap<String, HashSet<i32>>,
+    ledger_templates: HashMap<i32, HashSet<i32>>,
+    deleted_templates: HashMap<i32, HashMap<i32, DeletedTransactionTemplate>>,
+    /// The latest change of each template, including deleted ones
+    changes: HashMap<i32, i64>,
+    last_purged_changes: HashMap<i32, i64>,
     next_id: i32,
 }
 
 pub struct MemTransactionTemplateRepo {
     state: RwLock<State>,
+    change_sequence: Arc<ChangeSequence>,
+    ledger_repo: Arc<MemLedgerRepo>,
 }
 
 impl MemTransactionTemplateRepo {
-    pub fn new() -> Self {
+    pub(crate) fn new(
+        change_sequence: Arc<ChangeSequence>,
+        ledger_repo: Arc<MemLedgerRepo>,
+    ) -> Self {
         let state = State {
             templates: HashMap::new(),
-            user_templates: HashMap::new(),
+            ledger_templates: HashMap::new(),
+            deleted_templates: HashMap::new(),
+            changes: HashMap::new(),
+            last_purged_changes: HashMap::new(),
             next_id: 0,
         };
         MemTransactionTemplateRepo {
             state: RwLock::new(state),
+            change_sequence,
+            ledger_repo,
         }
     }
 
-    fn read_lock(&self) -> Result<RwLockReadGuard<State>, anyhow::Error> {
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
         self.state
             .read()
             .map_err(|_| anyhow!("Unable to acquire lock"))
     }
 
-    fn write_lock(&self) -> Result<RwLockWriteGuard<State>, anyhow::Error> {
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
         self.state
             .write()
             .map_err(|_| anyhow!("Unable to acquire lock"))
     }
+
+    fn current_ledger(&self, user_id: &str) -> Result<Option<i32>, TransactionTemplateRepoError> {
+        Ok(self
+            .ledger_repo
+            .current_ledger(user_id)?
+            .map(|ledger| ledger.id))
+    }
+
+    /// The user's current ledger, if they can change its templates
+    fn writable_ledger(&self, user_id: &str) -> Result<i32, TransactionTemplateRepoError> {
+        match self.ledger_repo.current_ledger(user_id)? {
+            Some(ledger) if ledger.role.can_write() => Ok(ledger.id),
+            _ => Err(TransactionTemplateRepoError::NotPermitted),
+        }
+    }
+
+    fn current_version(
+        state: &State,
+        ledger_id: i32,
+        template_id: i32,
+    ) -> Result<i32, TransactionTemplateRepoError> {
+        if !state
+            .ledger_templates
+            .get(&ledger_id)
+            .is_some_and(|ids| ids.contains(&template_id))
+        {
+            return Err(TransactionTemplateRepoError::TemplateNotFound(template_id));
+        }
+        Ok(state
+            .templates
+            .get(&template_id)
+            .expect("templates should have all the ids in ledger_templates")
+            .version)
+    }
 }
 
 #[async_trait]
@@ -49,17 +100,19 @@ impl TransactionTemplateRepo for MemTransactionTemplateRepo {
         user_id: &str,
         new_template: NewTransactionTemplate,
     ) -> Result<TransactionTemplate, TransactionTemplateRepoError> {
+        let ledger_id = self.writable_ledger(user_id)?;
         let mut write_guard = self.write_lock()?;
 
         let id = write_guard.next_id;
         write_guard.next_id += 1;
 
-        let template = new_template.to_transaction_template(id);
+        let template = new_template.to_transaction_template(id, 1);
 
         write_guard.templates.insert(id, template.clone());
+        write_guard.changes.insert(id, self.change_sequence.next());
         write_guard
-            .user_templates
-            .entry(user_id.to_owned())
+            .ledger_templates
+            .entry(ledger_id)
             .or_insert_with(HashSet::new)
             .insert(id);
 
@@ -71,19 +124,48 @@ impl TransactionTemplateRepo for MemTransactionTemplateRepo {
         user_id: &str,
         template_id: i32,
         template: NewTransactionTemplate,
+        expected_version: Option<i32>,
     ) -> Result<TransactionTemplate, TransactionTemplateRepoError> {
+        let ledger_id = self.writable_ledger(user_id)?;
         let mut write_guard = self.write_lock()?;
 
-        let Some(template_ids) = write_guard.user_templates.get_mut(user_id) else {
+        let version = Self::current_version(&write_guard, ledger_id, template_id)?;
+        if expected_version.is_some_and(|v| v != version) {
+            return Err(TransactionTemplateRepoError::VersionMismatch(template_id));
+        }
+
+        let template = template.to_transaction_template(template_id, version + 1);
+        write_guard.templates.insert(template_id, template.clone());
+        write_guard
+            .changes
+            .insert(template_id, self.change_sequence.next());
+
+        Ok(template)
+    }
+
+    async fn get_template(
+        &self,
+        user_id: &str,
+        template_id: i32,
+    ) -> Result<TransactionTemplate, TransactionTemplateRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id)? else {
             return Err(TransactionTemplateRepoError::TemplateNotFound(template_id));
         };
-        if !template_ids.contains(&template_id) {
+        let read_guard = self.read_lock()?;
+
+        if !read_guard
+            .ledger_templates
+            .get(&ledger_id)
+            .is_some_and(|ids| ids.contains(&template_id))
+        {
             return Err(TransactionTemplateRepoError::TemplateNotFound(template_id));
         }
 
-        let template = template.to_transaction_template(template_id);
-        write_guard.templates.insert(template_id, template.clone());
-
+        let template = read_guard
+            .templates
+            .get(&template_id)
+            .expect("templates should have all the ids in ledger_templates")
+            .clone();
         Ok(template)
     }
 
@@ -91,19 +173,22 @@ impl TransactionTemplateRepo for MemTransactionTemplateRepo {
         &self,
         user_id: &str,
     ) -> Result<Vec<TransactionTemplate>, TransactionTemplateRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id)? else {
+            return Ok(Vec::new());
+        };
         let read_guard = self.read_lock()?;
 
-        let Some(template_ids) = read_guard.user_templates.get(user_id) else {
+        let Some(template_ids) = read_guard.ledger_templates.get(&ledger_id) else {
             return Ok(Vec::new());
         };
 
         let templates = template_ids
-            .into_iter()
+            .iter()
             .map(|id| {
                 read_guard
                     .templates
                     .get(id)
-                    .expect("templates should have all the ids in user_templates")
+                    .expect("templates should have all the ids in ledger_templates")
             })
             .cloned()
             .collect();
@@ -115,20 +200,279 @@ impl TransactionTemplateRepo for MemTransactionTemplateRepo {
         &self,
         user_id: &str,
         template_id: i32,
+        expected_version: Option<i32>,
     ) -> Result<TransactionTemplate, TransactionTemplateRepoError> {
+        let ledger_id = self.writable_ledger(user_id)?;
         let mut write_guard = self.write_lock()?;
 
-        let Some(template_ids) = write_guard.user_templates.get_mut(user_id) else {
-            return Err(TransactionTemplateRepoError::TemplateNotFound(template_id));
-        };
-        if !template_ids.remove(&template_id) {
-            return Err(TransactionTemplateRepoError::TemplateNotFound(template_id));
+        let version = Self::current_version(&write_guard, ledger_id, template_id)?;
+        if expected_version.is_some_and(|v| v != version) {
+            return Err(TransactionTemplateRepoError::VersionMismatch(template_id));
         }
 
+        write_guard
+            .ledger_templates
+            .get_mut(&ledger_id)
+            .expect("ledger_templates was checked above")
+            .remove(&template_id);
         let template = write_guard
             .templates
             .remove(&template_id)
-            .expect("template should exist if there is an entry in user_templates");
+            .expect("template should exist if there is an entry in ledger_templates");
+        write_guard
+            .deleted_templates
+            .entry(ledger_id)
+            .or_default()
+            .insert(
+                template_id,
+                DeletedTransactionTemplate {
+                    template: template.clone(),
+                    deleted_at: Utc::now(),
+                },
+            );
+        write_guard
+            .changes
+            .insert(template_id, self.change_sequence.next());
+
+        Ok(template)
+    }
+
+    async fn get_deleted_templates(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<DeletedTransactionTemplate>, TransactionTemplateRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id)? else {
+            return Ok(Vec::new());
+        };
+        let read_guard = self.read_lock()?;
+
+        let mut deleted_templates: Vec<DeletedTransactionTemplate> = read_guard
+            .deleted_templates
+            .get(&ledger_id)
+            .map(|deleted| deleted.values().cloned().collect())
+            .unwrap_or_default();
+        deleted_templates.sort_by(|a, b| {
+            b.deleted_at
+                .cmp(&a.deleted_at)
+                .then_with(|| b.template.template_id.cmp(&a.template.template_id))
+        });
+
+        Ok(deleted_templates)
+    }
+
+    async fn restore_template(
+        &self,
+        user_id: &str,
+        template_id: i32,
+    ) -> Result<TransactionTemplate, TransactionTemplateRepoError> {
+        let ledger_id = self.writable_ledger(user_id)?;
+        let mut write_guard = self.write_lock()?;
+
+        let Some(deleted) = write_guard
+            .deleted_templates
+            .get_mut(&ledger_id)
+            .and_then(|deleted| deleted.remove(&template_id))
+        else {
+            return Err(TransactionTemplateRepoError::TemplateNotFound(template_id));
+        };
+
+        let mut template = deleted.template;
+        template.version += 1;
+        write_guard.templates.insert(template_id, template.clone());
+        write_guard
+            .changes
+            .insert(template_id, self.change_sequence.next());
+        write_guard
+            .ledger_templates
+            .entry(ledger_id)
+            .or_insert_with(HashSet::new)
+            .insert(template_id);
+
         Ok(template)
     }
+
+    async fn purge_deleted_templates(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<u64, TransactionTemplateRepoError> {
+        let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
+
+        let mut count = 0;
+        for (ledger_id, deleted) in state.deleted_templates.iter_mut() {
+            deleted.retain(|id, d| {
+                if d.deleted_at >= deleted_before {
+                    return true;
+                }
+                let change = state
+                    .changes
+                    .remove(id)
+                    .expect("changes should have all template ids");
+                let last_purged = state.last_purged_changes.entry(*ledger_id).or_default();
+                *last_purged = change.max(*last_purged);
+                count += 1;
+                false
+            });
+        }
+
+        Ok(count)
+    }
+
+    async fn get_changed_templates(
+        &self,
+        user_id: &str,
+        since: i64,
+    ) -> Result<Vec<ChangedTemplate>, TransactionTemplateRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id)? else {
+            return Ok(Vec::new());
+        };
+        let read_guard = self.read_lock()?;
+
+        let live = read_guard
+            .ledger_templates
+            .get(&ledger_id)
+            .into_iter()
+            .flatten()
+            .map(|id| {
+                let template = read_guard
+                    .templates
+                    .get(id)
+                    .expect("templates should have all the ids in ledger_templates");
+                (template, false)
+            });
+        let deleted = read_guard
+            .deleted_templates
+            .get(&ledger_id)
+            .into_iter()
+            .flat_map(|deleted| deleted.values())
+            .map(|d| (&d.template, true));
+        let mut changed: Vec<ChangedTemplate> = live
+            .chain(deleted)
+            .map(|(template, deleted)| ChangedTemplate {
+                template: template.clone(),
+                deleted,
+                change: read_guard.changes[&template.template_id],
+            })
+            .filter(|c| c.change > since)
+            .collect();
+        changed.sort_by_key(|c| c.change);
+
+        Ok(changed)
+    }
+
+    async fn get_last_purged_change(
+        &self,
+        user_id: &str,
+    ) -> Result<i64, TransactionTemplateRepoError> {
+        let Some(ledger) = self.ledger_repo.current_ledger(user_id)? else {
+            return Ok(0);
+        };
+        let read_guard = self.read_lock()?;
+
+        let last_purged = read_guard
+            .last_purged_changes
+            .get(&ledger.id)
+            .copied()
+            .unwrap_or_default();
+        Ok(last_purged.max(ledger.since))
+    }
+
+    async fn merge_tags(
+        &self,
+        user_id: &str,
+        tags: &[String],
+        new_tag: &str,
+    ) -> Result<u64, TransactionTemplateRepoError> {
+        let ledger_id = self.writable_ledger(user_id)?;
+        let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
+
+        let Some(template_ids) = state.ledger_templates.get(&ledger_id) else {
+            return Ok(0);
+        };
+
+        let mut count = 0;
+        for id in template_ids {
+            let template = state
+                .templates
+                .get_mut(id)
+                .expect("templates should have all the ids in ledger_templates");
+            let mut changed = false;
+            for tag in tags {
+                changed |= template.tags.remove(tag);
+            }
+            if changed {
+                template.tags.insert(new_tag.to_owned());
+                template.version += 1;
+                state.changes.insert(*id, self.change_sequence.next());
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn delete_tag(
+        &self,
+        user_id: &str,
+        tag: &str,
+    ) -> Result<u64, TransactionTemplateRepoError> {
+        let ledger_id = self.writable_ledger(user_id)?;
+        let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
+
+        let Some(template_ids) = state.ledger_templates.get(&ledger_id) else {
+            return Ok(0);
+        };
+
+        let mut count = 0;
+        for id in template_ids {
+            let template = state
+                .templates
+                .get_mut(id)
+                .expect("templates should have all the ids in ledger_templates");
+            if template.tags.remove(tag) {
+                template.version += 1;
+                state.changes.insert(*id, self.change_sequence.next());
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn merge_transactees(
+        &self,
+        user_id: &str,
+        transactees: &[String],
+        new_transactee: &str,
+    ) -> Result<u64, TransactionTemplateRepoError> {
+        let ledger_id = self.writable_ledger(user_id)?;
+        let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
+
+        let Some(template_ids) = state.ledger_templates.get(&ledger_id) else {
+            return Ok(0);
+        };
+
+        let mut count = 0;
+        for id in template_ids {
+            let template = state
+                .templates
+                .get_mut(id)
+                .expect("templates should have all the ids in ledger_templates");
+            if template
+                .transactee
+                .as_ref()
+                .is_some_and(|t| transactees.contains(t))
+            {
+                template.transactee = Some(new_transactee.to_owned());
+                template.version += 1;
+                state.changes.insert(*id, self.change_sequence.next());
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
 }
@@ -1,82 +1,271 @@
-use crate::user_repo::UserRepoError::{UserAlreadyExists, UserNotFound};
-use crate::user_repo::{User, UserRepo, UserRepoError};
+use crate::mem_repo::ledger_repo::MemLedgerRepo;
+use crate::mem_repo::transaction_repo::MemTransactionRepo;
+use crate::user_repo::UserRepoError::{
+    InvalidInviteCode, InviteCodeNotFound, UserAlreadyExists, UserNotFound,
+};
+use crate::user_repo::{InviteCode, NewInviteCode, User, UserRepo, UserRepoError, UserSummary};
 use anyhow::anyhow;
 use async_trait::async_trait;
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
-use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+struct StoredUser {
+    user: User,
+    last_login_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct InviteCodes {
+    next_id: i32,
+    /// Invite codes with the hash of their code, by id
+    codes: BTreeMap<i32, (String, InviteCode)>,
+}
+
+/// Locks are taken users first, then invite codes
 pub struct MemUserRepo {
-    password_hash: RwLock<HashMap<String, String>>,
+    users: RwLock<BTreeMap<String, StoredUser>>,
+    invite_codes: RwLock<InviteCodes>,
+    ledger_repo: Arc<MemLedgerRepo>,
+    transaction_repo: Arc<MemTransactionRepo>,
 }
 
 impl MemUserRepo {
-    pub fn new() -> MemUserRepo {
+    pub(crate) fn new(
+        ledger_repo: Arc<MemLedgerRepo>,
+        transaction_repo: Arc<MemTransactionRepo>,
+    ) -> MemUserRepo {
         MemUserRepo {
-            password_hash: RwLock::new(HashMap::new()),
+            users: RwLock::new(BTreeMap::new()),
+            invite_codes: RwLock::new(InviteCodes::default()),
+            ledger_repo,
+            transaction_repo,
         }
     }
 
-    fn read_lock(&self) -> Result<RwLockReadGuard<HashMap<String, String>>, anyhow::Error> {
-        self.password_hash
+    fn read_lock(
+        &self,
+    ) -> Result<RwLockReadGuard<'_, BTreeMap<String, StoredUser>>, anyhow::Error> {
+        self.users
             .read()
             .map_err(|_| anyhow!("Unable to acquire lock"))
     }
 
-    fn write_lock(&self) -> Result<RwLockWriteGuard<HashMap<String, String>>, anyhow::Error> {
-        self.password_hash
+    fn write_lock(
+        &self,
+    ) -> Result<RwLockWriteGuard<'_, BTreeMap<String, StoredUser>>, anyhow::Error> {
+        self.users
             .write()
             .map_err(|_| anyhow!("Unable to acquire lock"))
     }
-}
 
-#[async_trait]
-impl UserRepo for MemUserRepo {
-    async fn get_user(&self, user_id: &str) -> Result<User, UserRepoError> {
-        let read_guard = self.read_lock()?;
+    fn invite_codes_lock(&self) -> Result<RwLockWriteGuard<'_, InviteCodes>, anyhow::Error> {
+        self.invite_codes
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
 
-        if let Some(h) = read_guard.get(user_id) {
-            Ok(User::new(user_id.to_string(), h.to_owned()))
-        } else {
-            Err(UserNotFound(user_id.to_owned()))
-        }
+    fn newest_first<'a>(codes: impl Iterator<Item = &'a InviteCode>) -> Vec<InviteCode> {
+        let mut codes: Vec<InviteCode> = codes.cloned().collect();
+        codes.sort_by_key(|code| Reverse((code.created_at, code.id)));
+        codes
     }
 
-    async fn create_user(&self, user: User) -> Result<(), UserRepoError> {
+    /// Creates the user without waiting, so that other repos can create users while they hold
+    /// their own locks
+    pub(crate) fn insert_user(&self, user: User) -> Result<(), UserRepoError> {
         let mut write_guard = self.write_lock()?;
 
         match write_guard.entry(user.id.clone()) {
             Entry::Occupied(_) => Err(UserAlreadyExists(user.id)),
             Entry::Vacant(e) => {
-                e.insert(user.password_hash);
+                let user_id = user.id.clone();
+                e.insert(StoredUser {
+                    user,
+                    last_login_at: None,
+                });
+                self.ledger_repo.create_own_ledger(&user_id)?;
                 Ok(())
             }
         }
     }
 
-    async fn update_password_hash(
+    fn update_user(
         &self,
         user_id: &str,
-        password_hash: &str,
+        update: impl FnOnce(&mut StoredUser),
     ) -> Result<(), UserRepoError> {
         let mut write_guard = self.write_lock()?;
 
-        match write_guard.entry(user_id.to_owned()) {
-            Entry::Occupied(mut e) => {
-                e.insert(password_hash.to_owned());
-                Ok(())
-            }
-            Entry::Vacant(e) => Err(UserNotFound(e.into_key())),
+        let stored = write_guard
+            .get_mut(user_id)
+            .ok_or_else(|| UserNotFound(user_id.to_owned()))?;
+        update(stored);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserRepo for MemUserRepo {
+    async fn get_user(&self, user_id: &str) -> Result<User, UserRepoError> {
+        let read_guard = self.read_lock()?;
+
+        if let Some(stored) = read_guard.get(user_id) {
+            Ok(stored.user.clone())
+        } else {
+            Err(UserNotFound(user_id.to_owned()))
         }
     }
 
+    async fn create_user(&self, user: User) -> Result<(), UserRepoError> {
+        self.insert_user(user)
+    }
+
+    async fn update_password_hash(
+        &self,
+        user_id: &str,
+        password_hash: &str,
+    ) -> Result<(), UserRepoError> {
+        self.update_user(user_id, |stored| {
+            stored.user.password_hash = password_hash.to_owned();
+            stored.user.password_reset_required = false;
+        })
+    }
+
     async fn delete_user(&self, user_id: &str) -> Result<(), UserRepoError> {
         let mut write_guard = self.write_lock()?;
 
         if write_guard.remove(user_id).is_some() {
+            self.invite_codes_lock()?
+                .codes
+                .retain(|_, (_, code)| code.created_by != user_id);
+            self.ledger_repo.remove_user(user_id)?;
+            self.transaction_repo.remove_creator(user_id)?;
             Ok(())
         } else {
             Err(UserNotFound(user_id.to_owned()))
         }
     }
+
+    async fn get_users(&self) -> Result<Vec<UserSummary>, UserRepoError> {
+        let read_guard = self.read_lock()?;
+
+        let mut users = Vec::new();
+        for stored in read_guard.values() {
+            let user = &stored.user;
+            users.push(UserSummary {
+                id: user.id.clone(),
+                admin: user.admin,
+                disabled: user.disabled,
+                password_reset_required: user.password_reset_required,
+                transaction_count: self.transaction_repo.count_created(&user.id)?,
+                last_login_at: stored.last_login_at,
+            });
+        }
+        Ok(users)
+    }
+
+    async fn set_admin(&self, user_id: &str, admin: bool) -> Result<(), UserRepoError> {
+        self.update_user(user_id, |stored| stored.user.admin = admin)
+    }
+
+    async fn set_disabled(&self, user_id: &str, disabled: bool) -> Result<(), UserRepoError> {
+        self.update_user(user_id, |stored| stored.user.disabled = disabled)
+    }
+
+    async fn require_password_reset(&self, user_id: &str) -> Result<(), UserRepoError> {
+        self.update_user(user_id, |stored| stored.user.password_reset_required = true)
+    }
+
+    async fn record_login(&self, user_id: &str) -> Result<(), UserRepoError> {
+        self.update_user(user_id, |stored| stored.last_login_at = Some(Utc::now()))
+    }
+
+    async fn create_user_with_invite(
+        &self,
+        user: User,
+        code_hash: &str,
+    ) -> Result<(), UserRepoError> {
+        let mut write_guard = self.write_lock()?;
+        let mut invite_codes = self.invite_codes_lock()?;
+
+        let now = Utc::now();
+        let (_, invite_code) = invite_codes
+            .codes
+            .values_mut()
+            .find(|(hash, code)| {
+                hash == code_hash && code.uses < code.max_uses && code.expires_at > now
+            })
+            .ok_or(InvalidInviteCode)?;
+        match write_guard.entry(user.id.clone()) {
+            Entry::Occupied(_) => Err(UserAlreadyExists(user.id)),
+            Entry::Vacant(e) => {
+                invite_code.uses += 1;
+                let user_id = user.id.clone();
+                e.insert(StoredUser {
+                    user,
+                    last_login_at: None,
+                });
+                self.ledger_repo.create_own_ledger(&user_id)?;
+                Ok(())
+            }
+        }
+    }
+
+    async fn create_invite_code(
+        &self,
+        created_by: &str,
+        new_invite_code: NewInviteCode,
+        code_hash: &str,
+    ) -> Result<InviteCode, UserRepoError> {
+        let mut invite_codes = self.invite_codes_lock()?;
+
+        invite_codes.next_id += 1;
+        let invite_code = InviteCode {
+            id: invite_codes.next_id,
+            created_by: created_by.to_owned(),
+            max_uses: new_invite_code.max_uses,
+            uses: 0,
+            created_at: Utc::now(),
+            expires_at: new_invite_code.expires_at,
+        };
+        invite_codes
+            .codes
+            .insert(invite_code.id, (code_hash.to_owned(), invite_code.clone()));
+        Ok(invite_code)
+    }
+
+    async fn get_invite_codes(&self, created_by: &str) -> Result<Vec<InviteCode>, UserRepoError> {
+        let invite_codes = self.invite_codes_lock()?;
+
+        Ok(Self::newest_first(
+            invite_codes
+                .codes
+                .values()
+                .map(|(_, code)| code)
+                .filter(|code| code.created_by == created_by),
+        ))
+    }
+
+    async fn get_all_invite_codes(&self) -> Result<Vec<InviteCode>, UserRepoError> {
+        let invite_codes = self.invite_codes_lock()?;
+
+        Ok(Self::newest_first(
+            invite_codes.codes.values().map(|(_, code)| code),
+        ))
+    }
+
+    async fn delete_invite_code(&self, created_by: &str, id: i32) -> Result<(), UserRepoError> {
+        let mut invite_codes = self.invite_codes_lock()?;
+
+        match invite_codes.codes.get(&id) {
+            Some((_, code)) if code.created_by == created_by => {
+                invite_codes.codes.remove(&id);
+                Ok(())
+            }
+            _ => Err(InviteCodeNotFound(id)),
+        }
+    }
 }
@@ -0,0 +1,226 @@
+use crate::webhook_repo::{
+    DeliveryAttempt, DeliveryStatus, DueDelivery, NewWebhook, Webhook, WebhookDelivery,
+    WebhookEvent, WebhookRepo, WebhookRepoError,
+};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+struct StoredWebhook {
+    user_id: String,
+    webhook: Webhook,
+}
+
+struct State {
+    webhooks: HashMap<i32, StoredWebhook>,
+    deliveries: BTreeMap<i64, WebhookDelivery>,
+    next_webhook_id: i32,
+    next_delivery_id: i64,
+}
+
+impl State {
+    fn get_webhook(&self, user_id: &str, webhook_id: i32) -> Result<&Webhook, WebhookRepoError> {
+        self.webhooks
+            .get(&webhook_id)
+            .filter(|stored| stored.user_id == user_id)
+            .map(|stored| &stored.webhook)
+            .ok_or(WebhookRepoError::WebhookNotFound(webhook_id))
+    }
+}
+
+pub struct MemWebhookRepo {
+    state: RwLock<State>,
+}
+
+impl MemWebhookRepo {
+    pub fn new() -> MemWebhookRepo {
+        let state = State {
+            webhooks: HashMap::new(),
+            deliveries: BTreeMap::new(),
+            next_webhook_id: 0,
+            next_delivery_id: 0,
+        };
+        MemWebhookRepo {
+            state: RwLock::new(state),
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, State>, anyhow::Error> {
+        self.state
+            .read()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, State>, anyhow::Error> {
+        self.state
+            .write()
+            .map_err(|_| anyhow!("Unable to acquire lock"))
+    }
+}
+
+#[async_trait]
+impl WebhookRepo for MemWebhookRepo {
+    async fn create_webhook(
+        &self,
+        user_id: &str,
+        new_webhook: NewWebhook,
+        secret: String,
+    ) -> Result<Webhook, WebhookRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        let id = write_guard.next_webhook_id;
+        write_guard.next_webhook_id += 1;
+
+        let webhook = Webhook {
+            id,
+            url: new_webhook.url,
+            secret,
+            events: new_webhook.events,
+            filter: new_webhook.filter,
+            created_at: Utc::now(),
+        };
+        write_guard.webhooks.insert(
+            id,
+            StoredWebhook {
+                user_id: user_id.to_owned(),
+                webhook: webhook.clone(),
+            },
+        );
+        Ok(webhook)
+    }
+
+    async fn get_webhooks(&self, user_id: &str) -> Result<Vec<Webhook>, WebhookRepoError> {
+        let read_guard = self.read_lock()?;
+
+        let mut webhooks: Vec<Webhook> = read_guard
+            .webhooks
+            .values()
+            .filter(|stored| stored.user_id == user_id)
+            .map(|stored| stored.webhook.clone())
+            .collect();
+        webhooks.sort_by_key(|webhook| webhook.id);
+        Ok(webhooks)
+    }
+
+    async fn get_webhook(
+        &self,
+        user_id: &str,
+        webhook_id: i32,
+    ) -> Result<Webhook, WebhookRepoError> {
+        let read_guard = self.read_lock()?;
+        read_guard.get_webhook(user_id, webhook_id).cloned()
+    }
+
+    async fn delete_webhook(&self, user_id: &str, webhook_id: i32) -> Result<(), WebhookRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        write_guard.get_webhook(user_id, webhook_id)?;
+        write_guard.webhooks.remove(&webhook_id);
+        write_guard
+            .deliveries
+            .retain(|_, delivery| delivery.webhook_id != webhook_id);
+        Ok(())
+    }
+
+    async fn queue_delivery(
+        &self,
+        webhook_id: i32,
+        event: WebhookEvent,
+        payload: Value,
+    ) -> Result<WebhookDelivery, WebhookRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        if !write_guard.webhooks.contains_key(&webhook_id) {
+            return Err(WebhookRepoError::WebhookNotFound(webhook_id));
+        }
+        let id = write_guard.next_delivery_id;
+        write_guard.next_delivery_id += 1;
+
+        let now = Utc::now();
+        let delivery = WebhookDelivery {
+            id,
+            webhook_id,
+            event,
+            payload,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_response_status: None,
+            last_error: None,
+            created_at: now,
+        };
+        write_guard.deliveries.insert(id, delivery.clone());
+        Ok(delivery)
+    }
+
+    async fn claim_due_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        claimed_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<DueDelivery>, WebhookRepoError> {
+        let mut write_guard = self.write_lock()?;
+        let state = &mut *write_guard;
+
+        let mut due: Vec<&mut WebhookDelivery> = state
+            .deliveries
+            .values_mut()
+            .filter(|delivery| {
+                delivery.status == DeliveryStatus::Pending && delivery.next_attempt_at <= now
+            })
+            .collect();
+        due.sort_by_key(|delivery| (delivery.next_attempt_at, delivery.id));
+
+        let mut claimed = Vec::new();
+        for delivery in due.into_iter().take(limit as usize) {
+            delivery.next_attempt_at = claimed_until;
+            let webhook = &state.webhooks[&delivery.webhook_id].webhook;
+            claimed.push(DueDelivery {
+                delivery: delivery.clone(),
+                url: webhook.url.clone(),
+                secret: webhook.secret.clone(),
+            });
+        }
+        Ok(claimed)
+    }
+
+    async fn record_attempt(
+        &self,
+        delivery_id: i64,
+        attempt: DeliveryAttempt,
+    ) -> Result<(), WebhookRepoError> {
+        let mut write_guard = self.write_lock()?;
+
+        // The webhook may have been deleted while the delivery was being sent
+        if let Some(delivery) = write_guard.deliveries.get_mut(&delivery_id) {
+            delivery.status = attempt.status;
+            delivery.attempts += 1;
+            delivery.next_attempt_at = attempt.next_attempt_at;
+            delivery.last_response_status = attempt.response_status;
+            delivery.last_error = attempt.error;
+        }
+        Ok(())
+    }
+
+    async fn get_deliveries(
+        &self,
+        user_id: &str,
+        webhook_id: i32,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, WebhookRepoError> {
+        let read_guard = self.read_lock()?;
+
+        read_guard.get_webhook(user_id, webhook_id)?;
+        Ok(read_guard
+            .deliveries
+            .values()
+            .rev()
+            .filter(|delivery| delivery.webhook_id == webhook_id)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,59 @@
+use crate::user_repo::User;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+/// A login started with an OpenID Connect provider, waiting for the provider to redirect back
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct OidcLogin {
+    pub state: String,
+    pub code_verifier: String,
+    pub nonce: String,
+}
+
+#[derive(Error, Debug)]
+pub enum OidcRepoError {
+    #[error("Subject {1} of {0} is already linked")]
+    IdentityAlreadyLinked(String, String),
+    #[error("User {0} already exists")]
+    UserAlreadyExists(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Logins in progress with OpenID Connect providers, and the users that their subjects are linked
+/// to
+#[async_trait]
+pub trait OidcRepo: Sync + Send {
+    /// Saves a login until the provider redirects back. Logins started before `expire_before` are
+    /// deleted, as they can't be finished anymore.
+    async fn create_login(
+        &self,
+        login: OidcLogin,
+        expire_before: DateTime<Utc>,
+    ) -> Result<(), OidcRepoError>;
+
+    /// Removes and returns the login for `state`, so that it can only be finished once. Logins
+    /// started before `expire_before` aren't returned.
+    async fn take_login(
+        &self,
+        state: &str,
+        expire_before: DateTime<Utc>,
+    ) -> Result<Option<OidcLogin>, OidcRepoError>;
+
+    /// The id of the user linked to the provider's subject
+    async fn get_linked_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<String>, OidcRepoError>;
+
+    /// Creates the user, linked to the provider's subject. Neither is saved if the subject is
+    /// already linked or there's already a user with the id.
+    async fn create_linked_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+        user: User,
+    ) -> Result<(), OidcRepoError>;
+}
@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+/// Consecutive failed logins for a user or a client address
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FailedLogins {
+    pub failures: i32,
+    pub last_failure_at: DateTime<Utc>,
+}
+
+#[derive(Error, Debug)]
+pub enum RateLimitRepoError {
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Counters for rate limiting and login lockouts. Keys are chosen by the caller, and name what is
+/// being limited, like a user or a client address.
+#[async_trait]
+pub trait RateLimitRepo: Sync + Send {
+    /// Counts a request in the window starting at `window_start`, returning the number of
+    /// requests counted in that window so far
+    async fn count_request(
+        &self,
+        key: &str,
+        window_start: DateTime<Utc>,
+    ) -> Result<i64, RateLimitRepoError>;
+
+    async fn get_failed_logins(
+        &self,
+        key: &str,
+    ) -> Result<Option<FailedLogins>, RateLimitRepoError>;
+
+    /// Counts a failed login. Failures from before `reset_before` are forgotten, so the count
+    /// starts again from one.
+    async fn record_failed_login(
+        &self,
+        key: &str,
+        reset_before: DateTime<Utc>,
+    ) -> Result<FailedLogins, RateLimitRepoError>;
+
+    async fn clear_failed_logins(&self, key: &str) -> Result<(), RateLimitRepoError>;
+
+    /// Deletes request counts from windows that started before `before`, and failed logins last
+    /// recorded before it. Returns how many were deleted.
+    async fn purge_counters(&self, before: DateTime<Utc>) -> Result<u64, RateLimitRepoError>;
+}
@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+/// The user and token family a refresh token belongs to. A family starts when a user logs in and
+/// holds every token that was rotated from the first one.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RefreshTokenOwner {
+    pub user_id: String,
+    pub family_id: String,
+}
+
+#[derive(Error, Debug)]
+pub enum RefreshTokenRepoError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token of family {0} was used more than once")]
+    TokenReused(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Refresh tokens are only stored as hashes, so the repo never sees a usable token
+#[async_trait]
+pub trait RefreshTokenRepo: Sync + Send {
+    /// Starts a token family with its first token
+    async fn create_family(
+        &self,
+        user_id: &str,
+        family_id: &str,
+        token_hash: &str,
+    ) -> Result<(), RefreshTokenRepoError>;
+
+    /// Replaces a token with the next token of its family. Each token can only be used once, so
+    /// using a token again revokes its family, as the token may have been stolen, and fails with
+    /// [RefreshTokenRepoError::TokenReused]. Tokens created before `issued_after` and tokens of
+    /// revoked families are not found.
+    async fn rotate_token(
+        &self,
+        token_hash: &str,
+        new_token_hash: &str,
+        issued_after: DateTime<Utc>,
+    ) -> Result<RefreshTokenOwner, RefreshTokenRepoError>;
+
+    /// Revokes the family of the token, if the token exists
+    async fn revoke_family(&self, token_hash: &str) -> Result<(), RefreshTokenRepoError>;
+
+    async fn revoke_all_families(&self, user_id: &str) -> Result<(), RefreshTokenRepoError>;
+
+    /// Deletes the tokens created before `created_before`, along with the families left without
+    /// tokens, returning how many tokens were deleted
+    async fn purge_tokens(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<u64, RefreshTokenRepoError>;
+}
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AmountSign {
+    Positive,
+    Negative,
+}
+
+impl Display for AmountSign {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let sign = match self {
+            AmountSign::Positive => "positive",
+            AmountSign::Negative => "negative",
+        };
+        f.write_str(sign)
+    }
+}
+
+impl FromStr for AmountSign {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "positive" => Ok(AmountSign::Positive),
+            "negative" => Ok(AmountSign::Negative),
+            _ => Err(anyhow::anyhow!("Invalid amount sign {}", s)),
+        }
+    }
+}
+
+/// Conditions a transaction has to meet for a rule to apply. Conditions that are not set always
+/// match. `transactee` and `note` are regex patterns, and the amount range is checked against the
+/// absolute amount so that it can be combined with `sign`.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct RuleConditions {
+    pub transactee: Option<String>,
+    pub note: Option<String>,
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+    pub sign: Option<AmountSign>,
+    pub day_of_month: Option<i32>,
+}
+
+/// Changes made to a transaction when a rule applies. Tags are added to the existing tags.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct RuleActions {
+    pub category: Option<String>,
+    pub transactee: Option<String>,
+    #[serde(default)]
+    pub tags: HashSet<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Rule {
+    pub id: i32,
+    pub name: String,
+    pub priority: i32,
+    pub conditions: RuleConditions,
+    pub actions: RuleActions,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NewRule {
+    pub name: String,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub conditions: RuleConditions,
+    #[serde(default)]
+    pub actions: RuleActions,
+}
+
+impl NewRule {
+    pub fn new(
+        name: String,
+        priority: i32,
+        conditions: RuleConditions,
+        actions: RuleActions,
+    ) -> NewRule {
+        NewRule {
+            name,
+            priority,
+            conditions,
+            actions,
+        }
+    }
+
+    pub fn to_rule(self, id: i32) -> Rule {
+        Rule {
+            id,
+            name: self.name,
+            priority: self.priority,
+            conditions: self.conditions,
+            actions: self.actions,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RuleRepoError {
+    #[error("Rule with id {0} not found")]
+    RuleNotFound(i32),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[async_trait]
+pub trait RuleRepo: Sync + Send {
+    /// Gets all rules in the order they should be evaluated, i.e. highest priority first
+    async fn get_rules(&self, user_id: &str) -> Result<Vec<Rule>, RuleRepoError>;
+
+    async fn create_rule(&self, user_id: &str, new_rule: NewRule) -> Result<Rule, RuleRepoError>;
+
+    async fn update_rule(
+        &self,
+        user_id: &str,
+        rule_id: i32,
+        rule: NewRule,
+    ) -> Result<Rule, RuleRepoError>;
+
+    async fn delete_rule(&self, user_id: &str, rule_id: i32) -> Result<Rule, RuleRepoError>;
+}
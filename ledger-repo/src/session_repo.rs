@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use thiserror::Error;
+
+/// A login on one device. Each session is a refresh token family, and shares its id.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct Session {
+    pub id: String,
+    pub device_name: Option<String>,
+    /// The address the session was last used from
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+#[derive(Error, Debug)]
+pub enum SessionRepoError {
+    #[error("Session {0} not found")]
+    SessionNotFound(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[async_trait]
+pub trait SessionRepo: Sync + Send {
+    /// Records where a session was started from. The session's token family has to exist.
+    async fn start_session(
+        &self,
+        session_id: &str,
+        device_name: Option<String>,
+        ip_address: Option<String>,
+    ) -> Result<(), SessionRepoError>;
+
+    /// Records that a session was used just now
+    async fn touch_session(
+        &self,
+        session_id: &str,
+        ip_address: Option<String>,
+    ) -> Result<(), SessionRepoError>;
+
+    /// Gets the user's sessions that have not been revoked and were used after `used_after`, most
+    /// recently used first
+    async fn get_sessions(
+        &self,
+        user_id: &str,
+        used_after: DateTime<Utc>,
+    ) -> Result<Vec<Session>, SessionRepoError>;
+
+    /// Revokes the session's token family, logging the device out
+    async fn revoke_session(&self, user_id: &str, session_id: &str)
+        -> Result<(), SessionRepoError>;
+}
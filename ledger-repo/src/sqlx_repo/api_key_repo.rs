@@ -0,0 +1,117 @@
+use crate::api_key_repo::{ApiKey, ApiKeyOwner, ApiKeyRepo, ApiKeyRepoError, NewApiKey};
+use crate::sqlx_repo::SQLxRepo;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as};
+use tracing::instrument;
+
+struct ApiKeyEntry {
+    id: i32,
+    name: String,
+    scopes: Vec<String>,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<ApiKeyEntry> for ApiKey {
+    type Error = ApiKeyRepoError;
+
+    fn try_from(value: ApiKeyEntry) -> Result<Self, Self::Error> {
+        Ok(ApiKey {
+            id: value.id,
+            name: value.name,
+            scopes: value
+                .scopes
+                .iter()
+                .map(|scope| scope.parse())
+                .collect::<Result<_, _>>()?,
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+        })
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepo for SQLxRepo {
+    #[instrument(skip(self, key_hash))]
+    async fn create_api_key(
+        &self,
+        user_id: &str,
+        new_api_key: NewApiKey,
+        key_hash: &str,
+    ) -> Result<ApiKey, ApiKeyRepoError> {
+        let scopes: Vec<String> = new_api_key.scopes.iter().map(|s| s.to_string()).collect();
+        query_as!(
+            ApiKeyEntry,
+            "INSERT INTO api_keys(user_id, name, key_hash, scopes, expires_at) VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, name, scopes, created_at, expires_at",
+            user_id,
+            new_api_key.name,
+            key_hash,
+            scopes.as_slice(),
+            new_api_key.expires_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Unable to create API key")?
+        .try_into()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_api_keys(&self, user_id: &str) -> Result<Vec<ApiKey>, ApiKeyRepoError> {
+        let entries = query_as!(
+            ApiKeyEntry,
+            "SELECT id, name, scopes, created_at, expires_at FROM api_keys WHERE user_id = $1 ORDER BY id",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Unable to get API keys")?;
+
+        entries.into_iter().map(|entry| entry.try_into()).collect()
+    }
+
+    #[instrument(skip(self, key_hash))]
+    async fn find_api_key(&self, key_hash: &str) -> Result<Option<ApiKeyOwner>, ApiKeyRepoError> {
+        let entry = query!(
+            "SELECT id, user_id, name, scopes, created_at, expires_at FROM api_keys WHERE key_hash = $1",
+            key_hash
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Unable to find API key")?;
+
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+        let api_key = ApiKeyEntry {
+            id: entry.id,
+            name: entry.name,
+            scopes: entry.scopes,
+            created_at: entry.created_at,
+            expires_at: entry.expires_at,
+        };
+        Ok(Some(ApiKeyOwner {
+            user_id: entry.user_id,
+            api_key: api_key.try_into()?,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_api_key(&self, user_id: &str, api_key_id: i32) -> Result<(), ApiKeyRepoError> {
+        let result = query!(
+            "DELETE FROM api_keys WHERE user_id = $1 AND id = $2",
+            user_id,
+            api_key_id
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to delete API key {}", api_key_id))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyRepoError::ApiKeyNotFound(api_key_id));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::category_repo::{Category, CategoryRepo, CategoryRepoError};
+use crate::sqlx_repo::SQLxRepo;
+use anyhow::Context;
+use async_trait::async_trait;
+use sqlx::query_as;
+use tracing::instrument;
+
+struct CategoryEntry {
+    #[allow(dead_code)]
+    user_id: String,
+    name: String,
+    kind: String,
+    color: Option<String>,
+    icon: Option<String>,
+    archived: bool,
+}
+
+impl TryFrom<CategoryEntry> for Category {
+    type Error = CategoryRepoError;
+
+    fn try_from(value: CategoryEntry) -> Result<Self, Self::Error> {
+        Ok(Category::new(
+            value.name,
+            value.kind.parse()?,
+            value.color,
+            value.icon,
+            value.archived,
+        ))
+    }
+}
+
+#[async_trait]
+impl CategoryRepo for SQLxRepo {
+    #[instrument(skip(self))]
+    async fn get_categories(&self, user_id: &str) -> Result<Vec<Category>, CategoryRepoError> {
+        let category_entries = query_as!(
+            CategoryEntry,
+            "SELECT * FROM categories WHERE user_id = $1 ORDER BY name",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get categories for user {}", user_id))?;
+
+        category_entries.into_iter().map(|c| c.try_into()).collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_category(&self, user_id: &str, name: &str) -> Result<Category, CategoryRepoError> {
+        query_as!(
+            CategoryEntry,
+            "SELECT * FROM categories WHERE user_id = $1 AND name = $2",
+            user_id,
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get category {}", name))?
+        .ok_or_else(|| CategoryRepoError::CategoryNotFound(name.to_owned()))?
+        .try_into()
+    }
+
+    #[instrument(skip(self))]
+    async fn set_category(
+        &self,
+        user_id: &str,
+        category: Category,
+    ) -> Result<Category, CategoryRepoError> {
+        query_as!(
+            CategoryEntry,
+            "INSERT INTO categories(user_id, name, kind, color, icon, archived) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (user_id, name) DO UPDATE SET kind = $3, color = $4, icon = $5, archived = $6 RETURNING *",
+            user_id,
+            category.name,
+            category.kind.to_string(),
+            category.color,
+            category.icon,
+            category.archived
+        )
+        .fetch_one(&self.pool)
+        .await
+        .with_context(|| format!("Unable to set category {}", category.name))?
+        .try_into()
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_category(
+        &self,
+        user_id: &str,
+        name: &str,
+    ) -> Result<Category, CategoryRepoError> {
+        query_as!(
+            CategoryEntry,
+            "DELETE FROM categories WHERE user_id = $1 AND name = $2 RETURNING *",
+            user_id,
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("Unable to delete category {}", name))?
+        .ok_or_else(|| CategoryRepoError::CategoryNotFound(name.to_owned()))?
+        .try_into()
+    }
+}
@@ -0,0 +1,38 @@
+use crate::sqlx_repo::SQLxRepo;
+use anyhow::{Context, Result};
+use sqlx::postgres::PgListener;
+
+const CHANGES_CHANNEL: &str = "ledger_changes";
+
+/// Receives the ids of users whose transactions or templates were changed through any connection
+/// to the database, including ones from other servers
+pub struct ChangeListener {
+    listener: PgListener,
+}
+
+impl ChangeListener {
+    /// Waits for the next change. If the connection is lost it reconnects, but changes made while
+    /// disconnected are not received.
+    pub async fn recv(&mut self) -> Result<String> {
+        let notification = self
+            .listener
+            .recv()
+            .await
+            .context("Unable to receive change notification")?;
+        Ok(notification.payload().to_string())
+    }
+}
+
+impl SQLxRepo {
+    /// The listener keeps one of the pool's connections for as long as it lives
+    pub async fn listen_for_changes(&self) -> Result<ChangeListener> {
+        let mut listener = PgListener::connect_with(&self.pool)
+            .await
+            .context("Unable to connect change listener")?;
+        listener
+            .listen(CHANGES_CHANNEL)
+            .await
+            .context("Unable to listen for changes")?;
+        Ok(ChangeListener { listener })
+    }
+}
@@ -0,0 +1,48 @@
+use crate::duplicate_repo::{DuplicateRepo, DuplicateRepoError};
+use crate::sqlx_repo::SQLxRepo;
+use anyhow::Context;
+use async_trait::async_trait;
+use sqlx::query;
+use tracing::instrument;
+
+#[async_trait]
+impl DuplicateRepo for SQLxRepo {
+    #[instrument(skip(self))]
+    async fn get_dismissed_pairs(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<(i32, i32)>, DuplicateRepoError> {
+        let pairs = query!(
+            "SELECT transaction_id, other_transaction_id FROM dismissed_duplicates WHERE user_id = $1 ORDER BY transaction_id, other_transaction_id",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get dismissed duplicates for user {}", user_id))?
+        .into_iter()
+        .map(|r| (r.transaction_id, r.other_transaction_id))
+        .collect();
+
+        Ok(pairs)
+    }
+
+    #[instrument(skip(self))]
+    async fn dismiss_pair(
+        &self,
+        user_id: &str,
+        transaction_id: i32,
+        other_transaction_id: i32,
+    ) -> Result<(), DuplicateRepoError> {
+        query!(
+            "INSERT INTO dismissed_duplicates(user_id, transaction_id, other_transaction_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            user_id,
+            transaction_id.min(other_transaction_id),
+            transaction_id.max(other_transaction_id)
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to dismiss duplicate")?;
+
+        Ok(())
+    }
+}
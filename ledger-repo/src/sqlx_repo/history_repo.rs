@@ -0,0 +1,118 @@
+use crate::history_repo::{HistoryRepo, HistoryRepoError, NewTransactionChange, TransactionChange};
+use crate::sqlx_repo::SQLxRepo;
+use crate::transaction_repo::Transaction;
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::query_as;
+use sqlx::types::Json;
+use tracing::instrument;
+
+struct TransactionChangeEntry {
+    id: i32,
+    transaction_id: i32,
+    kind: String,
+    before: Option<Json<Transaction>>,
+    after: Option<Json<Transaction>>,
+    changed_at: DateTime<Utc>,
+    changed_by: String,
+    client: Option<String>,
+}
+
+impl TryFrom<TransactionChangeEntry> for TransactionChange {
+    type Error = HistoryRepoError;
+
+    fn try_from(value: TransactionChangeEntry) -> Result<Self, Self::Error> {
+        Ok(TransactionChange {
+            id: value.id,
+            transaction_id: value.transaction_id,
+            kind: value.kind.parse()?,
+            before: value.before.map(|t| t.0),
+            after: value.after.map(|t| t.0),
+            changed_at: value.changed_at,
+            changed_by: value.changed_by,
+            client: value.client,
+        })
+    }
+}
+
+#[async_trait]
+impl HistoryRepo for SQLxRepo {
+    #[instrument(skip(self, change))]
+    async fn record_change(
+        &self,
+        user_id: &str,
+        change: NewTransactionChange,
+    ) -> Result<TransactionChange, HistoryRepoError> {
+        let ledger_id = self
+            .current_ledger(user_id)
+            .await?
+            .ok_or_else(|| anyhow!("User {} has no current ledger", user_id))?;
+        query_as!(
+            TransactionChangeEntry,
+            r#"INSERT INTO transaction_history(ledger_id, user_id, transaction_id, kind, before, after, changed_by, client)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, transaction_id, kind, before as "before: Json<Transaction>", after as "after: Json<Transaction>", changed_at, changed_by, client"#,
+            ledger_id,
+            user_id,
+            change.transaction_id,
+            change.kind.to_string(),
+            change.before.map(Json) as Option<Json<Transaction>>,
+            change.after.map(Json) as Option<Json<Transaction>>,
+            change.changed_by,
+            change.client,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .with_context(|| format!("Unable to record change to transaction {}", change.transaction_id))?
+        .try_into()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_transaction_history(
+        &self,
+        user_id: &str,
+        transaction_id: i32,
+    ) -> Result<Vec<TransactionChange>, HistoryRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id).await? else {
+            return Ok(vec![]);
+        };
+        let entries = query_as!(
+            TransactionChangeEntry,
+            r#"SELECT id, transaction_id, kind, before as "before: Json<Transaction>", after as "after: Json<Transaction>", changed_at, changed_by, client
+            FROM transaction_history WHERE ledger_id = $1 AND transaction_id = $2 ORDER BY id"#,
+            ledger_id,
+            transaction_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get history of transaction {}", transaction_id))?;
+
+        entries.into_iter().map(|e| e.try_into()).collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_change(
+        &self,
+        user_id: &str,
+        transaction_id: i32,
+        change_id: i32,
+    ) -> Result<TransactionChange, HistoryRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id).await? else {
+            return Err(HistoryRepoError::ChangeNotFound(change_id));
+        };
+        query_as!(
+            TransactionChangeEntry,
+            r#"SELECT id, transaction_id, kind, before as "before: Json<Transaction>", after as "after: Json<Transaction>", changed_at, changed_by, client
+            FROM transaction_history WHERE ledger_id = $1 AND transaction_id = $2 AND id = $3"#,
+            ledger_id,
+            transaction_id,
+            change_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get change {}", change_id))?
+        .ok_or(HistoryRepoError::ChangeNotFound(change_id))?
+        .try_into()
+    }
+}
@@ -0,0 +1,146 @@
+use crate::idempotency_repo::{
+    IdempotencyRecord, IdempotencyRepo, IdempotencyRepoError, StoredResponse,
+};
+use crate::sqlx_repo::SQLxRepo;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as};
+use tracing::instrument;
+
+struct IdempotencyEntry {
+    request_path: String,
+    request_hash: Option<String>,
+    response_status: Option<i32>,
+    response_body: Option<String>,
+}
+
+impl From<IdempotencyEntry> for IdempotencyRecord {
+    fn from(value: IdempotencyEntry) -> Self {
+        let response = match (value.response_status, value.response_body) {
+            (Some(status), Some(body)) => Some(StoredResponse {
+                status: status as u16,
+                body,
+            }),
+            _ => None,
+        };
+        IdempotencyRecord {
+            request_path: value.request_path,
+            request_hash: value.request_hash,
+            response,
+        }
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepo for SQLxRepo {
+    #[instrument(skip(self))]
+    async fn start_request(
+        &self,
+        user_id: &str,
+        key: &str,
+        request_path: &str,
+        request_hash: &str,
+        expired_before: DateTime<Utc>,
+        abandoned_before: DateTime<Utc>,
+    ) -> Result<Option<IdempotencyRecord>, IdempotencyRepoError> {
+        loop {
+            let claimed = query!(
+                "INSERT INTO idempotency_keys(user_id, key, request_path, request_hash) VALUES ($1, $2, $3, $4) ON CONFLICT (user_id, key) DO UPDATE SET request_path = EXCLUDED.request_path, request_hash = EXCLUDED.request_hash, response_status = NULL, response_body = NULL, created_at = NOW() WHERE idempotency_keys.created_at < $5 OR (idempotency_keys.response_status IS NULL AND idempotency_keys.created_at < $6)",
+                user_id,
+                key,
+                request_path,
+                request_hash,
+                expired_before,
+                abandoned_before
+            )
+            .execute(&self.pool)
+            .await
+            .context("Unable to claim idempotency key")?
+            .rows_affected()
+                > 0;
+            if claimed {
+                return Ok(None);
+            }
+
+            let existing = query_as!(
+                IdempotencyEntry,
+                "SELECT request_path, request_hash, response_status, response_body FROM idempotency_keys WHERE user_id = $1 AND key = $2",
+                user_id,
+                key
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .context("Unable to get idempotency key")?;
+            // The record may have been abandoned in between, in which case the key is free again
+            if let Some(existing) = existing {
+                return Ok(Some(existing.into()));
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn renew_request(&self, user_id: &str, key: &str) -> Result<(), IdempotencyRepoError> {
+        query!(
+            "UPDATE idempotency_keys SET created_at = NOW() WHERE user_id = $1 AND key = $2 AND response_status IS NULL",
+            user_id,
+            key
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to renew idempotency key")?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, response))]
+    async fn complete_request(
+        &self,
+        user_id: &str,
+        key: &str,
+        response: StoredResponse,
+    ) -> Result<(), IdempotencyRepoError> {
+        query!(
+            "UPDATE idempotency_keys SET response_status = $3, response_body = $4 WHERE user_id = $1 AND key = $2",
+            user_id,
+            key,
+            response.status as i32,
+            response.body
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to store idempotent response")?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn abandon_request(&self, user_id: &str, key: &str) -> Result<(), IdempotencyRepoError> {
+        query!(
+            "DELETE FROM idempotency_keys WHERE user_id = $1 AND key = $2 AND response_status IS NULL",
+            user_id,
+            key
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to abandon idempotency key")?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn purge_records(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<u64, IdempotencyRepoError> {
+        let result = query!(
+            "DELETE FROM idempotency_keys WHERE created_at < $1",
+            created_before
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to purge idempotency keys")?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,475 @@
+use crate::ledger_repo::LedgerRepoError::{
+    AlreadyMember, InvitationNotFound, LedgerNotFound, MemberNotFound,
+};
+use crate::ledger_repo::{Ledger, LedgerMember, LedgerRepo, LedgerRepoError, LedgerRole};
+use crate::sqlx_repo::SQLxRepo;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar, PgConnection};
+use tracing::instrument;
+
+struct LedgerEntry {
+    id: i32,
+    name: String,
+    role: String,
+    current: bool,
+}
+
+impl TryFrom<LedgerEntry> for Ledger {
+    type Error = LedgerRepoError;
+
+    fn try_from(value: LedgerEntry) -> Result<Self, Self::Error> {
+        Ok(Ledger {
+            id: value.id,
+            name: value.name,
+            role: value.role.parse()?,
+            current: value.current,
+        })
+    }
+}
+
+struct MemberEntry {
+    user_id: String,
+    role: String,
+}
+
+impl TryFrom<MemberEntry> for LedgerMember {
+    type Error = LedgerRepoError;
+
+    fn try_from(value: MemberEntry) -> Result<Self, Self::Error> {
+        Ok(LedgerMember {
+            user_id: value.user_id,
+            role: value.role.parse()?,
+        })
+    }
+}
+
+impl SQLxRepo {
+    /// The user's current ledger, if they have one
+    pub(super) async fn current_ledger(&self, user_id: &str) -> anyhow::Result<Option<i32>> {
+        let ledger_id = query_scalar!(
+            "SELECT ledger_id FROM ledger_members WHERE user_id = $1 AND current",
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get current ledger of {}", user_id))?;
+        Ok(ledger_id)
+    }
+
+    /// The user's current ledger, if they can change its transactions and templates. The ledger
+    /// stays locked until the transaction ends: numbering a change locks it as well, so writers
+    /// take that lock before any row they change, or two of them could wait on each other.
+    pub(super) async fn lock_writable_ledger(
+        conn: &mut PgConnection,
+        user_id: &str,
+    ) -> anyhow::Result<Option<i32>> {
+        let membership = query!(
+            "SELECT m.ledger_id, m.role FROM ledger_members m JOIN ledgers l ON l.id = m.ledger_id WHERE m.user_id = $1 AND m.current FOR UPDATE OF l",
+            user_id
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .with_context(|| format!("Unable to get current ledger of {}", user_id))?;
+        let Some(membership) = membership else {
+            return Ok(None);
+        };
+        let role: LedgerRole = membership.role.parse()?;
+        Ok(role.can_write().then_some(membership.ledger_id))
+    }
+
+    /// Creates a ledger owned by the user, which becomes their current ledger if they don't have
+    /// one. Nothing could have been synced before, so it is current since the start.
+    pub(super) async fn insert_ledger(
+        conn: &mut PgConnection,
+        user_id: &str,
+        name: &str,
+    ) -> Result<Ledger, anyhow::Error> {
+        let id = query_scalar!("INSERT INTO ledgers(name) VALUES ($1) RETURNING id", name)
+            .fetch_one(&mut *conn)
+            .await
+            .context("Unable to create ledger")?;
+        let current = query_scalar!(
+            r#"
+            INSERT INTO ledger_members(ledger_id, user_id, role, current)
+            VALUES ($1, $2::VARCHAR, 'owner',
+                    NOT EXISTS(SELECT 1 FROM ledger_members WHERE user_id = $2::VARCHAR AND current))
+            RETURNING current
+            "#,
+            id,
+            user_id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .context("Unable to add ledger owner")?;
+
+        Ok(Ledger {
+            id,
+            name: name.to_owned(),
+            role: LedgerRole::Owner,
+            current,
+        })
+    }
+
+    /// Makes the first ledger of each of the users current, if they don't have a current one
+    async fn ensure_current(conn: &mut PgConnection, user_ids: &[String]) -> anyhow::Result<()> {
+        query!(
+            r#"
+            UPDATE ledger_members m
+            SET current = TRUE, current_since = next_ledger_change(m.ledger_id)
+            FROM (SELECT DISTINCT ON (user_id) ledger_id, user_id
+                  FROM ledger_members
+                  WHERE user_id = ANY ($1)
+                  ORDER BY user_id, ledger_id) first
+            WHERE m.ledger_id = first.ledger_id
+              AND m.user_id = first.user_id
+              AND NOT EXISTS(SELECT 1 FROM ledger_members c WHERE c.user_id = m.user_id AND c.current)
+            "#,
+            user_ids
+        )
+        .execute(conn)
+        .await
+        .context("Unable to set current ledgers")?;
+        Ok(())
+    }
+
+    /// Removes a user that is being deleted from their ledgers. Ledgers without other members are
+    /// deleted, and the earliest remaining member of a ledger left without an owner becomes its
+    /// owner.
+    pub(super) async fn remove_from_ledgers(
+        conn: &mut PgConnection,
+        user_id: &str,
+    ) -> anyhow::Result<()> {
+        query!(
+            r#"
+            DELETE FROM ledgers
+            WHERE id IN (SELECT ledger_id FROM ledger_members
+                         GROUP BY ledger_id
+                         HAVING BOOL_AND(user_id = $1))
+            "#,
+            user_id
+        )
+        .execute(&mut *conn)
+        .await
+        .context("Unable to delete ledgers")?;
+        let ledger_ids = query_scalar!(
+            "DELETE FROM ledger_members WHERE user_id = $1 RETURNING ledger_id",
+            user_id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("Unable to remove ledger memberships")?;
+        query!(
+            r#"
+            UPDATE ledger_members m
+            SET role = 'owner'
+            FROM (SELECT DISTINCT ON (ledger_id) ledger_id, user_id
+                  FROM ledger_members
+                  WHERE ledger_id = ANY ($1)
+                  ORDER BY ledger_id, joined_at, user_id) first
+            WHERE m.ledger_id = first.ledger_id
+              AND m.user_id = first.user_id
+              AND NOT EXISTS(SELECT 1 FROM ledger_members o WHERE o.ledger_id = m.ledger_id AND o.role = 'owner')
+            "#,
+            &ledger_ids
+        )
+        .execute(&mut *conn)
+        .await
+        .context("Unable to promote ledger owners")?;
+        Ok(())
+    }
+
+    async fn get_ledger_entry(
+        conn: &mut PgConnection,
+        user_id: &str,
+        ledger_id: i32,
+    ) -> Result<Ledger, LedgerRepoError> {
+        query_as!(
+            LedgerEntry,
+            "SELECT l.id, l.name, m.role, m.current FROM ledgers l JOIN ledger_members m ON m.ledger_id = l.id WHERE m.user_id = $1 AND l.id = $2",
+            user_id,
+            ledger_id
+        )
+        .fetch_optional(conn)
+        .await
+        .with_context(|| format!("Unable to get ledger {}", ledger_id))?
+        .ok_or(LedgerNotFound(ledger_id))?
+        .try_into()
+    }
+}
+
+#[async_trait]
+impl LedgerRepo for SQLxRepo {
+    #[instrument(skip(self))]
+    async fn get_ledgers(&self, user_id: &str) -> Result<Vec<Ledger>, LedgerRepoError> {
+        query_as!(
+            LedgerEntry,
+            "SELECT l.id, l.name, m.role, m.current FROM ledgers l JOIN ledger_members m ON m.ledger_id = l.id WHERE m.user_id = $1 ORDER BY l.id",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get ledgers of {}", user_id))?
+        .into_iter()
+        .map(|entry| entry.try_into())
+        .collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_ledger(&self, user_id: &str, ledger_id: i32) -> Result<Ledger, LedgerRepoError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .context("Unable to acquire connection")?;
+        Self::get_ledger_entry(&mut conn, user_id, ledger_id).await
+    }
+
+    #[instrument(skip(self))]
+    async fn create_ledger(&self, user_id: &str, name: &str) -> Result<Ledger, LedgerRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger = Self::insert_ledger(&mut tx, user_id, name).await?;
+        tx.commit().await.context("Unable to commit ledger")?;
+        Ok(ledger)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_ledger(&self, ledger_id: i32) -> Result<(), LedgerRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let current_for = query_scalar!(
+            "SELECT user_id FROM ledger_members WHERE ledger_id = $1 AND current",
+            ledger_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .context("Unable to get ledger members")?;
+        let result = query!("DELETE FROM ledgers WHERE id = $1", ledger_id)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Unable to delete ledger {}", ledger_id))?;
+        if result.rows_affected() == 0 {
+            return Err(LedgerNotFound(ledger_id));
+        }
+        Self::ensure_current(&mut tx, &current_for).await?;
+        tx.commit()
+            .await
+            .context("Unable to commit ledger deletion")?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn set_current_ledger(
+        &self,
+        user_id: &str,
+        ledger_id: i32,
+    ) -> Result<(), LedgerRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let current = query_scalar!(
+            "SELECT current FROM ledger_members WHERE ledger_id = $1 AND user_id = $2",
+            ledger_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Unable to get ledger membership")?
+        .ok_or(LedgerNotFound(ledger_id))?;
+        if current {
+            return Ok(());
+        }
+
+        query!(
+            "UPDATE ledger_members SET current = FALSE WHERE user_id = $1 AND current",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to unset current ledger")?;
+        query!(
+            "UPDATE ledger_members SET current = TRUE, current_since = next_ledger_change($1) WHERE ledger_id = $1 AND user_id = $2",
+            ledger_id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to set current ledger")?;
+        tx.commit()
+            .await
+            .context("Unable to commit current ledger")?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_last_change(&self, user_id: &str) -> Result<i64, LedgerRepoError> {
+        let change = query_scalar!(
+            "SELECT l.last_change FROM ledgers l JOIN ledger_members m ON m.ledger_id = l.id WHERE m.user_id = $1 AND m.current",
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Unable to get last change")?;
+        Ok(change.unwrap_or_default())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_members(&self, ledger_id: i32) -> Result<Vec<LedgerMember>, LedgerRepoError> {
+        let members: Vec<LedgerMember> = query_as!(
+            MemberEntry,
+            "SELECT user_id, role FROM ledger_members WHERE ledger_id = $1 ORDER BY joined_at, user_id",
+            ledger_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get members of ledger {}", ledger_id))?
+        .into_iter()
+        .map(|entry| entry.try_into())
+        .collect::<Result<_, _>>()?;
+
+        if members.is_empty() {
+            let exists = query_scalar!(
+                r#"SELECT EXISTS(SELECT 1 FROM ledgers WHERE id = $1) AS "exists!""#,
+                ledger_id
+            )
+            .fetch_one(&self.pool)
+            .await
+            .context("Unable to check ledger")?;
+            if !exists {
+                return Err(LedgerNotFound(ledger_id));
+            }
+        }
+        Ok(members)
+    }
+
+    #[instrument(skip(self))]
+    async fn set_role(
+        &self,
+        ledger_id: i32,
+        user_id: &str,
+        role: LedgerRole,
+    ) -> Result<(), LedgerRepoError> {
+        let result = query!(
+            "UPDATE ledger_members SET role = $3 WHERE ledger_id = $1 AND user_id = $2",
+            ledger_id,
+            user_id,
+            role.to_string()
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to set role of {}", user_id))?;
+        if result.rows_affected() == 0 {
+            return Err(MemberNotFound(user_id.to_owned()));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn remove_member(&self, ledger_id: i32, user_id: &str) -> Result<(), LedgerRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let current = query_scalar!(
+            "DELETE FROM ledger_members WHERE ledger_id = $1 AND user_id = $2 RETURNING current",
+            ledger_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Unable to remove member {}", user_id))?
+        .ok_or_else(|| MemberNotFound(user_id.to_owned()))?;
+        if current {
+            Self::ensure_current(&mut tx, &[user_id.to_owned()]).await?;
+        }
+        tx.commit()
+            .await
+            .context("Unable to commit member removal")?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, code_hash))]
+    async fn create_invitation(
+        &self,
+        ledger_id: i32,
+        code_hash: &str,
+        role: LedgerRole,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), LedgerRepoError> {
+        query!("DELETE FROM ledger_invitations WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .context("Unable to delete expired invitations")?;
+        let result = query!(
+            "INSERT INTO ledger_invitations(code_hash, ledger_id, role, expires_at) SELECT $1, $2, $3, $4 WHERE EXISTS(SELECT 1 FROM ledgers WHERE id = $2)",
+            code_hash,
+            ledger_id,
+            role.to_string(),
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create invitation")?;
+        if result.rows_affected() == 0 {
+            return Err(LedgerNotFound(ledger_id));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, code_hash))]
+    async fn accept_invitation(
+        &self,
+        user_id: &str,
+        code_hash: &str,
+    ) -> Result<Ledger, LedgerRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let invitation = query!(
+            "SELECT ledger_id, role FROM ledger_invitations WHERE code_hash = $1 AND expires_at > NOW() FOR UPDATE",
+            code_hash
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Unable to get invitation")?
+        .ok_or(InvitationNotFound)?;
+
+        let result = query!(
+            "INSERT INTO ledger_members(ledger_id, user_id, role) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            invitation.ledger_id,
+            user_id,
+            invitation.role
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to add ledger member")?;
+        if result.rows_affected() == 0 {
+            return Err(AlreadyMember(user_id.to_owned()));
+        }
+        query!(
+            "DELETE FROM ledger_invitations WHERE code_hash = $1",
+            code_hash
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to delete invitation")?;
+        Self::ensure_current(&mut tx, &[user_id.to_owned()]).await?;
+
+        let ledger = Self::get_ledger_entry(&mut tx, user_id, invitation.ledger_id).await?;
+        tx.commit().await.context("Unable to commit invitation")?;
+        Ok(ledger)
+    }
+}
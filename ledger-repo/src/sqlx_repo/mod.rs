@@ -1,11 +1,23 @@
+mod api_key_repo;
+mod category_repo;
+mod change_listener;
+mod duplicate_repo;
+mod history_repo;
+mod idempotency_repo;
+mod ledger_repo;
+mod oidc_repo;
+mod rate_limit_repo;
+mod refresh_token_repo;
+mod rule_repo;
+mod session_repo;
+mod totp_repo;
+mod transactee_repo;
 mod transaction_repo;
 mod transaction_template_repo;
 mod user_repo;
+mod webhook_repo;
 
-use crate::transaction_repo::TransactionRepo;
-use crate::transaction_template_repo::TransactionTemplateRepo;
-use crate::user_repo::UserRepo;
-use crate::HealthCheck;
+use crate::{HealthCheck, Repos};
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -13,6 +25,8 @@ use sqlx::postgres::PgPoolOptions;
 use sqlx::{query, Pool, Postgres};
 use std::sync::Arc;
 
+pub use change_listener::ChangeListener;
+
 #[derive(Clone)]
 pub struct SQLxRepo {
     pool: Pool<Postgres>,
@@ -41,18 +55,31 @@ impl HealthCheck for SQLxRepo {
     }
 }
 
-pub async fn create_repos(
-    database_url: String,
-    max_pool_size: u32,
-) -> (
-    Arc<dyn UserRepo>,
-    Arc<dyn TransactionRepo>,
-    Arc<dyn TransactionTemplateRepo>,
-) {
+impl From<SQLxRepo> for Repos {
+    fn from(repo: SQLxRepo) -> Self {
+        Repos {
+            user_repo: Arc::new(repo.clone()),
+            transaction_repo: Arc::new(repo.clone()),
+            template_repo: Arc::new(repo.clone()),
+            category_repo: Arc::new(repo.clone()),
+            transactee_repo: Arc::new(repo.clone()),
+            rule_repo: Arc::new(repo.clone()),
+            duplicate_repo: Arc::new(repo.clone()),
+            history_repo: Arc::new(repo.clone()),
+            idempotency_repo: Arc::new(repo.clone()),
+            webhook_repo: Arc::new(repo.clone()),
+            refresh_token_repo: Arc::new(repo.clone()),
+            session_repo: Arc::new(repo.clone()),
+            api_key_repo: Arc::new(repo.clone()),
+            totp_repo: Arc::new(repo.clone()),
+            rate_limit_repo: Arc::new(repo.clone()),
+            oidc_repo: Arc::new(repo.clone()),
+            ledger_repo: Arc::new(repo),
+        }
+    }
+}
+
+pub async fn create_repos(database_url: String, max_pool_size: u32) -> Repos {
     let repo = SQLxRepo::new(database_url, max_pool_size).await.unwrap();
-    (
-        Arc::new(repo.clone()),
-        Arc::new(repo.clone()),
-        Arc::new(repo),
-    )
+    repo.into()
 }
@@ -0,0 +1,118 @@
+use crate::oidc_repo::{OidcLogin, OidcRepo, OidcRepoError};
+use crate::sqlx_repo::SQLxRepo;
+use crate::user_repo::{User, UserRepoError};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar};
+use tracing::instrument;
+
+#[async_trait]
+impl OidcRepo for SQLxRepo {
+    #[instrument(skip(self, login))]
+    async fn create_login(
+        &self,
+        login: OidcLogin,
+        expire_before: DateTime<Utc>,
+    ) -> Result<(), OidcRepoError> {
+        query!(
+            "DELETE FROM oidc_logins WHERE created_at < $1",
+            expire_before
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to delete expired OIDC logins")?;
+        query!(
+            "INSERT INTO oidc_logins(state, code_verifier, nonce) VALUES ($1, $2, $3)",
+            login.state,
+            login.code_verifier,
+            login.nonce
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to create OIDC login")?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, state))]
+    async fn take_login(
+        &self,
+        state: &str,
+        expire_before: DateTime<Utc>,
+    ) -> Result<Option<OidcLogin>, OidcRepoError> {
+        let login = query_as!(
+            OidcLogin,
+            "DELETE FROM oidc_logins WHERE state = $1 AND created_at >= $2 \
+             RETURNING state, code_verifier, nonce",
+            state,
+            expire_before
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Unable to take OIDC login")?;
+
+        Ok(login)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_linked_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<String>, OidcRepoError> {
+        let user_id = query_scalar!(
+            "SELECT user_id FROM oidc_identities WHERE issuer = $1 AND subject = $2",
+            issuer,
+            subject
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Unable to get linked user")?;
+
+        Ok(user_id)
+    }
+
+    #[instrument(skip(self, user))]
+    async fn create_linked_user(
+        &self,
+        issuer: &str,
+        subject: &str,
+        user: User,
+    ) -> Result<(), OidcRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        Self::insert_user(&mut tx, &user)
+            .await
+            .map_err(|e| match e {
+                UserRepoError::UserAlreadyExists(user_id) => {
+                    OidcRepoError::UserAlreadyExists(user_id)
+                }
+                e => OidcRepoError::Other(e.into()),
+            })?;
+        let result = query!(
+            "INSERT INTO oidc_identities(issuer, subject, user_id) VALUES ($1, $2, $3) \
+             ON CONFLICT DO NOTHING",
+            issuer,
+            subject,
+            user.id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to link user")?;
+        if result.rows_affected() == 0 {
+            return Err(OidcRepoError::IdentityAlreadyLinked(
+                issuer.to_owned(),
+                subject.to_owned(),
+            ));
+        }
+        tx.commit()
+            .await
+            .with_context(|| format!("Unable to commit user {}", user.id))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use crate::rate_limit_repo::{FailedLogins, RateLimitRepo, RateLimitRepoError};
+use crate::sqlx_repo::SQLxRepo;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar};
+use tracing::instrument;
+
+#[async_trait]
+impl RateLimitRepo for SQLxRepo {
+    #[instrument(skip(self))]
+    async fn count_request(
+        &self,
+        key: &str,
+        window_start: DateTime<Utc>,
+    ) -> Result<i64, RateLimitRepoError> {
+        let count = query_scalar!(
+            "INSERT INTO rate_limit_counters(key, window_start, count) VALUES ($1, $2, 1)
+            ON CONFLICT (key, window_start) DO UPDATE SET count = rate_limit_counters.count + 1
+            RETURNING count",
+            key,
+            window_start
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Unable to count request")?;
+
+        Ok(count)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_failed_logins(
+        &self,
+        key: &str,
+    ) -> Result<Option<FailedLogins>, RateLimitRepoError> {
+        let failed_logins = query_as!(
+            FailedLogins,
+            "SELECT failures, last_failure_at FROM failed_logins WHERE key = $1",
+            key
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Unable to get failed logins")?;
+
+        Ok(failed_logins)
+    }
+
+    #[instrument(skip(self))]
+    async fn record_failed_login(
+        &self,
+        key: &str,
+        reset_before: DateTime<Utc>,
+    ) -> Result<FailedLogins, RateLimitRepoError> {
+        let failed_logins = query_as!(
+            FailedLogins,
+            "INSERT INTO failed_logins(key, failures, last_failure_at) VALUES ($1, 1, NOW())
+            ON CONFLICT (key) DO UPDATE SET
+                failures = CASE WHEN failed_logins.last_failure_at < $2 THEN 1
+                    ELSE failed_logins.failures + 1 END,
+                last_failure_at = NOW()
+            RETURNING failures, last_failure_at",
+            key,
+            reset_before
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Unable to record failed login")?;
+
+        Ok(failed_logins)
+    }
+
+    #[instrument(skip(self))]
+    async fn clear_failed_logins(&self, key: &str) -> Result<(), RateLimitRepoError> {
+        query!("DELETE FROM failed_logins WHERE key = $1", key)
+            .execute(&self.pool)
+            .await
+            .context("Unable to clear failed logins")?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn purge_counters(&self, before: DateTime<Utc>) -> Result<u64, RateLimitRepoError> {
+        let counters = query!(
+            "DELETE FROM rate_limit_counters WHERE window_start < $1",
+            before
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to purge request counts")?;
+        let failed_logins = query!(
+            "DELETE FROM failed_logins WHERE last_failure_at < $1",
+            before
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to purge failed logins")?;
+
+        Ok(counters.rows_affected() + failed_logins.rows_affected())
+    }
+}
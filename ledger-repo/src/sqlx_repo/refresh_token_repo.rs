@@ -0,0 +1,163 @@
+use crate::refresh_token_repo::{RefreshTokenOwner, RefreshTokenRepo, RefreshTokenRepoError};
+use crate::sqlx_repo::SQLxRepo;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::query;
+use tracing::instrument;
+
+#[async_trait]
+impl RefreshTokenRepo for SQLxRepo {
+    #[instrument(skip(self, token_hash))]
+    async fn create_family(
+        &self,
+        user_id: &str,
+        family_id: &str,
+        token_hash: &str,
+    ) -> Result<(), RefreshTokenRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+
+        query!(
+            "INSERT INTO refresh_token_families(id, user_id) VALUES ($1, $2)",
+            family_id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to create refresh token family")?;
+        query!(
+            "INSERT INTO refresh_tokens(token_hash, family_id) VALUES ($1, $2)",
+            token_hash,
+            family_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to create refresh token")?;
+
+        tx.commit()
+            .await
+            .context("Unable to commit refresh token family")?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, token_hash, new_token_hash))]
+    async fn rotate_token(
+        &self,
+        token_hash: &str,
+        new_token_hash: &str,
+        issued_after: DateTime<Utc>,
+    ) -> Result<RefreshTokenOwner, RefreshTokenRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+
+        // Locking the family makes concurrent uses of its tokens take turns, so only one of them
+        // can rotate a token
+        let token = query!(
+            "SELECT t.family_id, t.used_at, f.user_id FROM refresh_tokens t
+            JOIN refresh_token_families f ON f.id = t.family_id
+            WHERE t.token_hash = $1 AND t.created_at >= $2 AND f.revoked_at IS NULL
+            FOR UPDATE OF f",
+            token_hash,
+            issued_after
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Unable to get refresh token")?
+        .ok_or(RefreshTokenRepoError::TokenNotFound)?;
+
+        if token.used_at.is_some() {
+            query!(
+                "UPDATE refresh_token_families SET revoked_at = NOW() WHERE id = $1",
+                token.family_id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Unable to revoke refresh token family")?;
+            tx.commit()
+                .await
+                .context("Unable to commit refresh token family")?;
+            return Err(RefreshTokenRepoError::TokenReused(token.family_id));
+        }
+
+        query!(
+            "UPDATE refresh_tokens SET used_at = NOW() WHERE token_hash = $1",
+            token_hash
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to use refresh token")?;
+        query!(
+            "INSERT INTO refresh_tokens(token_hash, family_id) VALUES ($1, $2)",
+            new_token_hash,
+            token.family_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to create refresh token")?;
+
+        tx.commit()
+            .await
+            .context("Unable to commit refresh token")?;
+        Ok(RefreshTokenOwner {
+            user_id: token.user_id,
+            family_id: token.family_id,
+        })
+    }
+
+    #[instrument(skip(self, token_hash))]
+    async fn revoke_family(&self, token_hash: &str) -> Result<(), RefreshTokenRepoError> {
+        query!(
+            "UPDATE refresh_token_families SET revoked_at = NOW()
+            WHERE revoked_at IS NULL AND id = (SELECT family_id FROM refresh_tokens WHERE token_hash = $1)",
+            token_hash
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to revoke refresh token family")?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke_all_families(&self, user_id: &str) -> Result<(), RefreshTokenRepoError> {
+        query!(
+            "UPDATE refresh_token_families SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to revoke refresh tokens of user {}", user_id))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn purge_tokens(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<u64, RefreshTokenRepoError> {
+        let result = query!(
+            "DELETE FROM refresh_tokens WHERE created_at < $1",
+            created_before
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to purge refresh tokens")?;
+        query!(
+            "DELETE FROM refresh_token_families f
+            WHERE NOT EXISTS (SELECT 1 FROM refresh_tokens t WHERE t.family_id = f.id)"
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to purge refresh token families")?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,148 @@
+use crate::rule_repo::{NewRule, Rule, RuleActions, RuleConditions, RuleRepo, RuleRepoError};
+use crate::sqlx_repo::SQLxRepo;
+use anyhow::Context;
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use sqlx::query_as;
+use tracing::instrument;
+
+struct RuleEntry {
+    id: i32,
+    #[allow(dead_code)]
+    user_id: String,
+    name: String,
+    priority: i32,
+    transactee_pattern: Option<String>,
+    note_pattern: Option<String>,
+    min_amount: Option<Decimal>,
+    max_amount: Option<Decimal>,
+    sign: Option<String>,
+    day_of_month: Option<i32>,
+    set_category: Option<String>,
+    set_transactee: Option<String>,
+    add_tags: Vec<String>,
+    set_note: Option<String>,
+}
+
+impl TryFrom<RuleEntry> for Rule {
+    type Error = RuleRepoError;
+
+    fn try_from(value: RuleEntry) -> Result<Self, Self::Error> {
+        let conditions = RuleConditions {
+            transactee: value.transactee_pattern,
+            note: value.note_pattern,
+            min_amount: value.min_amount,
+            max_amount: value.max_amount,
+            sign: value.sign.map(|s| s.parse()).transpose()?,
+            day_of_month: value.day_of_month,
+        };
+        let actions = RuleActions {
+            category: value.set_category,
+            transactee: value.set_transactee,
+            tags: value.add_tags.into_iter().collect(),
+            note: value.set_note,
+        };
+        Ok(Rule {
+            id: value.id,
+            name: value.name,
+            priority: value.priority,
+            conditions,
+            actions,
+        })
+    }
+}
+
+#[async_trait]
+impl RuleRepo for SQLxRepo {
+    #[instrument(skip(self))]
+    async fn get_rules(&self, user_id: &str) -> Result<Vec<Rule>, RuleRepoError> {
+        let rule_entries = query_as!(
+            RuleEntry,
+            "SELECT * FROM rules WHERE user_id = $1 ORDER BY priority DESC, id",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get rules for user {}", user_id))?;
+
+        rule_entries.into_iter().map(|r| r.try_into()).collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn create_rule(&self, user_id: &str, new_rule: NewRule) -> Result<Rule, RuleRepoError> {
+        let conditions = &new_rule.conditions;
+        let actions = &new_rule.actions;
+        let tags: Vec<String> = actions.tags.iter().cloned().collect();
+        query_as!(
+            RuleEntry,
+            "INSERT INTO rules(user_id, name, priority, transactee_pattern, note_pattern, min_amount, max_amount, sign, day_of_month, set_category, set_transactee, add_tags, set_note) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING *",
+            user_id,
+            new_rule.name,
+            new_rule.priority,
+            conditions.transactee,
+            conditions.note,
+            conditions.min_amount,
+            conditions.max_amount,
+            conditions.sign.map(|s| s.to_string()),
+            conditions.day_of_month,
+            actions.category,
+            actions.transactee,
+            tags.as_slice(),
+            actions.note
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Unable to insert rule")?
+        .try_into()
+    }
+
+    #[instrument(skip(self))]
+    async fn update_rule(
+        &self,
+        user_id: &str,
+        rule_id: i32,
+        rule: NewRule,
+    ) -> Result<Rule, RuleRepoError> {
+        let conditions = &rule.conditions;
+        let actions = &rule.actions;
+        let tags: Vec<String> = actions.tags.iter().cloned().collect();
+        query_as!(
+            RuleEntry,
+            "UPDATE rules SET name = $3, priority = $4, transactee_pattern = $5, note_pattern = $6, min_amount = $7, max_amount = $8, sign = $9, day_of_month = $10, set_category = $11, set_transactee = $12, add_tags = $13, set_note = $14 WHERE user_id = $1 AND id = $2 RETURNING *",
+            user_id,
+            rule_id,
+            rule.name,
+            rule.priority,
+            conditions.transactee,
+            conditions.note,
+            conditions.min_amount,
+            conditions.max_amount,
+            conditions.sign.map(|s| s.to_string()),
+            conditions.day_of_month,
+            actions.category,
+            actions.transactee,
+            tags.as_slice(),
+            actions.note
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("Unable to update rule {}", rule_id))?
+        .ok_or(RuleRepoError::RuleNotFound(rule_id))?
+        .try_into()
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_rule(&self, user_id: &str, rule_id: i32) -> Result<Rule, RuleRepoError> {
+        query_as!(
+            RuleEntry,
+            "DELETE FROM rules WHERE user_id = $1 AND id = $2 RETURNING *",
+            user_id,
+            rule_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("Unable to delete rule {}", rule_id))?
+        .ok_or(RuleRepoError::RuleNotFound(rule_id))?
+        .try_into()
+    }
+}
@@ -0,0 +1,97 @@
+use crate::session_repo::{Session, SessionRepo, SessionRepoError};
+use crate::sqlx_repo::SQLxRepo;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as};
+use tracing::instrument;
+
+/// Sessions are stored with the refresh token families they belong to
+#[async_trait]
+impl SessionRepo for SQLxRepo {
+    #[instrument(skip(self))]
+    async fn start_session(
+        &self,
+        session_id: &str,
+        device_name: Option<String>,
+        ip_address: Option<String>,
+    ) -> Result<(), SessionRepoError> {
+        let result = query!(
+            "UPDATE refresh_token_families SET device_name = $2, ip_address = $3, last_used_at = NOW() WHERE id = $1",
+            session_id,
+            device_name,
+            ip_address
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to start session {}", session_id))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionRepoError::SessionNotFound(session_id.to_owned()));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn touch_session(
+        &self,
+        session_id: &str,
+        ip_address: Option<String>,
+    ) -> Result<(), SessionRepoError> {
+        let result = query!(
+            "UPDATE refresh_token_families SET ip_address = $2, last_used_at = NOW() WHERE id = $1",
+            session_id,
+            ip_address
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to update session {}", session_id))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionRepoError::SessionNotFound(session_id.to_owned()));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_sessions(
+        &self,
+        user_id: &str,
+        used_after: DateTime<Utc>,
+    ) -> Result<Vec<Session>, SessionRepoError> {
+        let sessions = query_as!(
+            Session,
+            "SELECT id, device_name, ip_address, created_at, last_used_at FROM refresh_token_families
+            WHERE user_id = $1 AND revoked_at IS NULL AND last_used_at >= $2
+            ORDER BY last_used_at DESC",
+            user_id,
+            used_after
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Unable to get sessions")?;
+
+        Ok(sessions)
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke_session(
+        &self,
+        user_id: &str,
+        session_id: &str,
+    ) -> Result<(), SessionRepoError> {
+        let result = query!(
+            "UPDATE refresh_token_families SET revoked_at = NOW() WHERE user_id = $1 AND id = $2 AND revoked_at IS NULL",
+            user_id,
+            session_id
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to revoke session {}", session_id))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionRepoError::SessionNotFound(session_id.to_owned()));
+        }
+        Ok(())
+    }
+}
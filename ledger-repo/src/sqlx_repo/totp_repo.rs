@@ -0,0 +1,135 @@
+use crate::sqlx_repo::SQLxRepo;
+use crate::totp_repo::{TotpCredential, TotpRepo, TotpRepoError};
+use anyhow::Context;
+use async_trait::async_trait;
+use sqlx::{query, query_as};
+use tracing::instrument;
+
+#[async_trait]
+impl TotpRepo for SQLxRepo {
+    #[instrument(skip(self))]
+    async fn get_totp(&self, user_id: &str) -> Result<Option<TotpCredential>, TotpRepoError> {
+        let credential = query_as!(
+            TotpCredential,
+            "SELECT secret, enabled, last_used_step FROM totp_credentials WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Unable to get TOTP credential")?;
+
+        Ok(credential)
+    }
+
+    #[instrument(skip(self, secret))]
+    async fn create_totp(&self, user_id: &str, secret: Vec<u8>) -> Result<(), TotpRepoError> {
+        // Replacing the row also removes the old recovery codes
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        query!("DELETE FROM totp_credentials WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Unable to delete TOTP credential")?;
+        query!(
+            "INSERT INTO totp_credentials(user_id, secret) VALUES ($1, $2)",
+            user_id,
+            secret
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to create TOTP credential")?;
+        tx.commit()
+            .await
+            .context("Unable to commit TOTP credential")?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, recovery_code_hashes))]
+    async fn enable_totp(
+        &self,
+        user_id: &str,
+        recovery_code_hashes: Vec<String>,
+    ) -> Result<(), TotpRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+
+        let result = query!(
+            "UPDATE totp_credentials SET enabled = TRUE WHERE user_id = $1",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to enable TOTP credential")?;
+        if result.rows_affected() == 0 {
+            return Err(TotpRepoError::TotpNotFound(user_id.to_owned()));
+        }
+
+        query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Unable to delete recovery codes")?;
+        query!(
+            "INSERT INTO recovery_codes(user_id, code_hash) SELECT $1, * FROM UNNEST($2::VARCHAR[])",
+            user_id,
+            recovery_code_hashes.as_slice()
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to create recovery codes")?;
+
+        tx.commit()
+            .await
+            .context("Unable to commit TOTP credential")?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_totp(&self, user_id: &str) -> Result<(), TotpRepoError> {
+        query!("DELETE FROM totp_credentials WHERE user_id = $1", user_id)
+            .execute(&self.pool)
+            .await
+            .context("Unable to delete TOTP credential")?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn use_step(&self, user_id: &str, step: i64) -> Result<bool, TotpRepoError> {
+        let result = query!(
+            "UPDATE totp_credentials SET last_used_step = $2
+            WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)",
+            user_id,
+            step
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to record TOTP code use")?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[instrument(skip(self, code_hash))]
+    async fn use_recovery_code(
+        &self,
+        user_id: &str,
+        code_hash: &str,
+    ) -> Result<bool, TotpRepoError> {
+        let result = query!(
+            "UPDATE recovery_codes SET used_at = NOW() WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+            user_id,
+            code_hash
+        )
+        .execute(&self.pool)
+        .await
+        .context("Unable to use recovery code")?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
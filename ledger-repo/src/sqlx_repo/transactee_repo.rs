@@ -0,0 +1,96 @@
+use crate::sqlx_repo::SQLxRepo;
+use crate::transactee_repo::{Transactee, TransacteeRepo, TransacteeRepoError};
+use anyhow::Context;
+use async_trait::async_trait;
+use sqlx::query_as;
+use tracing::instrument;
+
+struct TransacteeEntry {
+    #[allow(dead_code)]
+    user_id: String,
+    name: String,
+    aliases: Vec<String>,
+}
+
+impl From<TransacteeEntry> for Transactee {
+    fn from(value: TransacteeEntry) -> Self {
+        Transactee::new(value.name, value.aliases)
+    }
+}
+
+#[async_trait]
+impl TransacteeRepo for SQLxRepo {
+    #[instrument(skip(self))]
+    async fn get_transactees(&self, user_id: &str) -> Result<Vec<Transactee>, TransacteeRepoError> {
+        let transactee_entries = query_as!(
+            TransacteeEntry,
+            "SELECT * FROM transactees WHERE user_id = $1 ORDER BY name",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get transactees for user {}", user_id))?;
+
+        Ok(transactee_entries.into_iter().map(|t| t.into()).collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_transactee(
+        &self,
+        user_id: &str,
+        name: &str,
+    ) -> Result<Transactee, TransacteeRepoError> {
+        let transactee_entry = query_as!(
+            TransacteeEntry,
+            "SELECT * FROM transactees WHERE user_id = $1 AND name = $2",
+            user_id,
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get transactee {}", name))?
+        .ok_or_else(|| TransacteeRepoError::TransacteeNotFound(name.to_owned()))?;
+
+        Ok(transactee_entry.into())
+    }
+
+    #[instrument(skip(self))]
+    async fn set_transactee(
+        &self,
+        user_id: &str,
+        transactee: Transactee,
+    ) -> Result<Transactee, TransacteeRepoError> {
+        let transactee_entry = query_as!(
+            TransacteeEntry,
+            "INSERT INTO transactees(user_id, name, aliases) VALUES ($1, $2, $3) ON CONFLICT (user_id, name) DO UPDATE SET aliases = $3 RETURNING *",
+            user_id,
+            transactee.name,
+            &transactee.aliases
+        )
+        .fetch_one(&self.pool)
+        .await
+        .with_context(|| format!("Unable to set transactee {}", transactee.name))?;
+
+        Ok(transactee_entry.into())
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_transactee(
+        &self,
+        user_id: &str,
+        name: &str,
+    ) -> Result<Transactee, TransacteeRepoError> {
+        let transactee_entry = query_as!(
+            TransacteeEntry,
+            "DELETE FROM transactees WHERE user_id = $1 AND name = $2 RETURNING *",
+            user_id,
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("Unable to delete transactee {}", name))?
+        .ok_or_else(|| TransacteeRepoError::TransacteeNotFound(name.to_owned()))?;
+
+        Ok(transactee_entry.into())
+    }
+}
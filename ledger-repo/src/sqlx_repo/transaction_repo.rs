@@ -1,12 +1,17 @@
 use crate::sqlx_repo::SQLxRepo;
-use crate::transaction_repo::TransactionRepoError::TransactionNotFound;
-use crate::transaction_repo::{Filter, MonthlyTotal, PageOptions};
+use crate::transaction_repo::TransactionRepoError::{
+    NotPermitted, TransactionNotFound, VersionMismatch,
+};
+use crate::transaction_repo::{
+    ChangedTransaction, DeletedTransaction, Filter, MonthlyTotal, PageOptions, TagStats,
+    TransacteeCount, UpdatedTransaction,
+};
 use crate::transaction_repo::{NewTransaction, Transaction, TransactionRepo, TransactionRepoError};
 use anyhow::Context;
 use async_trait::async_trait;
 use chrono::{DateTime, NaiveDate, Utc};
 use rust_decimal::Decimal;
-use sqlx::{query, query_as, query_scalar, Executor, Postgres, QueryBuilder};
+use sqlx::{query, query_as, query_scalar, Executor, PgConnection, Postgres, QueryBuilder};
 use tracing::instrument;
 
 #[derive(sqlx::FromRow)]
@@ -18,8 +23,13 @@ struct TransactionEntry {
     date: NaiveDate,
     amount: Decimal,
     #[allow(dead_code)]
-    user_id: String,
+    user_id: Option<String>,
+    #[allow(dead_code)]
+    ledger_id: i32,
     tags: Vec<String>,
+    deleted_at: Option<DateTime<Utc>>,
+    version: i32,
+    change_seq: i64,
 }
 
 impl From<TransactionEntry> for Transaction {
@@ -32,6 +42,7 @@ impl From<TransactionEntry> for Transaction {
             value.date,
             value.amount,
             value.tags.into_iter().collect(),
+            value.version,
         )
     }
 }
@@ -43,6 +54,22 @@ struct MonthlyTotalResult {
     expense: Option<Decimal>,
 }
 
+/// Pairs the transactions a bulk update locked, in id order, with the rows it returned for them
+fn updated_transactions(
+    before: Vec<TransactionEntry>,
+    mut after: Vec<TransactionEntry>,
+) -> Vec<UpdatedTransaction> {
+    after.sort_by_key(|t| t.id);
+    before
+        .into_iter()
+        .zip(after)
+        .map(|(before, after)| UpdatedTransaction {
+            before: before.into(),
+            after: after.into(),
+        })
+        .collect()
+}
+
 impl SQLxRepo {
     #[instrument(skip(self))]
     async fn get_transaction_entry(
@@ -50,11 +77,14 @@ impl SQLxRepo {
         user: &str,
         transaction_id: i32,
     ) -> Result<Option<TransactionEntry>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user).await? else {
+            return Ok(None);
+        };
         let transaction_entry: Option<TransactionEntry> = query_as!(
             TransactionEntry,
-            "SELECT * FROM transactions WHERE id = $1 AND user_id = $2",
+            "SELECT * FROM transactions WHERE id = $1 AND ledger_id = $2 AND deleted_at IS NULL",
             transaction_id,
-            user
+            ledger_id
         )
         .fetch_optional(&self.pool)
         .await
@@ -72,8 +102,13 @@ impl SQLxRepo {
         transactee: Option<String>,
         page_options: Option<PageOptions>,
     ) -> Result<Vec<TransactionEntry>, TransactionRepoError> {
-        let mut query_builder = QueryBuilder::new("SELECT * FROM transactions WHERE user_id = ");
-        query_builder.push_bind(user);
+        let Some(ledger_id) = self.current_ledger(user).await? else {
+            return Ok(vec![]);
+        };
+        let mut query_builder = QueryBuilder::new(
+            "SELECT * FROM transactions WHERE deleted_at IS NULL AND ledger_id = ",
+        );
+        query_builder.push_bind(ledger_id);
         if let Some(from) = from {
             query_builder.push(" AND date >= ").push_bind(from);
         }
@@ -107,6 +142,7 @@ impl SQLxRepo {
     #[instrument(skip(db_executor))]
     async fn insert_transaction_entry<'e, E>(
         db_executor: E,
+        ledger_id: i32,
         user: &str,
         new_transaction: &NewTransaction,
     ) -> Result<i32, TransactionRepoError>
@@ -115,60 +151,88 @@ impl SQLxRepo {
     {
         let tags: Vec<String> = new_transaction.tags.iter().cloned().collect();
         let id = query_scalar!(
-            "INSERT INTO transactions(category, transactee, note, date, amount, user_id, tags) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+            "INSERT INTO transactions(category, transactee, note, date, amount, ledger_id, user_id, tags) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
             new_transaction.category,
             new_transaction.transactee,
             new_transaction.note,
             new_transaction.date,
             new_transaction.amount,
+            ledger_id,
             user,
             tags.as_slice(),
         ).fetch_one(db_executor).await.context("Unable to insert transaction")?;
         Ok(id)
     }
 
+    /// Updates the transaction if it is at `expected_version`, returning the new version. Returns
+    /// `None` if no transaction was updated.
     #[instrument(skip(db_executor))]
     async fn update_transaction_entry<'e, E>(
         db_executor: E,
-        user: &str,
+        ledger_id: i32,
         transaction_id: i32,
         updated_transaction: &NewTransaction,
-    ) -> Result<(), TransactionRepoError>
+        expected_version: Option<i32>,
+    ) -> Result<Option<i32>, TransactionRepoError>
     where
         E: Executor<'e, Database = Postgres>,
     {
         let tags: Vec<String> = updated_transaction.tags.iter().cloned().collect();
-        let result = query!(
-            "UPDATE transactions SET category = $1, transactee = $2, note = $3, date = $4, amount = $5, tags = $6 WHERE user_id = $7 AND id = $8",
+        let version = query_scalar!(
+            "UPDATE transactions SET category = $1, transactee = $2, note = $3, date = $4, amount = $5, tags = $6, version = version + 1 WHERE ledger_id = $7 AND id = $8 AND deleted_at IS NULL AND ($9::INTEGER IS NULL OR version = $9) RETURNING version",
             updated_transaction.category,
             updated_transaction.transactee,
             updated_transaction.note,
             updated_transaction.date,
             updated_transaction.amount,
             tags.as_slice(),
-            user,
-            transaction_id
-        ).execute(db_executor).await.with_context(|| format!("Unable to update transaction {}", transaction_id))?;
-        if result.rows_affected() == 0 {
-            Err(TransactionNotFound(transaction_id))
-        } else {
-            Ok(())
-        }
+            ledger_id,
+            transaction_id,
+            expected_version
+        ).fetch_optional(db_executor).await.with_context(|| format!("Unable to update transaction {}", transaction_id))?;
+        Ok(version)
     }
 
-    #[instrument(skip(self))]
-    async fn delete_transaction_entry(
-        &self,
-        user: &str,
+    #[instrument(skip(db_executor))]
+    async fn delete_transaction_entry<'e, E>(
+        db_executor: E,
+        ledger_id: i32,
         transaction_id: i32,
-    ) -> Result<TransactionEntry, TransactionRepoError> {
-        let transaction_entry = query_as!(TransactionEntry, "DELETE FROM transactions WHERE user_id = $1 AND id = $2 RETURNING id, category, transactee, note, date, amount, user_id, tags", user, transaction_id)
-            .fetch_optional(&self.pool)
+        expected_version: Option<i32>,
+    ) -> Result<Option<TransactionEntry>, TransactionRepoError>
+    where
+        E: Executor<'e, Database = Postgres>,
+    {
+        let transaction_entry = query_as!(TransactionEntry, "UPDATE transactions SET deleted_at = NOW() WHERE ledger_id = $1 AND id = $2 AND deleted_at IS NULL AND ($3::INTEGER IS NULL OR version = $3) RETURNING *", ledger_id, transaction_id, expected_version)
+            .fetch_optional(db_executor)
             .await
-            .with_context(|| format!("Unable to delete transaction {}", transaction_id))?
-            .ok_or(TransactionNotFound(transaction_id))?;
+            .with_context(|| format!("Unable to delete transaction {}", transaction_id))?;
         Ok(transaction_entry)
     }
+
+    /// Locks the user's current ledger, if they can change its transactions
+    async fn transactions_ledger(
+        conn: &mut PgConnection,
+        user: &str,
+    ) -> Result<i32, TransactionRepoError> {
+        Self::lock_writable_ledger(conn, user)
+            .await?
+            .ok_or(NotPermitted)
+    }
+
+    /// Error for a conditional write that changed nothing, depending on whether the transaction
+    /// exists
+    async fn transaction_not_written_error(
+        &self,
+        user: &str,
+        transaction_id: i32,
+    ) -> TransactionRepoError {
+        match self.get_transaction_entry(user, transaction_id).await {
+            Ok(Some(_)) => VersionMismatch(transaction_id),
+            Ok(None) => TransactionNotFound(transaction_id),
+            Err(e) => e,
+        }
+    }
 }
 
 #[async_trait]
@@ -215,7 +279,17 @@ impl TransactionRepo for SQLxRepo {
         user: &str,
         new_transaction: NewTransaction,
     ) -> Result<Transaction, TransactionRepoError> {
-        let id = Self::insert_transaction_entry(&self.pool, user, &new_transaction).await?;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::transactions_ledger(&mut tx, user).await?;
+        let id =
+            Self::insert_transaction_entry(&mut *tx, ledger_id, user, &new_transaction).await?;
+        tx.commit()
+            .await
+            .context("Unable to commit new transaction")?;
 
         Ok(Transaction::new(
             id,
@@ -225,6 +299,7 @@ impl TransactionRepo for SQLxRepo {
             new_transaction.date,
             new_transaction.amount,
             new_transaction.tags,
+            1,
         ))
     }
 
@@ -234,9 +309,30 @@ impl TransactionRepo for SQLxRepo {
         user: &str,
         transaction_id: i32,
         updated_transaction: NewTransaction,
+        expected_version: Option<i32>,
     ) -> Result<Transaction, TransactionRepoError> {
-        Self::update_transaction_entry(&self.pool, user, transaction_id, &updated_transaction)
-            .await?;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::transactions_ledger(&mut tx, user).await?;
+        let version = Self::update_transaction_entry(
+            &mut *tx,
+            ledger_id,
+            transaction_id,
+            &updated_transaction,
+            expected_version,
+        )
+        .await?;
+        tx.commit()
+            .await
+            .with_context(|| format!("Unable to commit updating transaction {}", transaction_id))?;
+        let Some(version) = version else {
+            return Err(self
+                .transaction_not_written_error(user, transaction_id)
+                .await);
+        };
 
         Ok(Transaction::new(
             transaction_id,
@@ -246,6 +342,7 @@ impl TransactionRepo for SQLxRepo {
             updated_transaction.date,
             updated_transaction.amount,
             updated_transaction.tags,
+            version,
         ))
     }
 
@@ -254,10 +351,153 @@ impl TransactionRepo for SQLxRepo {
         &self,
         user: &str,
         transaction_id: i32,
+        expected_version: Option<i32>,
     ) -> Result<Transaction, TransactionRepoError> {
-        self.delete_transaction_entry(user, transaction_id)
+        let mut tx = self
+            .pool
+            .begin()
             .await
-            .map(|transaction_entry| transaction_entry.into())
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::transactions_ledger(&mut tx, user).await?;
+        let transaction_entry =
+            Self::delete_transaction_entry(&mut *tx, ledger_id, transaction_id, expected_version)
+                .await?;
+        tx.commit()
+            .await
+            .with_context(|| format!("Unable to commit deleting transaction {}", transaction_id))?;
+        match transaction_entry {
+            Some(transaction_entry) => Ok(transaction_entry.into()),
+            None => Err(self
+                .transaction_not_written_error(user, transaction_id)
+                .await),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_deleted_transactions(
+        &self,
+        user: &str,
+    ) -> Result<Vec<DeletedTransaction>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user).await? else {
+            return Ok(vec![]);
+        };
+        let deleted_transactions = query_as!(
+            TransactionEntry,
+            "SELECT * FROM transactions WHERE ledger_id = $1 AND deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC",
+            ledger_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get deleted transactions for user {}", user))?
+        .into_iter()
+        .map(|transaction_entry| {
+            let deleted_at = transaction_entry
+                .deleted_at
+                .expect("query only returns deleted transactions");
+            DeletedTransaction::new(transaction_entry.into(), deleted_at)
+        })
+        .collect();
+        Ok(deleted_transactions)
+    }
+
+    #[instrument(skip(self))]
+    async fn restore_transaction(
+        &self,
+        user: &str,
+        transaction_id: i32,
+    ) -> Result<Transaction, TransactionRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::transactions_ledger(&mut tx, user).await?;
+        let transaction_entry = query_as!(
+            TransactionEntry,
+            "UPDATE transactions SET deleted_at = NULL, version = version + 1 WHERE ledger_id = $1 AND id = $2 AND deleted_at IS NOT NULL RETURNING *",
+            ledger_id,
+            transaction_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .with_context(|| format!("Unable to restore transaction {}", transaction_id))?
+        .ok_or(TransactionNotFound(transaction_id))?;
+        tx.commit().await.with_context(|| {
+            format!("Unable to commit restoring transaction {}", transaction_id)
+        })?;
+        Ok(transaction_entry.into())
+    }
+
+    #[instrument(skip(self))]
+    async fn purge_deleted_transactions(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<u64, TransactionRepoError> {
+        let count = query_scalar!(
+            r#"
+            WITH purged AS (DELETE FROM transactions WHERE deleted_at < $1 RETURNING ledger_id, change_seq),
+                 marked AS (
+                     INSERT INTO purged_changes(ledger_id, transactions_purged_through)
+                         SELECT ledger_id, MAX(change_seq) FROM purged GROUP BY ledger_id
+                     ON CONFLICT (ledger_id) DO UPDATE SET transactions_purged_through = GREATEST(
+                         purged_changes.transactions_purged_through,
+                         EXCLUDED.transactions_purged_through)
+                 )
+            SELECT COUNT(*) AS "count!" FROM purged
+            "#,
+            deleted_before
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Unable to purge deleted transactions")?;
+        Ok(count as u64)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_changed_transactions(
+        &self,
+        user: &str,
+        since: i64,
+    ) -> Result<Vec<ChangedTransaction>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user).await? else {
+            return Ok(vec![]);
+        };
+        let entries = query_as!(
+            TransactionEntry,
+            "SELECT * FROM transactions WHERE ledger_id = $1 AND change_seq > $2 ORDER BY change_seq",
+            ledger_id,
+            since
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get changed transactions for user {}", user))?;
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| ChangedTransaction {
+                deleted: entry.deleted_at.is_some(),
+                change: entry.change_seq,
+                transaction: entry.into(),
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_last_purged_change(&self, user: &str) -> Result<i64, TransactionRepoError> {
+        let change = query_scalar!(
+            r#"
+            SELECT GREATEST(m.current_since, p.transactions_purged_through) AS "change!"
+            FROM ledger_members m
+                     LEFT JOIN purged_changes p ON p.ledger_id = m.ledger_id
+            WHERE m.user_id = $1
+              AND m.current
+            "#,
+            user
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Unable to get last purged change")?;
+        Ok(change.unwrap_or_default())
     }
 
     #[instrument(skip(self))]
@@ -266,16 +506,20 @@ impl TransactionRepo for SQLxRepo {
         user: &str,
         filter: Filter,
     ) -> Result<Vec<MonthlyTotal>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user).await? else {
+            return Ok(vec![]);
+        };
         let mut query_builder = QueryBuilder::new(
             r#"
             SELECT DATE_TRUNC('month', date)             as month,
                    SUM(amount) FILTER (WHERE amount > 0) as income,
                    SUM(amount * -1) FILTER (WHERE amount < 0) as expense
             FROM transactions
-            WHERE user_id = 
+            WHERE deleted_at IS NULL
+              AND ledger_id = 
             "#,
         );
-        query_builder.push_bind(user);
+        query_builder.push_bind(ledger_id);
 
         if let Some(from) = filter.from {
             query_builder.push(" AND date >= ").push_bind(from);
@@ -316,9 +560,12 @@ impl TransactionRepo for SQLxRepo {
 
     #[instrument(skip(self))]
     async fn get_all_categories(&self, user: &str) -> Result<Vec<String>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user).await? else {
+            return Ok(vec![]);
+        };
         let categories = query_scalar!(
-            "SELECT DISTINCT category FROM transactions WHERE user_id = $1",
-            user
+            "SELECT DISTINCT category FROM transactions WHERE ledger_id = $1 AND deleted_at IS NULL",
+            ledger_id
         )
         .fetch_all(&self.pool)
         .await
@@ -328,49 +575,198 @@ impl TransactionRepo for SQLxRepo {
 
     #[instrument(skip(self))]
     async fn get_all_tags(&self, user: &str) -> Result<Vec<String>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user).await? else {
+            return Ok(vec![]);
+        };
         let tags = query_scalar!(
-            "SELECT DISTINCT UNNEST(tags) FROM transactions WHERE user_id = $1",
-            user
+            "SELECT DISTINCT UNNEST(tags) FROM transactions WHERE ledger_id = $1 AND deleted_at IS NULL",
+            ledger_id
         )
-            .fetch_all(&self.pool)
-            .await
-            .with_context(|| format!("Unable to get tags for user {}", user))?
-            .into_iter()
-            .filter_map(|t| t)
-            .collect();
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get tags for user {}", user))?
+        .into_iter()
+        .flatten()
+        .collect();
 
         Ok(tags)
     }
 
+    #[instrument(skip(self))]
+    async fn get_tag_stats(&self, user: &str) -> Result<Vec<TagStats>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user).await? else {
+            return Ok(vec![]);
+        };
+        let tag_stats = query!(
+            "SELECT tag as \"tag!\", COUNT(*) as \"count!\", SUM(amount) as \"total!\" FROM transactions, UNNEST(tags) AS tag WHERE ledger_id = $1 AND deleted_at IS NULL GROUP BY tag ORDER BY COUNT(*) DESC, tag",
+            ledger_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get tag stats for user {}", user))?
+        .into_iter()
+        .map(|r| TagStats::new(r.tag, r.count, r.total))
+        .collect();
+
+        Ok(tag_stats)
+    }
+
+    #[instrument(skip(self))]
+    async fn merge_tags(
+        &self,
+        user: &str,
+        tags: &[String],
+        new_tag: &str,
+    ) -> Result<Vec<UpdatedTransaction>, TransactionRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::transactions_ledger(&mut tx, user).await?;
+        let before = query_as!(
+            TransactionEntry,
+            "SELECT * FROM transactions WHERE ledger_id = $1 AND tags && $2 AND deleted_at IS NULL ORDER BY id FOR UPDATE",
+            ledger_id,
+            tags
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .with_context(|| format!("Unable to get transactions tagged with {:?}", tags))?;
+        let ids: Vec<i32> = before.iter().map(|t| t.id).collect();
+        // DISTINCT so that transactions which already have the new tag don't end up with it twice
+        let after = query_as!(
+            TransactionEntry,
+            "UPDATE transactions SET tags = ARRAY(SELECT DISTINCT CASE WHEN tag = ANY($2) THEN $3 ELSE tag END FROM UNNEST(tags) AS tag), version = version + 1 WHERE id = ANY($1) RETURNING *",
+            ids.as_slice(),
+            tags,
+            new_tag
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .with_context(|| format!("Unable to merge tags into {}", new_tag))?;
+        tx.commit()
+            .await
+            .with_context(|| format!("Unable to commit merging tags into {}", new_tag))?;
+        Ok(updated_transactions(before, after))
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_tag(
+        &self,
+        user: &str,
+        tag: &str,
+    ) -> Result<Vec<UpdatedTransaction>, TransactionRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::transactions_ledger(&mut tx, user).await?;
+        let before = query_as!(
+            TransactionEntry,
+            "SELECT * FROM transactions WHERE ledger_id = $1 AND $2 = ANY(tags) AND deleted_at IS NULL ORDER BY id FOR UPDATE",
+            ledger_id,
+            tag
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .with_context(|| format!("Unable to get transactions tagged with {}", tag))?;
+        let ids: Vec<i32> = before.iter().map(|t| t.id).collect();
+        let after = query_as!(
+            TransactionEntry,
+            "UPDATE transactions SET tags = ARRAY_REMOVE(tags, $2), version = version + 1 WHERE id = ANY($1) RETURNING *",
+            ids.as_slice(),
+            tag
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .with_context(|| format!("Unable to delete tag {}", tag))?;
+        tx.commit()
+            .await
+            .with_context(|| format!("Unable to commit deleting tag {}", tag))?;
+        Ok(updated_transactions(before, after))
+    }
+
     #[instrument(skip(self))]
     async fn get_all_transactees(
         &self,
         user: &str,
         category: Option<String>,
-    ) -> Result<Vec<String>, TransactionRepoError> {
-        let query = if let Some(category) = category {
-            query_scalar!(
-                "SELECT transactees.transactee as \"transactee!\" FROM (SELECT DISTINCT transactee FROM transactions WHERE user_id = $1 AND transactee IS NOT NULL) transactees LEFT JOIN (SELECT transactee, COUNT(*) AS t_count FROM transactions WHERE user_id = $1 AND category = $2 GROUP BY transactee) AS t ON transactees.transactee = t.transactee ORDER BY COALESCE(t.t_count, 0) DESC",
-                user, category
+    ) -> Result<Vec<TransacteeCount>, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user).await? else {
+            return Ok(vec![]);
+        };
+        let transactees = if let Some(category) = category {
+            query_as!(
+                TransacteeCount,
+                "SELECT transactees.transactee as \"transactee!\", COALESCE(t.t_count, 0) as \"count!\" FROM (SELECT DISTINCT transactee FROM transactions WHERE ledger_id = $1 AND transactee IS NOT NULL AND deleted_at IS NULL) transactees LEFT JOIN (SELECT transactee, COUNT(*) AS t_count FROM transactions WHERE ledger_id = $1 AND category = $2 AND deleted_at IS NULL GROUP BY transactee) AS t ON transactees.transactee = t.transactee ORDER BY 2 DESC, 1",
+                ledger_id, category
             )
+            .fetch_all(&self.pool)
+            .await
         } else {
-            query_scalar!(
-                "SELECT transactee as \"transactee!\" FROM transactions WHERE user_id = $1 AND transactee IS NOT NULL GROUP BY transactee ORDER BY COUNT(transactee) DESC",
-                user
+            query_as!(
+                TransacteeCount,
+                "SELECT transactee as \"transactee!\", COUNT(transactee) as \"count!\" FROM transactions WHERE ledger_id = $1 AND transactee IS NOT NULL AND deleted_at IS NULL GROUP BY transactee ORDER BY 2 DESC, 1",
+                ledger_id
             )
-        };
-        let transactees = query
             .fetch_all(&self.pool)
             .await
-            .with_context(|| format!("Unable to get transactees for user {}", user))?;
+        }
+        .with_context(|| format!("Unable to get transactees for user {}", user))?;
         Ok(transactees)
     }
 
+    #[instrument(skip(self))]
+    async fn merge_transactees(
+        &self,
+        user: &str,
+        transactees: &[String],
+        new_transactee: &str,
+    ) -> Result<Vec<UpdatedTransaction>, TransactionRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::transactions_ledger(&mut tx, user).await?;
+        let before = query_as!(
+            TransactionEntry,
+            "SELECT * FROM transactions WHERE ledger_id = $1 AND transactee = ANY($2) AND deleted_at IS NULL ORDER BY id FOR UPDATE",
+            ledger_id,
+            transactees
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .with_context(|| format!("Unable to get transactions of {:?}", transactees))?;
+        let ids: Vec<i32> = before.iter().map(|t| t.id).collect();
+        let after = query_as!(
+            TransactionEntry,
+            "UPDATE transactions SET transactee = $2, version = version + 1 WHERE id = ANY($1) RETURNING *",
+            ids.as_slice(),
+            new_transactee
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .with_context(|| format!("Unable to merge transactees into {}", new_transactee))?;
+        tx.commit().await.with_context(|| {
+            format!(
+                "Unable to commit merging transactees into {}",
+                new_transactee
+            )
+        })?;
+        Ok(updated_transactions(before, after))
+    }
+
     #[instrument(skip(self))]
     async fn get_balance(&self, user: &str) -> Result<Decimal, TransactionRepoError> {
+        let Some(ledger_id) = self.current_ledger(user).await? else {
+            return Ok(Decimal::ZERO);
+        };
         let balance = query_scalar!(
-            "SELECT SUM(amount) FROM transactions WHERE user_id = $1",
-            user
+            "SELECT SUM(amount) FROM transactions WHERE ledger_id = $1 AND deleted_at IS NULL",
+            ledger_id
         )
         .fetch_one(&self.pool)
         .await
@@ -1,36 +1,67 @@
 use crate::sqlx_repo::SQLxRepo;
 use crate::transaction_template_repo::{
-    NewTransactionTemplate, TransactionTemplate, TransactionTemplateRepo,
-    TransactionTemplateRepoError,
+    ChangedTemplate, DeletedTransactionTemplate, NewTransactionTemplate, TransactionTemplate,
+    TransactionTemplateRepo, TransactionTemplateRepoError,
 };
 use anyhow::Context;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use sqlx::{query, query_as, query_scalar};
+use sqlx::{query, query_as, query_scalar, PgConnection};
 
 struct TransactionTemplateEntry {
     template_id: i32,
     #[allow(dead_code)]
-    user_id: String,
+    user_id: Option<String>,
+    #[allow(dead_code)]
+    ledger_id: i32,
     name: String,
     category: Option<String>,
     transactee: Option<String>,
     note: Option<String>,
     amount: Option<Decimal>,
     tags: Vec<String>,
+    deleted_at: Option<DateTime<Utc>>,
+    version: i32,
+    change_seq: i64,
 }
 
-impl Into<TransactionTemplate> for TransactionTemplateEntry {
-    fn into(self) -> TransactionTemplate {
-        let tags = self.tags.into_iter().collect();
+impl From<TransactionTemplateEntry> for TransactionTemplate {
+    fn from(value: TransactionTemplateEntry) -> Self {
+        let tags = value.tags.into_iter().collect();
         TransactionTemplate {
-            template_id: self.template_id,
-            name: self.name,
-            category: self.category,
-            transactee: self.transactee,
-            amount: self.amount,
-            note: self.note,
+            template_id: value.template_id,
+            name: value.name,
+            category: value.category,
+            transactee: value.transactee,
+            amount: value.amount,
+            note: value.note,
             tags,
+            version: value.version,
+        }
+    }
+}
+
+impl SQLxRepo {
+    /// Locks the user's current ledger, if they can change its templates
+    async fn templates_ledger(
+        conn: &mut PgConnection,
+        user_id: &str,
+    ) -> Result<i32, TransactionTemplateRepoError> {
+        Self::lock_writable_ledger(conn, user_id)
+            .await?
+            .ok_or(TransactionTemplateRepoError::NotPermitted)
+    }
+
+    /// Error for a conditional write that changed nothing, depending on whether the template exists
+    async fn template_not_written_error(
+        &self,
+        user_id: &str,
+        template_id: i32,
+    ) -> TransactionTemplateRepoError {
+        match TransactionTemplateRepo::get_template(self, user_id, template_id).await {
+            Ok(_) => TransactionTemplateRepoError::VersionMismatch(template_id),
+            Err(e) => e,
         }
     }
 }
@@ -42,19 +73,27 @@ impl TransactionTemplateRepo for SQLxRepo {
         user_id: &str,
         new_template: NewTransactionTemplate,
     ) -> Result<TransactionTemplate, TransactionTemplateRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::templates_ledger(&mut tx, user_id).await?;
         let tags: Vec<String> = new_template.tags.iter().cloned().collect();
         let template_id = query_scalar!(
-            "INSERT INTO transaction_templates(category, transactee, note, amount, user_id, tags, name) VALUES($1, $2, $3, $4, $5, $6, $7) RETURNING template_id",
+            "INSERT INTO transaction_templates(category, transactee, note, amount, ledger_id, user_id, tags, name) VALUES($1, $2, $3, $4, $5, $6, $7, $8) RETURNING template_id",
             new_template.category,
             new_template.transactee,
             new_template.note,
             new_template.amount,
+            ledger_id,
             user_id,
             tags.as_slice(),
             new_template.name
-        ).fetch_one(&self.pool).await.context("Unable to insert template")?;
+        ).fetch_one(&mut *tx).await.context("Unable to insert template")?;
+        tx.commit().await.context("Unable to commit new template")?;
 
-        Ok(new_template.to_transaction_template(template_id))
+        Ok(new_template.to_transaction_template(template_id, 1))
     }
 
     async fn update_template(
@@ -62,11 +101,18 @@ impl TransactionTemplateRepo for SQLxRepo {
         user_id: &str,
         template_id: i32,
         template: NewTransactionTemplate,
+        expected_version: Option<i32>,
     ) -> Result<TransactionTemplate, TransactionTemplateRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::templates_ledger(&mut tx, user_id).await?;
         let tags: Vec<String> = template.tags.iter().cloned().collect();
 
-        let result = query!(
-            "UPDATE transaction_templates SET category = $1, transactee = $2, note = $3, amount = $4, tags = $5, name = $6 WHERE template_id = $7 and user_id = $8",
+        let version = query_scalar!(
+            "UPDATE transaction_templates SET category = $1, transactee = $2, note = $3, amount = $4, tags = $5, name = $6, version = version + 1 WHERE template_id = $7 AND ledger_id = $8 AND deleted_at IS NULL AND ($9::INTEGER IS NULL OR version = $9) RETURNING version",
             template.category,
             template.transactee,
             template.note,
@@ -74,28 +120,58 @@ impl TransactionTemplateRepo for SQLxRepo {
             tags.as_slice(),
             template.name,
             template_id,
-            user_id,
-        ).execute(&self.pool).await.context("Unable to update template")?;
+            ledger_id,
+            expected_version,
+        ).fetch_optional(&mut *tx).await.context("Unable to update template")?;
+        tx.commit()
+            .await
+            .context("Unable to commit updating template")?;
+
+        let Some(version) = version else {
+            return Err(self.template_not_written_error(user_id, template_id).await);
+        };
+
+        Ok(template.to_transaction_template(template_id, version))
+    }
 
-        if result.rows_affected() == 0 {
+    async fn get_template(
+        &self,
+        user_id: &str,
+        template_id: i32,
+    ) -> Result<TransactionTemplate, TransactionTemplateRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id).await? else {
             return Err(TransactionTemplateRepoError::TemplateNotFound(template_id));
-        }
+        };
+        let template_entry = query_as!(
+            TransactionTemplateEntry,
+            "SELECT * FROM transaction_templates WHERE ledger_id = $1 AND template_id = $2 AND deleted_at IS NULL",
+            ledger_id,
+            template_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Unable to retrieve template")?;
 
-        Ok(template.to_transaction_template(template_id))
+        template_entry
+            .map(|t| t.into())
+            .ok_or(TransactionTemplateRepoError::TemplateNotFound(template_id))
     }
 
     async fn get_templates(
         &self,
         user_id: &str,
     ) -> Result<Vec<TransactionTemplate>, TransactionTemplateRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id).await? else {
+            return Ok(vec![]);
+        };
         let transaction_templates: Vec<TransactionTemplateEntry> = query_as!(
             TransactionTemplateEntry,
-            "SELECT * FROM transaction_templates WHERE user_id = $1",
-            user_id
+            "SELECT * FROM transaction_templates WHERE ledger_id = $1 AND deleted_at IS NULL",
+            ledger_id
         )
-            .fetch_all(&self.pool)
-            .await
-            .context("Unable to retrieve templates")?;
+        .fetch_all(&self.pool)
+        .await
+        .context("Unable to retrieve templates")?;
 
         let transaction_templates = transaction_templates
             .into_iter()
@@ -109,17 +185,238 @@ impl TransactionTemplateRepo for SQLxRepo {
         &self,
         user_id: &str,
         template_id: i32,
+        expected_version: Option<i32>,
     ) -> Result<TransactionTemplate, TransactionTemplateRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::templates_ledger(&mut tx, user_id).await?;
         let template_entry = query_as!(
             TransactionTemplateEntry,
-            "DELETE FROM transaction_templates WHERE user_id = $1 AND template_id = $2 returning template_id, category, transactee, note, amount, user_id, tags, name",
-            user_id, template_id)
-            .fetch_optional(&self.pool)
+            "UPDATE transaction_templates SET deleted_at = NOW() WHERE ledger_id = $1 AND template_id = $2 AND deleted_at IS NULL AND ($3::INTEGER IS NULL OR version = $3) RETURNING *",
+            ledger_id, template_id, expected_version)
+            .fetch_optional(&mut *tx)
             .await
             .context("Unable to delete template")?;
+        tx.commit()
+            .await
+            .context("Unable to commit deleting template")?;
+
+        match template_entry {
+            Some(t) => Ok(t.into()),
+            None => Err(self.template_not_written_error(user_id, template_id).await),
+        }
+    }
+
+    async fn get_deleted_templates(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<DeletedTransactionTemplate>, TransactionTemplateRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id).await? else {
+            return Ok(vec![]);
+        };
+        let deleted_templates = query_as!(
+            TransactionTemplateEntry,
+            "SELECT * FROM transaction_templates WHERE ledger_id = $1 AND deleted_at IS NOT NULL ORDER BY deleted_at DESC, template_id DESC",
+            ledger_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Unable to retrieve deleted templates")?
+        .into_iter()
+        .map(|t| {
+            let deleted_at = t
+                .deleted_at
+                .expect("query only returns deleted templates");
+            DeletedTransactionTemplate {
+                template: t.into(),
+                deleted_at,
+            }
+        })
+        .collect();
+
+        Ok(deleted_templates)
+    }
+
+    async fn restore_template(
+        &self,
+        user_id: &str,
+        template_id: i32,
+    ) -> Result<TransactionTemplate, TransactionTemplateRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::templates_ledger(&mut tx, user_id).await?;
+        let template_entry = query_as!(
+            TransactionTemplateEntry,
+            "UPDATE transaction_templates SET deleted_at = NULL, version = version + 1 WHERE ledger_id = $1 AND template_id = $2 AND deleted_at IS NOT NULL RETURNING *",
+            ledger_id,
+            template_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Unable to restore template")?;
+        tx.commit()
+            .await
+            .context("Unable to commit restoring template")?;
 
         template_entry
             .map(|t| t.into())
             .ok_or(TransactionTemplateRepoError::TemplateNotFound(template_id))
     }
+
+    async fn purge_deleted_templates(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<u64, TransactionTemplateRepoError> {
+        let count = query_scalar!(
+            r#"
+            WITH purged AS (DELETE FROM transaction_templates WHERE deleted_at < $1 RETURNING ledger_id, change_seq),
+                 marked AS (
+                     INSERT INTO purged_changes(ledger_id, templates_purged_through)
+                         SELECT ledger_id, MAX(change_seq) FROM purged GROUP BY ledger_id
+                     ON CONFLICT (ledger_id) DO UPDATE SET templates_purged_through = GREATEST(
+                         purged_changes.templates_purged_through,
+                         EXCLUDED.templates_purged_through)
+                 )
+            SELECT COUNT(*) AS "count!" FROM purged
+            "#,
+            deleted_before
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Unable to purge deleted templates")?;
+        Ok(count as u64)
+    }
+
+    async fn get_changed_templates(
+        &self,
+        user_id: &str,
+        since: i64,
+    ) -> Result<Vec<ChangedTemplate>, TransactionTemplateRepoError> {
+        let Some(ledger_id) = self.current_ledger(user_id).await? else {
+            return Ok(vec![]);
+        };
+        let entries = query_as!(
+            TransactionTemplateEntry,
+            "SELECT * FROM transaction_templates WHERE ledger_id = $1 AND change_seq > $2 ORDER BY change_seq",
+            ledger_id,
+            since
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get changed templates for user {}", user_id))?;
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| ChangedTemplate {
+                deleted: entry.deleted_at.is_some(),
+                change: entry.change_seq,
+                template: entry.into(),
+            })
+            .collect())
+    }
+
+    async fn get_last_purged_change(
+        &self,
+        user_id: &str,
+    ) -> Result<i64, TransactionTemplateRepoError> {
+        let change = query_scalar!(
+            r#"
+            SELECT GREATEST(m.current_since, p.templates_purged_through) AS "change!"
+            FROM ledger_members m
+                     LEFT JOIN purged_changes p ON p.ledger_id = m.ledger_id
+            WHERE m.user_id = $1
+              AND m.current
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Unable to get last purged template change")?;
+        Ok(change.unwrap_or_default())
+    }
+
+    async fn merge_tags(
+        &self,
+        user_id: &str,
+        tags: &[String],
+        new_tag: &str,
+    ) -> Result<u64, TransactionTemplateRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::templates_ledger(&mut tx, user_id).await?;
+        let result = query!(
+            "UPDATE transaction_templates SET tags = ARRAY(SELECT DISTINCT CASE WHEN tag = ANY($2) THEN $3 ELSE tag END FROM UNNEST(tags) AS tag), version = version + 1 WHERE ledger_id = $1 AND tags && $2 AND deleted_at IS NULL",
+            ledger_id,
+            tags,
+            new_tag
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to merge template tags")?;
+        tx.commit()
+            .await
+            .context("Unable to commit bulk template update")?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_tag(
+        &self,
+        user_id: &str,
+        tag: &str,
+    ) -> Result<u64, TransactionTemplateRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::templates_ledger(&mut tx, user_id).await?;
+        let result = query!(
+            "UPDATE transaction_templates SET tags = ARRAY_REMOVE(tags, $2), version = version + 1 WHERE ledger_id = $1 AND $2 = ANY(tags) AND deleted_at IS NULL",
+            ledger_id,
+            tag
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to delete template tag")?;
+        tx.commit()
+            .await
+            .context("Unable to commit bulk template update")?;
+        Ok(result.rows_affected())
+    }
+
+    async fn merge_transactees(
+        &self,
+        user_id: &str,
+        transactees: &[String],
+        new_transactee: &str,
+    ) -> Result<u64, TransactionTemplateRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let ledger_id = Self::templates_ledger(&mut tx, user_id).await?;
+        let result = query!(
+            "UPDATE transaction_templates SET transactee = $3, version = version + 1 WHERE ledger_id = $1 AND transactee = ANY($2) AND deleted_at IS NULL",
+            ledger_id,
+            transactees,
+            new_transactee
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Unable to merge template transactees")?;
+        tx.commit()
+            .await
+            .context("Unable to commit bulk template update")?;
+        Ok(result.rows_affected())
+    }
 }
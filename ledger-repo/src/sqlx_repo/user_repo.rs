@@ -1,36 +1,64 @@
 use crate::sqlx_repo::SQLxRepo;
-use crate::user_repo::{User, UserRepo, UserRepoError};
+use crate::user_repo::{InviteCode, NewInviteCode, User, UserRepo, UserRepoError, UserSummary};
 use anyhow::Context;
 use async_trait::async_trait;
-use sqlx::{query, query_as};
+use sqlx::{query, query_as, query_scalar, PgConnection};
 use tracing::instrument;
 
+impl SQLxRepo {
+    /// Inserts the user together with the ledger of their own every user starts with
+    pub(super) async fn insert_user(
+        conn: &mut PgConnection,
+        user: &User,
+    ) -> Result<(), UserRepoError> {
+        let result = query!(
+            "INSERT INTO users(id, password_hash, admin, disabled, password_reset_required) \
+            VALUES($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING",
+            &user.id,
+            user.password_hash,
+            user.admin,
+            user.disabled,
+            user.password_reset_required
+        )
+        .execute(&mut *conn)
+        .await
+        .with_context(|| format!("Unable to create user {}", user.id))?;
+        if result.rows_affected() == 0 {
+            return Err(UserRepoError::UserAlreadyExists(user.id.clone()));
+        }
+        Self::insert_ledger(conn, &user.id, &user.id).await?;
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl UserRepo for SQLxRepo {
     #[instrument(skip(self))]
     async fn get_user(&self, user_id: &str) -> Result<User, UserRepoError> {
-        let user: Option<User> = query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
-            .fetch_optional(&self.pool)
-            .await
-            .with_context(|| format!("Unable to get user {}", user_id))?;
+        let user: Option<User> = query_as!(
+            User,
+            "SELECT id, password_hash, admin, disabled, password_reset_required FROM users \
+            WHERE id = $1",
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get user {}", user_id))?;
         user.ok_or_else(|| UserRepoError::UserNotFound(user_id.to_owned()))
     }
 
     #[instrument(skip(self, user))]
     async fn create_user(&self, user: User) -> Result<(), UserRepoError> {
-        let result = query!(
-            "INSERT INTO users(id, password_hash) VALUES($1, $2) ON CONFLICT DO NOTHING",
-            &user.id,
-            user.password_hash
-        )
-        .execute(&self.pool)
-        .await
-        .with_context(|| format!("Unable to create user {}", user.id))?;
-        if result.rows_affected() == 1 {
-            Ok(())
-        } else {
-            Err(UserRepoError::UserAlreadyExists(user.id))
-        }
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        Self::insert_user(&mut tx, &user).await?;
+        tx.commit()
+            .await
+            .with_context(|| format!("Unable to commit user {}", user.id))?;
+        Ok(())
     }
 
     #[instrument(skip(self, password_hash))]
@@ -40,7 +68,7 @@ impl UserRepo for SQLxRepo {
         password_hash: &str,
     ) -> Result<(), UserRepoError> {
         let result = query!(
-            "UPDATE users SET password_hash = $1 WHERE id = $2",
+            "UPDATE users SET password_hash = $1, password_reset_required = false WHERE id = $2",
             password_hash,
             user_id
         )
@@ -56,14 +84,209 @@ impl UserRepo for SQLxRepo {
 
     #[instrument(skip(self))]
     async fn delete_user(&self, user_id: &str) -> Result<(), UserRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        // Deleting the user clears them from what they wrote, which numbers those changes, so the
+        // ledgers are locked before their rows like for any other write
+        query!(
+            r#"
+            SELECT id FROM ledgers
+            WHERE id IN (SELECT ledger_id FROM transactions WHERE user_id = $1
+                         UNION
+                         SELECT ledger_id FROM transaction_templates WHERE user_id = $1)
+            ORDER BY id
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .with_context(|| format!("Unable to lock ledgers of {}", user_id))?;
+        Self::remove_from_ledgers(&mut tx, user_id).await?;
         let result = query!("DELETE FROM users WHERE id = $1", user_id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
             .with_context(|| format!("Unable to delete user {}", user_id))?;
-        if result.rows_affected() == 1 {
-            Ok(())
-        } else {
-            Err(UserRepoError::UserNotFound(user_id.to_owned()))
+        if result.rows_affected() == 0 {
+            return Err(UserRepoError::UserNotFound(user_id.to_owned()));
+        }
+        tx.commit()
+            .await
+            .with_context(|| format!("Unable to commit deleting user {}", user_id))?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_users(&self) -> Result<Vec<UserSummary>, UserRepoError> {
+        let users = query_as!(
+            UserSummary,
+            r#"SELECT id, admin, disabled, password_reset_required, last_login_at,
+                (SELECT COUNT(*) FROM transactions t WHERE t.user_id = u.id AND t.deleted_at IS NULL)
+                    AS "transaction_count!"
+            FROM users u
+            ORDER BY id"#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Unable to get users")?;
+        Ok(users)
+    }
+
+    #[instrument(skip(self))]
+    async fn set_admin(&self, user_id: &str, admin: bool) -> Result<(), UserRepoError> {
+        let result = query!("UPDATE users SET admin = $1 WHERE id = $2", admin, user_id)
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Unable to update admin for {}", user_id))?;
+        if result.rows_affected() == 0 {
+            return Err(UserRepoError::UserNotFound(user_id.to_owned()));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn set_disabled(&self, user_id: &str, disabled: bool) -> Result<(), UserRepoError> {
+        let result = query!(
+            "UPDATE users SET disabled = $1 WHERE id = $2",
+            disabled,
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to update disabled for {}", user_id))?;
+        if result.rows_affected() == 0 {
+            return Err(UserRepoError::UserNotFound(user_id.to_owned()));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn require_password_reset(&self, user_id: &str) -> Result<(), UserRepoError> {
+        let result = query!(
+            "UPDATE users SET password_reset_required = true WHERE id = $1",
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to require password reset for {}", user_id))?;
+        if result.rows_affected() == 0 {
+            return Err(UserRepoError::UserNotFound(user_id.to_owned()));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn record_login(&self, user_id: &str) -> Result<(), UserRepoError> {
+        let result = query!(
+            "UPDATE users SET last_login_at = NOW() WHERE id = $1",
+            user_id
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to record login for {}", user_id))?;
+        if result.rows_affected() == 0 {
+            return Err(UserRepoError::UserNotFound(user_id.to_owned()));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, user, code_hash))]
+    async fn create_user_with_invite(
+        &self,
+        user: User,
+        code_hash: &str,
+    ) -> Result<(), UserRepoError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Unable to start transaction")?;
+        let used = query_scalar!(
+            "UPDATE invite_codes SET uses = uses + 1 \
+            WHERE code_hash = $1 AND uses < max_uses AND expires_at > NOW() \
+            RETURNING id",
+            code_hash
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Unable to use invite code")?;
+        if used.is_none() {
+            return Err(UserRepoError::InvalidInviteCode);
+        }
+        Self::insert_user(&mut tx, &user).await?;
+        tx.commit()
+            .await
+            .with_context(|| format!("Unable to commit user {}", user.id))?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, code_hash))]
+    async fn create_invite_code(
+        &self,
+        created_by: &str,
+        new_invite_code: NewInviteCode,
+        code_hash: &str,
+    ) -> Result<InviteCode, UserRepoError> {
+        let invite_code = query_as!(
+            InviteCode,
+            "INSERT INTO invite_codes(code_hash, created_by, max_uses, expires_at) \
+            VALUES($1, $2, $3, $4) \
+            RETURNING id, created_by, max_uses, uses, created_at, expires_at",
+            code_hash,
+            created_by,
+            new_invite_code.max_uses,
+            new_invite_code.expires_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Unable to create invite code")?;
+        Ok(invite_code)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_invite_codes(&self, created_by: &str) -> Result<Vec<InviteCode>, UserRepoError> {
+        let invite_codes = query_as!(
+            InviteCode,
+            "SELECT id, created_by, max_uses, uses, created_at, expires_at FROM invite_codes \
+            WHERE created_by = $1 \
+            ORDER BY created_at DESC, id DESC",
+            created_by
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Unable to get invite codes")?;
+        Ok(invite_codes)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_all_invite_codes(&self) -> Result<Vec<InviteCode>, UserRepoError> {
+        let invite_codes = query_as!(
+            InviteCode,
+            "SELECT id, created_by, max_uses, uses, created_at, expires_at FROM invite_codes \
+            ORDER BY created_at DESC, id DESC"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Unable to get invite codes")?;
+        Ok(invite_codes)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_invite_code(&self, created_by: &str, id: i32) -> Result<(), UserRepoError> {
+        let result = query!(
+            "DELETE FROM invite_codes WHERE id = $1 AND created_by = $2",
+            id,
+            created_by
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to delete invite code {}", id))?;
+        if result.rows_affected() == 0 {
+            return Err(UserRepoError::InviteCodeNotFound(id));
         }
+        Ok(())
     }
 }
@@ -0,0 +1,289 @@
+use crate::sqlx_repo::SQLxRepo;
+use crate::webhook_repo::{
+    DeliveryAttempt, DueDelivery, NewWebhook, Webhook, WebhookDelivery, WebhookEvent,
+    WebhookFilter, WebhookRepo, WebhookRepoError,
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde_json::Value;
+use sqlx::{query, query_as};
+use tracing::instrument;
+
+struct WebhookEntry {
+    id: i32,
+    url: String,
+    secret: String,
+    events: Vec<String>,
+    category: Option<String>,
+    min_expense: Option<Decimal>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<WebhookEntry> for Webhook {
+    type Error = WebhookRepoError;
+
+    fn try_from(value: WebhookEntry) -> Result<Self, Self::Error> {
+        Ok(Webhook {
+            id: value.id,
+            url: value.url,
+            secret: value.secret,
+            events: value
+                .events
+                .iter()
+                .map(|event| event.parse())
+                .collect::<Result<_, _>>()?,
+            filter: WebhookFilter {
+                category: value.category,
+                min_expense: value.min_expense,
+            },
+            created_at: value.created_at,
+        })
+    }
+}
+
+struct DeliveryEntry {
+    id: i64,
+    webhook_id: i32,
+    event: String,
+    payload: Value,
+    status: String,
+    attempts: i32,
+    next_attempt_at: DateTime<Utc>,
+    last_response_status: Option<i32>,
+    last_error: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<DeliveryEntry> for WebhookDelivery {
+    type Error = WebhookRepoError;
+
+    fn try_from(value: DeliveryEntry) -> Result<Self, Self::Error> {
+        Ok(WebhookDelivery {
+            id: value.id,
+            webhook_id: value.webhook_id,
+            event: value.event.parse()?,
+            payload: value.payload,
+            status: value.status.parse()?,
+            attempts: value.attempts,
+            next_attempt_at: value.next_attempt_at,
+            last_response_status: value.last_response_status,
+            last_error: value.last_error,
+            created_at: value.created_at,
+        })
+    }
+}
+
+struct DueDeliveryEntry {
+    id: i64,
+    webhook_id: i32,
+    event: String,
+    payload: Value,
+    status: String,
+    attempts: i32,
+    next_attempt_at: DateTime<Utc>,
+    last_response_status: Option<i32>,
+    last_error: Option<String>,
+    created_at: DateTime<Utc>,
+    url: String,
+    secret: String,
+}
+
+impl TryFrom<DueDeliveryEntry> for DueDelivery {
+    type Error = WebhookRepoError;
+
+    fn try_from(value: DueDeliveryEntry) -> Result<Self, Self::Error> {
+        let delivery = DeliveryEntry {
+            id: value.id,
+            webhook_id: value.webhook_id,
+            event: value.event,
+            payload: value.payload,
+            status: value.status,
+            attempts: value.attempts,
+            next_attempt_at: value.next_attempt_at,
+            last_response_status: value.last_response_status,
+            last_error: value.last_error,
+            created_at: value.created_at,
+        };
+        Ok(DueDelivery {
+            delivery: delivery.try_into()?,
+            url: value.url,
+            secret: value.secret,
+        })
+    }
+}
+
+#[async_trait]
+impl WebhookRepo for SQLxRepo {
+    #[instrument(skip(self, secret))]
+    async fn create_webhook(
+        &self,
+        user_id: &str,
+        new_webhook: NewWebhook,
+        secret: String,
+    ) -> Result<Webhook, WebhookRepoError> {
+        let events: Vec<String> = new_webhook.events.iter().map(|e| e.to_string()).collect();
+        query_as!(
+            WebhookEntry,
+            "INSERT INTO webhooks(user_id, url, secret, events, category, min_expense) VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, url, secret, events, category, min_expense, created_at",
+            user_id,
+            new_webhook.url,
+            secret,
+            events.as_slice(),
+            new_webhook.filter.category,
+            new_webhook.filter.min_expense
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Unable to create webhook")?
+        .try_into()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_webhooks(&self, user_id: &str) -> Result<Vec<Webhook>, WebhookRepoError> {
+        let entries = query_as!(
+            WebhookEntry,
+            "SELECT id, url, secret, events, category, min_expense, created_at FROM webhooks WHERE user_id = $1 ORDER BY id",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Unable to get webhooks")?;
+
+        entries.into_iter().map(|entry| entry.try_into()).collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_webhook(
+        &self,
+        user_id: &str,
+        webhook_id: i32,
+    ) -> Result<Webhook, WebhookRepoError> {
+        query_as!(
+            WebhookEntry,
+            "SELECT id, url, secret, events, category, min_expense, created_at FROM webhooks WHERE user_id = $1 AND id = $2",
+            user_id,
+            webhook_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get webhook {}", webhook_id))?
+        .ok_or(WebhookRepoError::WebhookNotFound(webhook_id))?
+        .try_into()
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_webhook(&self, user_id: &str, webhook_id: i32) -> Result<(), WebhookRepoError> {
+        let result = query!(
+            "DELETE FROM webhooks WHERE user_id = $1 AND id = $2",
+            user_id,
+            webhook_id
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to delete webhook {}", webhook_id))?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebhookRepoError::WebhookNotFound(webhook_id));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, payload))]
+    async fn queue_delivery(
+        &self,
+        webhook_id: i32,
+        event: WebhookEvent,
+        payload: Value,
+    ) -> Result<WebhookDelivery, WebhookRepoError> {
+        query_as!(
+            DeliveryEntry,
+            "INSERT INTO webhook_deliveries(webhook_id, event, payload) VALUES ($1, $2, $3)
+            RETURNING id, webhook_id, event, payload, status, attempts, next_attempt_at, last_response_status, last_error, created_at",
+            webhook_id,
+            event.to_string(),
+            payload
+        )
+        .fetch_one(&self.pool)
+        .await
+        .with_context(|| format!("Unable to queue delivery for webhook {}", webhook_id))?
+        .try_into()
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_due_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        claimed_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<DueDelivery>, WebhookRepoError> {
+        let entries = query_as!(
+            DueDeliveryEntry,
+            r#"UPDATE webhook_deliveries d SET next_attempt_at = $2
+            FROM webhooks w
+            WHERE w.id = d.webhook_id AND d.id IN (
+                SELECT id FROM webhook_deliveries WHERE status = 'pending' AND next_attempt_at <= $1
+                ORDER BY next_attempt_at, id LIMIT $3 FOR UPDATE SKIP LOCKED
+            )
+            RETURNING d.id, d.webhook_id, d.event, d.payload, d.status, d.attempts, d.next_attempt_at, d.last_response_status, d.last_error, d.created_at, w.url as "url!", w.secret as "secret!""#,
+            now,
+            claimed_until,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Unable to claim due webhook deliveries")?;
+
+        let mut deliveries: Vec<DueDelivery> = entries
+            .into_iter()
+            .map(|entry| entry.try_into())
+            .collect::<Result<_, _>>()?;
+        deliveries.sort_by_key(|due| due.delivery.id);
+        Ok(deliveries)
+    }
+
+    #[instrument(skip(self))]
+    async fn record_attempt(
+        &self,
+        delivery_id: i64,
+        attempt: DeliveryAttempt,
+    ) -> Result<(), WebhookRepoError> {
+        query!(
+            "UPDATE webhook_deliveries SET status = $2, attempts = attempts + 1, next_attempt_at = $3, last_response_status = $4, last_error = $5 WHERE id = $1",
+            delivery_id,
+            attempt.status.to_string(),
+            attempt.next_attempt_at,
+            attempt.response_status,
+            attempt.error
+        )
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Unable to record attempt of delivery {}", delivery_id))?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_deliveries(
+        &self,
+        user_id: &str,
+        webhook_id: i32,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, WebhookRepoError> {
+        self.get_webhook(user_id, webhook_id).await?;
+        let entries = query_as!(
+            DeliveryEntry,
+            "SELECT id, webhook_id, event, payload, status, attempts, next_attempt_at, last_response_status, last_error, created_at
+            FROM webhook_deliveries WHERE webhook_id = $1 ORDER BY id DESC LIMIT $2",
+            webhook_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .with_context(|| format!("Unable to get deliveries of webhook {}", webhook_id))?;
+
+        entries.into_iter().map(|entry| entry.try_into()).collect()
+    }
+}
@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use thiserror::Error;
+
+/// A user's TOTP secret. The secret has to be stored as is, as codes are generated from it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TotpCredential {
+    pub secret: Vec<u8>,
+    /// Credentials are only enabled once the user has shown that they can generate codes
+    pub enabled: bool,
+    /// The time step of the last code that was accepted, which can't be used again
+    pub last_used_step: Option<i64>,
+}
+
+#[derive(Error, Debug)]
+pub enum TotpRepoError {
+    #[error("Two-factor authentication is not set up for user {0}")]
+    TotpNotFound(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[async_trait]
+pub trait TotpRepo: Sync + Send {
+    async fn get_totp(&self, user_id: &str) -> Result<Option<TotpCredential>, TotpRepoError>;
+
+    /// Starts enrollment with a new secret, replacing any credential the user has
+    async fn create_totp(&self, user_id: &str, secret: Vec<u8>) -> Result<(), TotpRepoError>;
+
+    /// Enables the user's credential, replacing their recovery codes
+    async fn enable_totp(
+        &self,
+        user_id: &str,
+        recovery_code_hashes: Vec<String>,
+    ) -> Result<(), TotpRepoError>;
+
+    /// Removes the user's credential along with their recovery codes
+    async fn delete_totp(&self, user_id: &str) -> Result<(), TotpRepoError>;
+
+    /// Records that a code from `step` was used. Returns false if a code from this step or a later
+    /// one was already used, so that codes can't be replayed.
+    async fn use_step(&self, user_id: &str, step: i64) -> Result<bool, TotpRepoError>;
+
+    /// Marks the recovery code as used. Returns false if the user doesn't have the code or it was
+    /// already used.
+    async fn use_recovery_code(
+        &self,
+        user_id: &str,
+        code_hash: &str,
+    ) -> Result<bool, TotpRepoError>;
+}
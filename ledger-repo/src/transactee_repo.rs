@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// A transactee with its canonical name. Aliases are patterns matching the other names the same
+/// transactee shows up as, e.g. in bank exports.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Transactee {
+    pub name: String,
+    pub aliases: Vec<String>,
+}
+
+impl Transactee {
+    pub fn new(name: String, aliases: Vec<String>) -> Transactee {
+        Transactee { name, aliases }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TransacteeRepoError {
+    #[error("Transactee {0} not found")]
+    TransacteeNotFound(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Stores the transactee directory. Like categories, transactees are plain strings on transactions
+/// so a transactee may be used without being in the directory.
+#[async_trait]
+pub trait TransacteeRepo: Sync + Send {
+    async fn get_transactees(&self, user_id: &str) -> Result<Vec<Transactee>, TransacteeRepoError>;
+
+    async fn get_transactee(
+        &self,
+        user_id: &str,
+        name: &str,
+    ) -> Result<Transactee, TransacteeRepoError>;
+
+    /// Creates the transactee, or replaces its aliases if it already exists
+    async fn set_transactee(
+        &self,
+        user_id: &str,
+        transactee: Transactee,
+    ) -> Result<Transactee, TransacteeRepoError>;
+
+    async fn delete_transactee(
+        &self,
+        user_id: &str,
+        name: &str,
+    ) -> Result<Transactee, TransacteeRepoError>;
+}
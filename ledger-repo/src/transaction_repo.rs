@@ -1,10 +1,10 @@
 use async_trait::async_trait;
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, Utc};
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::cmp::Ordering;
 use std::cmp::Ordering::Equal;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use thiserror::Error;
 
 #[derive(Debug)]
@@ -50,6 +50,9 @@ impl PageOptions {
     }
 }
 
+/// Transactions belong to ledgers. Methods taking a user act on the user's current ledger, and
+/// those that change transactions fail with [TransactionRepoError::NotPermitted] if the user can
+/// only view it.
 #[async_trait]
 pub trait TransactionRepo: Sync + Send {
     async fn get_transaction(
@@ -71,19 +74,60 @@ pub trait TransactionRepo: Sync + Send {
         new_transaction: NewTransaction,
     ) -> Result<Transaction, TransactionRepoError>;
 
+    /// Replaces the transaction and increments its version. If `expected_version` is given the
+    /// update only happens if it is still the current version, otherwise
+    /// [TransactionRepoError::VersionMismatch] is returned.
     async fn update_transaction(
         &self,
         user: &str,
         transaction_id: i32,
         updated_transaction: NewTransaction,
+        expected_version: Option<i32>,
     ) -> Result<Transaction, TransactionRepoError>;
 
+    /// Moves the transaction to the trash. Trashed transactions are excluded from all other queries
+    /// until they are restored. `expected_version` is checked the same way as for updates.
     async fn delete_transaction(
         &self,
         user: &str,
         transaction_id: i32,
+        expected_version: Option<i32>,
     ) -> Result<Transaction, TransactionRepoError>;
 
+    /// Gets the transactions in the trash, most recently deleted first
+    async fn get_deleted_transactions(
+        &self,
+        user: &str,
+    ) -> Result<Vec<DeletedTransaction>, TransactionRepoError>;
+
+    /// Moves a transaction out of the trash
+    async fn restore_transaction(
+        &self,
+        user: &str,
+        transaction_id: i32,
+    ) -> Result<Transaction, TransactionRepoError>;
+
+    /// Permanently deletes the transactions of all ledgers that were moved to the trash before
+    /// `deleted_before`. Returns the number of transactions that were deleted.
+    async fn purge_deleted_transactions(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<u64, TransactionRepoError>;
+
+    /// Gets the transactions that were created, updated or deleted after the change `since`,
+    /// ordered by their latest change. Changes are numbered from a sequence shared with templates.
+    /// Deleted transactions are included until they are purged from the trash.
+    async fn get_changed_transactions(
+        &self,
+        user: &str,
+        since: i64,
+    ) -> Result<Vec<ChangedTransaction>, TransactionRepoError>;
+
+    /// Gets the latest change of the ledger's transactions that have been purged, or the change
+    /// the ledger became the user's current ledger at if that is later. Changes up to it can no
+    /// longer be returned by [TransactionRepo::get_changed_transactions].
+    async fn get_last_purged_change(&self, user: &str) -> Result<i64, TransactionRepoError>;
+
     async fn get_monthly_totals(
         &self,
         user: &str,
@@ -94,11 +138,44 @@ pub trait TransactionRepo: Sync + Send {
 
     async fn get_all_tags(&self, user: &str) -> Result<Vec<String>, TransactionRepoError>;
 
+    /// Gets the number of transactions and the total amount for each tag, sorted by the most used
+    /// tags
+    async fn get_tag_stats(&self, user: &str) -> Result<Vec<TagStats>, TransactionRepoError>;
+
+    /// Replaces each of `tags` with `new_tag` in all transactions, so renaming a tag is merging a
+    /// single tag. Returns the transactions that were changed, ordered by id.
+    async fn merge_tags(
+        &self,
+        user: &str,
+        tags: &[String],
+        new_tag: &str,
+    ) -> Result<Vec<UpdatedTransaction>, TransactionRepoError>;
+
+    /// Removes `tag` from all transactions. Returns the transactions that were changed, ordered by
+    /// id.
+    async fn delete_tag(
+        &self,
+        user: &str,
+        tag: &str,
+    ) -> Result<Vec<UpdatedTransaction>, TransactionRepoError>;
+
+    /// Gets each transactee along with the number of transactions for it, sorted by the most used
+    /// transactees. If `category` is given only transactions in that category are counted, but all
+    /// transactees are still returned.
     async fn get_all_transactees(
         &self,
         user: &str,
         category: Option<String>,
-    ) -> Result<Vec<String>, TransactionRepoError>;
+    ) -> Result<Vec<TransacteeCount>, TransactionRepoError>;
+
+    /// Sets the transactee of all transactions with any of `transactees` to `new_transactee`.
+    /// Returns the transactions that were changed, ordered by id.
+    async fn merge_transactees(
+        &self,
+        user: &str,
+        transactees: &[String],
+        new_transactee: &str,
+    ) -> Result<Vec<UpdatedTransaction>, TransactionRepoError>;
 
     async fn get_balance(&self, user: &str) -> Result<Decimal, TransactionRepoError>;
 }
@@ -107,6 +184,10 @@ pub trait TransactionRepo: Sync + Send {
 pub enum TransactionRepoError {
     #[error("Transaction with id {0} not found")]
     TransactionNotFound(i32),
+    #[error("Transaction with id {0} has been modified")]
+    VersionMismatch(i32),
+    #[error("Not permitted to change the ledger's transactions")]
+    NotPermitted,
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -120,9 +201,12 @@ pub struct Transaction {
     pub date: NaiveDate,
     pub amount: Decimal,
     pub tags: HashSet<String>,
+    /// Incremented on every change, used to detect concurrent modifications
+    pub version: i32,
 }
 
 impl Transaction {
+    #[allow(clippy::too_many_arguments)]
     pub const fn new(
         id: i32,
         category: String,
@@ -131,6 +215,7 @@ impl Transaction {
         date: NaiveDate,
         amount: Decimal,
         tags: HashSet<String>,
+        version: i32,
     ) -> Transaction {
         Transaction {
             id,
@@ -140,18 +225,14 @@ impl Transaction {
             date,
             amount,
             tags,
+            version,
         }
     }
 }
 
 impl PartialOrd for Transaction {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let date_ordering = self.date.partial_cmp(&other.date);
-        if let Some(Equal) = date_ordering {
-            self.id.partial_cmp(&other.id)
-        } else {
-            date_ordering
-        }
+        Some(self.cmp(other))
     }
 }
 
@@ -166,6 +247,37 @@ impl Ord for Transaction {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct DeletedTransaction {
+    #[serde(flatten)]
+    pub transaction: Transaction,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl DeletedTransaction {
+    pub fn new(transaction: Transaction, deleted_at: DateTime<Utc>) -> DeletedTransaction {
+        DeletedTransaction {
+            transaction,
+            deleted_at,
+        }
+    }
+}
+
+/// A transaction along with the number of its latest change
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ChangedTransaction {
+    pub transaction: Transaction,
+    pub deleted: bool,
+    pub change: i64,
+}
+
+/// A transaction as it was before and after an update that changed many transactions at once
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UpdatedTransaction {
+    pub before: Transaction,
+    pub after: Transaction,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NewTransaction {
     pub category: String,
@@ -173,9 +285,18 @@ pub struct NewTransaction {
     pub note: Option<String>,
     pub date: NaiveDate,
     pub amount: Decimal,
+    #[serde(serialize_with = "serialize_sorted")]
     pub tags: HashSet<String>,
 }
 
+/// Serializes tags in order, so that the same tags always serialize the same way
+pub(crate) fn serialize_sorted<S: Serializer>(
+    tags: &HashSet<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    tags.iter().collect::<BTreeSet<_>>().serialize(serializer)
+}
+
 impl NewTransaction {
     pub const fn new(
         category: String,
@@ -195,7 +316,7 @@ impl NewTransaction {
         }
     }
 
-    pub fn to_transaction(self, id: i32) -> Transaction {
+    pub fn to_transaction(self, id: i32, version: i32) -> Transaction {
         Transaction::new(
             id,
             self.category,
@@ -204,6 +325,20 @@ impl NewTransaction {
             self.date,
             self.amount,
             self.tags,
+            version,
+        )
+    }
+}
+
+impl From<Transaction> for NewTransaction {
+    fn from(value: Transaction) -> Self {
+        NewTransaction::new(
+            value.category,
+            value.transactee,
+            value.note,
+            value.date,
+            value.amount,
+            value.tags,
         )
     }
 }
@@ -224,3 +359,28 @@ impl MonthlyTotal {
         }
     }
 }
+
+#[derive(PartialEq, Debug)]
+pub struct TagStats {
+    pub tag: String,
+    pub count: i64,
+    pub total: Decimal,
+}
+
+impl TagStats {
+    pub fn new(tag: String, count: i64, total: Decimal) -> TagStats {
+        TagStats { tag, count, total }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct TransacteeCount {
+    pub transactee: String,
+    pub count: i64,
+}
+
+impl TransacteeCount {
+    pub fn new(transactee: String, count: i64) -> TransacteeCount {
+        TransacteeCount { transactee, count }
+    }
+}
@@ -1,4 +1,6 @@
+use crate::transaction_repo::serialize_sorted;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -13,6 +15,8 @@ pub struct TransactionTemplate {
     pub amount: Option<Decimal>,
     pub note: Option<String>,
     pub tags: HashSet<String>,
+    /// Incremented on every change, used to detect concurrent modifications
+    pub version: i32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -22,6 +26,7 @@ pub struct NewTransactionTemplate {
     pub transactee: Option<String>,
     pub amount: Option<Decimal>,
     pub note: Option<String>,
+    #[serde(serialize_with = "serialize_sorted")]
     pub tags: HashSet<String>,
 }
 
@@ -44,7 +49,7 @@ impl NewTransactionTemplate {
         }
     }
 
-    pub fn to_transaction_template(self, template_id: i32) -> TransactionTemplate {
+    pub fn to_transaction_template(self, template_id: i32, version: i32) -> TransactionTemplate {
         TransactionTemplate {
             template_id,
             name: self.name,
@@ -53,18 +58,54 @@ impl NewTransactionTemplate {
             amount: self.amount,
             note: self.note,
             tags: self.tags,
+            version,
         }
     }
 }
 
+impl From<TransactionTemplate> for NewTransactionTemplate {
+    fn from(value: TransactionTemplate) -> Self {
+        NewTransactionTemplate::new(
+            value.name,
+            value.category,
+            value.transactee,
+            value.amount,
+            value.note,
+            value.tags,
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeletedTransactionTemplate {
+    #[serde(flatten)]
+    pub template: TransactionTemplate,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// A template along with the number of its latest change
+#[derive(Debug, Clone)]
+pub struct ChangedTemplate {
+    pub template: TransactionTemplate,
+    pub deleted: bool,
+    pub change: i64,
+}
+
 #[derive(Error, Debug)]
 pub enum TransactionTemplateRepoError {
     #[error("Template with id {0} not found")]
     TemplateNotFound(i32),
+    #[error("Template with id {0} has been modified")]
+    VersionMismatch(i32),
+    #[error("Not permitted to change the ledger's templates")]
+    NotPermitted,
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+/// Templates belong to ledgers, the same as transactions. Methods taking a user act on the user's
+/// current ledger, and those that change templates fail with
+/// [TransactionTemplateRepoError::NotPermitted] if the user can only view it.
 #[async_trait]
 pub trait TransactionTemplateRepo: Sync + Send {
     async fn create_template(
@@ -73,11 +114,21 @@ pub trait TransactionTemplateRepo: Sync + Send {
         new_template: NewTransactionTemplate,
     ) -> Result<TransactionTemplate, TransactionTemplateRepoError>;
 
+    /// Replaces the template and increments its version. If `expected_version` is given the update
+    /// only happens if it is still the current version, otherwise
+    /// [TransactionTemplateRepoError::VersionMismatch] is returned.
     async fn update_template(
         &self,
         user_id: &str,
         template_id: i32,
         template: NewTransactionTemplate,
+        expected_version: Option<i32>,
+    ) -> Result<TransactionTemplate, TransactionTemplateRepoError>;
+
+    async fn get_template(
+        &self,
+        user_id: &str,
+        template_id: i32,
     ) -> Result<TransactionTemplate, TransactionTemplateRepoError>;
 
     async fn get_templates(
@@ -85,9 +136,72 @@ pub trait TransactionTemplateRepo: Sync + Send {
         user_id: &str,
     ) -> Result<Vec<TransactionTemplate>, TransactionTemplateRepoError>;
 
+    /// Moves the template to the trash. Trashed templates are excluded from all other queries until
+    /// they are restored. `expected_version` is checked the same way as for updates.
     async fn delete_template(
         &self,
         user_id: &str,
         template_id: i32,
+        expected_version: Option<i32>,
+    ) -> Result<TransactionTemplate, TransactionTemplateRepoError>;
+
+    /// Gets the templates in the trash, most recently deleted first
+    async fn get_deleted_templates(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<DeletedTransactionTemplate>, TransactionTemplateRepoError>;
+
+    /// Moves a template out of the trash
+    async fn restore_template(
+        &self,
+        user_id: &str,
+        template_id: i32,
     ) -> Result<TransactionTemplate, TransactionTemplateRepoError>;
+
+    /// Permanently deletes the templates of all ledgers that were moved to the trash before
+    /// `deleted_before`. Returns the number of templates that were deleted.
+    async fn purge_deleted_templates(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<u64, TransactionTemplateRepoError>;
+
+    /// Gets the templates that were created, updated or deleted after the change `since`, ordered
+    /// by their latest change. Deleted templates are included until they are purged from the trash.
+    async fn get_changed_templates(
+        &self,
+        user_id: &str,
+        since: i64,
+    ) -> Result<Vec<ChangedTemplate>, TransactionTemplateRepoError>;
+
+    /// Gets the latest change of the ledger's templates that have been purged, or the change the
+    /// ledger became the user's current ledger at if that is later
+    async fn get_last_purged_change(
+        &self,
+        user_id: &str,
+    ) -> Result<i64, TransactionTemplateRepoError>;
+
+    /// Replaces each of `tags` with `new_tag` in all templates. Returns the number of templates
+    /// that were changed.
+    async fn merge_tags(
+        &self,
+        user_id: &str,
+        tags: &[String],
+        new_tag: &str,
+    ) -> Result<u64, TransactionTemplateRepoError>;
+
+    /// Removes `tag` from all templates. Returns the number of templates that were changed.
+    async fn delete_tag(
+        &self,
+        user_id: &str,
+        tag: &str,
+    ) -> Result<u64, TransactionTemplateRepoError>;
+
+    /// Sets the transactee of all templates with any of `transactees` to `new_transactee`. Returns
+    /// the number of templates that were changed.
+    async fn merge_transactees(
+        &self,
+        user_id: &str,
+        transactees: &[String],
+        new_transactee: &str,
+    ) -> Result<u64, TransactionTemplateRepoError>;
 }
@@ -1,36 +1,120 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[async_trait]
 pub trait UserRepo: Sync + Send {
     async fn get_user(&self, user_id: &str) -> Result<User, UserRepoError>;
     async fn create_user(&self, user: User) -> Result<(), UserRepoError>;
+    /// Also clears [User::password_reset_required], as the user has chosen a new password
     async fn update_password_hash(
         &self,
         user_id: &str,
         password_hash: &str,
     ) -> Result<(), UserRepoError>;
     async fn delete_user(&self, user_id: &str) -> Result<(), UserRepoError>;
+
+    /// Gets every user, ordered by id, for administrators
+    async fn get_users(&self) -> Result<Vec<UserSummary>, UserRepoError>;
+    async fn set_admin(&self, user_id: &str, admin: bool) -> Result<(), UserRepoError>;
+    async fn set_disabled(&self, user_id: &str, disabled: bool) -> Result<(), UserRepoError>;
+    /// Makes the user choose a new password the next time they log in with their password
+    async fn require_password_reset(&self, user_id: &str) -> Result<(), UserRepoError>;
+    /// Records that the user has just logged in
+    async fn record_login(&self, user_id: &str) -> Result<(), UserRepoError>;
+
+    /// Creates the user with an invite code, counting it as used. Fails with
+    /// [UserRepoError::InvalidInviteCode], without creating the user, if the code is unknown, has
+    /// expired or has been used up.
+    async fn create_user_with_invite(
+        &self,
+        user: User,
+        code_hash: &str,
+    ) -> Result<(), UserRepoError>;
+    /// Saves an invite code created by the user. Only the hash of the code is stored.
+    async fn create_invite_code(
+        &self,
+        created_by: &str,
+        new_invite_code: NewInviteCode,
+        code_hash: &str,
+    ) -> Result<InviteCode, UserRepoError>;
+    /// Gets the invite codes the user created, newest first
+    async fn get_invite_codes(&self, created_by: &str) -> Result<Vec<InviteCode>, UserRepoError>;
+    /// Gets every invite code, newest first, for administrators
+    async fn get_all_invite_codes(&self) -> Result<Vec<InviteCode>, UserRepoError>;
+    async fn delete_invite_code(&self, created_by: &str, id: i32) -> Result<(), UserRepoError>;
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
     pub id: String,
     pub password_hash: String,
+    /// Whether the user can manage other users
+    pub admin: bool,
+    /// Disabled users can't log in or use credentials issued to them before
+    pub disabled: bool,
+    pub password_reset_required: bool,
 }
 
 impl User {
     pub fn new(id: String, password_hash: String) -> User {
-        User { id, password_hash }
+        User {
+            id,
+            password_hash,
+            admin: false,
+            disabled: false,
+            password_reset_required: false,
+        }
     }
 }
 
+/// A user as shown to administrators
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct UserSummary {
+    pub id: String,
+    pub admin: bool,
+    pub disabled: bool,
+    pub password_reset_required: bool,
+    /// Number of transactions the user has created that aren't in the trash
+    pub transaction_count: i64,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+/// Lets people sign up when signups need an invite. The code itself is only shown when it is
+/// created.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct InviteCode {
+    pub id: i32,
+    pub created_by: String,
+    /// Number of signups the code can be used for
+    pub max_uses: i32,
+    pub uses: i32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NewInviteCode {
+    #[serde(default = "default_max_uses")]
+    pub max_uses: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+fn default_max_uses() -> i32 {
+    1
+}
+
 #[derive(Error, Debug)]
 pub enum UserRepoError {
     #[error("User {0} not found")]
     UserNotFound(String),
     #[error("User {0} already exists")]
     UserAlreadyExists(String),
+    #[error("Invite code with id {0} not found")]
+    InviteCodeNotFound(i32),
+    #[error("Invite code is invalid, expired or used up")]
+    InvalidInviteCode,
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
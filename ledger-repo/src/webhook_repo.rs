@@ -0,0 +1,202 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum WebhookEvent {
+    #[serde(rename = "transaction.created")]
+    TransactionCreated,
+    #[serde(rename = "transaction.updated")]
+    TransactionUpdated,
+    #[serde(rename = "transaction.deleted")]
+    TransactionDeleted,
+    #[serde(rename = "transaction.restored")]
+    TransactionRestored,
+}
+
+impl Display for WebhookEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let event = match self {
+            WebhookEvent::TransactionCreated => "transaction.created",
+            WebhookEvent::TransactionUpdated => "transaction.updated",
+            WebhookEvent::TransactionDeleted => "transaction.deleted",
+            WebhookEvent::TransactionRestored => "transaction.restored",
+        };
+        f.write_str(event)
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "transaction.created" => Ok(WebhookEvent::TransactionCreated),
+            "transaction.updated" => Ok(WebhookEvent::TransactionUpdated),
+            "transaction.deleted" => Ok(WebhookEvent::TransactionDeleted),
+            "transaction.restored" => Ok(WebhookEvent::TransactionRestored),
+            _ => Err(anyhow::anyhow!("Invalid webhook event {}", s)),
+        }
+    }
+}
+
+/// Conditions a transaction has to meet for a webhook to be called. Conditions that are not set
+/// always match.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct WebhookFilter {
+    pub category: Option<String>,
+    /// Only matches expenses at least this large, e.g. 100 matches an amount of -150 but not -50
+    pub min_expense: Option<Decimal>,
+}
+
+/// The secret is only shown when the webhook is created, so it is not serialized
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    #[serde(skip)]
+    pub secret: String,
+    pub events: HashSet<WebhookEvent>,
+    pub filter: WebhookFilter,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NewWebhook {
+    pub url: String,
+    pub events: HashSet<WebhookEvent>,
+    #[serde(default)]
+    pub filter: WebhookFilter,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+impl Display for DeliveryStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Succeeded => "succeeded",
+            DeliveryStatus::Failed => "failed",
+        };
+        f.write_str(status)
+    }
+}
+
+impl FromStr for DeliveryStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "succeeded" => Ok(DeliveryStatus::Succeeded),
+            "failed" => Ok(DeliveryStatus::Failed),
+            _ => Err(anyhow::anyhow!("Invalid delivery status {}", s)),
+        }
+    }
+}
+
+/// An event to be sent to a webhook, along with the result of the latest attempt to send it
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub event: WebhookEvent,
+    pub payload: Value,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A delivery claimed for sending, with where to send it
+#[derive(Clone, Debug)]
+pub struct DueDelivery {
+    pub delivery: WebhookDelivery,
+    pub url: String,
+    pub secret: String,
+}
+
+/// The result of an attempt to send a delivery. `next_attempt_at` only matters if the delivery is
+/// still pending.
+#[derive(Clone, Debug)]
+pub struct DeliveryAttempt {
+    pub status: DeliveryStatus,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+#[derive(Error, Debug)]
+pub enum WebhookRepoError {
+    #[error("Webhook with id {0} not found")]
+    WebhookNotFound(i32),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[async_trait]
+pub trait WebhookRepo: Sync + Send {
+    async fn create_webhook(
+        &self,
+        user_id: &str,
+        new_webhook: NewWebhook,
+        secret: String,
+    ) -> Result<Webhook, WebhookRepoError>;
+
+    async fn get_webhooks(&self, user_id: &str) -> Result<Vec<Webhook>, WebhookRepoError>;
+
+    async fn get_webhook(
+        &self,
+        user_id: &str,
+        webhook_id: i32,
+    ) -> Result<Webhook, WebhookRepoError>;
+
+    /// Deletes the webhook along with its deliveries
+    async fn delete_webhook(&self, user_id: &str, webhook_id: i32) -> Result<(), WebhookRepoError>;
+
+    /// Queues a delivery to be sent as soon as possible
+    async fn queue_delivery(
+        &self,
+        webhook_id: i32,
+        event: WebhookEvent,
+        payload: Value,
+    ) -> Result<WebhookDelivery, WebhookRepoError>;
+
+    /// Claims up to `limit` pending deliveries of any user that are due at `now`, oldest first.
+    /// Their next attempt is moved to `claimed_until`, so that other servers don't send them
+    /// while they are being sent.
+    async fn claim_due_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        claimed_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<DueDelivery>, WebhookRepoError>;
+
+    async fn record_attempt(
+        &self,
+        delivery_id: i64,
+        attempt: DeliveryAttempt,
+    ) -> Result<(), WebhookRepoError>;
+
+    /// Gets the latest `limit` deliveries of a webhook, newest first
+    async fn get_deliveries(
+        &self,
+        user_id: &str,
+        webhook_id: i32,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, WebhookRepoError>;
+}
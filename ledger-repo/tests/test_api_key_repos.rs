@@ -0,0 +1,81 @@
+mod utils;
+
+use chrono::{Duration, DurationRound, Utc};
+use ledger_repo::api_key_repo::{ApiKeyRepoError, ApiKeyScope, NewApiKey};
+use ledger_repo::Repos;
+use rstest::rstest;
+use std::collections::HashSet;
+use utils::test_user::TestUser;
+use utils::RepoType;
+use uuid::Uuid;
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_api_keys(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        api_key_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let other_user = TestUser::new(&user_repo).await;
+
+    // Postgres stores microseconds
+    let expires_at = (Utc::now() + Duration::days(7))
+        .duration_trunc(Duration::microseconds(1))
+        .unwrap();
+    let key_hash = Uuid::new_v4().to_string();
+    let api_key = api_key_repo
+        .create_api_key(
+            &user.id,
+            NewApiKey {
+                name: "Import script".to_string(),
+                scopes: HashSet::from([ApiKeyScope::ReadOnly, ApiKeyScope::TransactionsWrite]),
+                expires_at: Some(expires_at),
+            },
+            &key_hash,
+        )
+        .await
+        .unwrap();
+    assert_eq!("Import script", api_key.name);
+    assert_eq!(Some(expires_at), api_key.expires_at);
+
+    assert_eq!(
+        vec![api_key.clone()],
+        api_key_repo.get_api_keys(&user.id).await.unwrap()
+    );
+    assert!(api_key_repo
+        .get_api_keys(&other_user.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let owner = api_key_repo.find_api_key(&key_hash).await.unwrap().unwrap();
+    assert_eq!(user.id, owner.user_id);
+    assert_eq!(api_key, owner.api_key);
+    assert_eq!(
+        None,
+        api_key_repo
+            .find_api_key(&Uuid::new_v4().to_string())
+            .await
+            .unwrap()
+    );
+
+    // keys can only be deleted by their user
+    let result = api_key_repo
+        .delete_api_key(&other_user.id, api_key.id)
+        .await;
+    assert!(matches!(result, Err(ApiKeyRepoError::ApiKeyNotFound(_))));
+    api_key_repo
+        .delete_api_key(&user.id, api_key.id)
+        .await
+        .unwrap();
+    assert_eq!(None, api_key_repo.find_api_key(&key_hash).await.unwrap());
+    let result = api_key_repo.delete_api_key(&user.id, api_key.id).await;
+    assert!(matches!(result, Err(ApiKeyRepoError::ApiKeyNotFound(_))));
+
+    user.delete().await;
+    other_user.delete().await;
+}
@@ -0,0 +1,148 @@
+mod utils;
+
+use ledger_repo::category_repo::{Category, CategoryKind, CategoryRepoError};
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::test_user::TestUser;
+use utils::RepoType;
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_set_and_get_categories(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        category_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let salary = Category::new(
+        "Salary".to_string(),
+        CategoryKind::Income,
+        Some("#00ff00".to_string()),
+        Some("wallet".to_string()),
+        false,
+    );
+    let groceries = Category::new(
+        "Groceries".to_string(),
+        CategoryKind::Expense,
+        None,
+        Some("cart".to_string()),
+        false,
+    );
+    category_repo
+        .set_category(&user.id, salary.clone())
+        .await
+        .unwrap();
+    category_repo
+        .set_category(&user.id, groceries.clone())
+        .await
+        .unwrap();
+
+    let categories = category_repo.get_categories(&user.id).await.unwrap();
+    // Should be sorted by name
+    assert_eq!(vec![groceries, salary.clone()], categories);
+
+    let category = category_repo
+        .get_category(&user.id, "Salary")
+        .await
+        .unwrap();
+    assert_eq!(salary, category);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_set_existing_category(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        category_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let category = Category::new("Misc".to_string(), CategoryKind::Expense, None, None, false);
+    category_repo
+        .set_category(&user.id, category.clone())
+        .await
+        .unwrap();
+
+    let archived = Category {
+        archived: true,
+        ..category
+    };
+    category_repo
+        .set_category(&user.id, archived.clone())
+        .await
+        .unwrap();
+
+    let categories = category_repo.get_categories(&user.id).await.unwrap();
+    assert_eq!(vec![archived], categories);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_get_invalid_category(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        category_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let result = category_repo.get_category(&user.id, "Misc").await;
+    assert!(matches!(
+        result,
+        Err(CategoryRepoError::CategoryNotFound(_))
+    ));
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_delete_category(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        category_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let category = Category::new(
+        "Transfers".to_string(),
+        CategoryKind::Transfer,
+        None,
+        None,
+        false,
+    );
+    category_repo
+        .set_category(&user.id, category.clone())
+        .await
+        .unwrap();
+
+    let deleted = category_repo
+        .delete_category(&user.id, "Transfers")
+        .await
+        .unwrap();
+    assert_eq!(category, deleted);
+
+    let result = category_repo.delete_category(&user.id, "Transfers").await;
+    assert!(matches!(
+        result,
+        Err(CategoryRepoError::CategoryNotFound(_))
+    ));
+
+    user.delete().await;
+}
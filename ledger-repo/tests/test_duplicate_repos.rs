@@ -0,0 +1,50 @@
+mod utils;
+
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::generator::NewTransactionGenerator;
+use utils::test_user::TestUser;
+use utils::RepoType;
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_dismiss_pairs(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        duplicate_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTransactionGenerator::default();
+    let mut ids = Vec::new();
+    for new_transaction in generator.generate_many(3) {
+        let transaction = transaction_repo
+            .create_new_transaction(&user.id, new_transaction)
+            .await
+            .unwrap();
+        ids.push(transaction.id);
+    }
+
+    duplicate_repo
+        .dismiss_pair(&user.id, ids[1], ids[0])
+        .await
+        .unwrap();
+    duplicate_repo
+        .dismiss_pair(&user.id, ids[1], ids[2])
+        .await
+        .unwrap();
+    // Dismissing the same pair again is a no-op
+    duplicate_repo
+        .dismiss_pair(&user.id, ids[0], ids[1])
+        .await
+        .unwrap();
+
+    let pairs = duplicate_repo.get_dismissed_pairs(&user.id).await.unwrap();
+    assert_eq!(vec![(ids[0], ids[1]), (ids[1], ids[2])], pairs);
+
+    user.delete().await;
+}
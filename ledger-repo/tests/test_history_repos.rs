@@ -0,0 +1,238 @@
+mod utils;
+
+use chrono::{Duration, Utc};
+use ledger_repo::history_repo::{ChangeKind, HistoryRepoError, NewTransactionChange};
+use ledger_repo::ledger_repo::LedgerRole;
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::generator::NewTransactionGenerator;
+use utils::test_user::TestUser;
+use utils::RepoType;
+use uuid::Uuid;
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_record_and_get_history(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        history_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTransactionGenerator::default();
+    let transaction = transaction_repo
+        .create_new_transaction(&user.id, generator.generate())
+        .await
+        .unwrap();
+    let updated = transaction_repo
+        .update_transaction(&user.id, transaction.id, generator.generate(), None)
+        .await
+        .unwrap();
+    let other = transaction_repo
+        .create_new_transaction(&user.id, generator.generate())
+        .await
+        .unwrap();
+
+    let created = history_repo
+        .record_change(
+            &user.id,
+            NewTransactionChange {
+                transaction_id: transaction.id,
+                kind: ChangeKind::Create,
+                before: None,
+                after: Some(transaction.clone()),
+                changed_by: user.id.clone(),
+                client: Some("test-client".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+    let changed = history_repo
+        .record_change(
+            &user.id,
+            NewTransactionChange {
+                transaction_id: transaction.id,
+                kind: ChangeKind::Update,
+                before: Some(transaction.clone()),
+                after: Some(updated.clone()),
+                changed_by: user.id.clone(),
+                client: None,
+            },
+        )
+        .await
+        .unwrap();
+    history_repo
+        .record_change(
+            &user.id,
+            NewTransactionChange {
+                transaction_id: other.id,
+                kind: ChangeKind::Create,
+                before: None,
+                after: Some(other),
+                changed_by: user.id.clone(),
+                client: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    let history = history_repo
+        .get_transaction_history(&user.id, transaction.id)
+        .await
+        .unwrap();
+    assert_eq!(vec![created.clone(), changed.clone()], history);
+    assert_eq!(Some(transaction), history[0].after);
+    assert_eq!(Some("test-client".to_string()), history[0].client);
+    assert_eq!(Some(updated), history[1].after);
+
+    let change = history_repo
+        .get_change(&user.id, changed.transaction_id, changed.id)
+        .await
+        .unwrap();
+    assert_eq!(changed, change);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_get_invalid_change(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        history_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let result = history_repo.get_change(&user.id, 1234, 1234).await;
+    assert!(matches!(result, Err(HistoryRepoError::ChangeNotFound(_))));
+    assert!(history_repo
+        .get_transaction_history(&user.id, 1234)
+        .await
+        .unwrap()
+        .is_empty());
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_shared_history(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        ledger_repo,
+        transaction_repo,
+        history_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let owner = TestUser::new(&user_repo).await;
+    let member = TestUser::new(&user_repo).await;
+
+    let ledger_id = ledger_repo.get_ledgers(&owner.id).await.unwrap()[0].id;
+    let code = Uuid::new_v4().to_string();
+    ledger_repo
+        .create_invitation(
+            ledger_id,
+            &code,
+            LedgerRole::Editor,
+            Utc::now() + Duration::days(1),
+        )
+        .await
+        .unwrap();
+    ledger_repo
+        .accept_invitation(&member.id, &code)
+        .await
+        .unwrap();
+
+    let mut generator = NewTransactionGenerator::default();
+    let transaction = transaction_repo
+        .create_new_transaction(&owner.id, generator.generate())
+        .await
+        .unwrap();
+    let created = history_repo
+        .record_change(
+            &owner.id,
+            NewTransactionChange {
+                transaction_id: transaction.id,
+                kind: ChangeKind::Create,
+                before: None,
+                after: Some(transaction.clone()),
+                changed_by: owner.id.clone(),
+                client: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    // the history is only seen in the ledger it was recorded in
+    assert!(history_repo
+        .get_transaction_history(&member.id, transaction.id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        history_repo
+            .get_change(&member.id, transaction.id, created.id)
+            .await,
+        Err(HistoryRepoError::ChangeNotFound(_))
+    ));
+
+    ledger_repo
+        .set_current_ledger(&member.id, ledger_id)
+        .await
+        .unwrap();
+    let updated = transaction_repo
+        .update_transaction(&member.id, transaction.id, generator.generate(), None)
+        .await
+        .unwrap();
+    let changed = history_repo
+        .record_change(
+            &member.id,
+            NewTransactionChange {
+                transaction_id: transaction.id,
+                kind: ChangeKind::Update,
+                before: Some(transaction.clone()),
+                after: Some(updated),
+                changed_by: member.id.clone(),
+                client: None,
+            },
+        )
+        .await
+        .unwrap();
+    for user in [&owner, &member] {
+        assert_eq!(
+            vec![created.clone(), changed.clone()],
+            history_repo
+                .get_transaction_history(&user.id, transaction.id)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            created,
+            history_repo
+                .get_change(&user.id, transaction.id, created.id)
+                .await
+                .unwrap()
+        );
+    }
+
+    // changes stay in the ledger when the member that made them is deleted
+    member.delete().await;
+    assert_eq!(
+        vec![created, changed],
+        history_repo
+            .get_transaction_history(&owner.id, transaction.id)
+            .await
+            .unwrap()
+    );
+
+    owner.delete().await;
+}
@@ -0,0 +1,316 @@
+mod utils;
+
+use chrono::{Duration, Utc};
+use ledger_repo::idempotency_repo::{IdempotencyRecord, StoredResponse};
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::test_user::TestUser;
+use utils::RepoType;
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_idempotent_request(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        idempotency_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let other_user = TestUser::new(&user_repo).await;
+    let expired_before = Utc::now() - Duration::hours(1);
+    let abandoned_before = Utc::now() - Duration::minutes(1);
+
+    let previous = idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            abandoned_before,
+        )
+        .await
+        .unwrap();
+    assert_eq!(None, previous);
+
+    // a retry while the first request is still in progress
+    let previous = idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            abandoned_before,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        Some(IdempotencyRecord {
+            request_path: "/transactions".to_string(),
+            request_hash: Some("hash".to_string()),
+            response: None,
+        }),
+        previous
+    );
+
+    // keys are per user
+    let previous = idempotency_repo
+        .start_request(
+            &other_user.id,
+            "key",
+            "/templates",
+            "hash",
+            expired_before,
+            abandoned_before,
+        )
+        .await
+        .unwrap();
+    assert_eq!(None, previous);
+
+    let response = StoredResponse {
+        status: 200,
+        body: r#"{"id":1}"#.to_string(),
+    };
+    idempotency_repo
+        .complete_request(&user.id, "key", response.clone())
+        .await
+        .unwrap();
+    let previous = idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            abandoned_before,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        Some(IdempotencyRecord {
+            request_path: "/transactions".to_string(),
+            request_hash: Some("hash".to_string()),
+            response: Some(response.clone()),
+        }),
+        previous
+    );
+
+    // completed requests aren't abandoned
+    idempotency_repo
+        .abandon_request(&user.id, "key")
+        .await
+        .unwrap();
+    let previous = idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            abandoned_before,
+        )
+        .await
+        .unwrap();
+    assert_eq!(Some(response), previous.unwrap().response);
+
+    // the key can be reused once it has expired
+    let previous = idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/templates",
+            "hash",
+            Utc::now() + Duration::minutes(1),
+            abandoned_before,
+        )
+        .await
+        .unwrap();
+    assert_eq!(None, previous);
+
+    user.delete().await;
+    other_user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_abandon_request(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        idempotency_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let expired_before = Utc::now() - Duration::hours(1);
+    let abandoned_before = Utc::now() - Duration::minutes(1);
+
+    idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            abandoned_before,
+        )
+        .await
+        .unwrap();
+    idempotency_repo
+        .abandon_request(&user.id, "key")
+        .await
+        .unwrap();
+    let previous = idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            abandoned_before,
+        )
+        .await
+        .unwrap();
+    assert_eq!(None, previous);
+
+    // only records from before the cutoff are purged
+    idempotency_repo
+        .purge_records(expired_before)
+        .await
+        .unwrap();
+    let previous = idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            abandoned_before,
+        )
+        .await
+        .unwrap();
+    assert!(previous.is_some());
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_stale_claim(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        idempotency_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let expired_before = Utc::now() - Duration::hours(1);
+
+    idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            Utc::now() - Duration::minutes(1),
+        )
+        .await
+        .unwrap();
+
+    // a claim still in progress after the lease is taken over
+    let previous = idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            Utc::now() + Duration::minutes(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(None, previous);
+
+    // but completed requests are still replayed
+    let response = StoredResponse {
+        status: 200,
+        body: r#"{"id":1}"#.to_string(),
+    };
+    idempotency_repo
+        .complete_request(&user.id, "key", response.clone())
+        .await
+        .unwrap();
+    let previous = idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            Utc::now() + Duration::minutes(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(Some(response), previous.unwrap().response);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_renewed_claim(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        idempotency_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let expired_before = Utc::now() - Duration::hours(1);
+
+    idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "hash",
+            expired_before,
+            Utc::now() - Duration::minutes(1),
+        )
+        .await
+        .unwrap();
+    let renewed_after = Utc::now();
+    idempotency_repo
+        .renew_request(&user.id, "key")
+        .await
+        .unwrap();
+
+    // the claim was made before the cutoff, but renewed since
+    let previous = idempotency_repo
+        .start_request(
+            &user.id,
+            "key",
+            "/transactions",
+            "other",
+            expired_before,
+            renewed_after,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        Some(IdempotencyRecord {
+            request_path: "/transactions".to_string(),
+            request_hash: Some("hash".to_string()),
+            response: None,
+        }),
+        previous
+    );
+
+    user.delete().await;
+}
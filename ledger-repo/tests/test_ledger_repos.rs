@@ -0,0 +1,436 @@
+mod utils;
+
+use chrono::{Duration, Utc};
+use ledger_repo::ledger_repo::{Ledger, LedgerMember, LedgerRepoError, LedgerRole};
+use ledger_repo::sqlx_repo::SQLxRepo;
+use ledger_repo::transaction_repo::{Filter, TransactionRepoError};
+use ledger_repo::transaction_template_repo::TransactionTemplateRepoError;
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::generator::{NewTemplateGenerator, NewTransactionGenerator};
+use utils::test_user::TestUser;
+use utils::RepoType;
+use uuid::Uuid;
+
+fn member(user: &TestUser, role: LedgerRole) -> LedgerMember {
+    LedgerMember {
+        user_id: user.id.clone(),
+        role,
+    }
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_own_ledger(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        ledger_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let ledgers = ledger_repo.get_ledgers(&user.id).await.unwrap();
+    assert_eq!(1, ledgers.len());
+    let own = &ledgers[0];
+    assert_eq!(user.id, own.name);
+    assert_eq!(LedgerRole::Owner, own.role);
+    assert!(own.current);
+    assert_eq!(
+        vec![member(&user, LedgerRole::Owner)],
+        ledger_repo.get_members(own.id).await.unwrap()
+    );
+
+    // a second ledger doesn't replace the current one until it is switched to
+    let second = ledger_repo.create_ledger(&user.id, "Second").await.unwrap();
+    assert!(!second.current);
+    ledger_repo
+        .set_current_ledger(&user.id, second.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![false, true],
+        ledger_repo
+            .get_ledgers(&user.id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|l| l.current)
+            .collect::<Vec<_>>()
+    );
+
+    // deleting the current ledger moves the user back to their own
+    ledger_repo.delete_ledger(second.id).await.unwrap();
+    assert!(matches!(
+        ledger_repo.get_ledger(&user.id, second.id).await,
+        Err(LedgerRepoError::LedgerNotFound(_))
+    ));
+    assert!(
+        ledger_repo
+            .get_ledger(&user.id, own.id)
+            .await
+            .unwrap()
+            .current
+    );
+
+    user.delete().await;
+    assert!(matches!(
+        ledger_repo.get_members(own.id).await,
+        Err(LedgerRepoError::LedgerNotFound(_))
+    ));
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_shared_ledger(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        ledger_repo,
+        transaction_repo,
+        template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let owner = TestUser::new(&user_repo).await;
+    let editor = TestUser::new(&user_repo).await;
+    let viewer = TestUser::new(&user_repo).await;
+
+    let household = ledger_repo
+        .create_ledger(&owner.id, "Household")
+        .await
+        .unwrap();
+    ledger_repo
+        .set_current_ledger(&owner.id, household.id)
+        .await
+        .unwrap();
+    let expires_at = Utc::now() + Duration::days(1);
+    let (editor_code, viewer_code) = (Uuid::new_v4().to_string(), Uuid::new_v4().to_string());
+    ledger_repo
+        .create_invitation(household.id, &editor_code, LedgerRole::Editor, expires_at)
+        .await
+        .unwrap();
+    ledger_repo
+        .create_invitation(household.id, &viewer_code, LedgerRole::Viewer, expires_at)
+        .await
+        .unwrap();
+
+    let joined = ledger_repo
+        .accept_invitation(&editor.id, &editor_code)
+        .await
+        .unwrap();
+    assert_eq!(
+        Ledger {
+            id: household.id,
+            name: "Household".to_string(),
+            role: LedgerRole::Editor,
+            current: false,
+        },
+        joined
+    );
+    // invitations can only be used once
+    assert!(matches!(
+        ledger_repo
+            .accept_invitation(&viewer.id, &editor_code)
+            .await,
+        Err(LedgerRepoError::InvitationNotFound)
+    ));
+    ledger_repo
+        .accept_invitation(&viewer.id, &viewer_code)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![
+            member(&owner, LedgerRole::Owner),
+            member(&editor, LedgerRole::Editor),
+            member(&viewer, LedgerRole::Viewer),
+        ],
+        ledger_repo.get_members(household.id).await.unwrap()
+    );
+
+    let transaction = transaction_repo
+        .create_new_transaction(&owner.id, NewTransactionGenerator::default().generate())
+        .await
+        .unwrap();
+    // members only see the ledger once it is their current one
+    assert!(matches!(
+        transaction_repo
+            .get_transaction(&editor.id, transaction.id)
+            .await,
+        Err(TransactionRepoError::TransactionNotFound(_))
+    ));
+    let since = transaction_repo
+        .get_changed_transactions(&editor.id, 0)
+        .await
+        .unwrap()
+        .last()
+        .map_or(0, |c| c.change);
+    for user in [&editor, &viewer] {
+        ledger_repo
+            .set_current_ledger(&user.id, household.id)
+            .await
+            .unwrap();
+    }
+    assert!(
+        transaction_repo
+            .get_last_purged_change(&editor.id)
+            .await
+            .unwrap()
+            > since
+    );
+    assert_eq!(
+        transaction,
+        transaction_repo
+            .get_transaction(&viewer.id, transaction.id)
+            .await
+            .unwrap()
+    );
+
+    let updated = transaction_repo
+        .update_transaction(
+            &editor.id,
+            transaction.id,
+            NewTransactionGenerator::default().generate(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        updated,
+        transaction_repo
+            .get_transaction(&owner.id, transaction.id)
+            .await
+            .unwrap()
+    );
+
+    // viewers can't change anything
+    assert!(matches!(
+        transaction_repo
+            .create_new_transaction(&viewer.id, NewTransactionGenerator::default().generate())
+            .await,
+        Err(TransactionRepoError::NotPermitted)
+    ));
+    assert!(matches!(
+        transaction_repo
+            .delete_transaction(&viewer.id, transaction.id, None)
+            .await,
+        Err(TransactionRepoError::NotPermitted)
+    ));
+    assert!(matches!(
+        template_repo
+            .create_template(&viewer.id, NewTemplateGenerator::default().generate())
+            .await,
+        Err(TransactionTemplateRepoError::NotPermitted)
+    ));
+    ledger_repo
+        .set_role(household.id, &viewer.id, LedgerRole::Editor)
+        .await
+        .unwrap();
+    template_repo
+        .create_template(&viewer.id, NewTemplateGenerator::default().generate())
+        .await
+        .unwrap();
+    assert_eq!(
+        1,
+        template_repo.get_templates(&owner.id).await.unwrap().len()
+    );
+
+    // leaving moves the member back to their own ledger
+    ledger_repo
+        .remove_member(household.id, &editor.id)
+        .await
+        .unwrap();
+    let editor_ledgers = ledger_repo.get_ledgers(&editor.id).await.unwrap();
+    assert_eq!(1, editor_ledgers.len());
+    assert!(editor_ledgers[0].current);
+    assert!(transaction_repo
+        .get_all_transactions(&editor.id, Filter::NONE, None)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // the shared ledger stays with the remaining member when its owner is deleted
+    owner.delete().await;
+    assert_eq!(
+        vec![member(&viewer, LedgerRole::Owner)],
+        ledger_repo.get_members(household.id).await.unwrap()
+    );
+    assert!(transaction_repo
+        .get_transaction(&viewer.id, transaction.id)
+        .await
+        .is_ok());
+
+    editor.delete().await;
+    viewer.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_invitations(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        ledger_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let owner = TestUser::new(&user_repo).await;
+    let user = TestUser::new(&user_repo).await;
+    let ledger = ledger_repo.get_ledgers(&owner.id).await.unwrap()[0].clone();
+
+    let expired = Uuid::new_v4().to_string();
+    ledger_repo
+        .create_invitation(
+            ledger.id,
+            &expired,
+            LedgerRole::Editor,
+            Utc::now() - Duration::seconds(1),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        ledger_repo.accept_invitation(&user.id, &expired).await,
+        Err(LedgerRepoError::InvitationNotFound)
+    ));
+
+    let code = Uuid::new_v4().to_string();
+    ledger_repo
+        .create_invitation(
+            ledger.id,
+            &code,
+            LedgerRole::Editor,
+            Utc::now() + Duration::days(1),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        ledger_repo.accept_invitation(&owner.id, &code).await,
+        Err(LedgerRepoError::AlreadyMember(_))
+    ));
+    assert!(matches!(
+        ledger_repo
+            .create_invitation(-1, &code, LedgerRole::Editor, Utc::now())
+            .await,
+        Err(LedgerRepoError::LedgerNotFound(-1))
+    ));
+
+    owner.delete().await;
+    // invitations go with their ledger
+    assert!(matches!(
+        ledger_repo.accept_invitation(&user.id, &code).await,
+        Err(LedgerRepoError::InvitationNotFound)
+    ));
+    user.delete().await;
+}
+
+/// A change that commits late must not be numbered before changes that committed ahead of it, or
+/// clients that synced in between would skip it
+#[actix_rt::test]
+async fn test_changes_numbered_in_commit_order() {
+    let pool = utils::build_pool().await;
+    let Repos {
+        user_repo,
+        ledger_repo,
+        transaction_repo,
+        ..
+    } = SQLxRepo::from_pool(pool.clone()).into();
+    let user = TestUser::new(&user_repo).await;
+    let mut generator = NewTransactionGenerator::default();
+    let first = transaction_repo
+        .create_new_transaction(&user.id, generator.generate())
+        .await
+        .unwrap();
+
+    let mut tx = pool.begin().await.unwrap();
+    let first_change: i64 = sqlx::query_scalar(
+        "UPDATE transactions SET category = 'Late' WHERE id = $1 RETURNING change_seq",
+    )
+    .bind(first.id)
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+
+    let second = actix_rt::spawn({
+        let transaction_repo = transaction_repo.clone();
+        let user_id = user.id.clone();
+        let new_transaction = generator.generate();
+        async move {
+            transaction_repo
+                .create_new_transaction(&user_id, new_transaction)
+                .await
+                .unwrap()
+        }
+    });
+    actix_rt::time::sleep(std::time::Duration::from_millis(200)).await;
+    // the second change waits for the first to commit
+    assert!(!second.is_finished());
+    let synced_through = ledger_repo.get_last_change(&user.id).await.unwrap();
+    assert!(synced_through < first_change);
+
+    tx.commit().await.unwrap();
+    let second = second.await.unwrap();
+    let changed = transaction_repo
+        .get_changed_transactions(&user.id, synced_through)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![first.id, second.id],
+        changed.iter().map(|c| c.transaction.id).collect::<Vec<_>>()
+    );
+    assert_eq!(first_change, changed[0].change);
+    assert_eq!(
+        changed[1].change,
+        ledger_repo.get_last_change(&user.id).await.unwrap()
+    );
+
+    user.delete().await;
+}
+
+/// Numbering a change locks its ledger, so a bulk update racing single updates of the same rows
+/// must not deadlock
+#[actix_rt::test]
+async fn test_concurrent_writes_to_ledger() {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = SQLxRepo::from_pool(utils::build_pool().await).into();
+    let user = TestUser::new(&user_repo).await;
+    let mut generator = NewTransactionGenerator::default();
+    let mut transactions = vec![];
+    for _ in 0..20 {
+        let mut new_transaction = generator.generate();
+        new_transaction.transactee = Some("A".to_owned());
+        transactions.push(
+            transaction_repo
+                .create_new_transaction(&user.id, new_transaction)
+                .await
+                .unwrap(),
+        );
+    }
+
+    for round in 0..10 {
+        let (old, new) = if round % 2 == 0 {
+            ("A", "B")
+        } else {
+            ("B", "A")
+        };
+        let updates = transactions.iter().rev().take(3).map(|t| {
+            let mut updated = generator.generate();
+            updated.transactee = Some(old.to_owned());
+            transaction_repo.update_transaction(&user.id, t.id, updated, None)
+        });
+        let merged = [old.to_owned()];
+        let (merged, updated) = futures::join!(
+            transaction_repo.merge_transactees(&user.id, &merged, new),
+            futures::future::join_all(updates)
+        );
+        merged.unwrap();
+        for u in updated {
+            u.unwrap();
+        }
+    }
+
+    user.delete().await;
+}
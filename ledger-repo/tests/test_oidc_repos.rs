@@ -0,0 +1,131 @@
+mod utils;
+
+use chrono::{Duration, Utc};
+use ledger_repo::oidc_repo::{OidcLogin, OidcRepoError};
+use ledger_repo::user_repo::{User, UserRepoError};
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::test_user::TestUser;
+use utils::RepoType;
+use uuid::Uuid;
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_logins(#[case] repo_type: RepoType) {
+    let Repos { oidc_repo, .. } = utils::build_repos(repo_type).await;
+    let login = OidcLogin {
+        state: Uuid::new_v4().to_string(),
+        code_verifier: "verifier".to_string(),
+        nonce: "nonce".to_string(),
+    };
+    let expire_before = Utc::now() - Duration::minutes(10);
+
+    oidc_repo
+        .create_login(login.clone(), expire_before)
+        .await
+        .unwrap();
+    assert_eq!(
+        Some(login.clone()),
+        oidc_repo
+            .take_login(&login.state, expire_before)
+            .await
+            .unwrap()
+    );
+    // a login can only be finished once
+    assert_eq!(
+        None,
+        oidc_repo
+            .take_login(&login.state, expire_before)
+            .await
+            .unwrap()
+    );
+
+    let expired = OidcLogin {
+        state: Uuid::new_v4().to_string(),
+        ..login
+    };
+    oidc_repo
+        .create_login(expired.clone(), expire_before)
+        .await
+        .unwrap();
+    assert_eq!(
+        None,
+        oidc_repo
+            .take_login(&expired.state, Utc::now() + Duration::minutes(1))
+            .await
+            .unwrap()
+    );
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_identities(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        oidc_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let issuer = "https://idp.example.com";
+    let subject = Uuid::new_v4().to_string();
+    let user_id = format!("oidc-user-{}", subject);
+
+    assert_eq!(
+        None,
+        oidc_repo.get_linked_user(issuer, &subject).await.unwrap()
+    );
+    oidc_repo
+        .create_linked_user(issuer, &subject, User::new(user_id.clone(), String::new()))
+        .await
+        .unwrap();
+    assert!(user_repo.get_user(&user_id).await.is_ok());
+    assert_eq!(
+        Some(user_id.clone()),
+        oidc_repo.get_linked_user(issuer, &subject).await.unwrap()
+    );
+    assert_eq!(
+        None,
+        oidc_repo
+            .get_linked_user("https://other.example.com", &subject)
+            .await
+            .unwrap()
+    );
+
+    // neither the user nor the link is created when either already exists
+    let other_id = format!("{}-2", user_id);
+    let result = oidc_repo
+        .create_linked_user(issuer, &subject, User::new(other_id.clone(), String::new()))
+        .await;
+    assert!(matches!(
+        result,
+        Err(OidcRepoError::IdentityAlreadyLinked(_, _))
+    ));
+    assert!(matches!(
+        user_repo.get_user(&other_id).await,
+        Err(UserRepoError::UserNotFound(_))
+    ));
+
+    let local_user = TestUser::new(&user_repo).await;
+    let other_subject = Uuid::new_v4().to_string();
+    let result = oidc_repo
+        .create_linked_user(
+            issuer,
+            &other_subject,
+            User::new(local_user.id.clone(), String::new()),
+        )
+        .await;
+    assert!(matches!(result, Err(OidcRepoError::UserAlreadyExists(_))));
+    assert_eq!(
+        None,
+        oidc_repo
+            .get_linked_user(issuer, &other_subject)
+            .await
+            .unwrap()
+    );
+
+    local_user.delete().await;
+    user_repo.delete_user(&user_id).await.unwrap();
+}
@@ -0,0 +1,120 @@
+mod utils;
+
+use chrono::{Duration, DurationRound, Utc};
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::RepoType;
+use uuid::Uuid;
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_rate_limits(#[case] repo_type: RepoType) {
+    let Repos {
+        rate_limit_repo, ..
+    } = utils::build_repos(repo_type).await;
+
+    let key = Uuid::new_v4().to_string();
+    let other_key = Uuid::new_v4().to_string();
+    let window_start = Utc::now().duration_trunc(Duration::minutes(1)).unwrap();
+    let next_window_start = window_start + Duration::minutes(1);
+
+    assert_eq!(
+        1,
+        rate_limit_repo
+            .count_request(&key, window_start)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        2,
+        rate_limit_repo
+            .count_request(&key, window_start)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        1,
+        rate_limit_repo
+            .count_request(&other_key, window_start)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        1,
+        rate_limit_repo
+            .count_request(&key, next_window_start)
+            .await
+            .unwrap()
+    );
+
+    let login_key = Uuid::new_v4().to_string();
+    let reset_before = Utc::now() - Duration::days(1);
+    assert_eq!(
+        None,
+        rate_limit_repo.get_failed_logins(&login_key).await.unwrap()
+    );
+    let first = rate_limit_repo
+        .record_failed_login(&login_key, reset_before)
+        .await
+        .unwrap();
+    assert_eq!(1, first.failures);
+    let second = rate_limit_repo
+        .record_failed_login(&login_key, reset_before)
+        .await
+        .unwrap();
+    assert_eq!(2, second.failures);
+    assert!(second.last_failure_at >= first.last_failure_at);
+    assert_eq!(
+        Some(second),
+        rate_limit_repo.get_failed_logins(&login_key).await.unwrap()
+    );
+
+    // failures from before the reset time are forgotten
+    let reset = rate_limit_repo
+        .record_failed_login(&login_key, Utc::now() + Duration::minutes(1))
+        .await
+        .unwrap();
+    assert_eq!(1, reset.failures);
+
+    rate_limit_repo
+        .clear_failed_logins(&login_key)
+        .await
+        .unwrap();
+    assert_eq!(
+        None,
+        rate_limit_repo.get_failed_logins(&login_key).await.unwrap()
+    );
+
+    // purging deletes the failed logins and the earlier window, but not the later one
+    rate_limit_repo
+        .record_failed_login(&login_key, reset_before)
+        .await
+        .unwrap();
+    assert!(
+        rate_limit_repo
+            .purge_counters(next_window_start)
+            .await
+            .unwrap()
+            >= 3
+    );
+    assert_eq!(
+        None,
+        rate_limit_repo.get_failed_logins(&login_key).await.unwrap()
+    );
+    assert_eq!(
+        1,
+        rate_limit_repo
+            .count_request(&key, window_start)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        2,
+        rate_limit_repo
+            .count_request(&key, next_window_start)
+            .await
+            .unwrap()
+    );
+}
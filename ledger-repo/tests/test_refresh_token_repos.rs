@@ -0,0 +1,207 @@
+mod utils;
+
+use chrono::{Duration, Utc};
+use ledger_repo::refresh_token_repo::{RefreshTokenOwner, RefreshTokenRepoError};
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::test_user::TestUser;
+use utils::RepoType;
+use uuid::Uuid;
+
+fn unique(name: &str) -> String {
+    format!("{}-{}", name, Uuid::new_v4())
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_rotate_token(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        refresh_token_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let issued_after = Utc::now() - Duration::days(30);
+
+    let family_id = unique("family");
+    let (first, second, third) = (unique("first"), unique("second"), unique("third"));
+    refresh_token_repo
+        .create_family(&user.id, &family_id, &first)
+        .await
+        .unwrap();
+
+    let owner = refresh_token_repo
+        .rotate_token(&first, &second, issued_after)
+        .await
+        .unwrap();
+    assert_eq!(
+        RefreshTokenOwner {
+            user_id: user.id.clone(),
+            family_id: family_id.clone(),
+        },
+        owner
+    );
+    let owner = refresh_token_repo
+        .rotate_token(&second, &third, issued_after)
+        .await
+        .unwrap();
+    assert_eq!(family_id, owner.family_id);
+
+    let result = refresh_token_repo
+        .rotate_token(&unique("unknown"), &unique("next"), issued_after)
+        .await;
+    assert!(matches!(result, Err(RefreshTokenRepoError::TokenNotFound)));
+
+    // tokens issued before the cutoff have expired
+    let result = refresh_token_repo
+        .rotate_token(&third, &unique("next"), Utc::now() + Duration::minutes(1))
+        .await;
+    assert!(matches!(result, Err(RefreshTokenRepoError::TokenNotFound)));
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_reused_token_revokes_family(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        refresh_token_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let issued_after = Utc::now() - Duration::days(30);
+
+    let family_id = unique("family");
+    let (first, second) = (unique("first"), unique("second"));
+    refresh_token_repo
+        .create_family(&user.id, &family_id, &first)
+        .await
+        .unwrap();
+    let other_family_token = unique("other");
+    refresh_token_repo
+        .create_family(&user.id, &unique("other-family"), &other_family_token)
+        .await
+        .unwrap();
+
+    refresh_token_repo
+        .rotate_token(&first, &second, issued_after)
+        .await
+        .unwrap();
+    let result = refresh_token_repo
+        .rotate_token(&first, &unique("next"), issued_after)
+        .await;
+    assert!(
+        matches!(result, Err(RefreshTokenRepoError::TokenReused(ref id)) if *id == family_id),
+        "{:?}",
+        result
+    );
+
+    // the legitimate token was revoked along with the family
+    let result = refresh_token_repo
+        .rotate_token(&second, &unique("next"), issued_after)
+        .await;
+    assert!(matches!(result, Err(RefreshTokenRepoError::TokenNotFound)));
+
+    // other families are unaffected
+    refresh_token_repo
+        .rotate_token(&other_family_token, &unique("next"), issued_after)
+        .await
+        .unwrap();
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_revoke_families(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        refresh_token_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let other_user = TestUser::new(&user_repo).await;
+    let issued_after = Utc::now() - Duration::days(30);
+
+    let (laptop, phone, other) = (unique("laptop"), unique("phone"), unique("other"));
+    for (user_id, token) in [
+        (&user.id, &laptop),
+        (&user.id, &phone),
+        (&other_user.id, &other),
+    ] {
+        refresh_token_repo
+            .create_family(user_id, &unique("family"), token)
+            .await
+            .unwrap();
+    }
+
+    // revoking one family leaves the user's other families
+    refresh_token_repo.revoke_family(&laptop).await.unwrap();
+    let result = refresh_token_repo
+        .rotate_token(&laptop, &unique("next"), issued_after)
+        .await;
+    assert!(matches!(result, Err(RefreshTokenRepoError::TokenNotFound)));
+    let phone_next = unique("phone-next");
+    refresh_token_repo
+        .rotate_token(&phone, &phone_next, issued_after)
+        .await
+        .unwrap();
+
+    // unknown tokens are ignored
+    refresh_token_repo
+        .revoke_family(&unique("unknown"))
+        .await
+        .unwrap();
+
+    refresh_token_repo
+        .revoke_all_families(&user.id)
+        .await
+        .unwrap();
+    let result = refresh_token_repo
+        .rotate_token(&phone_next, &unique("next"), issued_after)
+        .await;
+    assert!(matches!(result, Err(RefreshTokenRepoError::TokenNotFound)));
+    refresh_token_repo
+        .rotate_token(&other, &unique("next"), issued_after)
+        .await
+        .unwrap();
+
+    user.delete().await;
+    other_user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_purge_tokens(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        refresh_token_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let issued_after = Utc::now() - Duration::days(30);
+
+    let token = unique("token");
+    refresh_token_repo
+        .create_family(&user.id, &unique("family"), &token)
+        .await
+        .unwrap();
+
+    // only tokens from before the cutoff are purged
+    refresh_token_repo.purge_tokens(issued_after).await.unwrap();
+    refresh_token_repo
+        .rotate_token(&token, &unique("next"), issued_after)
+        .await
+        .unwrap();
+
+    user.delete().await;
+}
@@ -0,0 +1,114 @@
+mod utils;
+
+use ledger_repo::rule_repo::{AmountSign, NewRule, RuleActions, RuleConditions, RuleRepoError};
+use ledger_repo::Repos;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use std::collections::HashSet;
+use utils::test_user::TestUser;
+use utils::RepoType;
+
+fn new_rule(name: &str, priority: i32) -> NewRule {
+    let conditions = RuleConditions {
+        transactee: Some("^AMZN".to_string()),
+        min_amount: Some(Decimal::from(10)),
+        sign: Some(AmountSign::Negative),
+        day_of_month: Some(15),
+        ..RuleConditions::default()
+    };
+    let actions = RuleActions {
+        category: Some("Shopping".to_string()),
+        tags: HashSet::from(["online".to_string()]),
+        ..RuleActions::default()
+    };
+    NewRule::new(name.to_string(), priority, conditions, actions)
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_create_and_get_rules(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        rule_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let low = rule_repo
+        .create_rule(&user.id, new_rule("low", 0))
+        .await
+        .unwrap();
+    let high = rule_repo
+        .create_rule(&user.id, new_rule("high", 10))
+        .await
+        .unwrap();
+
+    let rules = rule_repo.get_rules(&user.id).await.unwrap();
+    // Should be sorted by highest priority first
+    assert_eq!(vec![high, low], rules);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_update_rule(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        rule_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let rule = rule_repo
+        .create_rule(&user.id, new_rule("rule", 0))
+        .await
+        .unwrap();
+
+    let mut updated = new_rule("updated", 5);
+    updated.conditions.note = Some("gift".to_string());
+    let updated = rule_repo
+        .update_rule(&user.id, rule.id, updated)
+        .await
+        .unwrap();
+
+    let rules = rule_repo.get_rules(&user.id).await.unwrap();
+    assert_eq!(vec![updated], rules);
+
+    let result = rule_repo
+        .update_rule(&user.id, rule.id + 1, new_rule("invalid", 0))
+        .await;
+    assert!(matches!(result, Err(RuleRepoError::RuleNotFound(_))));
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_delete_rule(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        rule_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let rule = rule_repo
+        .create_rule(&user.id, new_rule("rule", 0))
+        .await
+        .unwrap();
+
+    let deleted = rule_repo.delete_rule(&user.id, rule.id).await.unwrap();
+    assert_eq!(rule, deleted);
+
+    let result = rule_repo.delete_rule(&user.id, rule.id).await;
+    assert!(matches!(result, Err(RuleRepoError::RuleNotFound(_))));
+
+    user.delete().await;
+}
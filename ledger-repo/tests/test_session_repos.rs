@@ -0,0 +1,136 @@
+mod utils;
+
+use chrono::{Duration, Utc};
+use ledger_repo::refresh_token_repo::RefreshTokenRepoError;
+use ledger_repo::session_repo::SessionRepoError;
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::test_user::TestUser;
+use utils::RepoType;
+use uuid::Uuid;
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_sessions(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        refresh_token_repo,
+        session_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let other_user = TestUser::new(&user_repo).await;
+    let used_after = Utc::now() - Duration::days(30);
+
+    let laptop = Uuid::new_v4().to_string();
+    let phone = Uuid::new_v4().to_string();
+    let other = Uuid::new_v4().to_string();
+    for (user_id, session_id) in [
+        (&user.id, &laptop),
+        (&user.id, &phone),
+        (&other_user.id, &other),
+    ] {
+        refresh_token_repo
+            .create_family(user_id, session_id, &Uuid::new_v4().to_string())
+            .await
+            .unwrap();
+    }
+    session_repo
+        .start_session(
+            &laptop,
+            Some("Firefox".to_string()),
+            Some("192.0.2.1".to_string()),
+        )
+        .await
+        .unwrap();
+    session_repo
+        .start_session(&phone, Some("Ledger for Android".to_string()), None)
+        .await
+        .unwrap();
+    session_repo
+        .touch_session(&laptop, Some("192.0.2.2".to_string()))
+        .await
+        .unwrap();
+
+    let sessions = session_repo
+        .get_sessions(&user.id, used_after)
+        .await
+        .unwrap();
+    assert_eq!(2, sessions.len());
+    // most recently used first
+    assert_eq!(laptop, sessions[0].id);
+    assert_eq!(Some("Firefox".to_string()), sessions[0].device_name);
+    assert_eq!(Some("192.0.2.2".to_string()), sessions[0].ip_address);
+    assert!(sessions[0].last_used_at >= sessions[0].created_at);
+    assert_eq!(phone, sessions[1].id);
+    assert_eq!(
+        Some("Ledger for Android".to_string()),
+        sessions[1].device_name
+    );
+
+    let sessions = session_repo
+        .get_sessions(&user.id, Utc::now() + Duration::minutes(1))
+        .await
+        .unwrap();
+    assert!(sessions.is_empty());
+
+    let result = session_repo
+        .touch_session(&Uuid::new_v4().to_string(), None)
+        .await;
+    assert!(matches!(result, Err(SessionRepoError::SessionNotFound(_))));
+
+    user.delete().await;
+    other_user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_revoke_session(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        refresh_token_repo,
+        session_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let other_user = TestUser::new(&user_repo).await;
+    let used_after = Utc::now() - Duration::days(30);
+
+    let session_id = Uuid::new_v4().to_string();
+    let token = Uuid::new_v4().to_string();
+    refresh_token_repo
+        .create_family(&user.id, &session_id, &token)
+        .await
+        .unwrap();
+
+    // sessions can only be revoked by their user
+    let result = session_repo
+        .revoke_session(&other_user.id, &session_id)
+        .await;
+    assert!(matches!(result, Err(SessionRepoError::SessionNotFound(_))));
+
+    session_repo
+        .revoke_session(&user.id, &session_id)
+        .await
+        .unwrap();
+    let sessions = session_repo
+        .get_sessions(&user.id, used_after)
+        .await
+        .unwrap();
+    assert!(sessions.is_empty());
+    let result = session_repo.revoke_session(&user.id, &session_id).await;
+    assert!(matches!(result, Err(SessionRepoError::SessionNotFound(_))));
+
+    // the session's refresh tokens were revoked with it
+    let result = refresh_token_repo
+        .rotate_token(&token, &Uuid::new_v4().to_string(), used_after)
+        .await;
+    assert!(matches!(result, Err(RefreshTokenRepoError::TokenNotFound)));
+
+    user.delete().await;
+    other_user.delete().await;
+}
@@ -0,0 +1,86 @@
+mod utils;
+
+use ledger_repo::totp_repo::{TotpCredential, TotpRepoError};
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::test_user::TestUser;
+use utils::RepoType;
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_totp(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        totp_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    assert_eq!(None, totp_repo.get_totp(&user.id).await.unwrap());
+    let result = totp_repo.enable_totp(&user.id, vec![]).await;
+    assert!(matches!(result, Err(TotpRepoError::TotpNotFound(_))));
+
+    totp_repo
+        .create_totp(&user.id, b"first secret".to_vec())
+        .await
+        .unwrap();
+    // starting again replaces the secret
+    totp_repo
+        .create_totp(&user.id, b"second secret".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(
+        Some(TotpCredential {
+            secret: b"second secret".to_vec(),
+            enabled: false,
+            last_used_step: None,
+        }),
+        totp_repo.get_totp(&user.id).await.unwrap()
+    );
+
+    totp_repo
+        .enable_totp(&user.id, vec!["hash1".to_string(), "hash2".to_string()])
+        .await
+        .unwrap();
+    assert!(totp_repo.get_totp(&user.id).await.unwrap().unwrap().enabled);
+
+    // steps can't be reused, and earlier steps can't be used after later ones
+    assert!(totp_repo.use_step(&user.id, 100).await.unwrap());
+    assert!(!totp_repo.use_step(&user.id, 100).await.unwrap());
+    assert!(!totp_repo.use_step(&user.id, 99).await.unwrap());
+    assert!(totp_repo.use_step(&user.id, 101).await.unwrap());
+    assert_eq!(
+        Some(101),
+        totp_repo
+            .get_totp(&user.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .last_used_step
+    );
+
+    // recovery codes can be used once
+    assert!(totp_repo
+        .use_recovery_code(&user.id, "hash1")
+        .await
+        .unwrap());
+    assert!(!totp_repo
+        .use_recovery_code(&user.id, "hash1")
+        .await
+        .unwrap());
+    assert!(!totp_repo
+        .use_recovery_code(&user.id, "hash3")
+        .await
+        .unwrap());
+
+    totp_repo.delete_totp(&user.id).await.unwrap();
+    assert_eq!(None, totp_repo.get_totp(&user.id).await.unwrap());
+    assert!(!totp_repo
+        .use_recovery_code(&user.id, "hash2")
+        .await
+        .unwrap());
+
+    user.delete().await;
+}
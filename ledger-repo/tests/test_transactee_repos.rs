@@ -0,0 +1,112 @@
+mod utils;
+
+use ledger_repo::transactee_repo::{Transactee, TransacteeRepoError};
+use ledger_repo::Repos;
+use rstest::rstest;
+use utils::test_user::TestUser;
+use utils::RepoType;
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_set_and_get_transactees(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transactee_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let amazon = Transactee::new(
+        "Amazon".to_string(),
+        vec!["^AMZN Mktp".to_string(), "amazon\\.com".to_string()],
+    );
+    let alice = Transactee::new("Alice".to_string(), Vec::new());
+    transactee_repo
+        .set_transactee(&user.id, amazon.clone())
+        .await
+        .unwrap();
+    transactee_repo
+        .set_transactee(&user.id, alice.clone())
+        .await
+        .unwrap();
+
+    let transactees = transactee_repo.get_transactees(&user.id).await.unwrap();
+    // Should be sorted by name
+    assert_eq!(vec![alice, amazon.clone()], transactees);
+
+    let transactee = transactee_repo
+        .get_transactee(&user.id, "Amazon")
+        .await
+        .unwrap();
+    assert_eq!(amazon, transactee);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_set_existing_transactee(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transactee_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let transactee = Transactee::new("Amazon".to_string(), vec!["^AMZN".to_string()]);
+    transactee_repo
+        .set_transactee(&user.id, transactee)
+        .await
+        .unwrap();
+
+    let updated = Transactee::new(
+        "Amazon".to_string(),
+        vec!["^AMZN".to_string(), "^AMAZON$".to_string()],
+    );
+    transactee_repo
+        .set_transactee(&user.id, updated.clone())
+        .await
+        .unwrap();
+
+    let transactees = transactee_repo.get_transactees(&user.id).await.unwrap();
+    assert_eq!(vec![updated], transactees);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_delete_transactee(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transactee_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let transactee = Transactee::new("Amazon".to_string(), Vec::new());
+    transactee_repo
+        .set_transactee(&user.id, transactee.clone())
+        .await
+        .unwrap();
+
+    let deleted = transactee_repo
+        .delete_transactee(&user.id, "Amazon")
+        .await
+        .unwrap();
+    assert_eq!(transactee, deleted);
+
+    let result = transactee_repo.get_transactee(&user.id, "Amazon").await;
+    assert!(matches!(
+        result,
+        Err(TransacteeRepoError::TransacteeNotFound(_))
+    ));
+
+    user.delete().await;
+}
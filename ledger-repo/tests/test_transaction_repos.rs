@@ -1,11 +1,12 @@
 mod utils;
 
-use chrono::NaiveDate;
+use chrono::{Duration, NaiveDate, Utc};
 use futures::future::try_join_all;
 use ledger_repo::transaction_repo::{
-    Filter, MonthlyTotal, NewTransaction, PageOptions, Transaction, TransactionRepo,
-    TransactionRepoError,
+    Filter, MonthlyTotal, NewTransaction, PageOptions, TagStats, TransacteeCount, Transaction,
+    TransactionRepo, TransactionRepoError,
 };
+use ledger_repo::Repos;
 use rstest::rstest;
 use rust_decimal::Decimal;
 use std::collections::btree_set::BTreeSet;
@@ -37,8 +38,11 @@ async fn insert_transactions(
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_create_and_get_transactions(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user = TestUser::new(&user_repo).await;
 
     let new_transaction = generate_new_transaction();
@@ -67,7 +71,11 @@ async fn test_create_and_get_transactions(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_invalid_transactions(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user = TestUser::new(&user_repo).await;
 
     let get_result = transaction_repo.get_transaction(&user.id, 1234).await;
@@ -84,7 +92,11 @@ async fn test_get_invalid_transactions(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_invalid_user(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user1 = TestUser::new(&user_repo).await;
     let user2 = TestUser::new(&user_repo).await;
 
@@ -112,7 +124,11 @@ async fn test_get_invalid_user(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_delete_transaction(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user = TestUser::new(&user_repo).await;
 
     let new_transaction = generate_new_transaction();
@@ -123,7 +139,7 @@ async fn test_delete_transaction(#[case] repo_type: RepoType) {
         .id;
 
     let delete_result = transaction_repo
-        .delete_transaction(&user.id, transaction_id)
+        .delete_transaction(&user.id, transaction_id, None)
         .await;
     assert!(delete_result.is_ok());
 
@@ -138,15 +154,177 @@ async fn test_delete_transaction(#[case] repo_type: RepoType) {
     user.delete().await;
 }
 
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_deleted_transactions_excluded(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTransactionGenerator::default();
+    let mut kept = generator.generate();
+    kept.amount = Decimal::from(-10);
+    kept.tags = HashSet::from(["kept".to_string()]);
+    let mut deleted = generator.generate();
+    deleted.amount = Decimal::from(-25);
+    deleted.tags = HashSet::from(["deleted".to_string()]);
+    let inserted = insert_transactions(&transaction_repo, &user, vec![kept, deleted])
+        .await
+        .unwrap();
+
+    transaction_repo
+        .delete_transaction(&user.id, inserted[1].id, None)
+        .await
+        .unwrap();
+
+    let transactions = transaction_repo
+        .get_all_transactions(&user.id, Filter::NONE, None)
+        .await
+        .unwrap();
+    assert_eq!(vec![inserted[0].clone()], transactions);
+
+    let balance = transaction_repo.get_balance(&user.id).await.unwrap();
+    assert_eq!(Decimal::from(-10), balance);
+
+    let expense: Decimal = transaction_repo
+        .get_monthly_totals(&user.id, Filter::NONE)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|mt| mt.expense)
+        .sum();
+    assert_eq!(Decimal::from(10), expense);
+
+    let tags = transaction_repo.get_all_tags(&user.id).await.unwrap();
+    assert_eq!(vec!["kept".to_string()], tags);
+
+    let result = transaction_repo
+        .update_transaction(&user.id, inserted[1].id, generator.generate(), None)
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionRepoError::TransactionNotFound(_))
+    ));
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_restore_and_purge_transaction(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let transaction = transaction_repo
+        .create_new_transaction(&user.id, generate_new_transaction())
+        .await
+        .unwrap();
+
+    let result = transaction_repo
+        .restore_transaction(&user.id, transaction.id)
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionRepoError::TransactionNotFound(_))
+    ));
+
+    transaction_repo
+        .delete_transaction(&user.id, transaction.id, None)
+        .await
+        .unwrap();
+    let deleted = transaction_repo
+        .get_deleted_transactions(&user.id)
+        .await
+        .unwrap();
+    assert_eq!(1, deleted.len());
+    assert_eq!(transaction, deleted[0].transaction);
+
+    let restored = transaction_repo
+        .restore_transaction(&user.id, transaction.id)
+        .await
+        .unwrap();
+    let transaction = Transaction {
+        version: 2,
+        ..transaction
+    };
+    assert_eq!(transaction, restored);
+    assert_eq!(
+        transaction,
+        transaction_repo
+            .get_transaction(&user.id, transaction.id)
+            .await
+            .unwrap()
+    );
+    assert!(transaction_repo
+        .get_deleted_transactions(&user.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    transaction_repo
+        .delete_transaction(&user.id, transaction.id, None)
+        .await
+        .unwrap();
+    transaction_repo
+        .purge_deleted_transactions(Utc::now() - Duration::days(1))
+        .await
+        .unwrap();
+    assert_eq!(
+        1,
+        transaction_repo
+            .get_deleted_transactions(&user.id)
+            .await
+            .unwrap()
+            .len()
+    );
+
+    let purged = transaction_repo
+        .purge_deleted_transactions(Utc::now() + Duration::seconds(1))
+        .await
+        .unwrap();
+    assert!(purged >= 1);
+    assert!(transaction_repo
+        .get_deleted_transactions(&user.id)
+        .await
+        .unwrap()
+        .is_empty());
+    let result = transaction_repo
+        .restore_transaction(&user.id, transaction.id)
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionRepoError::TransactionNotFound(_))
+    ));
+
+    user.delete().await;
+}
+
 #[rstest]
 #[case::sqlx(RepoType::SQLx)]
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_delete_invalid_transaction(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user = TestUser::new(&user_repo).await;
 
-    let delete_result = transaction_repo.delete_transaction(&user.id, 1234).await;
+    let delete_result = transaction_repo
+        .delete_transaction(&user.id, 1234, None)
+        .await;
     assert!(matches!(
         delete_result,
         Err(TransactionRepoError::TransactionNotFound(_))
@@ -160,7 +338,11 @@ async fn test_delete_invalid_transaction(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_all_transactions(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default();
@@ -188,7 +370,11 @@ async fn test_get_all_transactions(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_all_transactions_empty(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let transactions: Vec<Transaction> = transaction_repo
@@ -205,7 +391,11 @@ async fn test_get_all_transactions_empty(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_transactions_sorted(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default();
@@ -239,7 +429,11 @@ async fn test_transactions_sorted(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_transactions_filter_category(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default().with_categories(vec!["Loan", "Misc"]);
@@ -267,7 +461,11 @@ async fn test_get_transactions_filter_category(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_transactions_filter_transactee(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default().with_transactees(vec!["Alice", "Bob"]);
@@ -297,7 +495,11 @@ async fn test_get_transactions_filter_transactee(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_transactions_filter_from(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default().with_dates(vec![
@@ -335,7 +537,11 @@ async fn test_get_transactions_filter_from(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_transactions_filter_until(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default().with_dates(vec![
@@ -373,7 +579,11 @@ async fn test_get_transactions_filter_until(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_transactions_pagination(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default().with_dates(vec![
@@ -393,7 +603,7 @@ async fn test_transactions_pagination(#[case] repo_type: RepoType) {
         .await
         .unwrap();
     assert_eq!(2, transactions.len());
-    assert_eq!(transactions.get(0), inserted_transactions.get(1));
+    assert_eq!(transactions.first(), inserted_transactions.get(1));
     assert_eq!(transactions.get(1), inserted_transactions.get(2));
 
     test_user.delete().await
@@ -404,7 +614,11 @@ async fn test_transactions_pagination(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_update_transaction(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let new_transaction = generate_new_transaction();
@@ -423,7 +637,7 @@ async fn test_update_transaction(#[case] repo_type: RepoType) {
     );
 
     let updated_transaction: Transaction = transaction_repo
-        .update_transaction(&test_user.id, transaction.id, update.clone())
+        .update_transaction(&test_user.id, transaction.id, update.clone(), None)
         .await
         .unwrap();
     assert_eq!(transaction.id, updated_transaction.id);
@@ -433,12 +647,88 @@ async fn test_update_transaction(#[case] repo_type: RepoType) {
     test_user.delete().await
 }
 
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_transaction_versions(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let test_user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTransactionGenerator::default();
+    let transaction = transaction_repo
+        .create_new_transaction(&test_user.id, generator.generate())
+        .await
+        .unwrap();
+    assert_eq!(1, transaction.version);
+
+    let updated = transaction_repo
+        .update_transaction(&test_user.id, transaction.id, generator.generate(), Some(1))
+        .await
+        .unwrap();
+    assert_eq!(2, updated.version);
+    assert_eq!(
+        updated,
+        transaction_repo
+            .get_transaction(&test_user.id, transaction.id)
+            .await
+            .unwrap()
+    );
+
+    // a stale version doesn't overwrite the newer changes
+    let result = transaction_repo
+        .update_transaction(&test_user.id, transaction.id, generator.generate(), Some(1))
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionRepoError::VersionMismatch(_))
+    ));
+    let result = transaction_repo
+        .delete_transaction(&test_user.id, transaction.id, Some(1))
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionRepoError::VersionMismatch(_))
+    ));
+    assert_eq!(
+        updated,
+        transaction_repo
+            .get_transaction(&test_user.id, transaction.id)
+            .await
+            .unwrap()
+    );
+
+    let result = transaction_repo
+        .update_transaction(&test_user.id, 1234, generator.generate(), Some(1))
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionRepoError::TransactionNotFound(_))
+    ));
+
+    let deleted = transaction_repo
+        .delete_transaction(&test_user.id, transaction.id, Some(2))
+        .await
+        .unwrap();
+    assert_eq!(updated, deleted);
+
+    test_user.delete().await
+}
+
 #[rstest]
 #[case::sqlx(RepoType::SQLx)]
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_update_tags(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let new_transaction = generate_new_transaction();
@@ -456,7 +746,7 @@ async fn test_update_tags(#[case] repo_type: RepoType) {
         HashSet::from(["tag2".to_string(), "tag3".to_string()]),
     );
     let updated_transaction: Transaction = transaction_repo
-        .update_transaction(&test_user.id, transaction.id, update.clone())
+        .update_transaction(&test_user.id, transaction.id, update.clone(), None)
         .await
         .unwrap();
 
@@ -473,13 +763,17 @@ async fn test_update_tags(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_update_invalid_transaction(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let update = generate_new_transaction();
 
     let result = transaction_repo
-        .update_transaction(&test_user.id, 1234, update)
+        .update_transaction(&test_user.id, 1234, update, None)
         .await;
     assert!(matches!(
         result,
@@ -494,7 +788,11 @@ async fn test_update_invalid_transaction(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_update_invalid_user(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user1 = TestUser::new(&user_repo).await;
     let user2 = TestUser::new(&user_repo).await;
 
@@ -506,7 +804,7 @@ async fn test_update_invalid_user(#[case] repo_type: RepoType) {
 
     let update = generate_new_transaction();
     let result = transaction_repo
-        .update_transaction(&user2.id, transaction.id, update)
+        .update_transaction(&user2.id, transaction.id, update, None)
         .await;
     assert!(matches!(
         result,
@@ -522,7 +820,11 @@ async fn test_update_invalid_user(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_monthly_totals(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default()
@@ -572,7 +874,11 @@ async fn test_monthly_totals(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_categories(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator =
@@ -600,7 +906,11 @@ async fn test_get_categories(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_tags(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default().with_tags(vec![
@@ -622,12 +932,162 @@ async fn test_get_tags(#[case] repo_type: RepoType) {
     test_user.delete().await
 }
 
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_get_tag_stats(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let test_user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTransactionGenerator::default()
+        .with_tags(vec![
+            HashSet::from(["tag1".to_string(), "tag2".to_string()]),
+            HashSet::from(["tag2".to_string()]),
+            HashSet::new(),
+        ])
+        .with_amounts(vec![
+            Decimal::from(-20),
+            Decimal::from(-10),
+            Decimal::from(15),
+        ]);
+    let new_transactions = generator.generate_many(3);
+
+    insert_transactions(&transaction_repo, &test_user, new_transactions)
+        .await
+        .unwrap();
+
+    let tag_stats = transaction_repo.get_tag_stats(&test_user.id).await.unwrap();
+    assert_eq!(
+        vec![
+            TagStats::new("tag2".to_string(), 2, Decimal::from(-30)),
+            TagStats::new("tag1".to_string(), 1, Decimal::from(-20)),
+        ],
+        tag_stats
+    );
+
+    test_user.delete().await
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_merge_tags(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let test_user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTransactionGenerator::default().with_tags(vec![
+        HashSet::from(["food".to_string(), "Food".to_string()]),
+        HashSet::from(["groceries".to_string()]),
+        HashSet::from(["rent".to_string()]),
+    ]);
+    let new_transactions = generator.generate_many(3);
+
+    let inserted_transactions =
+        insert_transactions(&transaction_repo, &test_user, new_transactions)
+            .await
+            .unwrap();
+
+    let changed = transaction_repo
+        .merge_tags(
+            &test_user.id,
+            &["Food".to_string(), "groceries".to_string()],
+            "food",
+        )
+        .await
+        .unwrap();
+    let mut expected_before = inserted_transactions[..2].to_vec();
+    expected_before.sort_by_key(|t| t.id);
+    assert_eq!(
+        expected_before,
+        changed.iter().map(|u| u.before.clone()).collect::<Vec<_>>()
+    );
+    for u in &changed {
+        assert_eq!(HashSet::from(["food".to_string()]), u.after.tags);
+        assert_eq!(u.before.version + 1, u.after.version);
+    }
+
+    let tags: Vec<HashSet<String>> = try_join_all(
+        inserted_transactions
+            .iter()
+            .map(|t| transaction_repo.get_transaction(&test_user.id, t.id)),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|t| t.tags)
+    .collect();
+    assert_eq!(
+        vec![
+            HashSet::from(["food".to_string()]),
+            HashSet::from(["food".to_string()]),
+            HashSet::from(["rent".to_string()]),
+        ],
+        tags
+    );
+
+    test_user.delete().await
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_delete_tag(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let test_user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTransactionGenerator::default().with_tags(vec![
+        HashSet::from(["tag1".to_string(), "tag2".to_string()]),
+        HashSet::from(["tag2".to_string(), "tag3".to_string()]),
+    ]);
+    let new_transactions = generator.generate_many(2);
+
+    insert_transactions(&transaction_repo, &test_user, new_transactions)
+        .await
+        .unwrap();
+
+    let changed = transaction_repo
+        .delete_tag(&test_user.id, "tag2")
+        .await
+        .unwrap();
+    assert_eq!(2, changed.len());
+    assert!(changed
+        .iter()
+        .all(|u| u.before.tags.contains("tag2") && !u.after.tags.contains("tag2")));
+
+    let tags = transaction_repo.get_all_tags(&test_user.id).await.unwrap();
+    assert_eq!(
+        HashSet::from(["tag1".to_string(), "tag3".to_string()]),
+        HashSet::from_iter(tags.into_iter())
+    );
+
+    test_user.delete().await
+}
+
 #[rstest]
 #[case::sqlx(RepoType::SQLx)]
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_transactees(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator =
@@ -643,7 +1103,13 @@ async fn test_get_transactees(#[case] repo_type: RepoType) {
         .await
         .unwrap();
     // Should be sorted by most transactions
-    assert_eq!(vec!["Bob".to_owned(), "Alice".to_owned()], transactees);
+    assert_eq!(
+        vec![
+            TransacteeCount::new("Bob".to_owned(), 2),
+            TransacteeCount::new("Alice".to_owned(), 1)
+        ],
+        transactees
+    );
 
     test_user.delete().await
 }
@@ -653,7 +1119,11 @@ async fn test_get_transactees(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_category_transactees(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default()
@@ -670,7 +1140,65 @@ async fn test_get_category_transactees(#[case] repo_type: RepoType) {
         .await
         .unwrap();
     // Should be sorted by most transactions of that category
-    assert_eq!(vec!["Alice".to_owned(), "Bob".to_owned()], transactees);
+    assert_eq!(
+        vec![
+            TransacteeCount::new("Alice".to_owned(), 1),
+            TransacteeCount::new("Bob".to_owned(), 0)
+        ],
+        transactees
+    );
+
+    test_user.delete().await
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_merge_transactees(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let test_user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTransactionGenerator::default().with_transactees(vec![
+        "AMZN Mktp US",
+        "Amazon.com",
+        "Alice",
+    ]);
+    let new_transactions = generator.generate_many(3);
+
+    insert_transactions(&transaction_repo, &test_user, new_transactions)
+        .await
+        .unwrap();
+
+    let changed = transaction_repo
+        .merge_transactees(
+            &test_user.id,
+            &["AMZN Mktp US".to_string(), "Amazon.com".to_string()],
+            "Amazon",
+        )
+        .await
+        .unwrap();
+    assert_eq!(2, changed.len());
+    assert!(changed
+        .iter()
+        .all(|u| u.before.transactee.as_deref() != Some("Amazon")
+            && u.after.transactee.as_deref() == Some("Amazon")));
+
+    let transactees = transaction_repo
+        .get_all_transactees(&test_user.id, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![
+            TransacteeCount::new("Amazon".to_owned(), 2),
+            TransacteeCount::new("Alice".to_owned(), 1)
+        ],
+        transactees
+    );
 
     test_user.delete().await
 }
@@ -680,7 +1208,11 @@ async fn test_get_category_transactees(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_balance(#[case] repo_type: RepoType) {
-    let (user_repo, transaction_repo, _template_repo) = utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let test_user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTransactionGenerator::default().with_amounts(vec![
@@ -699,3 +1231,119 @@ async fn test_get_balance(#[case] repo_type: RepoType) {
 
     test_user.delete().await
 }
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_changed_transactions(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTransactionGenerator::default();
+    let first = transaction_repo
+        .create_new_transaction(&user.id, generator.generate())
+        .await
+        .unwrap();
+    let second = transaction_repo
+        .create_new_transaction(&user.id, generator.generate())
+        .await
+        .unwrap();
+
+    let changed = transaction_repo
+        .get_changed_transactions(&user.id, 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![(first.clone(), false), (second.clone(), false)],
+        changed
+            .iter()
+            .map(|c| (c.transaction.clone(), c.deleted))
+            .collect::<Vec<_>>()
+    );
+    assert!(changed[0].change < changed[1].change);
+    let since = changed[1].change;
+    assert!(transaction_repo
+        .get_changed_transactions(&user.id, since)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let deleted = transaction_repo
+        .delete_transaction(&user.id, second.id, None)
+        .await
+        .unwrap();
+    let updated = transaction_repo
+        .update_transaction(&user.id, first.id, generator.generate(), None)
+        .await
+        .unwrap();
+    let changed = transaction_repo
+        .get_changed_transactions(&user.id, since)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![(deleted, true), (updated, false)],
+        changed
+            .iter()
+            .map(|c| (c.transaction.clone(), c.deleted))
+            .collect::<Vec<_>>()
+    );
+    assert!(changed[0].change > since);
+
+    assert_eq!(
+        0,
+        transaction_repo
+            .get_last_purged_change(&user.id)
+            .await
+            .unwrap()
+    );
+    transaction_repo
+        .purge_deleted_transactions(Utc::now() + Duration::seconds(1))
+        .await
+        .unwrap();
+    assert_eq!(
+        changed[0].change,
+        transaction_repo
+            .get_last_purged_change(&user.id)
+            .await
+            .unwrap()
+    );
+    let changed_after_purge = transaction_repo
+        .get_changed_transactions(&user.id, since)
+        .await
+        .unwrap();
+    assert_eq!(1, changed_after_purge.len());
+    assert_eq!(changed[1], changed_after_purge[0]);
+
+    user.delete().await;
+}
+
+#[actix_rt::test]
+async fn test_listen_for_changes() {
+    let repo = utils::build_sqlx_repo().await;
+    let mut listener = repo.listen_for_changes().await.unwrap();
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = repo.into();
+    let user = TestUser::new(&user_repo).await;
+
+    transaction_repo
+        .create_new_transaction(&user.id, generate_new_transaction())
+        .await
+        .unwrap();
+
+    // Other tests share the database, so changes of other users may be received first
+    actix_rt::time::timeout(std::time::Duration::from_secs(5), async {
+        while listener.recv().await.unwrap() != user.id {}
+    })
+    .await
+    .expect("No change notification received");
+
+    user.delete().await;
+}
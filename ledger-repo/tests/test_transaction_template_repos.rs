@@ -1,7 +1,10 @@
 mod utils;
 
+use chrono::{Duration, Utc};
 use ledger_repo::transaction_template_repo::TransactionTemplateRepoError;
+use ledger_repo::Repos;
 use rstest::rstest;
+use std::collections::HashSet;
 use utils::generator::NewTemplateGenerator;
 use utils::test_user::TestUser;
 use utils::RepoType;
@@ -11,8 +14,11 @@ use utils::RepoType;
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_create_and_get_templates(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        template_repo: transaction_template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTemplateGenerator::default();
@@ -44,8 +50,11 @@ async fn test_create_and_get_templates(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_update_template(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        template_repo: transaction_template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTemplateGenerator::default();
@@ -58,7 +67,12 @@ async fn test_update_template(#[case] repo_type: RepoType) {
 
     let updated_template = generator.generate();
     transaction_template_repo
-        .update_template(&user.id, template.template_id, updated_template.clone())
+        .update_template(
+            &user.id,
+            template.template_id,
+            updated_template.clone(),
+            None,
+        )
         .await
         .unwrap();
 
@@ -83,8 +97,11 @@ async fn test_update_template(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_update_different_user(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        template_repo: transaction_template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTemplateGenerator::default();
@@ -95,15 +112,24 @@ async fn test_update_different_user(#[case] repo_type: RepoType) {
         .await
         .unwrap();
 
+    let different_user = TestUser::new(&user_repo).await;
     let updated_template = generator.generate();
     let result = transaction_template_repo
-        .update_template("different_user", template.template_id, updated_template)
+        .update_template(
+            &different_user.id,
+            template.template_id,
+            updated_template,
+            None,
+        )
         .await;
 
     assert!(matches!(
         result,
         Err(TransactionTemplateRepoError::TemplateNotFound(_))
-    ))
+    ));
+
+    different_user.delete().await;
+    user.delete().await;
 }
 
 #[rstest]
@@ -111,8 +137,11 @@ async fn test_update_different_user(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_get_invalid_user(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        template_repo: transaction_template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user1 = TestUser::new(&user_repo).await;
 
     let mut generator = NewTemplateGenerator::default();
@@ -138,8 +167,11 @@ async fn test_get_invalid_user(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_delete_template(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos {
+        user_repo,
+        template_repo: transaction_template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
     let user = TestUser::new(&user_repo).await;
 
     let mut generator = NewTemplateGenerator::default();
@@ -151,17 +183,329 @@ async fn test_delete_template(#[case] repo_type: RepoType) {
         .unwrap();
 
     let result = transaction_template_repo
-        .delete_template(&user.id, template.template_id)
+        .delete_template(&user.id, template.template_id, None)
         .await;
     assert!(result.is_ok());
 
     let result = transaction_template_repo
-        .delete_template(&user.id, template.template_id)
+        .delete_template(&user.id, template.template_id, None)
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionTemplateRepoError::TemplateNotFound(_))
+    ));
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_merge_and_delete_tags(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        template_repo: transaction_template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTemplateGenerator::default();
+
+    let mut new_template = generator.generate();
+    new_template.tags = HashSet::from(["tag1".to_string(), "tag2".to_string()]);
+    transaction_template_repo
+        .create_template(&user.id, new_template)
+        .await
+        .unwrap();
+
+    let changed = transaction_template_repo
+        .merge_tags(&user.id, &["tag1".to_string()], "tag3")
+        .await
+        .unwrap();
+    assert_eq!(1, changed);
+
+    let changed = transaction_template_repo
+        .delete_tag(&user.id, "tag2")
+        .await
+        .unwrap();
+    assert_eq!(1, changed);
+
+    let templates = transaction_template_repo
+        .get_templates(&user.id)
+        .await
+        .unwrap();
+    assert_eq!(HashSet::from(["tag3".to_string()]), templates[0].tags);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_merge_transactees(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        template_repo: transaction_template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTemplateGenerator::default();
+
+    let mut new_template = generator.generate();
+    new_template.transactee = Some("AMAZON".to_string());
+    transaction_template_repo
+        .create_template(&user.id, new_template)
+        .await
+        .unwrap();
+
+    let changed = transaction_template_repo
+        .merge_transactees(&user.id, &["AMAZON".to_string()], "Amazon")
+        .await
+        .unwrap();
+    assert_eq!(1, changed);
+
+    let templates = transaction_template_repo
+        .get_templates(&user.id)
+        .await
+        .unwrap();
+    assert_eq!(Some("Amazon".to_string()), templates[0].transactee);
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_restore_and_purge_template(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        template_repo: transaction_template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTemplateGenerator::default();
+    let template = transaction_template_repo
+        .create_template(&user.id, generator.generate())
+        .await
+        .unwrap();
+
+    transaction_template_repo
+        .delete_template(&user.id, template.template_id, None)
+        .await
+        .unwrap();
+    assert!(transaction_template_repo
+        .get_templates(&user.id)
+        .await
+        .unwrap()
+        .is_empty());
+    let result = transaction_template_repo
+        .update_template(&user.id, template.template_id, generator.generate(), None)
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionTemplateRepoError::TemplateNotFound(_))
+    ));
+
+    let deleted = transaction_template_repo
+        .get_deleted_templates(&user.id)
+        .await
+        .unwrap();
+    assert_eq!(1, deleted.len());
+    assert_eq!(template.template_id, deleted[0].template.template_id);
+
+    let restored = transaction_template_repo
+        .restore_template(&user.id, template.template_id)
+        .await
+        .unwrap();
+    assert_eq!(template.name, restored.name);
+    assert_eq!(
+        1,
+        transaction_template_repo
+            .get_templates(&user.id)
+            .await
+            .unwrap()
+            .len()
+    );
+
+    transaction_template_repo
+        .delete_template(&user.id, template.template_id, None)
+        .await
+        .unwrap();
+    transaction_template_repo
+        .purge_deleted_templates(Utc::now() - Duration::days(1))
+        .await
+        .unwrap();
+    assert_eq!(
+        1,
+        transaction_template_repo
+            .get_deleted_templates(&user.id)
+            .await
+            .unwrap()
+            .len()
+    );
+
+    transaction_template_repo
+        .purge_deleted_templates(Utc::now() + Duration::seconds(1))
+        .await
+        .unwrap();
+    assert!(transaction_template_repo
+        .get_deleted_templates(&user.id)
+        .await
+        .unwrap()
+        .is_empty());
+    let result = transaction_template_repo
+        .restore_template(&user.id, template.template_id)
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionTemplateRepoError::TemplateNotFound(_))
+    ));
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_template_versions(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        template_repo: transaction_template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTemplateGenerator::default();
+    let template = transaction_template_repo
+        .create_template(&user.id, generator.generate())
+        .await
+        .unwrap();
+    assert_eq!(1, template.version);
+
+    let updated = transaction_template_repo
+        .update_template(
+            &user.id,
+            template.template_id,
+            generator.generate(),
+            Some(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(2, updated.version);
+    let fetched = transaction_template_repo
+        .get_template(&user.id, template.template_id)
+        .await
+        .unwrap();
+    assert_eq!(updated.name, fetched.name);
+    assert_eq!(2, fetched.version);
+
+    let result = transaction_template_repo
+        .update_template(
+            &user.id,
+            template.template_id,
+            generator.generate(),
+            Some(1),
+        )
         .await;
+    assert!(matches!(
+        result,
+        Err(TransactionTemplateRepoError::VersionMismatch(_))
+    ));
+    let result = transaction_template_repo
+        .delete_template(&user.id, template.template_id, Some(1))
+        .await;
+    assert!(matches!(
+        result,
+        Err(TransactionTemplateRepoError::VersionMismatch(_))
+    ));
+
+    let result = transaction_template_repo.get_template(&user.id, 1234).await;
     assert!(matches!(
         result,
         Err(TransactionTemplateRepoError::TemplateNotFound(_))
     ));
 
+    transaction_template_repo
+        .delete_template(&user.id, template.template_id, Some(2))
+        .await
+        .unwrap();
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_changed_templates(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        template_repo: transaction_template_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let mut generator = NewTemplateGenerator::default();
+    let first = transaction_template_repo
+        .create_template(&user.id, generator.generate())
+        .await
+        .unwrap();
+    let second = transaction_template_repo
+        .create_template(&user.id, generator.generate())
+        .await
+        .unwrap();
+
+    let changed = transaction_template_repo
+        .get_changed_templates(&user.id, 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![(first.template_id, false), (second.template_id, false)],
+        changed
+            .iter()
+            .map(|c| (c.template.template_id, c.deleted))
+            .collect::<Vec<_>>()
+    );
+    let since = changed[1].change;
+
+    transaction_template_repo
+        .delete_template(&user.id, second.template_id, None)
+        .await
+        .unwrap();
+    let updated = transaction_template_repo
+        .update_template(&user.id, first.template_id, generator.generate(), None)
+        .await
+        .unwrap();
+    let changed = transaction_template_repo
+        .get_changed_templates(&user.id, since)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![(second.template_id, true), (first.template_id, false)],
+        changed
+            .iter()
+            .map(|c| (c.template.template_id, c.deleted))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(updated.name, changed[1].template.name);
+
+    transaction_template_repo
+        .purge_deleted_templates(Utc::now() + Duration::seconds(1))
+        .await
+        .unwrap();
+    assert_eq!(
+        changed[0].change,
+        transaction_template_repo
+            .get_last_purged_change(&user.id)
+            .await
+            .unwrap()
+    );
+
     user.delete().await;
 }
@@ -1,7 +1,11 @@
 mod utils;
 
-use ledger_repo::user_repo::{User, UserRepoError};
+use chrono::{Duration, Utc};
+use ledger_repo::user_repo::{InviteCode, NewInviteCode, User, UserRepoError};
+use ledger_repo::Repos;
 use rstest::rstest;
+use utils::generator::NewTransactionGenerator;
+use utils::test_user::TestUser;
 use utils::RepoType;
 use uuid::Uuid;
 
@@ -10,8 +14,7 @@ use uuid::Uuid;
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_create_and_get_user(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, _transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos { user_repo, .. } = utils::build_repos(repo_type).await;
 
     let user = User::new(
         "test-user-".to_owned() + &Uuid::new_v4().to_string(),
@@ -29,8 +32,7 @@ async fn test_create_and_get_user(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_create_existing_user(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, _transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos { user_repo, .. } = utils::build_repos(repo_type).await;
 
     let user = User::new(
         "test-user-".to_owned() + &Uuid::new_v4().to_string(),
@@ -50,8 +52,7 @@ async fn test_create_existing_user(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_update_password(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, _transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos { user_repo, .. } = utils::build_repos(repo_type).await;
 
     let user = User::new(
         "test-user-".to_owned() + &Uuid::new_v4().to_string(),
@@ -72,8 +73,7 @@ async fn test_update_password(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_update_password_invalid_user(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, _transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos { user_repo, .. } = utils::build_repos(repo_type).await;
 
     let update_result = user_repo
         .update_password_hash("invalid user", "new hash")
@@ -86,8 +86,7 @@ async fn test_update_password_invalid_user(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_delete_user(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, _transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos { user_repo, .. } = utils::build_repos(repo_type).await;
 
     let user = User::new(
         "test-user-".to_owned() + &Uuid::new_v4().to_string(),
@@ -107,9 +106,173 @@ async fn test_delete_user(#[case] repo_type: RepoType) {
 #[case::mem(RepoType::Mem)]
 #[actix_rt::test]
 async fn test_delete_invalid_user(#[case] repo_type: RepoType) {
-    let (user_repo, _transaction_repo, _transaction_template_repo) =
-        utils::build_repos(repo_type).await;
+    let Repos { user_repo, .. } = utils::build_repos(repo_type).await;
 
     let delete_result = user_repo.delete_user("test-user").await;
     assert!(matches!(delete_result, Err(UserRepoError::UserNotFound(_))))
 }
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_user_administration(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        transaction_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+
+    let transaction = transaction_repo
+        .create_new_transaction(&user.id, NewTransactionGenerator::default().generate())
+        .await
+        .unwrap();
+    transaction_repo
+        .create_new_transaction(&user.id, NewTransactionGenerator::default().generate())
+        .await
+        .unwrap();
+    // transactions in the trash aren't counted
+    transaction_repo
+        .delete_transaction(&user.id, transaction.id, None)
+        .await
+        .unwrap();
+    user_repo.record_login(&user.id).await.unwrap();
+    user_repo.set_admin(&user.id, true).await.unwrap();
+    user_repo.set_disabled(&user.id, true).await.unwrap();
+    user_repo.require_password_reset(&user.id).await.unwrap();
+
+    let summary = user_repo
+        .get_users()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|u| u.id == user.id)
+        .unwrap();
+    assert!(summary.admin);
+    assert!(summary.disabled);
+    assert!(summary.password_reset_required);
+    assert_eq!(1, summary.transaction_count);
+    assert!(summary
+        .last_login_at
+        .is_some_and(|at| Utc::now() - at < Duration::minutes(1)));
+
+    let stored = user_repo.get_user(&user.id).await.unwrap();
+    assert!(stored.admin && stored.disabled && stored.password_reset_required);
+    // choosing a new password is the reset
+    user_repo
+        .update_password_hash(&user.id, "new hash")
+        .await
+        .unwrap();
+    user_repo.set_disabled(&user.id, false).await.unwrap();
+    let stored = user_repo.get_user(&user.id).await.unwrap();
+    assert!(!stored.disabled && !stored.password_reset_required);
+
+    assert!(matches!(
+        user_repo.set_disabled("invalid user", true).await,
+        Err(UserRepoError::UserNotFound(_))
+    ));
+
+    user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_invite_codes(#[case] repo_type: RepoType) {
+    let Repos { user_repo, .. } = utils::build_repos(repo_type).await;
+    let inviter = TestUser::new(&user_repo).await;
+    let code_hash = Uuid::new_v4().to_string();
+    let expired_hash = Uuid::new_v4().to_string();
+
+    let invite_code = user_repo
+        .create_invite_code(
+            &inviter.id,
+            NewInviteCode {
+                max_uses: 1,
+                expires_at: Utc::now() + Duration::days(1),
+            },
+            &code_hash,
+        )
+        .await
+        .unwrap();
+    assert_eq!(0, invite_code.uses);
+    let expired = user_repo
+        .create_invite_code(
+            &inviter.id,
+            NewInviteCode {
+                max_uses: 1,
+                expires_at: Utc::now() - Duration::days(1),
+            },
+            &expired_hash,
+        )
+        .await
+        .unwrap();
+
+    let new_user = || {
+        User::new(
+            "test-user-".to_owned() + &Uuid::new_v4().to_string(),
+            "not a real hash".to_owned(),
+        )
+    };
+    assert!(matches!(
+        user_repo
+            .create_user_with_invite(new_user(), &expired_hash)
+            .await,
+        Err(UserRepoError::InvalidInviteCode)
+    ));
+    // an existing user doesn't use the code up
+    assert!(matches!(
+        user_repo
+            .create_user_with_invite(User::new(inviter.id.clone(), "hash".to_owned()), &code_hash)
+            .await,
+        Err(UserRepoError::UserAlreadyExists(_))
+    ));
+    let invitee = new_user();
+    user_repo
+        .create_user_with_invite(invitee.clone(), &code_hash)
+        .await
+        .unwrap();
+    assert_eq!(invitee, user_repo.get_user(&invitee.id).await.unwrap());
+    let rejected = new_user();
+    assert!(matches!(
+        user_repo
+            .create_user_with_invite(rejected.clone(), &code_hash)
+            .await,
+        Err(UserRepoError::InvalidInviteCode)
+    ));
+    assert!(matches!(
+        user_repo.get_user(&rejected.id).await,
+        Err(UserRepoError::UserNotFound(_))
+    ));
+
+    let invite_codes = user_repo.get_invite_codes(&inviter.id).await.unwrap();
+    assert_eq!(vec![expired.id, invite_code.id], ids(&invite_codes));
+    assert_eq!(1, invite_codes[1].uses);
+    let all = user_repo.get_all_invite_codes().await.unwrap();
+    assert!(all.iter().any(|code| code.id == invite_code.id));
+
+    assert!(matches!(
+        user_repo.delete_invite_code(&invitee.id, expired.id).await,
+        Err(UserRepoError::InviteCodeNotFound(_))
+    ));
+    user_repo
+        .delete_invite_code(&inviter.id, expired.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![invite_code.id],
+        ids(&user_repo.get_invite_codes(&inviter.id).await.unwrap())
+    );
+
+    // the codes go with the user who created them
+    user_repo.delete_user(&invitee.id).await.unwrap();
+    inviter.delete().await;
+    let all = user_repo.get_all_invite_codes().await.unwrap();
+    assert!(!all.iter().any(|code| code.id == invite_code.id));
+}
+
+fn ids(invite_codes: &[InviteCode]) -> Vec<i32> {
+    invite_codes.iter().map(|code| code.id).collect()
+}
@@ -0,0 +1,221 @@
+mod utils;
+
+use chrono::{Duration, Utc};
+use ledger_repo::webhook_repo::{
+    DeliveryAttempt, DeliveryStatus, NewWebhook, WebhookEvent, WebhookFilter, WebhookRepoError,
+};
+use ledger_repo::Repos;
+use rstest::rstest;
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::collections::HashSet;
+use utils::test_user::TestUser;
+use utils::RepoType;
+
+fn new_webhook(url: &str) -> NewWebhook {
+    NewWebhook {
+        url: url.to_string(),
+        events: HashSet::from([
+            WebhookEvent::TransactionCreated,
+            WebhookEvent::TransactionDeleted,
+        ]),
+        filter: WebhookFilter {
+            category: Some("Groceries".to_string()),
+            min_expense: Some(Decimal::from(100)),
+        },
+    }
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_webhooks(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        webhook_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let other_user = TestUser::new(&user_repo).await;
+
+    let first = webhook_repo
+        .create_webhook(
+            &user.id,
+            new_webhook("http://localhost/first"),
+            "first secret".to_string(),
+        )
+        .await
+        .unwrap();
+    assert_eq!("http://localhost/first", first.url);
+    assert_eq!("first secret", first.secret);
+    assert_eq!(new_webhook("").events, first.events);
+    assert_eq!(new_webhook("").filter, first.filter);
+    let second = webhook_repo
+        .create_webhook(
+            &user.id,
+            new_webhook("http://localhost/second"),
+            "second secret".to_string(),
+        )
+        .await
+        .unwrap();
+
+    let webhooks = webhook_repo.get_webhooks(&user.id).await.unwrap();
+    assert_eq!(vec![first.clone(), second.clone()], webhooks);
+    let webhook = webhook_repo.get_webhook(&user.id, first.id).await.unwrap();
+    assert_eq!(first, webhook);
+
+    // other users can't see or delete the webhooks
+    assert!(webhook_repo
+        .get_webhooks(&other_user.id)
+        .await
+        .unwrap()
+        .is_empty());
+    let result = webhook_repo.get_webhook(&other_user.id, first.id).await;
+    assert!(matches!(result, Err(WebhookRepoError::WebhookNotFound(id)) if id == first.id));
+    let result = webhook_repo.delete_webhook(&other_user.id, first.id).await;
+    assert!(matches!(result, Err(WebhookRepoError::WebhookNotFound(id)) if id == first.id));
+
+    webhook_repo
+        .delete_webhook(&user.id, first.id)
+        .await
+        .unwrap();
+    let webhooks = webhook_repo.get_webhooks(&user.id).await.unwrap();
+    assert_eq!(vec![second], webhooks);
+    let result = webhook_repo.get_webhook(&user.id, first.id).await;
+    assert!(matches!(result, Err(WebhookRepoError::WebhookNotFound(id)) if id == first.id));
+
+    user.delete().await;
+    other_user.delete().await;
+}
+
+#[rstest]
+#[case::sqlx(RepoType::SQLx)]
+#[case::mem(RepoType::Mem)]
+#[actix_rt::test]
+async fn test_webhook_deliveries(#[case] repo_type: RepoType) {
+    let Repos {
+        user_repo,
+        webhook_repo,
+        ..
+    } = utils::build_repos(repo_type).await;
+    let user = TestUser::new(&user_repo).await;
+    let webhook = webhook_repo
+        .create_webhook(
+            &user.id,
+            new_webhook("http://localhost/hook"),
+            "secret".to_string(),
+        )
+        .await
+        .unwrap();
+
+    let first = webhook_repo
+        .queue_delivery(
+            webhook.id,
+            WebhookEvent::TransactionCreated,
+            json!({"id": 1}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(DeliveryStatus::Pending, first.status);
+    assert_eq!(0, first.attempts);
+    let second = webhook_repo
+        .queue_delivery(
+            webhook.id,
+            WebhookEvent::TransactionDeleted,
+            json!({"id": 2}),
+        )
+        .await
+        .unwrap();
+
+    // other tests share the database, so only this webhook's deliveries are checked
+    let now = Utc::now();
+    let claimed: Vec<_> = webhook_repo
+        .claim_due_deliveries(now, now + Duration::minutes(1), 1000)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|due| due.delivery.webhook_id == webhook.id)
+        .collect();
+    assert_eq!(2, claimed.len());
+    assert_eq!(first.id, claimed[0].delivery.id);
+    assert_eq!(json!({"id": 1}), claimed[0].delivery.payload);
+    assert_eq!(second.id, claimed[1].delivery.id);
+    assert_eq!("http://localhost/hook", claimed[1].url);
+    assert_eq!("secret", claimed[1].secret);
+
+    // claimed deliveries are not claimed again
+    let claimed = webhook_repo
+        .claim_due_deliveries(now, now + Duration::minutes(1), 1000)
+        .await
+        .unwrap();
+    assert!(claimed
+        .iter()
+        .all(|due| due.delivery.webhook_id != webhook.id));
+
+    webhook_repo
+        .record_attempt(
+            first.id,
+            DeliveryAttempt {
+                status: DeliveryStatus::Succeeded,
+                response_status: Some(200),
+                error: None,
+                next_attempt_at: now,
+            },
+        )
+        .await
+        .unwrap();
+    webhook_repo
+        .record_attempt(
+            second.id,
+            DeliveryAttempt {
+                status: DeliveryStatus::Pending,
+                response_status: Some(500),
+                error: Some("Server error".to_string()),
+                next_attempt_at: now - Duration::seconds(1),
+            },
+        )
+        .await
+        .unwrap();
+
+    // the failed delivery is due again
+    let claimed: Vec<_> = webhook_repo
+        .claim_due_deliveries(now, now + Duration::minutes(1), 1000)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|due| due.delivery.webhook_id == webhook.id)
+        .collect();
+    assert_eq!(1, claimed.len());
+    assert_eq!(second.id, claimed[0].delivery.id);
+    assert_eq!(1, claimed[0].delivery.attempts);
+
+    let deliveries = webhook_repo
+        .get_deliveries(&user.id, webhook.id, 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![second.id, first.id],
+        deliveries.iter().map(|d| d.id).collect::<Vec<_>>()
+    );
+    assert_eq!(DeliveryStatus::Pending, deliveries[0].status);
+    assert_eq!(Some(500), deliveries[0].last_response_status);
+    assert_eq!(Some("Server error".to_string()), deliveries[0].last_error);
+    assert_eq!(DeliveryStatus::Succeeded, deliveries[1].status);
+    assert_eq!(1, deliveries[1].attempts);
+    let deliveries = webhook_repo
+        .get_deliveries(&user.id, webhook.id, 1)
+        .await
+        .unwrap();
+    assert_eq!(1, deliveries.len());
+
+    // deliveries are deleted with their webhook
+    webhook_repo
+        .delete_webhook(&user.id, webhook.id)
+        .await
+        .unwrap();
+    let result = webhook_repo.get_deliveries(&user.id, webhook.id, 10).await;
+    assert!(matches!(result, Err(WebhookRepoError::WebhookNotFound(_))));
+
+    user.delete().await;
+}
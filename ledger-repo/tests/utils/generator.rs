@@ -132,15 +132,14 @@ impl NewTransactionGenerator {
     }
 
     pub fn generate(&mut self) -> NewTransaction {
-        let new_transaction = NewTransaction::new(
+        NewTransaction::new(
             self.cat_gen.gen(),
             self.tran_gen.gen(),
             self.note_gen.gen(),
             self.date_gen.gen(),
             self.amnt_gen.gen(),
             self.tag_gen.gen(),
-        );
-        new_transaction
+        )
     }
 
     pub fn generate_many(&mut self, count: usize) -> Vec<NewTransaction> {
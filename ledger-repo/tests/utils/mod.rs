@@ -1,12 +1,12 @@
 pub mod generator;
 pub mod test_user;
 
-use ledger_repo::transaction_repo::TransactionRepo;
-use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
-use ledger_repo::user_repo::UserRepo;
+use ledger_repo::sqlx_repo::SQLxRepo;
+use ledger_repo::Repos;
 use serde::Deserialize;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
 use std::fs;
-use std::sync::Arc;
 
 #[derive(Deserialize)]
 struct TestConfig {
@@ -19,18 +19,30 @@ pub enum RepoType {
     Mem,
 }
 
-pub async fn build_repos(
-    repo_type: RepoType,
-) -> (
-    Arc<dyn UserRepo>,
-    Arc<dyn TransactionRepo>,
-    Arc<dyn TransactionTemplateRepo>,
-) {
+fn read_config() -> TestConfig {
     let config = fs::read_to_string("config_test.toml").unwrap();
-    let config: TestConfig = toml::from_str(config.as_str()).unwrap();
+    toml::from_str(config.as_str()).unwrap()
+}
 
+pub async fn build_repos(repo_type: RepoType) -> Repos {
     match repo_type {
-        RepoType::SQLx => ledger_repo::sqlx_repo::create_repos(config.database_url, 1).await,
+        RepoType::SQLx => ledger_repo::sqlx_repo::create_repos(read_config().database_url, 1).await,
         RepoType::Mem => ledger_repo::mem_repo::create_repos(),
     }
 }
+
+/// The pool has room for a change listener, which keeps a connection to itself
+#[allow(dead_code)]
+pub async fn build_sqlx_repo() -> SQLxRepo {
+    SQLxRepo::new(read_config().database_url, 2).await.unwrap()
+}
+
+/// A pool for tests that need to hold a database transaction open while the repos are used
+#[allow(dead_code)]
+pub async fn build_pool() -> PgPool {
+    PgPoolOptions::new()
+        .max_connections(4)
+        .connect(&read_config().database_url)
+        .await
+        .unwrap()
+}
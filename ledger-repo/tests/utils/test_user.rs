@@ -1,7 +1,7 @@
+use ledger_repo::user_repo::{User, UserRepo};
 use std::sync::Arc;
 use tracing::info;
 use uuid::Uuid;
-use ledger_repo::user_repo::{User, UserRepo};
 
 #[allow(dead_code)]
 pub struct TestUser {
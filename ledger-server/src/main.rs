@@ -22,12 +22,16 @@ use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::registry;
 
 use ledger_lib::auth::jwt::JWTAuth;
+use ledger_lib::auth::oidc::OidcProvider;
+use ledger_lib::auth::password::Passwords;
+use ledger_lib::client_ip::TrustedProxies;
 use ledger_lib::config::Config;
+use ledger_lib::events::EventBus;
+use ledger_lib::idempotency::IdempotencyWindow;
+use ledger_lib::rate_limit::CounterStorage;
+use ledger_lib::webhook::{WebhookDispatcher, WebhookTargets};
 use ledger_repo::sqlx_repo::SQLxRepo;
-use ledger_repo::transaction_repo::TransactionRepo;
-use ledger_repo::user_repo::UserRepo;
-use ledger_repo::HealthCheck;
-use ledger_repo::transaction_template_repo::TransactionTemplateRepo;
+use ledger_repo::{HealthCheck, Repos};
 
 const SERVICE_NAME: &str = "ledger-server";
 
@@ -52,14 +56,57 @@ async fn main() -> Result<(), Box<dyn Error>> {
     tracing::subscriber::set_global_default(subscriber).expect("set up subscriber");
     drop(tracing_guard);
 
+    let signup_mode = config.signup_mode();
     let repo = SQLxRepo::new(config.database_url, 10).await?;
-    let transaction_repo: Arc<dyn TransactionRepo> = Arc::new(repo.clone());
-    let template_repo: Arc<dyn TransactionTemplateRepo> = Arc::new(repo.clone());
-    let user_repo: Arc<dyn UserRepo> = Arc::new(repo.clone());
-    let repo_health: Arc<dyn HealthCheck> = Arc::new(repo);
+    let mut repos: Repos = repo.clone().into();
+    if config.rate_limits.storage == CounterStorage::Memory {
+        repos.rate_limit_repo = ledger_repo::mem_repo::create_rate_limit_repo();
+    }
+    let repo_health: Arc<dyn HealthCheck> = Arc::new(repo.clone());
+    ledger_lib::admin::grant_admins(&*repos.user_repo, &config.admins).await?;
+
+    actix_web::rt::spawn(ledger_lib::trash::purge_trash_periodically(
+        repos.clone(),
+        config.trash_retention_days,
+    ));
+    let idempotency_window = IdempotencyWindow::hours(config.idempotency_window_hours);
+    actix_web::rt::spawn(ledger_lib::idempotency::purge_expired_keys_periodically(
+        repos.clone(),
+        idempotency_window,
+    ));
+    actix_web::rt::spawn(
+        ledger_lib::auth::refresh_token::purge_expired_tokens_periodically(repos.clone()),
+    );
+
+    actix_web::rt::spawn(ledger_lib::rate_limit::purge_counters_periodically(
+        repos.clone(),
+    ));
+
+    let webhook_targets = WebhookTargets {
+        allow_private: config.allow_private_webhook_targets,
+    };
+    actix_web::rt::spawn(ledger_lib::webhook::deliver_webhooks_periodically(
+        repos.clone(),
+        WebhookDispatcher::with_targets(webhook_targets),
+    ));
+
+    let event_bus = EventBus::new();
+    if config.listen_for_changes {
+        let listener = repo.listen_for_changes().await?;
+        actix_web::rt::spawn(ledger_lib::events::forward_database_changes(
+            listener,
+            event_bus.clone(),
+        ));
+    }
+
+    let jwt_auth = match &config.jwt {
+        Some(jwt_config) => JWTAuth::from_config(jwt_config)?,
+        None => JWTAuth::from_secret(get_secret()?),
+    };
 
-    let secret = get_secret()?;
-    let jwt_auth = JWTAuth::from_secret(secret);
+    let oidc_provider = config.oidc.clone().map(OidcProvider::new);
+    let passwords = Passwords::from_config(&config.password)?;
+    let trusted_proxies = TrustedProxies(config.trusted_proxies.clone());
 
     let mut server = HttpServer::new(move || {
         let cors = Cors::permissive(); // We do authentication using the Authorization header, so don't need CORS
@@ -68,10 +115,15 @@ async fn main() -> Result<(), Box<dyn Error>> {
             .wrap(ledger_lib::tracing::create_middleware())
             .configure(ledger_lib::app_config_func(
                 jwt_auth.clone(),
-                user_repo.clone(),
-                transaction_repo.clone(),
-                template_repo.clone(),
-                config.signups_enabled,
+                repos.clone(),
+                signup_mode,
+                idempotency_window,
+                event_bus.clone(),
+                config.rate_limits.clone(),
+                oidc_provider.clone(),
+                passwords.clone(),
+                trusted_proxies.clone(),
+                webhook_targets,
             ))
             .configure(ledger_lib::health_check_config_func(repo_health.clone()))
     });